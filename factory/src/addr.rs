@@ -0,0 +1,82 @@
+use cosmwasm_std::{Api, CanonicalAddr, HumanAddr, ReadonlyStorage, StdError, StdResult};
+
+use crate::state::{load, Config, CONFIG_KEY};
+
+// Everything the factory assumes about the text form of addresses lives in this module, so a
+// port to a chain with a different bech32 prefix only needs to adjust it and the address_prefix
+// the factory is instantiated with.
+
+/// Returns StdResult<CanonicalAddr> with the canonical form of the address, or a descriptive
+/// error if it is not a valid address
+///
+/// # Arguments
+///
+/// * `api` - reference to the Api used to convert human and canonical addresses
+/// * `address` - a reference to the address to canonicalize
+pub fn canonicalize<A: Api>(api: &A, address: &HumanAddr) -> StdResult<CanonicalAddr> {
+    api.canonical_address(&HumanAddr(address.0.trim().to_string()))
+        .map_err(|_| StdError::generic_err(format!("{} is not a valid address", address)))
+}
+
+/// Returns StdResult<HumanAddr> with the form of the address the factory displays and stores
+///
+/// # Arguments
+///
+/// * `api` - reference to the Api used to convert human and canonical addresses
+/// * `address` - a reference to the canonical address to display
+pub fn display<A: Api>(api: &A, address: &CanonicalAddr) -> StdResult<HumanAddr> {
+    api.human_address(address)
+}
+
+/// Returns StdResult<HumanAddr> with the address in the format the factory stores it in, so that
+/// addresses pasted in another letter case or with surrounding whitespace still match. Returns a
+/// descriptive error for invalid addresses instead of letting them silently match nothing
+///
+/// # Arguments
+///
+/// * `api` - reference to the Api used to convert human and canonical addresses
+/// * `address` - a reference to the address to normalize
+pub fn normalize_address<A: Api>(api: &A, address: &HumanAddr) -> StdResult<HumanAddr> {
+    display(api, &canonicalize(api, address)?)
+}
+
+/// Returns StdResult<HumanAddr> with the normalized address, after making sure it has the
+/// address prefix the factory was instantiated with, if any. Use this for addresses that are
+/// about to be stored
+///
+/// # Arguments
+///
+/// * `storage` - a reference to the contract's storage
+/// * `api` - reference to the Api used to convert human and canonical addresses
+/// * `address` - a reference to the address to validate
+pub fn validate_address<S: ReadonlyStorage, A: Api>(
+    storage: &S,
+    api: &A,
+    address: &HumanAddr,
+) -> StdResult<HumanAddr> {
+    let normalized = normalize_address(api, address)?;
+    let config: Config = load(storage, CONFIG_KEY)?;
+    if let Some(prefix) = config.address_prefix {
+        // bech32 separates the prefix from the data with the last "1"
+        let has_prefix = matches!(
+            normalized.0.rfind('1'),
+            Some(separator) if normalized.0[..separator] == prefix
+        );
+        if !has_prefix {
+            return Err(StdError::generic_err(format!(
+                "{} is not a {} address",
+                address, prefix
+            )));
+        }
+    }
+    Ok(normalized)
+}
+
+/// Returns &[u8] with the key the owner's offspring lists are stored under
+///
+/// # Arguments
+///
+/// * `owner` - a reference to the owner's address
+pub fn owner_key(owner: &HumanAddr) -> &[u8] {
+    owner.0.as_bytes()
+}
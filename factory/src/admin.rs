@@ -0,0 +1,973 @@
+use cosmwasm_std::{
+    log, Api, BankMsg, CanonicalAddr, Coin, CosmosMsg, Env, Extern, HandleResult, HumanAddr,
+    Querier, ReadonlyStorage, StdError, StdResult, Storage, Uint128,
+};
+
+use cosmwasm_storage::{PrefixedStorage, ReadonlyPrefixedStorage};
+
+use secret_toolkit::{storage::AppendStoreMut, utils::HandleCallback};
+
+use secret_toolkit_incubator::CashMap;
+
+use crate::state::{
+    load, may_load, remove, save, Config, ADDRESS_BOOK_KEY, COLLECTED_FEES_KEY, CONFIG_KEY,
+    MAX_ADDRESS_BOOK_NAME_LEN, MAX_ADMIN_BATCH, MAX_ISSUE_DETAILS_LEN, MAX_MAINTENANCE_BUDGET,
+    MAX_DEPRECATION_NOTE_LEN, MAX_OWNER_NOTE_LEN, PREFIX_OWNER_NOTES, ALLOWED_CREATORS_KEY, DENIED_CREATORS_KEY, PREFIX_AUDIT_LOG, PREFIX_BLOCKED_ADDRESSES,
+    NATIVE_DENOM, PREFIX_DEPRECATED_VERSIONS, PREFIX_DISABLED_VERSIONS, PREFIX_OFFSPRING_OWNER,
+    PREFIX_TOKEN_FEES,
+};
+
+use crate::{
+    addr::validate_address,
+    creation::authenticate_offspring,
+    msg::{
+        AddressBookEntry, AdminAction, AuditEntry, CallbackPolicy, ContractInfo, CreationRules,
+        DeactivationSource, FilterTypes, HandleAnswer,
+        LifecycleStatus, OffspringContractInfo, OwnerNote, ResponseStatus::Success,
+        VersionDeprecation,
+    },
+    issues::open_issue,
+    offspring_msg::OffspringHandleMsg,
+    queries::{deprecated_among, list_owner_offspring, valid_page_size},
+    registry::{self, lifecycle_status},
+    response::{lifecycle_log, not_admin, ok_answer, ok_status, ok_with_log},
+    sealing::{open, seal_text},
+    stats::{record_usage, Usage},
+    snip20_msg::Snip20HandleMsg,
+    validators::{
+        collected_fees, collected_token_fees, register_fee_token, valid_code_hash,
+        valid_creation_rules,
+    },
+    versions::add_version,
+};
+
+/// Returns StdResult<Config>
+///
+/// makes sure the message was sent by the admin, and returns the factory config
+///
+/// # Arguments
+///
+/// * `deps` - reference to Extern containing all the contract's external dependencies
+/// * `env` - a reference to the Env of contract's environment
+pub fn enforce_admin<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    env: &Env,
+) -> StdResult<Config> {
+    let config: Config = load(&deps.storage, CONFIG_KEY)?;
+    let sender = deps.api.canonical_address(&env.message.sender)?;
+    if config.admin != sender {
+        return Err(not_admin());
+    }
+    Ok(config)
+}
+
+/// Returns StdResult<()>
+///
+/// appends an admin action to the audit log
+///
+/// # Arguments
+///
+/// * `storage` - mutable reference to contract's storage
+/// * `action` - name of the admin action
+/// * `env` - reference to the Env of the admin's message
+/// * `subject` - optional address the action was performed on
+pub fn record_audit<S: Storage>(
+    storage: &mut S,
+    action: &str,
+    env: &Env,
+    subject: Option<HumanAddr>,
+) -> StdResult<()> {
+    let mut audit_store = PrefixedStorage::new(PREFIX_AUDIT_LOG, storage);
+    let mut audit_log = AppendStoreMut::attach_or_create(&mut audit_store)?;
+    audit_log.push(&AuditEntry {
+        action: action.to_string(),
+        admin: env.message.sender.clone(),
+        subject,
+        height: env.block.height,
+    })
+}
+
+/// Returns HandleResult
+///
+/// allows admin to edit the offspring contract version.
+///
+/// # Arguments
+///
+/// * `deps` - mutable reference to Extern containing all the contract's external dependencies
+/// * `env` - Env of contract's environment
+/// * `offspring_contract` - OffspringContractInfo of the new offspring version
+pub fn try_new_contract<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    offspring_contract: OffspringContractInfo,
+) -> HandleResult {
+    // only allow admin to do this
+    let mut config = enforce_admin(deps, &env)?;
+    valid_code_hash(&offspring_contract.code_hash)?;
+    add_version(&mut deps.storage, &offspring_contract)?;
+    record_audit(&mut deps.storage, "new_version", &env, None)?;
+    let attributes = vec![
+        log("action", "new_version"),
+        log("code_id", offspring_contract.code_id),
+        log("code_hash", &offspring_contract.code_hash),
+        log("block_time", env.block.time),
+    ];
+    // keep the replaced version as a fallback
+    config.previous_version = Some(std::mem::replace(&mut config.version, offspring_contract));
+    save(&mut deps.storage, CONFIG_KEY, &config)?;
+
+    ok_with_log(
+        vec![],
+        attributes,
+        Some(HandleAnswer::Status {
+            status: Success,
+            message: None,
+        }),
+    )
+}
+
+/// Returns HandleResult
+///
+/// allows admin to switch back to the offspring version replaced by the last NewOffspringContract.
+/// The version rolled back from becomes the fallback, so rolling back again undoes the rollback
+///
+/// # Arguments
+///
+/// * `deps` - mutable reference to Extern containing all the contract's external dependencies
+/// * `env` - Env of contract's environment
+pub fn try_rollback_version<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+) -> HandleResult {
+    // only allow admin to do this
+    let mut config = enforce_admin(deps, &env)?;
+    let previous = config
+        .previous_version
+        .take()
+        .ok_or_else(|| StdError::generic_err("There is no previous version to roll back to"))?;
+    let attributes = vec![
+        log("action", "rollback_version"),
+        log("code_id", previous.code_id),
+        log("code_hash", &previous.code_hash),
+        log("block_time", env.block.time),
+    ];
+    config.previous_version = Some(std::mem::replace(&mut config.version, previous));
+    save(&mut deps.storage, CONFIG_KEY, &config)?;
+    record_audit(&mut deps.storage, "rollback_version", &env, None)?;
+
+    ok_with_log(
+        vec![],
+        attributes,
+        Some(HandleAnswer::Status {
+            status: Success,
+            message: None,
+        }),
+    )
+}
+
+/// Returns HandleResult
+///
+/// allows admin to change the factory status to (dis)allow the creation of new offspring
+///
+/// # Arguments
+///
+/// * `deps` - mutable reference to Extern containing all the contract's external dependencies
+/// * `env` - Env of contract's environment
+/// * `stop` - true if the factory should disallow offspring creation
+pub fn try_set_status<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    stop: bool,
+) -> HandleResult {
+    // only allow admin to do this
+    let mut config = enforce_admin(deps, &env)?;
+    config.stopped = stop;
+    save(&mut deps.storage, CONFIG_KEY, &config)?;
+
+    ok_status()
+}
+
+/// Returns HandleResult
+///
+/// allows admin to change the number of entries listed by queries that do not specify a page size
+///
+/// # Arguments
+///
+/// * `deps` - mutable reference to Extern containing all the contract's external dependencies
+/// * `env` - Env of contract's environment
+/// * `page_size` - the new default page size
+pub fn try_set_default_page_size<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    page_size: u32,
+) -> HandleResult {
+    // only allow admin to do this
+    let mut config = enforce_admin(deps, &env)?;
+    config.default_page_size = valid_page_size(page_size)?;
+    save(&mut deps.storage, CONFIG_KEY, &config)?;
+
+    ok_status()
+}
+
+/// Returns HandleResult
+///
+/// allows admin to change the number of characters of an offspring's description the registry
+/// keeps
+///
+/// # Arguments
+///
+/// * `deps` - mutable reference to Extern containing all the contract's external dependencies
+/// * `env` - Env of contract's environment
+/// * `limit` - the new description limit, or None to remove it
+pub fn try_set_description_limit<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    limit: Option<u32>,
+) -> HandleResult {
+    // only allow admin to do this
+    let mut config = enforce_admin(deps, &env)?;
+    config.description_limit = limit;
+    save(&mut deps.storage, CONFIG_KEY, &config)?;
+
+    ok_status()
+}
+
+/// Returns HandleResult
+///
+/// allows admin to change the number of units of deferred maintenance work performed by each
+/// handle
+///
+/// # Arguments
+///
+/// * `deps` - mutable reference to Extern containing all the contract's external dependencies
+/// * `env` - Env of contract's environment
+/// * `budget` - the new maintenance budget. 0 disables maintenance
+pub fn try_set_maintenance_budget<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    budget: u32,
+) -> HandleResult {
+    // only allow admin to do this
+    let mut config = enforce_admin(deps, &env)?;
+    config.maintenance_budget = valid_maintenance_budget(budget)?;
+    save(&mut deps.storage, CONFIG_KEY, &config)?;
+
+    ok_status()
+}
+
+/// Returns HandleResult
+///
+/// allows admin to change the funds the factory attaches to the messages it sends offspring
+///
+/// # Arguments
+///
+/// * `deps` - mutable reference to Extern containing all the contract's external dependencies
+/// * `env` - Env of contract's environment
+/// * `policy` - the new callback policy
+pub fn try_set_callback_policy<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    policy: CallbackPolicy,
+) -> HandleResult {
+    // only allow admin to do this
+    let mut config = enforce_admin(deps, &env)?;
+    config.callback_policy = policy;
+    save(&mut deps.storage, CONFIG_KEY, &config)?;
+
+    ok_status()
+}
+
+/// Returns HandleResult
+///
+/// allows admin to change the settings of the creation pipeline
+///
+/// # Arguments
+///
+/// * `deps` - mutable reference to Extern containing all the contract's external dependencies
+/// * `env` - Env of contract's environment
+/// * `rules` - the new creation rules
+pub fn try_set_creation_rules<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    rules: CreationRules,
+) -> HandleResult {
+    // only allow admin to do this
+    let mut config = enforce_admin(deps, &env)?;
+    config.creation_rules = valid_creation_rules(rules)?;
+    save(&mut deps.storage, CONFIG_KEY, &config)?;
+    record_audit(&mut deps.storage, "set_creation_rules", &env, None)?;
+
+    // make sure the fee token calls Receive, even if it was registered with before
+    ok_with_log(
+        register_fee_token(&env, &config.creation_rules)?.into_iter().collect(),
+        vec![],
+        Some(HandleAnswer::Status {
+            status: Success,
+            message: None,
+        }),
+    )
+}
+
+/// Returns HandleResult
+///
+/// allows admin to send collected creation fees to a treasury address
+///
+/// # Arguments
+///
+/// * `deps` - mutable reference to Extern containing all the contract's external dependencies
+/// * `env` - Env of contract's environment
+/// * `treasury` - address the fees are sent to
+/// * `amount` - optional amount to withdraw. Defaults to all collected fees
+/// * `token` - optional SNIP-20 token whose fees are withdrawn. Defaults to uscrt
+pub fn try_withdraw_fees<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    treasury: HumanAddr,
+    amount: Option<Uint128>,
+    token: Option<ContractInfo>,
+) -> HandleResult {
+    // only allow admin to do this
+    let _config = enforce_admin(deps, &env)?;
+    let treasury = validate_address(&deps.storage, &deps.api, &treasury)?;
+    let (collected, unit) = match &token {
+        Some(token) => (collected_token_fees(&deps.storage, &token.address)?, " tokens"),
+        None => (collected_fees(&deps.storage)?, NATIVE_DENOM),
+    };
+    let withdrawn = amount.map(|amount| amount.u128()).unwrap_or(collected);
+    if withdrawn == 0 || withdrawn > collected {
+        return Err(StdError::generic_err(format!(
+            "Withdrawals must be between 1 and the {}{} of collected fees",
+            collected, unit
+        )));
+    }
+    let message = match token {
+        Some(token) => {
+            let mut fee_store = PrefixedStorage::new(PREFIX_TOKEN_FEES, &mut deps.storage);
+            save(&mut fee_store, token.address.as_str().as_bytes(), &(collected - withdrawn))?;
+            Snip20HandleMsg::Transfer {
+                recipient: treasury.clone(),
+                amount: Uint128(withdrawn),
+            }
+            .to_cosmos_msg(token.code_hash, token.address, None)?
+        }
+        None => {
+            save(&mut deps.storage, COLLECTED_FEES_KEY, &(collected - withdrawn))?;
+            CosmosMsg::Bank(BankMsg::Send {
+                from_address: env.contract.address.clone(),
+                to_address: treasury.clone(),
+                amount: vec![Coin {
+                    denom: NATIVE_DENOM.to_string(),
+                    amount: Uint128(withdrawn),
+                }],
+            })
+        }
+    };
+    record_audit(&mut deps.storage, "withdraw_fees", &env, Some(treasury))?;
+
+    ok_with_log(
+        vec![message],
+        vec![],
+        Some(HandleAnswer::WithdrawFees {
+            status: Success,
+            withdrawn: Uint128(withdrawn),
+            remaining: Uint128(collected - withdrawn),
+        }),
+    )
+}
+
+/// Returns HandleResult
+///
+/// allows admin to allow or disallow an address to create offspring when the creation rules use
+/// an allow list
+///
+/// # Arguments
+///
+/// * `deps` - mutable reference to Extern containing all the contract's external dependencies
+/// * `env` - Env of contract's environment
+/// * `address` - address of the creator
+/// * `allowed` - true if the address may create offspring
+pub fn try_set_creator_allowed<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    address: HumanAddr,
+    allowed: bool,
+) -> HandleResult {
+    // only allow admin to do this
+    let _config = enforce_admin(deps, &env)?;
+    let address = validate_address(&deps.storage, &deps.api, &address)?;
+    set_creator_listed(deps, ALLOWED_CREATORS_KEY, &address, allowed)?;
+    let action = if allowed { "allow_creator" } else { "disallow_creator" };
+    record_audit(&mut deps.storage, action, &env, Some(address))?;
+
+    ok_status()
+}
+
+/// Returns HandleResult
+///
+/// allows admin to deny an address creating offspring, or to lift the denial
+///
+/// # Arguments
+///
+/// * `deps` - mutable reference to Extern containing all the contract's external dependencies
+/// * `env` - Env of contract's environment
+/// * `address` - address of the creator
+/// * `denied` - true if the address may not create offspring
+pub fn try_set_creator_denied<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    address: HumanAddr,
+    denied: bool,
+) -> HandleResult {
+    // only allow admin to do this
+    let _config = enforce_admin(deps, &env)?;
+    let address = validate_address(&deps.storage, &deps.api, &address)?;
+    set_creator_listed(deps, DENIED_CREATORS_KEY, &address, denied)?;
+    let action = if denied { "deny_creator" } else { "undeny_creator" };
+    record_audit(&mut deps.storage, action, &env, Some(address))?;
+
+    ok_status()
+}
+
+/// Returns StdResult<()>
+///
+/// adds an address to a creator list, or removes it
+///
+/// # Arguments
+///
+/// * `deps` - mutable reference to Extern containing all the contract's external dependencies
+/// * `list_key` - storage key of the creator list
+/// * `address` - a reference to the address of the creator
+/// * `listed` - true if the address should be on the list
+fn set_creator_listed<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    list_key: &[u8],
+    address: &HumanAddr,
+    listed: bool,
+) -> StdResult<()> {
+    let creator = deps.api.canonical_address(address)?;
+    let mut list: CashMap<HumanAddr, _> = CashMap::init(list_key, &mut deps.storage);
+    if listed {
+        list.insert(creator.as_slice(), address.clone())
+    } else if list.get(creator.as_slice()).is_some() {
+        list.remove(creator.as_slice())
+    } else {
+        Ok(())
+    }
+}
+
+/// Returns HandleResult
+///
+/// allows admin to hand the admin role over to another address, which must accept it before the
+/// change takes effect. Transferring to the current admin cancels a pending transfer
+///
+/// # Arguments
+///
+/// * `deps` - mutable reference to Extern containing all the contract's external dependencies
+/// * `env` - Env of contract's environment
+/// * `new_admin` - address of the new admin
+pub fn try_transfer_admin<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    new_admin: HumanAddr,
+) -> HandleResult {
+    // only allow admin to do this
+    let mut config = enforce_admin(deps, &env)?;
+    let new_admin = validate_address(&deps.storage, &deps.api, &new_admin)?;
+    let new_admin_raw = deps.api.canonical_address(&new_admin)?;
+    let pending_admin = if new_admin_raw == config.admin {
+        config.pending_admin = None;
+        record_audit(&mut deps.storage, "cancel_admin_transfer", &env, None)?;
+        None
+    } else {
+        config.pending_admin = Some(new_admin_raw);
+        record_audit(&mut deps.storage, "transfer_admin", &env, Some(new_admin.clone()))?;
+        Some(new_admin)
+    };
+    save(&mut deps.storage, CONFIG_KEY, &config)?;
+
+    ok_answer(HandleAnswer::TransferAdmin {
+        status: Success,
+        pending_admin,
+    })
+}
+
+/// Returns HandleResult
+///
+/// accepts the admin role the admin transferred to the sender
+///
+/// # Arguments
+///
+/// * `deps` - mutable reference to Extern containing all the contract's external dependencies
+/// * `env` - Env of contract's environment
+pub fn try_accept_admin<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+) -> HandleResult {
+    let mut config: Config = load(&deps.storage, CONFIG_KEY)?;
+    let sender = deps.api.canonical_address(&env.message.sender)?;
+    if config.pending_admin.as_ref() != Some(&sender) {
+        return Err(StdError::generic_err(
+            "The admin role has not been transferred to this address",
+        ));
+    }
+    config.admin = sender;
+    config.pending_admin = None;
+    save(&mut deps.storage, CONFIG_KEY, &config)?;
+    record_audit(&mut deps.storage, "accept_admin", &env, None)?;
+
+    ok_answer(HandleAnswer::AcceptAdmin {
+        status: Success,
+        admin: env.message.sender,
+    })
+}
+
+/// Returns StdResult<u32>
+///
+/// makes sure a maintenance budget does not exceed the maximum
+///
+/// # Arguments
+///
+/// * `budget` - the maintenance budget to validate
+pub fn valid_maintenance_budget(budget: u32) -> StdResult<u32> {
+    if budget > MAX_MAINTENANCE_BUDGET {
+        return Err(StdError::generic_err(format!(
+            "The maintenance budget can not exceed {}",
+            MAX_MAINTENANCE_BUDGET
+        )));
+    }
+    Ok(budget)
+}
+
+/// Returns HandleResult
+///
+/// allows admin to add, update, or remove an address book entry
+///
+/// # Arguments
+///
+/// * `deps` - mutable reference to Extern containing all the contract's external dependencies
+/// * `env` - Env of contract's environment
+/// * `name` - name the contract is resolved by
+/// * `contract` - code hash and address of the contract, or None to remove the entry
+pub fn try_set_address_book_entry<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    name: String,
+    contract: Option<ContractInfo>,
+) -> HandleResult {
+    // only allow admin to do this
+    let _config = enforce_admin(deps, &env)?;
+    if name.is_empty() || name.len() > MAX_ADDRESS_BOOK_NAME_LEN {
+        return Err(StdError::generic_err(format!(
+            "Address book names must be between 1 and {} bytes long",
+            MAX_ADDRESS_BOOK_NAME_LEN
+        )));
+    }
+    let mut book: CashMap<AddressBookEntry, _> = CashMap::init(ADDRESS_BOOK_KEY, &mut deps.storage);
+    match contract {
+        Some(contract) => {
+            // make sure the address is valid before saving it
+            deps.api.canonical_address(&contract.address)?;
+            book.insert(name.as_bytes(), AddressBookEntry { name: name.clone(), contract })?;
+        }
+        None => {
+            if book.contains(name.as_bytes()) {
+                book.remove(name.as_bytes())?;
+            }
+        }
+    }
+
+    ok_status()
+}
+
+/// Returns HandleResult
+///
+/// allows admin to (dis)allow the creation of new offspring from a specific code id
+///
+/// # Arguments
+///
+/// * `deps` - mutable reference to Extern containing all the contract's external dependencies
+/// * `env` - Env of contract's environment
+/// * `code_id` - code id of the offspring version
+/// * `enabled` - true if offspring of this version may be created
+pub fn try_set_version_status<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    code_id: u64,
+    enabled: bool,
+) -> HandleResult {
+    // only allow admin to do this
+    let _config = enforce_admin(deps, &env)?;
+    let mut disabled_store = PrefixedStorage::new(PREFIX_DISABLED_VERSIONS, &mut deps.storage);
+    if enabled {
+        remove(&mut disabled_store, &code_id.to_be_bytes());
+    } else {
+        save(&mut disabled_store, &code_id.to_be_bytes(), &true)?;
+    }
+
+    ok_status()
+}
+
+/// Returns HandleResult
+///
+/// allows admin to attach a deprecation notice to an offspring version, or to remove it
+///
+/// # Arguments
+///
+/// * `deps` - mutable reference to Extern containing all the contract's external dependencies
+/// * `env` - Env of contract's environment
+/// * `code_hash` - code hash of the offspring version
+/// * `deprecation` - the deprecation notice, or None to remove it
+pub fn try_set_version_deprecation<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    code_hash: String,
+    deprecation: Option<VersionDeprecation>,
+) -> HandleResult {
+    // only allow admin to do this
+    let _config = enforce_admin(deps, &env)?;
+    let mut deprecated_store = PrefixedStorage::new(PREFIX_DEPRECATED_VERSIONS, &mut deps.storage);
+    match deprecation {
+        Some(deprecation) => {
+            if deprecation.note.len() > MAX_DEPRECATION_NOTE_LEN {
+                return Err(StdError::generic_err(format!(
+                    "Deprecation notes can be at most {} bytes long",
+                    MAX_DEPRECATION_NOTE_LEN
+                )));
+            }
+            save(&mut deprecated_store, code_hash.as_bytes(), &deprecation)?;
+            record_audit(&mut deps.storage, "deprecate_version", &env, None)?;
+        }
+        None => {
+            remove(&mut deprecated_store, code_hash.as_bytes());
+            record_audit(&mut deps.storage, "undeprecate_version", &env, None)?;
+        }
+    }
+    // the lists flag offspring created from deprecated versions
+    registry::bump_revision(&mut deps.storage)?;
+
+    ok_status()
+}
+
+/// Returns HandleResult
+///
+/// allows admin to deactivate an active offspring. The offspring is moved to the inactive list
+/// as deactivated by the admin, and is told to stop accepting handles
+///
+/// # Arguments
+///
+/// * `deps` - mutable reference to Extern containing all the contract's external dependencies
+/// * `env` - Env of contract's environment
+/// * `offspring` - address of the offspring to deactivate
+pub fn try_deactivate_offspring_admin<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    offspring: HumanAddr,
+) -> HandleResult {
+    // only allow admin to do this
+    let config = enforce_admin(deps, &env)?;
+    let offspring_addr = deps.api.canonical_address(&offspring)?;
+    let offspring_info = authenticate_offspring(&deps.storage, &offspring_addr)?;
+    let owner: HumanAddr = load(
+        &ReadonlyPrefixedStorage::new(PREFIX_OFFSPRING_OWNER, &deps.storage),
+        offspring_addr.as_slice(),
+    )?;
+    let code_hash = registry::code_hash(&deps.storage, &offspring_addr)?;
+    registry::deactivate(
+        &mut deps.storage,
+        &offspring_addr,
+        &owner,
+        &offspring_info,
+        &env.block,
+        DeactivationSource::Admin,
+    )?;
+    record_usage(&mut deps.storage, &deps.api, &owner, Usage::Deactivated, env.block.height)?;
+    record_audit(&mut deps.storage, "deactivate_offspring", &env, Some(offspring.clone()))?;
+
+    let log = lifecycle_log(
+        "deactivate",
+        Some(&offspring),
+        &owner,
+        &offspring_info.label,
+        &env,
+    );
+    let message = OffspringHandleMsg::DeactivateByFactory {}.to_cosmos_msg(
+        code_hash,
+        offspring,
+        config.callback_policy.deactivate,
+    )?;
+    ok_with_log(
+        vec![message],
+        log,
+        Some(HandleAnswer::Status {
+            status: Success,
+            message: None,
+        }),
+    )
+}
+
+/// Returns HandleResult
+///
+/// allows admin to block an offspring address from registering, or to unblock it. Blocking an
+/// address that is already in the registry opens an issue about it in the issue queue
+///
+/// # Arguments
+///
+/// * `deps` - mutable reference to Extern containing all the contract's external dependencies
+/// * `env` - Env of contract's environment
+/// * `address` - address of the offspring contract
+/// * `blocked` - true to block the address, false to unblock it
+/// * `reason` - optional reason for blocking
+pub fn try_set_address_blocked<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    address: HumanAddr,
+    blocked: bool,
+    reason: Option<String>,
+) -> HandleResult {
+    // only allow admin to do this
+    let _config = enforce_admin(deps, &env)?;
+    if reason.as_ref().map(String::len).unwrap_or(0) > MAX_ISSUE_DETAILS_LEN {
+        return Err(StdError::generic_err(format!(
+            "Block reasons can be at most {} bytes long",
+            MAX_ISSUE_DETAILS_LEN
+        )));
+    }
+    let offspring_addr = deps.api.canonical_address(&address)?;
+    let mut blocked_store = PrefixedStorage::new(PREFIX_BLOCKED_ADDRESSES, &mut deps.storage);
+    if !blocked {
+        remove(&mut blocked_store, offspring_addr.as_slice());
+        record_audit(&mut deps.storage, "unblock_address", &env, Some(address))?;
+        return ok_status();
+    }
+    save(&mut blocked_store, offspring_addr.as_slice(), &reason)?;
+
+    // flag an offspring that is already registered, so the admin can follow up on it
+    let label = match lifecycle_status(&deps.storage, &offspring_addr, env.block.height) {
+        LifecycleStatus::Active { info } => Some(info.label),
+        LifecycleStatus::Inactive { info } => Some(info.label),
+        _ => None,
+    };
+    if let Some(label) = label {
+        open_issue(
+            &mut deps.storage,
+            &offspring_addr,
+            address.clone(),
+            label,
+            "blocked address".to_string(),
+            reason.unwrap_or_default(),
+            &env,
+        )?;
+    }
+    record_audit(&mut deps.storage, "block_address", &env, Some(address))?;
+
+    ok_status()
+}
+
+/// Returns StdResult<()>
+///
+/// makes sure the admin did not block an offspring address
+///
+/// # Arguments
+///
+/// * `storage` - a reference to contract's storage
+/// * `offspring_addr` - a reference to the canonical address of the offspring
+pub fn enforce_not_blocked<S: ReadonlyStorage>(
+    storage: &S,
+    offspring_addr: &CanonicalAddr,
+) -> StdResult<()> {
+    if is_blocked(storage, offspring_addr)? {
+        return Err(StdError::generic_err(
+            "This offspring address is blocked by the factory admin",
+        ));
+    }
+    Ok(())
+}
+
+/// Returns StdResult<bool> indicating whether the admin blocked an offspring address
+///
+/// # Arguments
+///
+/// * `storage` - a reference to contract's storage
+/// * `offspring_addr` - a reference to the canonical address of the offspring
+pub fn is_blocked<S: ReadonlyStorage>(storage: &S, offspring_addr: &CanonicalAddr) -> StdResult<bool> {
+    let blocked_store = ReadonlyPrefixedStorage::new(PREFIX_BLOCKED_ADDRESSES, storage);
+    let reason: Option<Option<String>> = may_load(&blocked_store, offspring_addr.as_slice())?;
+    Ok(reason.is_some())
+}
+
+/// Returns HandleResult
+///
+/// allows admin to see an owner's offspring listing the way the owner sees it, recording the
+/// use in the audit log
+///
+/// # Arguments
+///
+/// * `deps` - mutable reference to Extern containing all the contract's external dependencies
+/// * `env` - Env of contract's environment
+/// * `owner` - address whose offspring should be listed
+/// * `filter` - optional choice of display filters
+/// * `start_page` - optional start page for the offsprings returned and listed
+/// * `page_size` - optional number of offspring to return in this page
+pub fn try_list_of_owner_admin<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    owner: HumanAddr,
+    filter: Option<FilterTypes>,
+    start_page: Option<u32>,
+    page_size: Option<u32>,
+) -> HandleResult {
+    // only allow admin to do this
+    let _config = enforce_admin(deps, &env)?;
+    record_audit(
+        &mut deps.storage,
+        "list_offspring_of_owner",
+        &env,
+        Some(owner.clone()),
+    )?;
+    let page = list_owner_offspring(deps, &owner, filter, start_page, page_size, false, None)?;
+    let (active, inactive) = (page.active, page.inactive);
+    let deprecated = deprecated_among(deps, &active, &inactive)?;
+    let owner_addr = deps.api.canonical_address(&owner)?;
+    let note_store = ReadonlyPrefixedStorage::new(PREFIX_OWNER_NOTES, &deps.storage);
+    let note = match may_load::<OwnerNote, _>(&note_store, owner_addr.as_slice())? {
+        Some(mut note) => {
+            note.note = open(&deps.storage, Some(note.note))?.unwrap_or_default();
+            Some(note)
+        }
+        None => None,
+    };
+
+    ok_answer(HandleAnswer::ListOffspringOfOwnerAdmin {
+        active,
+        inactive,
+        deprecated,
+        note,
+    })
+}
+
+/// Returns HandleResult
+///
+/// allows admin to attach a private support note to an owner, replacing any previous note
+///
+/// # Arguments
+///
+/// * `deps` - mutable reference to Extern containing all the contract's external dependencies
+/// * `env` - Env of contract's environment
+/// * `owner` - address of the owner
+/// * `note` - the note
+pub fn try_set_owner_note<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    owner: HumanAddr,
+    note: String,
+) -> HandleResult {
+    // only allow admin to do this
+    let _config = enforce_admin(deps, &env)?;
+    if note.trim().is_empty() || note.len() > MAX_OWNER_NOTE_LEN {
+        return Err(StdError::generic_err(format!(
+            "Owner notes must be between 1 and {} bytes long",
+            MAX_OWNER_NOTE_LEN
+        )));
+    }
+    let owner_addr = deps.api.canonical_address(&owner)?;
+    let note = OwnerNote {
+        note: seal_text(&deps.storage, owner_addr.as_slice(), &note)?,
+        height: env.block.height,
+        time: env.block.time,
+    };
+    let mut note_store = PrefixedStorage::new(PREFIX_OWNER_NOTES, &mut deps.storage);
+    save(&mut note_store, owner_addr.as_slice(), &note)?;
+    record_audit(&mut deps.storage, "set_owner_note", &env, Some(owner))?;
+
+    ok_status()
+}
+
+/// Returns HandleResult
+///
+/// allows admin to remove the note about an owner
+///
+/// # Arguments
+///
+/// * `deps` - mutable reference to Extern containing all the contract's external dependencies
+/// * `env` - Env of contract's environment
+/// * `owner` - address of the owner
+pub fn try_clear_owner_note<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    owner: HumanAddr,
+) -> HandleResult {
+    // only allow admin to do this
+    let _config = enforce_admin(deps, &env)?;
+    let owner_addr = deps.api.canonical_address(&owner)?;
+    let mut note_store = PrefixedStorage::new(PREFIX_OWNER_NOTES, &mut deps.storage);
+    remove(&mut note_store, owner_addr.as_slice());
+    record_audit(&mut deps.storage, "clear_owner_note", &env, Some(owner))?;
+
+    ok_status()
+}
+
+/// Returns HandleResult
+///
+/// allows admin to execute several admin actions in order. Since a failing action fails the whole
+/// transaction, either all actions take effect or none do
+///
+/// # Arguments
+///
+/// * `deps` - mutable reference to Extern containing all the contract's external dependencies
+/// * `env` - Env of contract's environment
+/// * `actions` - the admin actions to execute
+pub fn try_admin_batch<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    actions: Vec<AdminAction>,
+) -> HandleResult {
+    // only allow admin to do this
+    enforce_admin(deps, &env)?;
+    if actions.is_empty() {
+        return Err(StdError::generic_err("An admin batch needs at least one action"));
+    }
+    if actions.len() > MAX_ADMIN_BATCH {
+        return Err(StdError::generic_err(format!(
+            "An admin batch can have at most {} actions",
+            MAX_ADMIN_BATCH
+        )));
+    }
+
+    let executed = actions.len() as u32;
+    let mut messages = vec![];
+    let mut log = vec![];
+    for action in actions {
+        let response = match action {
+            AdminAction::NewOffspringContract { offspring_contract } => {
+                try_new_contract(deps, env.clone(), offspring_contract)
+            }
+            AdminAction::RollbackVersion {} => try_rollback_version(deps, env.clone()),
+            AdminAction::SetStatus { stop } => try_set_status(deps, env.clone(), stop),
+            AdminAction::SetDefaultPageSize { page_size } => {
+                try_set_default_page_size(deps, env.clone(), page_size)
+            }
+            AdminAction::SetAddressBookEntry { name, contract } => {
+                try_set_address_book_entry(deps, env.clone(), name, contract)
+            }
+            AdminAction::SetVersionStatus { code_id, enabled } => {
+                try_set_version_status(deps, env.clone(), code_id, enabled)
+            }
+            AdminAction::SetAddressBlocked {
+                address,
+                blocked,
+                reason,
+            } => try_set_address_blocked(deps, env.clone(), address, blocked, reason),
+        }?;
+        messages.extend(response.messages);
+        log.extend(response.log);
+    }
+
+    ok_with_log(
+        messages,
+        log,
+        Some(HandleAnswer::AdminBatch {
+            status: Success,
+            executed,
+        }),
+    )
+}
@@ -0,0 +1,274 @@
+use cosmwasm_std::{from_binary, to_binary, Binary, HumanAddr, QueryResult, StdError, StdResult};
+
+use serde::Serialize;
+
+use crate::msg::{
+    CountPrivacy, OffspringAttribute, QueryAnswer, QueryMsg, StoreInactiveOffspringInfo,
+    StoreOffspringInfo, VersionedAnswer,
+};
+use crate::state::{API_VERSION, OLDEST_API_VERSION};
+
+/// an answer in the format of the requested api version
+// the body is only built to be serialized once, so its size does not matter
+#[allow(clippy::large_enum_variant)]
+#[derive(Serialize)]
+#[serde(untagged)]
+enum VersionedBody {
+    /// the answer's format did not change since the requested version
+    Current(QueryAnswer),
+    /// the answer in the format of api version 1
+    V1(QueryAnswerV1),
+    /// the answer in the format of api version 2
+    V2(QueryAnswerV2),
+    /// the answer in the format of api version 3
+    V3(QueryAnswerV3),
+}
+
+/// the answers whose format changed after api version 1
+#[derive(Serialize)]
+#[serde(rename_all = "snake_case")]
+enum QueryAnswerV1 {
+    ListMyOffspring {
+        #[serde(skip_serializing_if = "Option::is_none")]
+        active: Option<Vec<StoreOffspringInfo>>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        inactive: Option<Vec<InactiveOffspringInfoV1>>,
+        deprecated: Vec<HumanAddr>,
+    },
+    ListInactiveOffspring {
+        inactive: Vec<InactiveOffspringInfoV1>,
+        deprecated: Vec<HumanAddr>,
+    },
+}
+
+/// the answers whose format changed after api version 2, before list answers carried the
+/// registry revision
+#[derive(Serialize)]
+#[serde(rename_all = "snake_case")]
+#[allow(clippy::enum_variant_names)]
+enum QueryAnswerV2 {
+    ListMyOffspring {
+        #[serde(skip_serializing_if = "Option::is_none")]
+        active: Option<Vec<StoreOffspringInfo>>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        inactive: Option<Vec<StoreInactiveOffspringInfo>>,
+        deprecated: Vec<HumanAddr>,
+    },
+    ListActiveOffspring {
+        active: Vec<StoreOffspringInfo>,
+        deprecated: Vec<HumanAddr>,
+    },
+    ListInactiveOffspring {
+        inactive: Vec<StoreInactiveOffspringInfo>,
+        deprecated: Vec<HumanAddr>,
+    },
+}
+
+/// the answers whose format changed after api version 3, before list answers carried
+/// pagination metadata
+#[derive(Serialize)]
+#[serde(rename_all = "snake_case")]
+#[allow(clippy::enum_variant_names)]
+enum QueryAnswerV3 {
+    ListMyOffspring {
+        #[serde(skip_serializing_if = "Option::is_none")]
+        active: Option<Vec<StoreOffspringInfo>>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        inactive: Option<Vec<StoreInactiveOffspringInfo>>,
+        deprecated: Vec<HumanAddr>,
+        revision: u64,
+    },
+    ListActiveOffspring {
+        active: Vec<StoreOffspringInfo>,
+        deprecated: Vec<HumanAddr>,
+        revision: u64,
+    },
+    ListInactiveOffspring {
+        inactive: Vec<StoreInactiveOffspringInfo>,
+        deprecated: Vec<HumanAddr>,
+        revision: u64,
+    },
+}
+
+/// inactive offspring info in the format of api version 1, before it recorded who or what
+/// deactivated the offspring
+#[derive(Serialize)]
+struct InactiveOffspringInfoV1 {
+    address: HumanAddr,
+    label: String,
+    description: Option<String>,
+    count_privacy: Option<CountPrivacy>,
+    attributes: Vec<OffspringAttribute>,
+    created_height: u64,
+    created_time: u64,
+    deactivated_height: u64,
+    deactivated_time: u64,
+    checksum: u32,
+}
+
+impl From<StoreInactiveOffspringInfo> for InactiveOffspringInfoV1 {
+    fn from(info: StoreInactiveOffspringInfo) -> Self {
+        InactiveOffspringInfoV1 {
+            address: info.address,
+            label: info.label,
+            description: info.description,
+            count_privacy: info.count_privacy,
+            attributes: info.attributes,
+            created_height: info.created_height,
+            created_time: info.created_time,
+            deactivated_height: info.deactivated_height,
+            deactivated_time: info.deactivated_time,
+            checksum: info.checksum,
+        }
+    }
+}
+
+/// Returns StdResult<u32> with the api version a versioned query should be answered in
+///
+/// # Arguments
+///
+/// * `api_version` - optional requested api version. Defaults to the current version
+/// * `query` - a reference to the wrapped query
+pub fn resolve_api_version(api_version: Option<u32>, query: &QueryMsg) -> StdResult<u32> {
+    if let QueryMsg::Versioned { .. } = query {
+        return Err(StdError::generic_err("Versioned queries can not be nested"));
+    }
+    let api_version = api_version.unwrap_or(API_VERSION);
+    if !(OLDEST_API_VERSION..=API_VERSION).contains(&api_version) {
+        return Err(StdError::generic_err(format!(
+            "Api version {} is not supported. Supported versions are {} to {}",
+            api_version, OLDEST_API_VERSION, API_VERSION
+        )));
+    }
+    Ok(api_version)
+}
+
+/// Returns QueryResult with the answer converted to the requested api version, wrapped in a
+/// VersionedAnswer
+///
+/// # Arguments
+///
+/// * `api_version` - the api version to answer in
+/// * `answer` - the answer in the current format
+pub fn wrap_answer(api_version: u32, answer: Binary) -> QueryResult {
+    let answer: QueryAnswer = from_binary(&answer)?;
+    let body = match api_version {
+        1 => to_v1(answer),
+        2 => to_v2(answer),
+        3 => to_v3(answer),
+        _ => VersionedBody::Current(answer),
+    };
+    to_binary(&VersionedAnswer { api_version, body })
+}
+
+/// Returns VersionedBody with the answer in the format of api version 1
+///
+/// # Arguments
+///
+/// * `answer` - the answer in the current format
+fn to_v1(answer: QueryAnswer) -> VersionedBody {
+    let downgrade = |inactive: Vec<StoreInactiveOffspringInfo>| {
+        inactive
+            .into_iter()
+            .map(InactiveOffspringInfoV1::from)
+            .collect()
+    };
+    match answer {
+        QueryAnswer::ListMyOffspring {
+            active,
+            inactive,
+            deprecated,
+            ..
+        } => VersionedBody::V1(QueryAnswerV1::ListMyOffspring {
+            active,
+            inactive: inactive.map(downgrade),
+            deprecated,
+        }),
+        QueryAnswer::ListInactiveOffspring {
+            inactive,
+            deprecated,
+            ..
+        } => VersionedBody::V1(QueryAnswerV1::ListInactiveOffspring {
+            inactive: downgrade(inactive),
+            deprecated,
+        }),
+        // the other answers did not change between api versions 1 and 2
+        answer => to_v2(answer),
+    }
+}
+
+/// Returns VersionedBody with the answer in the format of api version 2
+///
+/// # Arguments
+///
+/// * `answer` - the answer in the current format
+fn to_v2(answer: QueryAnswer) -> VersionedBody {
+    match answer {
+        QueryAnswer::ListMyOffspring {
+            active,
+            inactive,
+            deprecated,
+            ..
+        } => VersionedBody::V2(QueryAnswerV2::ListMyOffspring {
+            active,
+            inactive,
+            deprecated,
+        }),
+        QueryAnswer::ListActiveOffspring {
+            active, deprecated, ..
+        } => VersionedBody::V2(QueryAnswerV2::ListActiveOffspring { active, deprecated }),
+        QueryAnswer::ListInactiveOffspring {
+            inactive,
+            deprecated,
+            ..
+        } => VersionedBody::V2(QueryAnswerV2::ListInactiveOffspring {
+            inactive,
+            deprecated,
+        }),
+        // the other answers did not change between api versions 2 and 3
+        answer => to_v3(answer),
+    }
+}
+
+/// Returns VersionedBody with the answer in the format of api version 3
+///
+/// # Arguments
+///
+/// * `answer` - the answer in the current format
+fn to_v3(answer: QueryAnswer) -> VersionedBody {
+    match answer {
+        QueryAnswer::ListMyOffspring {
+            active,
+            inactive,
+            deprecated,
+            revision,
+            ..
+        } => VersionedBody::V3(QueryAnswerV3::ListMyOffspring {
+            active,
+            inactive,
+            deprecated,
+            revision,
+        }),
+        QueryAnswer::ListActiveOffspring {
+            active,
+            deprecated,
+            revision,
+            ..
+        } => VersionedBody::V3(QueryAnswerV3::ListActiveOffspring {
+            active,
+            deprecated,
+            revision,
+        }),
+        QueryAnswer::ListInactiveOffspring {
+            inactive,
+            deprecated,
+            revision,
+            ..
+        } => VersionedBody::V3(QueryAnswerV3::ListInactiveOffspring {
+            inactive,
+            deprecated,
+            revision,
+        }),
+        answer => VersionedBody::Current(answer),
+    }
+}
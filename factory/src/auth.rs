@@ -0,0 +1,313 @@
+use cosmwasm_std::{
+    Api, Env, Extern, HandleResult, HumanAddr, Querier, ReadonlyStorage, StdError, StdResult,
+    Storage,
+};
+
+use cosmwasm_storage::{PrefixedStorage, ReadonlyPrefixedStorage};
+
+use secret_toolkit_viewing_key::{ViewingKey, ViewingKeyStore};
+
+use crate::state::{
+    load, may_load, remove, save, Config, OwnerSettings, CONFIG_KEY, PREFIX_CREATE_DELEGATES,
+    PREFIX_DELEGATES, PREFIX_HAS_KEY, PREFIX_OWNER_SETTINGS,
+};
+
+use crate::{
+    addr::validate_address,
+    creation::enforce_entropy,
+    msg::HandleAnswer,
+    queries::valid_page_size,
+    response::{ok_answer, ok_status},
+};
+
+/// Returns HandleResult
+///
+/// create a viewing key
+///
+/// # Arguments
+///
+/// * `deps` - mutable reference to Extern containing all the contract's external dependencies
+/// * `env` - Env of contract's environment
+/// * `entropy` - string to be used as an entropy source for randomization
+pub fn try_create_key<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    entropy: String,
+) -> HandleResult {
+    let config: Config = load(&deps.storage, CONFIG_KEY)?;
+    enforce_entropy(&config, &entropy)?;
+    let key = ViewingKey::create(&mut deps.storage, &env, &env.message.sender, entropy.as_bytes());
+    mark_viewing_key(&mut deps.storage, &deps.api, &env.message.sender)?;
+
+    ok_answer(HandleAnswer::ViewingKey {
+        key: format!("{}", key),
+    })
+}
+
+/// Returns HandleResult
+///
+/// sets the viewing key
+///
+/// # Arguments
+///
+/// * `deps` - mutable reference to Extern containing all the contract's external dependencies
+/// * `env` - Env of contract's environment
+/// * `key` - string slice to be used as the viewing key
+pub fn try_set_key<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    key: &str,
+) -> HandleResult {
+    ViewingKey::set(&mut deps.storage, &env.message.sender, key);
+    mark_viewing_key(&mut deps.storage, &deps.api, &env.message.sender)?;
+
+    ok_answer(HandleAnswer::ViewingKey {
+        key: key.to_string(),
+    })
+}
+
+/// Returns StdResult<()>
+///
+/// remembers that an address has a viewing key
+///
+/// # Arguments
+///
+/// * `storage` - mutable reference to the contract's storage
+/// * `api` - reference to the Api used to convert human and canonical addresses
+/// * `address` - a reference to the address that has a viewing key
+pub fn mark_viewing_key<S: Storage, A: Api>(
+    storage: &mut S,
+    api: &A,
+    address: &HumanAddr,
+) -> StdResult<()> {
+    let address_raw = api.canonical_address(address)?;
+    let mut has_key_store = PrefixedStorage::new(PREFIX_HAS_KEY, storage);
+    save(&mut has_key_store, address_raw.as_slice(), &true)
+}
+
+/// Returns StdResult<bool> result of checking whether an address has a viewing key
+///
+/// # Arguments
+///
+/// * `storage` - a reference to the contract's storage
+/// * `api` - reference to the Api used to convert human and canonical addresses
+/// * `address` - a reference to the address to check
+pub fn has_viewing_key<S: ReadonlyStorage, A: Api>(
+    storage: &S,
+    api: &A,
+    address: &HumanAddr,
+) -> StdResult<bool> {
+    let address_raw = api.canonical_address(address)?;
+    let has_key_store = ReadonlyPrefixedStorage::new(PREFIX_HAS_KEY, storage);
+    let has_key: Option<bool> = may_load(&has_key_store, address_raw.as_slice())?;
+    Ok(has_key.unwrap_or(false))
+}
+
+/// Returns HandleResult
+///
+/// sets the sender's own settings
+///
+/// # Arguments
+///
+/// * `deps` - mutable reference to Extern containing all the contract's external dependencies
+/// * `env` - Env of contract's environment
+/// * `page_size` - optional page size used when the sender's queries do not specify one
+pub fn try_set_owner_settings<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    page_size: Option<u32>,
+) -> HandleResult {
+    let page_size = page_size.map(valid_page_size).transpose()?;
+    let sender = deps.api.canonical_address(&env.message.sender)?;
+    let mut settings_store = PrefixedStorage::new(PREFIX_OWNER_SETTINGS, &mut deps.storage);
+    let mut settings: OwnerSettings =
+        may_load(&settings_store, sender.as_slice())?.unwrap_or_default();
+    settings.page_size = page_size;
+    save(&mut settings_store, sender.as_slice(), &settings)?;
+
+    ok_status()
+}
+
+/// Returns HandleResult
+///
+/// authorizes or revokes a delegate of the sender
+///
+/// # Arguments
+///
+/// * `deps` - mutable reference to Extern containing all the contract's external dependencies
+/// * `env` - Env of contract's environment
+/// * `delegate` - a reference to the address of the delegate
+/// * `allowed` - true to authorize the delegate, false to revoke it
+pub fn try_set_delegate<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    delegate: &HumanAddr,
+    allowed: bool,
+) -> HandleResult {
+    let delegate = validate_address(&deps.storage, &deps.api, delegate)?;
+    let key = [
+        deps.api.canonical_address(&env.message.sender)?.as_slice(),
+        deps.api.canonical_address(&delegate)?.as_slice(),
+    ]
+    .concat();
+    let mut delegate_store = PrefixedStorage::new(PREFIX_DELEGATES, &mut deps.storage);
+    if allowed {
+        save(&mut delegate_store, &key, &true)?;
+    } else {
+        remove(&mut delegate_store, &key);
+    }
+
+    ok_status()
+}
+
+/// Returns StdResult<bool> result of checking whether an address is a delegate of an owner
+///
+/// # Arguments
+///
+/// * `storage` - a reference to the contract's storage
+/// * `api` - reference to the Api used to convert human and canonical addresses
+/// * `owner` - a reference to the owner
+/// * `delegate` - a reference to the address that may be the owner's delegate
+pub fn is_delegate<S: ReadonlyStorage, A: Api>(
+    storage: &S,
+    api: &A,
+    owner: &HumanAddr,
+    delegate: &HumanAddr,
+) -> StdResult<bool> {
+    let key = [
+        api.canonical_address(owner)?.as_slice(),
+        api.canonical_address(delegate)?.as_slice(),
+    ]
+    .concat();
+    let delegate_store = ReadonlyPrefixedStorage::new(PREFIX_DELEGATES, storage);
+    let allowed: Option<bool> = may_load(&delegate_store, &key)?;
+    Ok(allowed.unwrap_or(false))
+}
+
+/// Returns HandleResult
+///
+/// grants or revokes an address' right to create offspring owned by the sender
+///
+/// # Arguments
+///
+/// * `deps` - mutable reference to Extern containing all the contract's external dependencies
+/// * `env` - Env of contract's environment
+/// * `creator` - a reference to the address creating offspring on the sender's behalf
+/// * `allowed` - true to grant the right, false to revoke it
+pub fn try_set_create_delegate<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    creator: &HumanAddr,
+    allowed: bool,
+) -> HandleResult {
+    let creator = validate_address(&deps.storage, &deps.api, creator)?;
+    let key = [
+        deps.api.canonical_address(&env.message.sender)?.as_slice(),
+        deps.api.canonical_address(&creator)?.as_slice(),
+    ]
+    .concat();
+    let mut delegate_store = PrefixedStorage::new(PREFIX_CREATE_DELEGATES, &mut deps.storage);
+    if allowed {
+        save(&mut delegate_store, &key, &true)?;
+    } else {
+        remove(&mut delegate_store, &key);
+    }
+
+    ok_status()
+}
+
+/// Returns StdResult<()>
+///
+/// makes sure the sender may create offspring for the owner. Anyone may unless the factory
+/// requires the owner to be the sender, in which case only the owner and the addresses it granted
+/// creation rights to may
+///
+/// # Arguments
+///
+/// * `storage` - a reference to the contract's storage
+/// * `api` - reference to the Api used to convert human and canonical addresses
+/// * `config` - a reference to the factory config
+/// * `owner` - a reference to the owner of the new offspring
+/// * `sender` - a reference to the address creating the offspring
+pub fn enforce_create_rights<S: ReadonlyStorage, A: Api>(
+    storage: &S,
+    api: &A,
+    config: &Config,
+    owner: &HumanAddr,
+    sender: &HumanAddr,
+) -> StdResult<()> {
+    if !config.require_owner_is_sender || owner == sender {
+        return Ok(());
+    }
+    let key = [
+        api.canonical_address(owner)?.as_slice(),
+        api.canonical_address(sender)?.as_slice(),
+    ]
+    .concat();
+    let delegate_store = ReadonlyPrefixedStorage::new(PREFIX_CREATE_DELEGATES, storage);
+    let allowed: Option<bool> = may_load(&delegate_store, &key)?;
+    if !allowed.unwrap_or(false) {
+        return Err(StdError::generic_err(
+            "The owner has not granted you the right to create offspring on their behalf",
+        ));
+    }
+    Ok(())
+}
+
+/// Returns bool result of validating an address' viewing key
+///
+/// # Arguments
+///
+/// * `storage` - a reference to the contract's storage
+/// * `address` - a reference to the address whose key should be validated
+/// * `viewing_key` - String key used for authentication
+pub fn is_key_valid<S: ReadonlyStorage>(
+    storage: &S,
+    address: &HumanAddr,
+    viewing_key: String,
+) -> bool {
+    return ViewingKey::check(storage, address, &viewing_key).is_ok();
+}
+
+/// Returns StdResult<bool> result of validating that the address is the admin and the viewing
+/// key is valid
+///
+/// # Arguments
+///
+/// * `deps` - reference to Extern containing all the contract's external dependencies
+/// * `address` - a reference to the address claiming to be the admin
+/// * `viewing_key` - String key used for authentication
+pub fn is_admin_key_valid<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    address: &HumanAddr,
+    viewing_key: String,
+) -> StdResult<bool> {
+    let config: Config = load(&deps.storage, CONFIG_KEY)?;
+    Ok(config.admin == deps.api.canonical_address(address)?
+        && is_key_valid(&deps.storage, address, viewing_key))
+}
+
+/// Returns StdResult<bool>
+///
+/// determines whether the full offspring lists may be displayed. Lists are public unless the
+/// factory uses private listing, in which case any valid address/viewing key pair is required
+///
+/// # Arguments
+///
+/// * `storage` - a reference to the contract's storage
+/// * `address` - optional address to authenticate as
+/// * `viewing_key` - optional viewing key of the address
+pub fn may_view_full_list<S: ReadonlyStorage>(
+    storage: &S,
+    address: Option<HumanAddr>,
+    viewing_key: Option<String>,
+) -> StdResult<bool> {
+    let config: Config = load(storage, CONFIG_KEY)?;
+    if !config.private_listing {
+        return Ok(true);
+    }
+    match (address, viewing_key) {
+        (Some(address), Some(key)) => Ok(is_key_valid(storage, &address, key)),
+        _ => Ok(false),
+    }
+}
@@ -0,0 +1,143 @@
+use cosmwasm_std::{debug_print, StdError, StdResult};
+
+use secret_toolkit::serialization::{Bincode2, Serde};
+
+use serde::Serialize;
+
+use crate::msg::{StoreInactiveOffspringInfo, StoreOffspringInfo};
+
+/// registry records that carry a checksum of their other fields
+pub trait Checksummed: Serialize + Clone {
+    /// Returns the checksum stored in the record
+    fn checksum(&self) -> u32;
+    /// replaces the checksum stored in the record
+    fn set_checksum(&mut self, checksum: u32);
+    /// Returns the address of the offspring the record belongs to, for diagnostics
+    fn address(&self) -> &str;
+}
+
+impl Checksummed for StoreOffspringInfo {
+    fn checksum(&self) -> u32 {
+        self.checksum
+    }
+    fn set_checksum(&mut self, checksum: u32) {
+        self.checksum = checksum;
+    }
+    fn address(&self) -> &str {
+        self.address.as_str()
+    }
+}
+
+impl Checksummed for StoreInactiveOffspringInfo {
+    fn checksum(&self) -> u32 {
+        self.checksum
+    }
+    fn set_checksum(&mut self, checksum: u32) {
+        self.checksum = checksum;
+    }
+    fn address(&self) -> &str {
+        self.address.as_str()
+    }
+}
+
+/// Returns StdResult<u32> with the crc32 of the record serialized with its checksum zeroed
+///
+/// # Arguments
+///
+/// * `record` - a reference to the registry record
+fn compute<T: Checksummed>(record: &T) -> StdResult<u32> {
+    let mut zeroed = record.clone();
+    zeroed.set_checksum(0);
+    Ok(crc32fast::hash(&Bincode2::serialize(&zeroed)?))
+}
+
+/// Returns StdResult<T> with the record's checksum set to match its fields. Call this right
+/// before a record is stored
+///
+/// # Arguments
+///
+/// * `record` - the registry record
+pub fn stamp<T: Checksummed>(mut record: T) -> StdResult<T> {
+    let checksum = compute(&record)?;
+    record.set_checksum(checksum);
+    Ok(record)
+}
+
+/// Returns bool indicating whether the record's checksum matches its fields
+///
+/// # Arguments
+///
+/// * `record` - a reference to the registry record
+pub fn is_intact<T: Checksummed>(record: &T) -> bool {
+    compute(record)
+        .map(|checksum| checksum == record.checksum())
+        .unwrap_or(false)
+}
+
+/// Returns StdResult<T> with the record if its checksum matches, or an error if it is corrupted
+///
+/// # Arguments
+///
+/// * `record` - the registry record read from storage
+pub fn verify<T: Checksummed>(record: T) -> StdResult<T> {
+    if !is_intact(&record) {
+        return Err(StdError::generic_err(format!(
+            "The registry record of {} is corrupted",
+            record.address()
+        )));
+    }
+    Ok(record)
+}
+
+/// Returns Vec<T> with the corrupted records of a listed page left out, so one bad record does
+/// not fail the whole page
+///
+/// # Arguments
+///
+/// * `list` - the registry records read from storage
+pub fn retain_intact<T: Checksummed>(list: Vec<T>) -> Vec<T> {
+    list.into_iter()
+        .filter(|record| {
+            let intact = is_intact(record);
+            if !intact {
+                debug_print!("skipping corrupted registry record of {}", record.address());
+            }
+            intact
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cosmwasm_std::HumanAddr;
+
+    /// This test checks that a record whose fields changed after it was stamped is detected, and
+    /// left out of a listed page without failing it.
+    #[test]
+    fn test_detects_corruption() {
+        let record = |label: &str| {
+            stamp(StoreOffspringInfo {
+                address: HumanAddr(format!("offspring {}", label)),
+                label: label.to_string(),
+                description: None,
+                description_truncated: false,
+                metadata_url: None,
+                count_privacy: None,
+                attributes: vec![],
+                created_height: 1,
+                created_time: 1,
+                checksum: 0,
+            })
+            .unwrap()
+        };
+        let mut corrupted = record("corrupted");
+        corrupted.label = "tampered".to_string();
+        assert!(verify(record("intact")).is_ok());
+        assert!(verify(corrupted.clone()).is_err());
+
+        let listed = retain_intact(vec![record("first"), corrupted, record("last")]);
+        let labels: Vec<&str> = listed.iter().map(|info| info.label.as_str()).collect();
+        assert_eq!(labels, vec!["first", "last"]);
+    }
+}
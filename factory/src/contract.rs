@@ -1,35 +1,38 @@
 use cosmwasm_std::{
-    log, to_binary, Api, CanonicalAddr, Env, Extern, HandleResponse, HandleResult, HumanAddr,
-    InitResponse, InitResult, Querier, QueryResult, ReadonlyStorage, StdError, StdResult, Storage,
+    Api, CosmosMsg, Env, Extern, HandleResult, InitResponse, InitResult, Querier, QueryResult,
+    StdError, Storage,
 };
 
-use cosmwasm_storage::{PrefixedStorage, ReadonlyPrefixedStorage};
+use secret_toolkit::utils::{pad_handle_result, pad_query_result};
 
-use secret_toolkit::{
-    utils::{pad_handle_result, pad_query_result, InitCallback},
-    
-};
-
-use secret_toolkit_viewing_key::{ViewingKey, ViewingKeyStore};
-
-use secret_toolkit_incubator::{CashMap, ReadOnlyCashMap};
-
-use crate::{rand::sha_256, state::DEFAULT_PAGE_SIZE};
 use crate::state::{
-    load, may_load, remove, save, Config, ACTIVE_KEY, BLOCK_SIZE, CONFIG_KEY, PENDING_KEY, INACTIVE_KEY, PREFIX_OWNERS_ACTIVE, PREFIX_OWNERS_INACTIVE,
-    PRNG_SEED_KEY,
+    save, Config, BLOCK_SIZE, CONFIG_KEY, DEFAULT_MAINTENANCE_BUDGET, LAST_HEIGHT_KEY,
+    DEFAULT_MIN_ENTROPY_DISTINCT, DEFAULT_MIN_ENTROPY_LEN, DEFAULT_PAGE_SIZE,
+    DEFAULT_REGISTRATION_RETRY_BLOCKS, DEFAULT_STATS_CHECKPOINT_INTERVAL,
+    DEFAULT_TOMBSTONE_RETENTION, MAX_BOOTSTRAP_OFFSPRING,
+    PRNG_SEED_KEY, REGISTRY_SCHEMA_VERSION, SCHEMA_VERSION_KEY,
 };
 
 use crate::{
-    msg::{
-        ContractInfo, FilterTypes, HandleAnswer, HandleMsg, InitMsg,
-        OffspringContractInfo, QueryAnswer, QueryMsg, RegisterOffspringInfo,
-        ResponseStatus::Success, StoreInactiveOffspringInfo, StoreOffspringInfo,
-    },
-    offspring_msg::OffspringInitMsg,
-    rand::Prng,
+    admin::{self, valid_maintenance_budget},
+    api_version,
+    auth, creation,
+    features::{self, initial_features},
+    issues, maintenance,
+    msg::{HandleMsg, InitMsg, QueryMsg},
+    permits, quarantine,
+    queries::{self, valid_page_size},
+    recovery,
+    registry, snapshot, stats, transfer,
+    validators::{self, register_fee_token, valid_code_hash, valid_creation_rules},
+    versions::{self, add_version, load_version},
 };
 
+pub use crate::creation::new_entropy;
+
+use crate::creation::{derive_init_seed, enforce_entropy};
+use crate::sealing::init_sealing_key;
+
 ////////////////////////////////////// Init ///////////////////////////////////////
 /// Returns InitResult
 ///
@@ -45,18 +48,79 @@ pub fn init<S: Storage, A: Api, Q: Querier>(
     env: Env,
     msg: InitMsg,
 ) -> InitResult {
-    let prng_seed: Vec<u8> = sha_256(base64::encode(msg.entropy).as_bytes()).to_vec();
-
+    valid_code_hash(&msg.offspring_contract.code_hash)?;
     let config = Config {
         version: msg.offspring_contract,
+        previous_version: None,
         stopped: false,
         admin: deps.api.canonical_address(&env.message.sender)?,
+        pending_admin: None,
+        tombstone_retention: msg.tombstone_retention.unwrap_or(DEFAULT_TOMBSTONE_RETENTION),
+        default_page_size: valid_page_size(msg.default_page_size.unwrap_or(DEFAULT_PAGE_SIZE))?,
+        private_listing: msg.private_listing.unwrap_or(false),
+        auto_viewing_key: msg.auto_viewing_key.unwrap_or(false),
+        require_registration_same_tx: msg.require_registration_same_tx.unwrap_or(false),
+        require_owner_is_sender: msg.require_owner_is_sender.unwrap_or(false),
+        min_entropy_len: msg.min_entropy_len.unwrap_or(DEFAULT_MIN_ENTROPY_LEN),
+        min_entropy_distinct: msg.min_entropy_distinct.unwrap_or(DEFAULT_MIN_ENTROPY_DISTINCT),
+        maintenance_budget: valid_maintenance_budget(
+            msg.maintenance_budget.unwrap_or(DEFAULT_MAINTENANCE_BUDGET),
+        )?,
+        contract_address: env.contract.address.clone(),
+        callback_policy: msg.callback_policy.unwrap_or_default(),
+        registration_retry_blocks: msg
+            .registration_retry_blocks
+            .unwrap_or(DEFAULT_REGISTRATION_RETRY_BLOCKS),
+        quarantine_registrations: msg.quarantine_registrations.unwrap_or(false),
+        creation_rules: valid_creation_rules(msg.creation_rules.unwrap_or_default())?,
+        address_prefix: msg.address_prefix,
+        description_limit: msg.description_limit,
+        features: initial_features(
+            &msg.disabled_features.unwrap_or_default(),
+            msg.allow_recovery.unwrap_or(false),
+        ),
+        stats_checkpoint_interval: msg
+            .stats_checkpoint_interval
+            .unwrap_or(DEFAULT_STATS_CHECKPOINT_INTERVAL),
     };
+    enforce_entropy(&config, &msg.entropy)?;
+    let prng_seed: Vec<u8> = derive_init_seed(&msg.entropy);
 
     save(&mut deps.storage, CONFIG_KEY, &config)?;
     save(&mut deps.storage, PRNG_SEED_KEY, &prng_seed)?;
-
-    Ok(InitResponse::default())
+    init_sealing_key(&mut deps.storage, &prng_seed)?;
+    save(&mut deps.storage, LAST_HEIGHT_KEY, &env.block.height)?;
+    save(&mut deps.storage, SCHEMA_VERSION_KEY, &REGISTRY_SCHEMA_VERSION)?;
+    add_version(&mut deps.storage, &config.version)?;
+
+    // create the offspring listed to be created right away
+    let bootstrap = msg.bootstrap.unwrap_or_default();
+    if bootstrap.len() > MAX_BOOTSTRAP_OFFSPRING {
+        return Err(StdError::generic_err(format!(
+            "At most {} offspring can be created at init",
+            MAX_BOOTSTRAP_OFFSPRING
+        )));
+    }
+    for spec in bootstrap.iter() {
+        deps.api.canonical_address(&spec.owner)?;
+        if let Some(id) = spec.version {
+            load_version(&deps.storage, id)?;
+        }
+    }
+    let mut messages: Vec<CosmosMsg> =
+        register_fee_token(&env, &config.creation_rules)?.into_iter().collect();
+    messages.extend(creation::bootstrap_offspring(
+        &mut deps.storage,
+        &deps.api,
+        &env,
+        &config,
+        bootstrap,
+    )?);
+
+    Ok(InitResponse {
+        messages,
+        log: vec![],
+    })
 }
 
 ///////////////////////////////////// Handle //////////////////////////////////////
@@ -72,6 +136,8 @@ pub fn handle<S: Storage, A: Api, Q: Querier>(
     env: Env,
     msg: HandleMsg,
 ) -> HandleResult {
+    let height = env.block.height;
+    features::enforce_feature(&deps.storage, &msg)?;
     let response = match msg {
         HandleMsg::CreateOffspring {
             label,
@@ -79,595 +145,406 @@ pub fn handle<S: Storage, A: Api, Q: Querier>(
             owner,
             count,
             description,
-        } => try_create_offspring(deps, env, label, entropy, owner, count, description),
+            dependencies,
+            referrer,
+            count_privacy,
+            version,
+            context,
+        } => creation::try_create_offspring(
+            deps,
+            env,
+            label,
+            entropy,
+            owner,
+            count,
+            description,
+            dependencies,
+            referrer,
+            count_privacy,
+            version,
+            context,
+            false,
+        ),
+        HandleMsg::BatchCreateOffspring { entropy, offspring } => {
+            creation::try_batch_create_offspring(deps, env, entropy, offspring)
+        }
+        HandleMsg::Receive {
+            from, amount, msg, ..
+        } => creation::try_receive(deps, env, from, amount, msg),
         HandleMsg::RegisterOffspring { owner, offspring } => {
-            try_register_offspring(deps, env, owner, &offspring)
+            creation::try_register_offspring(deps, env, owner, &offspring)
+        }
+        HandleMsg::DeactivateOffspring { owner, expired } => {
+            creation::try_deactivate_offspring(deps, env, &owner, expired)
+        }
+        HandleMsg::DetachOffspring {} => creation::try_detach_offspring(deps, env),
+        HandleMsg::ReactivateOffspring { password } => {
+            creation::try_reactivate_offspring(deps, env, &password)
+        }
+        HandleMsg::DeactivateOffspringAdmin { offspring } => {
+            admin::try_deactivate_offspring_admin(deps, env, offspring)
         }
-        HandleMsg::DeactivateOffspring { owner } => {
-            try_deactivate_offspring(deps, env, &owner)
+        HandleMsg::UpdateOffspringDescription { description } => {
+            creation::try_update_offspring_description(deps, env, description)
+        }
+        HandleMsg::UpdateOffspringCountPrivacy { count_privacy } => {
+            creation::try_update_offspring_count_privacy(deps, env, count_privacy)
+        }
+        HandleMsg::CreateViewingKey { entropy } => auth::try_create_key(deps, env, entropy),
+        HandleMsg::SetViewingKey { key, .. } => auth::try_set_key(deps, env, &key),
+        HandleMsg::UpdateOffspringAttributes { attributes } => {
+            creation::try_update_offspring_attributes(deps, env, attributes)
+        }
+        HandleMsg::SetMetadataUrl { offspring, url } => {
+            creation::try_set_metadata_url(deps, env, offspring, url)
+        }
+        HandleMsg::FlagIssue { category, details } => {
+            issues::try_flag_issue(deps, env, category, details)
+        }
+        HandleMsg::UpdateOffspringOwner { new_owner } => {
+            transfer::try_update_offspring_owner(deps, env, new_owner)
+        }
+        HandleMsg::RequestRecovery { claimant } => {
+            recovery::try_request_recovery(deps, env, claimant)
+        }
+        HandleMsg::ApproveRecovery { offspring } => {
+            recovery::try_approve_recovery(deps, env, offspring)
+        }
+        HandleMsg::RejectRecovery { offspring } => {
+            recovery::try_reject_recovery(deps, env, offspring)
+        }
+        HandleMsg::OfferTransfer {
+            offspring,
+            recipient,
+            price,
+        } => transfer::try_offer_transfer(deps, env, offspring, recipient, price),
+        HandleMsg::AcceptTransfer { offspring } => {
+            transfer::try_accept_transfer(deps, env, offspring)
+        }
+        HandleMsg::CancelPendingCreations {} => {
+            creation::try_cancel_pending_creations(deps, env)
+        }
+        HandleMsg::CancelTransfer { offspring } => {
+            transfer::try_cancel_transfer(deps, env, offspring)
         }
-        HandleMsg::CreateViewingKey { entropy } => try_create_key(deps, env, entropy),
-        HandleMsg::SetViewingKey { key, .. } => try_set_key(deps, env, &key),
         HandleMsg::NewOffspringContract { offspring_contract } => {
-            try_new_contract(deps, env, offspring_contract)
+            admin::try_new_contract(deps, env, offspring_contract)
+        }
+        HandleMsg::RollbackVersion {} => admin::try_rollback_version(deps, env),
+        HandleMsg::SetStatus { stop } => admin::try_set_status(deps, env, stop),
+        HandleMsg::SetDefaultPageSize { page_size } => {
+            admin::try_set_default_page_size(deps, env, page_size)
+        }
+        HandleMsg::SetDescriptionLimit { limit } => {
+            admin::try_set_description_limit(deps, env, limit)
+        }
+        HandleMsg::SetMaintenanceBudget { budget } => {
+            admin::try_set_maintenance_budget(deps, env, budget)
+        }
+        HandleMsg::SetCallbackPolicy { policy } => {
+            admin::try_set_callback_policy(deps, env, policy)
+        }
+        HandleMsg::TransferAdmin { new_admin } => admin::try_transfer_admin(deps, env, new_admin),
+        HandleMsg::AcceptAdmin {} => admin::try_accept_admin(deps, env),
+        HandleMsg::SetOwnerSettings { page_size } => auth::try_set_owner_settings(deps, env, page_size),
+        HandleMsg::SetDelegate { delegate, allowed } => {
+            auth::try_set_delegate(deps, env, &delegate, allowed)
+        }
+        HandleMsg::GrantCreate { creator } => auth::try_set_create_delegate(deps, env, &creator, true),
+        HandleMsg::RevokeCreate { creator } => {
+            auth::try_set_create_delegate(deps, env, &creator, false)
+        }
+        HandleMsg::RevokePermit { permit_name } => {
+            permits::try_revoke_permit(deps, env, &permit_name)
+        }
+        HandleMsg::SetAddressBookEntry { name, contract } => {
+            admin::try_set_address_book_entry(deps, env, name, contract)
+        }
+        HandleMsg::SetVersionStatus { code_id, enabled } => {
+            admin::try_set_version_status(deps, env, code_id, enabled)
+        }
+        HandleMsg::ReviewQuarantine { address, approve } => {
+            quarantine::try_review_quarantine(deps, env, address, approve)
+        }
+        HandleMsg::SetVersionDeprecation {
+            code_hash,
+            deprecation,
+        } => admin::try_set_version_deprecation(deps, env, code_hash, deprecation),
+        HandleMsg::SetCreationRules { rules } => admin::try_set_creation_rules(deps, env, rules),
+        HandleMsg::WithdrawFees {
+            treasury,
+            amount,
+            token,
+        } => {
+            admin::try_withdraw_fees(deps, env, treasury, amount, token)
+        }
+        HandleMsg::WithdrawReferralFees { amount, token } => {
+            creation::try_withdraw_referral_fees(deps, env, amount, token)
+        }
+        HandleMsg::SetFeature { feature, enabled } => {
+            features::try_set_feature(deps, env, feature, enabled)
+        }
+        HandleMsg::SetCreatorAllowed { address, allowed } => {
+            admin::try_set_creator_allowed(deps, env, address, allowed)
+        }
+        HandleMsg::SetCreatorDenied { address, denied } => {
+            admin::try_set_creator_denied(deps, env, address, denied)
+        }
+        HandleMsg::SetAddressBlocked {
+            address,
+            blocked,
+            reason,
+        } => admin::try_set_address_blocked(deps, env, address, blocked, reason),
+        HandleMsg::SetOwnerNote { owner, note } => admin::try_set_owner_note(deps, env, owner, note),
+        HandleMsg::ClearOwnerNote { owner } => admin::try_clear_owner_note(deps, env, owner),
+        HandleMsg::AdminBatch { actions } => admin::try_admin_batch(deps, env, actions),
+        HandleMsg::Compact { max_items } => registry::try_compact(deps, env, max_items),
+        HandleMsg::AcknowledgeIssue { id } => issues::try_acknowledge_issue(deps, env, id),
+        HandleMsg::ResolveIssue { id, note } => issues::try_resolve_issue(deps, env, id, note),
+        HandleMsg::SnapshotRegistry { max_items } => {
+            snapshot::try_snapshot_registry(deps, env, max_items)
+        }
+        HandleMsg::ListOffspringOfOwnerAdmin {
+            owner,
+            filter,
+            start_page,
+            page_size,
+        } => admin::try_list_of_owner_admin(deps, env, owner, filter, start_page, page_size),
+        HandleMsg::ImportRegistry {
+            token,
+            next_token,
+            records,
+        } => registry::try_import_registry(deps, env, token, next_token, records),
+        HandleMsg::PurgeOffspring { address, reason } => {
+            registry::try_purge_offspring(deps, env, &address, reason)
+        }
+        HandleMsg::RemoveOffspring { addresses } => {
+            registry::try_remove_offspring(deps, env, addresses)
         }
-        HandleMsg::SetStatus { stop } => try_set_status(deps, env, stop),
     };
+    // amortize deferred maintenance across normal traffic
+    let response = response.and_then(|resp| {
+        maintenance::run_maintenance(&mut deps.storage, &deps.api, height)?;
+        save(&mut deps.storage, LAST_HEIGHT_KEY, &height)?;
+        Ok(resp)
+    });
     pad_handle_result(response, BLOCK_SIZE)
 }
 
-/// Returns [u8;32]
-///
-/// generates new entropy from block data, does not save it to the contract.
-///
-/// # Arguments
-///
-/// * `env` - Env of contract's environment
-/// * `seed` - (user generated) seed for rng
-/// * `entropy` - Entropy seed saved in the contract
-pub fn new_entropy(env: &Env, seed: &[u8], entropy: &[u8]) -> [u8; 32] {
-    // 16 here represents the lengths in bytes of the block height and time.
-    let entropy_len = 16 + env.message.sender.len() + entropy.len();
-    let mut rng_entropy = Vec::with_capacity(entropy_len);
-    rng_entropy.extend_from_slice(&env.block.height.to_be_bytes());
-    rng_entropy.extend_from_slice(&env.block.time.to_be_bytes());
-    rng_entropy.extend_from_slice(&env.message.sender.0.as_bytes());
-    rng_entropy.extend_from_slice(entropy);
-
-    let mut rng = Prng::new(seed, &rng_entropy);
-
-    rng.rand_bytes()
-}
-
-/// Returns HandleResult
-///
-/// create a new offspring
-///
-/// # Arguments
-///
-/// * `deps` - mutable reference to Extern containing all the contract's external dependencies
-/// * `env` - Env of contract's environment
-/// * `password` - String containing the password to give the offspring
-/// * `owner` - address of the owner associated to this offspring contract
-/// * `count` - the count for the counter template
-/// * `description` - optional free-form text string owner may have used to describe the offspring
-#[allow(clippy::too_many_arguments)]
-fn try_create_offspring<S: Storage, A: Api, Q: Querier>(
-    deps: &mut Extern<S, A, Q>,
-    env: Env,
-    label: String,
-    entropy: String,
-    owner: HumanAddr,
-    count: i32,
-    description: Option<String>,
-) -> HandleResult {
-    let config: Config = load(&deps.storage, CONFIG_KEY)?;
-    if config.stopped {
-        return Err(StdError::generic_err(
-            "The factory has been stopped. No new offspring can be created",
-        ));
-    }
-
-    let factory = ContractInfo {
-        code_hash: env.clone().contract_code_hash,
-        address: env.clone().contract.address,
-    };
-
-    // generate and save new prng, and password. (we only register an offspring retuning the matching password)
-    let prng_seed: Vec<u8> = load(&deps.storage, PRNG_SEED_KEY)?;
-    let new_prng_bytes = new_entropy(&env, prng_seed.as_ref(), entropy.as_bytes());
-    save(&mut deps.storage, PRNG_SEED_KEY, &new_prng_bytes.to_vec())?;
-
-    // store the password for future authentication
-    let password = sha_256(&new_prng_bytes);
-    save(&mut deps.storage, PENDING_KEY, &password)?;
-
-    let initmsg = OffspringInitMsg {
-        factory,
-        label: label.clone(),
-        password: password.clone(),
-        owner,
-        count,
-        description,
-    };
-
-    let cosmosmsg = initmsg.to_cosmos_msg(
-        label,
-        config.version.code_id,
-        config.version.code_hash,
-        None,
-    )?;
-
-    Ok(HandleResponse {
-        messages: vec![cosmosmsg],
-        log: vec![],
-        data: Some(to_binary(&HandleAnswer::Status {
-            status: Success,
-            message: None,
-        })?),
-    })
-}
-
-/// Returns HandleResult
-///
-/// Registers the calling offspring by saving its info and adding it to the appropriate lists
-///
-/// # Arguments
-///
-/// * `deps` - mutable reference to Extern containing all the contract's external dependencies
-/// * `env` - Env of contract's environment
-/// * `owner` - reference to the address of the offspring's owner
-/// * `reg_offspring` - reference to RegisterOffspringInfo of the offspring that is trying to register
-fn try_register_offspring<S: Storage, A: Api, Q: Querier>(
-    deps: &mut Extern<S, A, Q>,
-    env: Env,
-    owner: HumanAddr,
-    reg_offspring: &RegisterOffspringInfo,
-) -> HandleResult {
-    // verify this is the offspring we are waiting for
-    let load_password: Option<[u8; 32]> = may_load(&deps.storage, PENDING_KEY)?;
-    let auth_password = load_password
-        .ok_or_else(|| StdError::generic_err("Unable to authenticate registration."))?;
-    if auth_password != reg_offspring.password {
-        return Err(StdError::generic_err(
-            "password does not match the offspring we are creating",
-        ));
-    }
-    remove(&mut deps.storage, PENDING_KEY);
-
-    // convert register offspring info to storage format
-    let offspring_addr = deps.api.canonical_address(&env.message.sender)?;
-    let offspring = reg_offspring.to_store_offspring_info(env.message.sender.clone());
-
-    // save the offspring info
-    let mut info_store: CashMap<StoreOffspringInfo, _> = CashMap::init(ACTIVE_KEY, &mut deps.storage);
-    info_store.insert(offspring_addr.as_slice(), offspring.clone())?;
-
-    // get list of owner's active offspring
-    let mut owners_store = PrefixedStorage::new(PREFIX_OWNERS_ACTIVE, &mut deps.storage);
-    let mut my_active_store: CashMap<StoreOffspringInfo, _, _> = CashMap::init(owner.to_string().as_bytes(), &mut owners_store);
-    // add this offspring to owner's list
-    my_active_store.insert(offspring_addr.as_slice(), offspring)?;
-
-    Ok(HandleResponse {
-        messages: vec![],
-        log: vec![log("offspring_address", env.message.sender)],
-        data: None,
-    })
-}
-
-/// Returns HandleResult
-///
-/// deactivates the offspring by saving its info and adding/removing it to/from the
-/// appropriate lists
-///
-/// # Arguments
-///
-/// * `deps` - mutable reference to Extern containing all the contract's external dependencies
-/// * `env` - Env of contract's environment
-/// * `owner` - offspring's owner
-fn try_deactivate_offspring<S: Storage, A: Api, Q: Querier>(
-    deps: &mut Extern<S, A, Q>,
-    env: Env,
-    owner: &HumanAddr,
-) -> HandleResult {
-
-    let offspring_addr = &deps.api.canonical_address(&env.message.sender)?;
-
-    // verify offspring is in active list, and not a spam attempt
-    let may_info = authenticate_offspring(&deps.storage, offspring_addr)?;
-    // delete the active offspring info
-    let mut info_store: CashMap<StoreOffspringInfo, _, _> = CashMap::init(ACTIVE_KEY, &mut deps.storage);
-    info_store.remove(offspring_addr.as_slice())?;
-
-    // save owner's inactive offspring info
-    let offspring_info = may_info;
-    let inactive_info = offspring_info.to_store_inactive_offspring_info();
-    let mut owners_inactive_store = PrefixedStorage::new(PREFIX_OWNERS_INACTIVE, &mut deps.storage);
-    let mut inactive_store = CashMap::init(owner.to_string().as_bytes(), &mut owners_inactive_store);
-    inactive_store.insert(offspring_addr.as_slice(), inactive_info.clone())?;
-
-    // save inactive offspring info
-    let mut inactive_store = CashMap::init(INACTIVE_KEY, &mut deps.storage);
-    inactive_store.insert(offspring_addr.as_slice(), inactive_info)?;
-
-    // remove offspring from owner's active list
-    remove_from_persons_active(&mut deps.storage, PREFIX_OWNERS_ACTIVE, owner, offspring_addr)?;
-
-    Ok(HandleResponse {
-        messages: vec![],
-        log: vec![],
-        data: None,
-    })
-}
-
-/// Returns StdResult<(StoreOffspringInfo)>
-///
-/// verifies that the offspring is in the active list, and returns the active offspring info
-///
-/// # Arguments
-///
-/// * `storage` - a reference to contract's storage
-/// * `offspring` - a reference to the offspring's address
-fn authenticate_offspring<S: ReadonlyStorage>(
-    storage: &S,
-    offspring: &CanonicalAddr,
-) -> StdResult<StoreOffspringInfo> {
-    let info_store: ReadOnlyCashMap<StoreOffspringInfo, _, _> = ReadOnlyCashMap::init(ACTIVE_KEY, storage);
-
-    let info = info_store.get(offspring.as_slice());
-
-    if let Some(offspring_info) = info {
-        Ok(offspring_info)
-    } else {
-        return Err(StdError::generic_err(
-            "This is not an active offspring registered with factory.",
-        ));
-    }
-}
-
-/// Returns HandleResult
-///
-/// allows admin to edit the offspring contract version.
-///
-/// # Arguments
-///
-/// * `deps` - mutable reference to Extern containing all the contract's external dependencies
-/// * `env` - Env of contract's environment
-/// * `offspring_contract` - OffspringContractInfo of the new offspring version
-fn try_new_contract<S: Storage, A: Api, Q: Querier>(
-    deps: &mut Extern<S, A, Q>,
-    env: Env,
-    offspring_contract: OffspringContractInfo,
-) -> HandleResult {
-    // only allow admin to do this
-    let mut config: Config = load(&deps.storage, CONFIG_KEY)?;
-    let sender = deps.api.canonical_address(&env.message.sender)?;
-    if config.admin != sender {
-        return Err(StdError::generic_err(
-            "This is an admin command. Admin commands can only be run from admin address",
-        ));
-    }
-    config.version = offspring_contract;
-    save(&mut deps.storage, CONFIG_KEY, &config)?;
-
-    Ok(HandleResponse {
-        messages: vec![],
-        log: vec![],
-        data: Some(to_binary(&HandleAnswer::Status {
-            status: Success,
-            message: None,
-        })?),
-    })
-}
-
-/// Returns HandleResult
-///
-/// allows admin to change the factory status to (dis)allow the creation of new offspring
-///
-/// # Arguments
-///
-/// * `deps` - mutable reference to Extern containing all the contract's external dependencies
-/// * `env` - Env of contract's environment
-/// * `stop` - true if the factory should disallow offspring creation
-fn try_set_status<S: Storage, A: Api, Q: Querier>(
-    deps: &mut Extern<S, A, Q>,
-    env: Env,
-    stop: bool,
-) -> HandleResult {
-    // only allow admin to do this
-    let mut config: Config = load(&deps.storage, CONFIG_KEY)?;
-    let sender = deps.api.canonical_address(&env.message.sender)?;
-    if config.admin != sender {
-        return Err(StdError::generic_err(
-            "This is an admin command. Admin commands can only be run from admin address",
-        ));
-    }
-    config.stopped = stop;
-    save(&mut deps.storage, CONFIG_KEY, &config)?;
-
-    Ok(HandleResponse {
-        messages: vec![],
-        log: vec![],
-        data: Some(to_binary(&HandleAnswer::Status {
-            status: Success,
-            message: None,
-        })?),
-    })
-}
-
-/// Returns HandleResult
-///
-/// create a viewing key
-///
-/// # Arguments
-///
-/// * `deps` - mutable reference to Extern containing all the contract's external dependencies
-/// * `env` - Env of contract's environment
-/// * `entropy` - string to be used as an entropy source for randomization
-fn try_create_key<S: Storage, A: Api, Q: Querier>(
-    deps: &mut Extern<S, A, Q>,
-    env: Env,
-    entropy: String,
-) -> HandleResult {
-    let key = ViewingKey::create(&mut deps.storage, &env, &env.message.sender, entropy.as_bytes());
-
-    Ok(HandleResponse {
-        messages: vec![],
-        log: vec![],
-        data: Some(to_binary(&HandleAnswer::ViewingKey {
-            key: format!("{}", key),
-        })?),
-    })
-}
-
-/// Returns HandleResult
-///
-/// sets the viewing key
-///
-/// # Arguments
-///
-/// * `deps` - mutable reference to Extern containing all the contract's external dependencies
-/// * `env` - Env of contract's environment
-/// * `key` - string slice to be used as the viewing key
-fn try_set_key<S: Storage, A: Api, Q: Querier>(
-    deps: &mut Extern<S, A, Q>,
-    env: Env,
-    key: &str,
-) -> HandleResult {
-    ViewingKey::set(&mut deps.storage, &env.message.sender, key);
-
-    Ok(HandleResponse {
-        messages: vec![],
-        log: vec![],
-        data: Some(to_binary(&HandleAnswer::ViewingKey {
-            key: key.to_string(),
-        })?),
-    })
-}
-
-/// Returns StdResult<()>
-///
-/// remove an offspring from a person's list of active offspring. (This helper is implemented
-/// in case there are multiple users associated to an offspring)
+/////////////////////////////////////// Query /////////////////////////////////////
+/// Returns QueryResult
 ///
 /// # Arguments
 ///
-/// * `storage` - mutable reference to contract's storage
-/// * `prefix` - prefix to storage of a person's active offspring list
-/// * `person` - a reference to the canonical address of the person the list belongs to
-/// * `offspring_addr` - a reference to the canonical address of the offspring to remove
-fn remove_from_persons_active<S: Storage>(
-    storage: &mut S,
-    prefix: &[u8],
-    person: &HumanAddr,
-    offspring_addr: &CanonicalAddr,
-) -> StdResult<()> {
-    let mut store = PrefixedStorage::new(prefix, storage);
-    let mut load_active: CashMap<StoreOffspringInfo, _, _> = CashMap::init(person.to_string().as_bytes(), &mut store);
-    load_active.remove(offspring_addr.as_slice())?;
-    Ok(())
+/// * `deps` - reference to Extern containing all the contract's external dependencies
+/// * `msg` - QueryMsg passed in with the query call
+pub fn query<S: Storage, A: Api, Q: Querier>(deps: &Extern<S, A, Q>, msg: QueryMsg) -> QueryResult {
+    pad_query_result(answer_query(deps, msg), BLOCK_SIZE)
 }
 
-/////////////////////////////////////// Query /////////////////////////////////////
-/// Returns QueryResult
+/// Returns QueryResult with the unpadded answer to the query
 ///
 /// # Arguments
 ///
 /// * `deps` - reference to Extern containing all the contract's external dependencies
-/// * `msg` - QueryMsg passed in with the query call
-pub fn query<S: Storage, A: Api, Q: Querier>(deps: &Extern<S, A, Q>, msg: QueryMsg) -> QueryResult {
-    let response = match msg {
+/// * `msg` - QueryMsg to answer
+fn answer_query<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    msg: QueryMsg,
+) -> QueryResult {
+    match msg {
         QueryMsg::ListMyOffspring {
             address,
             viewing_key,
             filter,
             start_page,
             page_size,
-        } => try_list_my(deps, &address, viewing_key, filter, start_page, page_size),
-        QueryMsg::ListActiveOffspring { start_page, page_size } => try_list_active(deps, start_page, page_size),
-        QueryMsg::ListInactiveOffspring { start_page, page_size } => try_list_inactive(deps, start_page, page_size),
-        QueryMsg::IsKeyValid {
+            reverse,
+            label_contains,
+            if_modified_since,
+        } => queries::try_list_my(
+            deps,
+            &address,
+            viewing_key,
+            filter,
+            start_page,
+            page_size,
+            reverse.unwrap_or(false),
+            label_contains,
+            if_modified_since,
+        ),
+        QueryMsg::ListActiveOffspring {
+            start_page,
+            page_size,
             address,
             viewing_key,
-        } => try_validate_key(deps, &address, viewing_key),
-    };
-    pad_query_result(response, BLOCK_SIZE)
-}
-
-/// Returns QueryResult indicating whether the address/key pair is valid
-///
-/// # Arguments
-///
-/// * `deps` - reference to Extern containing all the contract's external dependencies
-/// * `address` - a reference to the address whose key should be validated
-/// * `viewing_key` - String key used for authentication
-fn try_validate_key<S: Storage, A: Api, Q: Querier>(
-    deps: &Extern<S, A, Q>,
-    address: &HumanAddr,
-    viewing_key: String,
-) -> QueryResult {
-    to_binary(&QueryAnswer::IsKeyValid {
-        is_valid: is_key_valid(&deps.storage, address, viewing_key),
-    })
-}
-
-/// Returns QueryResult listing the active offspring
-///
-/// # Arguments
-///
-/// * `deps` - reference to Extern containing all the contract's external dependencies
-/// * `start_page` - optional start page for the offsprings returned and listed
-/// * `page_size` - optional number of offspring to return in this page
-fn try_list_active<S: Storage, A: Api, Q: Querier>(
-    deps: &Extern<S, A, Q>,
-    start_page: Option<u32>,
-    page_size: Option<u32>,
-) -> QueryResult {
-    to_binary(&QueryAnswer::ListActiveOffspring {
-        active: display_active_list(&deps.storage, None, ACTIVE_KEY, start_page, page_size)?,
-    })
-}
-
-/// Returns bool result of validating an address' viewing key
-///
-/// # Arguments
-///
-/// * `storage` - a reference to the contract's storage
-/// * `address` - a reference to the address whose key should be validated
-/// * `viewing_key` - String key used for authentication
-fn is_key_valid<S: ReadonlyStorage>(
-    storage: &S,
-    address: &HumanAddr,
-    viewing_key: String,
-) -> bool {
-    return ViewingKey::check(storage, address, &viewing_key).is_ok();
-}
-
-/// Returns QueryResult listing the offspring with the address as its owner
-///
-/// # Arguments
-///
-/// * `deps` - reference to Extern containing all the contract's external dependencies
-/// * `address` - a reference to the address whose offspring should be listed
-/// * `viewing_key` - String key used to authenticate the query
-/// * `filter` - optional choice of display filters
-/// * `start_page` - optional start page for the offsprings returned and listed
-/// * `page_size` - optional number of offspring to return in this page
-fn try_list_my<S: Storage, A: Api, Q: Querier>(
-    deps: &Extern<S, A, Q>,
-    address: &HumanAddr,
-    viewing_key: String,
-    filter: Option<FilterTypes>,
-    start_page: Option<u32>,
-    page_size: Option<u32>,
-) -> QueryResult {
-    // if key matches
-    if !is_key_valid(&deps.storage, address, viewing_key) {
-        return to_binary(&QueryAnswer::ViewingKeyError {
-            error: "Wrong viewing key for this address or viewing key not set".to_string(),
-        });
-    }
-    let mut active_list: Option<Vec<StoreOffspringInfo>> = None;
-    let mut inactive_list: Option<Vec<StoreInactiveOffspringInfo>> = None;
-    // if no filter default to ALL
-    let types = filter.unwrap_or(FilterTypes::All);
-
-    // list the active offspring
-    if types == FilterTypes::Active || types == FilterTypes::All {
-        active_list = Some( display_active_list(
-            &deps.storage,
-            Some( PREFIX_OWNERS_ACTIVE ),
-            address.to_string().as_bytes(),
+            if_modified_since,
+            newest_first,
+        } => queries::try_list_active(
+            deps,
             start_page,
             page_size,
-        )?);
-    }
-    // list the inactive offspring
-    if types == FilterTypes::Inactive || types == FilterTypes::All {
-        inactive_list = Some( display_inactive_list(
-            &deps.storage,
-            Some( PREFIX_OWNERS_INACTIVE ),
-            address.to_string().as_bytes(),
+            address,
+            viewing_key,
+            if_modified_since,
+            newest_first.unwrap_or(false),
+        ),
+        QueryMsg::ListInactiveOffspring {
             start_page,
             page_size,
-        )?);
-    }
-
-    return to_binary(&QueryAnswer::ListMyOffspring {
-        active: active_list,
-        inactive: inactive_list,
-    });
-}
-
-/// Returns StdResult<Vec<StoreOffspringInfo>>
-///
-/// provide the appropriate list of active offspring
-///
-/// # Arguments
-///
-/// * `api` - reference to the Api used to convert canonical and human addresses
-/// * `storage` - a reference to the contract's storage
-/// * `prefix` - optional storage prefix to load from
-/// * `key` - storage key to read (user addr byte)
-/// * `start_page` - optional start page for the offsprings returned and listed
-/// * `page_size` - optional number of offspring to return in this page
-fn display_active_list<S: ReadonlyStorage>(
-    storage: &S,
-    prefix: Option<&[u8]>,
-    key: &[u8],
-    start_page: Option<u32>,
-    page_size: Option<u32>,
-) -> StdResult<Vec<StoreOffspringInfo>> {
-    let page_number = start_page.unwrap_or(0);
-    let size = page_size.unwrap_or(DEFAULT_PAGE_SIZE);
-    let list: Vec<StoreOffspringInfo>;
-    match prefix {
-        Some(pref) => {
-            // get owner's active list
-            let read = &ReadonlyPrefixedStorage::new(pref, storage);
-            let user_store: ReadOnlyCashMap<StoreOffspringInfo, _> = ReadOnlyCashMap::init(key, read);
-            list = user_store.paging(page_number, size)?;
-        },
-        None => {
-            // get factory's active list
-            let active_store: ReadOnlyCashMap<StoreOffspringInfo, _> = ReadOnlyCashMap::init(key, storage);
-            list = active_store.paging(page_number, size)?;
+            address,
+            viewing_key,
+            if_modified_since,
+            newest_first,
+        } => queries::try_list_inactive(
+            deps,
+            start_page,
+            page_size,
+            address,
+            viewing_key,
+            if_modified_since,
+            newest_first.unwrap_or(false),
+        ),
+        QueryMsg::IsKeyValid {
+            address,
+            viewing_key,
+        } => queries::try_validate_key(deps, &address, viewing_key),
+        QueryMsg::AuthenticateRole {
+            address,
+            viewing_key,
+            owner,
+        } => queries::try_authenticate_role(deps, &address, viewing_key, owner),
+        QueryMsg::AddressBook { start_page, page_size } => {
+            queries::try_list_address_book(deps, start_page, page_size)
         }
-    }
-    Ok(list)
-}
-
-/// Returns StdResult<Vec<InactiveOffspringInfo>>
-///
-/// provide the appropriate list of inactive offspring
-///
-/// # Arguments
-///
-/// * `storage` - a reference to the contract's storage
-/// * `prefix` - optional storage prefix to load from
-/// * `key` - storage key to read
-/// * `start_page` - optional start page for the offsprings returned and listed
-/// * `page_size` - optional number of offspring to return in this page
-fn display_inactive_list<S: ReadonlyStorage>(
-    storage: &S,
-    prefix: Option<&[u8]>,
-    key: &[u8],
-    start_page: Option<u32>,
-    page_size: Option<u32>,
-) -> StdResult<Vec<StoreInactiveOffspringInfo>> {
-    let page_number = start_page.unwrap_or(0);
-    let size = page_size.unwrap_or(DEFAULT_PAGE_SIZE);
-    let list: Vec<StoreInactiveOffspringInfo>;
-    match prefix {
-        Some(pref) => {
-            // get owner's inactive list
-            let read = &ReadonlyPrefixedStorage::new(pref, storage);
-            let user_store: ReadOnlyCashMap<StoreInactiveOffspringInfo, _> = ReadOnlyCashMap::init(key, read);
-            list = user_store.paging(page_number, size)?;
-        },
-        None => {
-            // get factory's inactive list
-            let active_store: ReadOnlyCashMap<StoreInactiveOffspringInfo, _> = ReadOnlyCashMap::init(key, storage);
-            list = active_store.paging(page_number, size)?;
+        QueryMsg::ResolveNames { names } => queries::try_resolve_names(deps, &names),
+        QueryMsg::OffspringLifecycle {
+            address,
+            viewer,
+            viewing_key,
+        } => queries::try_lifecycle(deps, &address, viewer, viewing_key),
+        QueryMsg::OffspringByLabel {
+            label,
+            viewer,
+            viewing_key,
+        } => queries::try_offspring_by_label(deps, label, viewer, viewing_key),
+        QueryMsg::OffspringInfo {
+            address,
+            viewer,
+            viewing_key,
+        } => queries::try_offspring_info(deps, &address, viewer, viewing_key),
+        QueryMsg::TransferOffer {
+            offspring,
+            address,
+            viewing_key,
+        } => queries::try_transfer_offer(deps, &offspring, &address, viewing_key),
+        QueryMsg::ReferralStats {
+            address,
+            viewing_key,
+        } => queries::try_referral_stats(deps, &address, viewing_key),
+        QueryMsg::CreationContexts {
+            address,
+            viewing_key,
+            start_page,
+            page_size,
+        } => queries::try_creation_contexts(deps, &address, viewing_key, start_page, page_size),
+        QueryMsg::CreatorList {
+            address,
+            viewing_key,
+            list,
+            start_page,
+            page_size,
+        } => queries::try_creator_list(deps, &address, viewing_key, list, start_page, page_size),
+        QueryMsg::RecoveryRequests {
+            address,
+            viewing_key,
+            start_page,
+            page_size,
+        } => recovery::try_recovery_requests(deps, &address, viewing_key, start_page, page_size),
+        QueryMsg::UsageStats {
+            address,
+            viewing_key,
+            start_page,
+            page_size,
+        } => stats::try_usage_stats(deps, &address, viewing_key, start_page, page_size),
+        QueryMsg::CreationNonce {
+            address,
+            viewing_key,
+        } => queries::try_creation_nonce(deps, &address, viewing_key),
+        QueryMsg::CanICreate {
+            address,
+            viewing_key,
+        } => queries::try_can_i_create(deps, &address, viewing_key),
+        QueryMsg::CreationQuota {
+            address,
+            viewing_key,
+        } => validators::try_creation_quota(deps, &address, viewing_key),
+        QueryMsg::RegistrySnapshot {} => queries::try_registry_snapshot(deps),
+        QueryMsg::MembershipProof {
+            offspring,
+            address,
+            viewing_key,
+        } => queries::try_membership_proof(deps, &offspring, address, viewing_key),
+        QueryMsg::AuditLog {
+            address,
+            viewing_key,
+            start_page,
+            page_size,
+        } => queries::try_audit_log(deps, &address, viewing_key, start_page, page_size),
+        QueryMsg::IssueQueue {
+            address,
+            viewing_key,
+            start_page,
+            page_size,
+        } => issues::try_issue_queue(deps, &address, viewing_key, start_page, page_size),
+        QueryMsg::Quarantine {
+            address,
+            viewing_key,
+            start_page,
+            page_size,
+        } => quarantine::try_quarantine(deps, &address, viewing_key, start_page, page_size),
+        QueryMsg::OffspringHistory {
+            offspring,
+            address,
+            viewing_key,
+            start_page,
+            page_size,
+        } => issues::try_offspring_history(
+            deps,
+            &offspring,
+            &address,
+            viewing_key,
+            start_page,
+            page_size,
+        ),
+        QueryMsg::RegistrySchema {} => queries::try_registry_schema(),
+        QueryMsg::BuildInfo {} => queries::try_build_info(),
+        QueryMsg::Features {} => features::try_features(deps),
+        QueryMsg::FactoryConfig {} => queries::try_factory_config(deps),
+        QueryMsg::Health {} => queries::try_health(deps),
+        QueryMsg::OffspringCount {
+            address,
+            viewing_key,
+        } => queries::try_offspring_count(deps, address, viewing_key),
+        QueryMsg::OffspringVersions {} => versions::try_offspring_versions(deps),
+        QueryMsg::ExportRegistry {
+            address,
+            viewing_key,
+            token,
+            batch_size,
+        } => queries::try_export_registry(deps, &address, viewing_key, token, batch_size),
+        QueryMsg::ExportMyOffspring {
+            address,
+            viewing_key,
+            token,
+            batch_size,
+        } => queries::try_export_my_offspring(deps, &address, viewing_key, token, batch_size),
+        QueryMsg::Versioned { api_version, query } => {
+            let api_version = api_version::resolve_api_version(api_version, &query)?;
+            api_version::wrap_answer(api_version, answer_query(deps, *query)?)
         }
+        QueryMsg::WithPermit { permit, query } => permits::try_with_permit(deps, permit, query),
+        QueryMsg::IsPermitValid { permit } => permits::try_validate_permit(deps, &permit),
     }
-    Ok(list)
 }
 
-/// Returns QueryResult listing the inactive offspring
-///
-/// # Arguments
-///
-/// * `deps` - reference to Extern containing all the contract's external dependencies
-/// * `start_page` - optional start page for the offsprings returned and listed
-/// * `page_size` - optional number of offspring to display
-fn try_list_inactive<S: Storage, A: Api, Q: Querier>(
-    deps: &Extern<S, A, Q>,
-    start_page: Option<u32>,
-    page_size: Option<u32>,
-) -> QueryResult {
-    to_binary(&QueryAnswer::ListInactiveOffspring {
-        inactive: display_inactive_list(&deps.storage, None, INACTIVE_KEY, start_page, page_size)?,
-    })
-}
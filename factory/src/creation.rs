@@ -0,0 +1,1438 @@
+use std::collections::HashSet;
+
+use cosmwasm_std::{
+    from_binary, Api, BankMsg, Binary, CanonicalAddr, Coin, CosmosMsg, Env, Extern, HandleResult, HumanAddr, Querier, ReadonlyStorage,
+    StdError, StdResult, Storage, Uint128,
+};
+
+use cosmwasm_storage::{PrefixedStorage, ReadonlyPrefixedStorage};
+
+use secret_toolkit::utils::{HandleCallback, InitCallback};
+
+use secret_toolkit_viewing_key::{ViewingKey, ViewingKeyStore};
+
+use secret_toolkit_incubator::{CashMap, ReadOnlyCashMap};
+
+use crate::state::{
+    load, may_load, save, Config, PendingOffspring, ACTIVE_KEY, CONFIG_KEY,
+    CONTEXTS_KEY, INACTIVE_KEY, MAX_ATTRIBUTE_KEY_LEN, MAX_ATTRIBUTE_VALUE_LEN, MAX_BATCH_CREATE,
+    MAX_CONTEXT_LEN, MAX_METADATA_URL_LEN,
+    MAX_OFFSPRING_ATTRIBUTES, NATIVE_DENOM, PENDING_KEY, PREFIX_OFFSPRING_PASSWORDS,
+    PREFIX_CREATION_NONCES, PREFIX_DISABLED_VERSIONS, PREFIX_OFFSPRING_CODE_HASH,
+    PREFIX_OFFSPRING_OWNER, PREFIX_REFERRALS, PREFIX_REGISTRATION_INDEX, PRNG_SEED_KEY, REGISTRATION_COUNT_KEY,
+};
+
+use crate::{
+    addr::validate_address,
+    admin::enforce_not_blocked,
+    auth::{enforce_create_rights, has_viewing_key, mark_viewing_key},
+    checksum::verify,
+    features::enforce_enabled,
+    msg::{
+        BatchCreateResult, BuildInfo, ContextStats, ContractInfo, CountPrivacy, DeactivationSource, Feature, HandleAnswer, HandleMsg, OffspringAttribute, OffspringSpec,
+        ReferralStats,
+        RegisterOffspringInfo, TokenEarnings, ResponseStatus::{Failure, Success}, StoreInactiveOffspringInfo,
+        StoreOffspringInfo,
+    },
+    offspring_msg::{OffspringHandleMsg, OffspringInitMsg},
+    quarantine::quarantine_registration,
+    rand::{sha_256, Prng},
+    registry,
+    response::{lifecycle_log, not_registered_offspring, ok_answer, ok_empty, ok_status, ok_with_log},
+    sealing::seal_description,
+    snip20_msg::Snip20HandleMsg,
+    stats::{checkpoint_log, record_usage, Usage},
+    validators::{
+        collect_fees, collect_token_fees, native_amount, native_fee, record_creation,
+        record_pending, release_label, release_pending, reserve_label, run_pipeline,
+        take_pending_creations, CreationRequest,
+    },
+    versions::resolve_version,
+};
+
+/// Returns [u8;32]
+///
+/// generates new entropy from block data, does not save it to the contract.
+///
+/// # Arguments
+///
+/// * `env` - Env of contract's environment
+/// * `seed` - (user generated) seed for rng
+/// * `entropy` - Entropy seed saved in the contract
+pub fn new_entropy(env: &Env, seed: &[u8], entropy: &[u8]) -> [u8; 32] {
+    derive_entropy(
+        env.block.height,
+        env.block.time,
+        &env.message.sender.0,
+        seed,
+        entropy,
+    )
+}
+
+/// Returns [u8;32]
+///
+/// generates new entropy from the block height and time, and the sender. This is the part of
+/// new_entropy that does not depend on Env, so it can be checked against fixed inputs
+///
+/// # Arguments
+///
+/// * `height` - block height
+/// * `time` - block time
+/// * `sender` - address of the message sender
+/// * `seed` - (user generated) seed for rng
+/// * `entropy` - Entropy seed saved in the contract
+pub fn derive_entropy(height: u64, time: u64, sender: &str, seed: &[u8], entropy: &[u8]) -> [u8; 32] {
+    // 16 here represents the lengths in bytes of the block height and time.
+    let entropy_len = 16 + sender.len() + entropy.len();
+    let mut rng_entropy = Vec::with_capacity(entropy_len);
+    rng_entropy.extend_from_slice(&height.to_be_bytes());
+    rng_entropy.extend_from_slice(&time.to_be_bytes());
+    rng_entropy.extend_from_slice(sender.as_bytes());
+    rng_entropy.extend_from_slice(entropy);
+
+    let mut rng = Prng::new(seed, &rng_entropy);
+
+    rng.rand_bytes()
+}
+
+/// Returns Vec<u8> with the prng seed the factory is initialized with
+///
+/// # Arguments
+///
+/// * `entropy` - the entropy string passed to init
+pub fn derive_init_seed(entropy: &str) -> Vec<u8> {
+    sha_256(base64::encode(entropy).as_bytes()).to_vec()
+}
+
+/// Returns [u8;32] with the password given to a new offspring
+///
+/// # Arguments
+///
+/// * `prng_bytes` - the new prng seed generated for the offspring's creation
+pub fn derive_password(prng_bytes: &[u8; 32]) -> [u8; 32] {
+    sha_256(prng_bytes)
+}
+
+/// Returns [u8;32] with the entropy of the viewing key given to an owner creating their first
+/// offspring
+///
+/// # Arguments
+///
+/// * `prng_bytes` - the new prng seed generated for the offspring's creation
+pub fn derive_key_entropy(prng_bytes: &[u8; 32]) -> [u8; 32] {
+    Prng::new(prng_bytes, b"viewing_key").rand_bytes()
+}
+
+/// Returns StdResult<()>
+///
+/// makes sure a user supplied entropy string is long and diverse enough to not weaken the
+/// passwords and keys derived from it
+///
+/// # Arguments
+///
+/// * `config` - a reference to the factory config holding the thresholds
+/// * `entropy` - the entropy string to check
+pub fn enforce_entropy(config: &Config, entropy: &str) -> StdResult<()> {
+    if (entropy.chars().count() as u32) < config.min_entropy_len {
+        return Err(StdError::generic_err(format!(
+            "Entropy must be at least {} characters long",
+            config.min_entropy_len
+        )));
+    }
+    let distinct: HashSet<char> = entropy.chars().collect();
+    if (distinct.len() as u32) < config.min_entropy_distinct {
+        return Err(StdError::generic_err(format!(
+            "Entropy must contain at least {} different characters",
+            config.min_entropy_distinct
+        )));
+    }
+    Ok(())
+}
+
+/// Returns HandleResult
+///
+/// create a new offspring
+///
+/// # Arguments
+///
+/// * `deps` - mutable reference to Extern containing all the contract's external dependencies
+/// * `env` - Env of contract's environment
+/// * `label` - optional label of the offspring. If not specified, one is generated
+/// * `entropy` - String used to generate the password for the offspring
+/// * `owner` - address of the owner associated to this offspring contract
+/// * `count` - the count for the counter template
+/// * `description` - optional free-form text string owner may have used to describe the offspring
+/// * `dependencies` - optional names of address book entries the offspring should resolve
+/// * `referrer` - optional address credited with referring this creation
+/// * `count_privacy` - optional privacy level of the offspring's count
+/// * `version` - optional id of the registered version to create the offspring from
+/// * `context` - optional context of the creation, counted in the creation counters
+/// * `token_fee_paid` - true if the creation fee was paid in the creation rules' token
+#[allow(clippy::too_many_arguments)]
+pub fn try_create_offspring<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    label: Option<String>,
+    entropy: String,
+    owner: HumanAddr,
+    count: i32,
+    description: Option<String>,
+    dependencies: Option<Vec<String>>,
+    referrer: Option<HumanAddr>,
+    count_privacy: Option<CountPrivacy>,
+    version: Option<u32>,
+    context: Option<String>,
+    token_fee_paid: bool,
+) -> HandleResult {
+    let config: Config = load(&deps.storage, CONFIG_KEY)?;
+    let owner = validate_address(&deps.storage, &deps.api, &owner)?;
+    if let Some(context) = &context {
+        if context.is_empty() || context.len() > MAX_CONTEXT_LEN {
+            return Err(StdError::generic_err(format!(
+                "Creation contexts must be between 1 and {} bytes long",
+                MAX_CONTEXT_LEN
+            )));
+        }
+    }
+    if referrer.is_some() {
+        enforce_enabled(&config, Feature::Referrals)?;
+    }
+    enforce_create_rights(&deps.storage, &deps.api, &config, &owner, &env.message.sender)?;
+    let creator = deps.api.canonical_address(&env.message.sender)?;
+    let offspring_contract = resolve_version(&deps.storage, &config, version)?;
+    let request = CreationRequest {
+        creator: &creator,
+        sent_funds: Some(&env.message.sent_funds),
+        token_fee_paid,
+        label: label.as_deref(),
+        height: Some(env.block.height),
+        code_id: offspring_contract.code_id,
+    };
+    if let Some(reason) = run_pipeline(&deps.storage, &config, &request).into_iter().next() {
+        return Err(StdError::generic_err(reason));
+    }
+    enforce_entropy(&config, &entropy)?;
+    record_creation(&mut deps.storage, &config, &creator, env.block.height)?;
+
+    let referrer_raw = match referrer {
+        Some(referrer) => {
+            if referrer == env.message.sender {
+                return Err(StdError::generic_err("You can not refer your own creations"));
+            }
+            record_referral(&mut deps.storage, &deps.api, &referrer)?;
+            Some(deps.api.canonical_address(&referrer)?)
+        }
+        None => None,
+    };
+    match config.creation_rules.token_fee.as_ref().filter(|_| token_fee_paid) {
+        Some(token_fee) => collect_token_fees(
+            &mut deps.storage,
+            &config,
+            &token_fee.token.address,
+            token_fee.amount.u128(),
+            referrer_raw.as_ref(),
+        )?,
+        None => collect_fees(
+            &mut deps.storage,
+            &config,
+            native_amount(&env.message.sent_funds)?,
+            referrer_raw.as_ref(),
+        )?,
+    }
+    if let Some(context) = context {
+        record_context(&mut deps.storage, context)?;
+    }
+    let label = match label {
+        Some(label) => label,
+        None => auto_label(
+            &env.contract.address,
+            &owner,
+            load_creation_nonce(&deps.storage, &deps.api, &owner)?,
+        ),
+    };
+
+    let (cosmosmsg, new_prng_bytes) = instantiate_offspring(
+        &mut deps.storage,
+        &deps.api,
+        &env,
+        &config,
+        OffspringSpec {
+            label: Some(label.clone()),
+            owner: owner.clone(),
+            count,
+            description,
+            count_privacy,
+            version,
+        },
+        dependencies,
+        entropy.as_bytes(),
+    )?;
+    record_pending(
+        &mut deps.storage,
+        &config,
+        &creator,
+        &derive_password(&new_prng_bytes),
+        env.block.height,
+    )?;
+
+    // give owners creating their own offspring a viewing key if they do not have one yet. It is
+    // only returned in the response data, which only the sender can read, and never logged. The
+    // data of a creation paid in tokens goes to the token, so no key is made for it
+    let mut viewing_key: Option<String> = None;
+    if config.auto_viewing_key
+        && !token_fee_paid
+        && owner == env.message.sender
+        && !has_viewing_key(&deps.storage, &deps.api, &owner)?
+    {
+        let key_entropy = derive_key_entropy(&new_prng_bytes);
+        viewing_key = Some(ViewingKey::create(&mut deps.storage, &env, &owner, &key_entropy));
+        mark_viewing_key(&mut deps.storage, &deps.api, &owner)?;
+    }
+
+    ok_with_log(
+        vec![cosmosmsg],
+        lifecycle_log("create", None, &owner, &label, &env),
+        Some(HandleAnswer::CreateOffspring {
+            status: Success,
+            viewing_key,
+        }),
+    )
+}
+
+/// Returns HandleResult
+///
+/// implements the SNIP-20 receiver interface. Tokens sent by the token of the creation rules'
+/// token fee pay for the CreateOffspring message embedded in them, which is executed on behalf of
+/// the owner of the tokens
+///
+/// # Arguments
+///
+/// * `deps` - mutable reference to Extern containing all the contract's external dependencies
+/// * `env` - Env of contract's environment
+/// * `from` - owner of the tokens
+/// * `amount` - amount of tokens received
+/// * `msg` - optional embedded CreateOffspring message
+pub fn try_receive<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    mut env: Env,
+    from: HumanAddr,
+    amount: Uint128,
+    msg: Option<Binary>,
+) -> HandleResult {
+    let config: Config = load(&deps.storage, CONFIG_KEY)?;
+    let token_fee = config
+        .creation_rules
+        .token_fee
+        .filter(|fee| fee.token.address == env.message.sender)
+        .ok_or_else(|| StdError::generic_err("This factory does not accept fees in this token"))?;
+    if amount != token_fee.amount {
+        return Err(StdError::generic_err(format!(
+            "Creating an offspring costs exactly {} of this token",
+            token_fee.amount
+        )));
+    }
+    let create = msg
+        .map(|msg| from_binary(&msg))
+        .transpose()?
+        .ok_or_else(|| StdError::generic_err("The tokens must embed a CreateOffspring message"))?;
+    match create {
+        HandleMsg::CreateOffspring {
+            label,
+            entropy,
+            owner,
+            count,
+            description,
+            dependencies,
+            referrer,
+            count_privacy,
+            version,
+            context,
+        } => {
+            env.message.sender = from;
+            try_create_offspring(
+                deps,
+                env,
+                label,
+                entropy,
+                owner,
+                count,
+                description,
+                dependencies,
+                referrer,
+                count_privacy,
+                version,
+                context,
+                true,
+            )
+        }
+        _ => Err(StdError::generic_err(
+            "The tokens must embed a CreateOffspring message",
+        )),
+    }
+}
+
+/// Returns HandleResult
+///
+/// creates a batch of offspring in one transaction. Offspring that fail the creation checks are
+/// skipped and reported in the answer, and the creation fee paid for them is refunded
+///
+/// # Arguments
+///
+/// * `deps` - mutable reference to Extern containing all the contract's external dependencies
+/// * `env` - Env of contract's environment
+/// * `entropy` - String used to generate the passwords for the offspring
+/// * `offspring` - the offspring to create
+pub fn try_batch_create_offspring<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    entropy: String,
+    offspring: Vec<OffspringSpec>,
+) -> HandleResult {
+    if offspring.is_empty() || offspring.len() > MAX_BATCH_CREATE {
+        return Err(StdError::generic_err(format!(
+            "A batch must create between 1 and {} offspring",
+            MAX_BATCH_CREATE
+        )));
+    }
+    let config: Config = load(&deps.storage, CONFIG_KEY)?;
+    enforce_entropy(&config, &entropy)?;
+    // the fee of every offspring in the batch is paid up front
+    let fee = native_fee(&config)?;
+    if native_amount(&env.message.sent_funds)? != fee * offspring.len() as u128 {
+        return Err(StdError::generic_err(format!(
+            "Creating {} offspring costs exactly {}{}",
+            offspring.len(),
+            fee * offspring.len() as u128,
+            NATIVE_DENOM
+        )));
+    }
+    let creator = deps.api.canonical_address(&env.message.sender)?;
+
+    let mut messages = vec![];
+    let mut log = vec![];
+    let mut results = vec![];
+    let mut failed: u128 = 0;
+    for mut spec in offspring.into_iter() {
+        let label = match check_batch_item(deps, &env, &config, &creator, &spec) {
+            Ok(label) => label,
+            Err(e) => {
+                failed += 1;
+                results.push(BatchCreateResult {
+                    label: None,
+                    status: Failure,
+                    error: Some(match e {
+                        StdError::GenericErr { msg, .. } => msg,
+                        other => other.to_string(),
+                    }),
+                });
+                continue;
+            }
+        };
+        record_creation(&mut deps.storage, &config, &creator, env.block.height)?;
+        spec.label = Some(label.clone());
+        log.extend(lifecycle_log("create", None, &spec.owner, &label, &env));
+        let (cosmosmsg, new_prng_bytes) = instantiate_offspring(
+            &mut deps.storage,
+            &deps.api,
+            &env,
+            &config,
+            spec,
+            None,
+            entropy.as_bytes(),
+        )?;
+        record_pending(
+            &mut deps.storage,
+            &config,
+            &creator,
+            &derive_password(&new_prng_bytes),
+            env.block.height,
+        )?;
+        messages.push(cosmosmsg);
+        results.push(BatchCreateResult {
+            label: Some(label),
+            status: Success,
+            error: None,
+        });
+    }
+
+    collect_fees(&mut deps.storage, &config, fee * (results.len() as u128 - failed), None)?;
+    // refund the fee of the offspring that were not created
+    if fee > 0 && failed > 0 {
+        messages.push(CosmosMsg::Bank(BankMsg::Send {
+            from_address: env.contract.address.clone(),
+            to_address: env.message.sender.clone(),
+            amount: vec![Coin {
+                denom: NATIVE_DENOM.to_string(),
+                amount: Uint128(fee * failed),
+            }],
+        }));
+    }
+
+    ok_with_log(
+        messages,
+        log,
+        Some(HandleAnswer::BatchCreateOffspring { results }),
+    )
+}
+
+/// Returns StdResult<String> with the label the offspring will be instantiated with
+///
+/// runs the creation checks of one offspring of a batch, without changing any state
+///
+/// # Arguments
+///
+/// * `deps` - reference to Extern containing all the contract's external dependencies
+/// * `env` - a reference to the Env of contract's environment
+/// * `config` - a reference to the factory config
+/// * `creator` - a reference to the canonical address of the creator
+/// * `spec` - a reference to the offspring to create
+fn check_batch_item<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    env: &Env,
+    config: &Config,
+    creator: &CanonicalAddr,
+    spec: &OffspringSpec,
+) -> StdResult<String> {
+    let owner = validate_address(&deps.storage, &deps.api, &spec.owner)?;
+    enforce_create_rights(&deps.storage, &deps.api, config, &owner, &env.message.sender)?;
+    let offspring_contract = resolve_version(&deps.storage, config, spec.version)?;
+    let request = CreationRequest {
+        creator,
+        // the fee was paid for the whole batch
+        sent_funds: None,
+        token_fee_paid: false,
+        label: spec.label.as_deref(),
+        height: Some(env.block.height),
+        code_id: offspring_contract.code_id,
+    };
+    if let Some(reason) = run_pipeline(&deps.storage, config, &request).into_iter().next() {
+        return Err(StdError::generic_err(reason));
+    }
+    match &spec.label {
+        Some(label) => Ok(label.clone()),
+        None => Ok(auto_label(
+            &env.contract.address,
+            &spec.owner,
+            load_creation_nonce(&deps.storage, &deps.api, &spec.owner)?,
+        )),
+    }
+}
+
+/// Returns StdResult<(CosmosMsg, [u8; 32])> with the message instantiating the offspring and the
+/// prng bytes its password was derived from
+///
+/// uses up the owner's creation nonce, advances the prng, and waits for the offspring to register
+/// with the derived password
+///
+/// # Arguments
+///
+/// * `storage` - mutable reference to contract's storage
+/// * `api` - reference to the Api used to convert human and canonical addresses
+/// * `env` - reference to the Env of contract's environment
+/// * `config` - a reference to the factory config
+/// * `spec` - the offspring to create
+/// * `dependencies` - optional names of address book entries the offspring should resolve
+/// * `entropy` - entropy used to advance the prng
+pub fn instantiate_offspring<S: Storage, A: Api>(
+    storage: &mut S,
+    api: &A,
+    env: &Env,
+    config: &Config,
+    spec: OffspringSpec,
+    dependencies: Option<Vec<String>>,
+    entropy: &[u8],
+) -> StdResult<(CosmosMsg, [u8; 32])> {
+    let factory = ContractInfo {
+        code_hash: env.contract_code_hash.clone(),
+        address: env.contract.address.clone(),
+    };
+
+    // every creation for the owner uses up a nonce, so generated labels are unique
+    let nonce = load_creation_nonce(storage, api, &spec.owner)?;
+    let owner_raw = api.canonical_address(&spec.owner)?;
+    let mut nonce_store = PrefixedStorage::new(PREFIX_CREATION_NONCES, storage);
+    save(&mut nonce_store, owner_raw.as_slice(), &(nonce + 1))?;
+    let label = match spec.label {
+        Some(label) => label,
+        None => auto_label(&factory.address, &spec.owner, nonce),
+    };
+
+    // generate and save new prng, and password. (we only register an offspring retuning the matching password)
+    let prng_seed: Vec<u8> = load(storage, PRNG_SEED_KEY)?;
+    let new_prng_bytes = new_entropy(env, prng_seed.as_ref(), entropy);
+    save(storage, PRNG_SEED_KEY, &new_prng_bytes.to_vec())?;
+
+    // store the password's hash for future authentication. Every creation gets its own entry,
+    // so offspring created in the same block can not overwrite each other's password
+    let password = derive_password(&new_prng_bytes);
+    let version = resolve_version(storage, config, spec.version)?;
+    let expires_at = if config.require_registration_same_tx {
+        env.block.height
+    } else {
+        env.block.height.saturating_add(config.registration_retry_blocks)
+    };
+    let key = sha_256(&password);
+    let mut pending_store: CashMap<PendingOffspring, _> = CashMap::init(PENDING_KEY, storage);
+    pending_store.insert(
+        &key,
+        PendingOffspring {
+            key,
+            label: label.clone(),
+            height: env.block.height,
+            expires_at,
+            owner: owner_raw,
+            code_hash: version.code_hash.clone(),
+        },
+    )?;
+    reserve_label(storage, &label, &key)?;
+
+    let initmsg = OffspringInitMsg {
+        factory,
+        label: label.clone(),
+        password,
+        owner: spec.owner,
+        count: spec.count,
+        description: spec.description,
+        dependencies,
+        count_privacy: spec.count_privacy,
+        factory_build: Some(BuildInfo::current()),
+    };
+
+    let cosmosmsg = initmsg.to_cosmos_msg(
+        label,
+        version.code_id,
+        version.code_hash,
+        config.callback_policy.instantiate,
+    )?;
+    Ok((cosmosmsg, new_prng_bytes))
+}
+
+/// Returns StdResult<Vec<CosmosMsg>> with the messages instantiating the offspring listed in
+/// InitMsg
+///
+/// all of them are instantiated at init. Each one waits for its own registration among the
+/// pending offspring, so they can register in any order, and one that fails to register does not
+/// hold up the others
+///
+/// # Arguments
+///
+/// * `storage` - mutable reference to contract's storage
+/// * `api` - reference to the Api used to convert human and canonical addresses
+/// * `env` - reference to the Env of contract's environment
+/// * `config` - a reference to the factory config
+/// * `bootstrap` - the offspring to create
+pub fn bootstrap_offspring<S: Storage, A: Api>(
+    storage: &mut S,
+    api: &A,
+    env: &Env,
+    config: &Config,
+    bootstrap: Vec<OffspringSpec>,
+) -> StdResult<Vec<CosmosMsg>> {
+    bootstrap
+        .into_iter()
+        .enumerate()
+        .map(|(index, spec)| {
+            let entropy = format!("bootstrap {} {}", spec.owner, index);
+            instantiate_offspring(storage, api, env, config, spec, None, entropy.as_bytes())
+                .map(|(msg, _)| msg)
+        })
+        .collect()
+}
+
+/// Returns StdResult<u64> with the nonce of the next offspring created for the owner
+///
+/// # Arguments
+///
+/// * `storage` - a reference to the contract's storage
+/// * `api` - reference to the Api used to convert human and canonical addresses
+/// * `owner` - a reference to the address of the owner
+pub fn load_creation_nonce<S: ReadonlyStorage, A: Api>(
+    storage: &S,
+    api: &A,
+    owner: &HumanAddr,
+) -> StdResult<u64> {
+    let owner_raw = api.canonical_address(owner)?;
+    let nonce_store = ReadonlyPrefixedStorage::new(PREFIX_CREATION_NONCES, storage);
+    Ok(may_load(&nonce_store, owner_raw.as_slice())?.unwrap_or(0))
+}
+
+/// Returns String with the label generated for an offspring created without one
+///
+/// # Arguments
+///
+/// * `factory` - a reference to the factory's address
+/// * `owner` - a reference to the address of the owner
+/// * `nonce` - the owner's creation nonce
+pub fn auto_label(factory: &HumanAddr, owner: &HumanAddr, nonce: u64) -> String {
+    format!("{}-{}-{}", factory, owner, nonce)
+}
+
+/// Returns HandleResult
+///
+/// sends the creation fees credited to the referrer who sent the message
+///
+/// # Arguments
+///
+/// * `deps` - mutable reference to Extern containing all the contract's external dependencies
+/// * `env` - Env of contract's environment
+/// * `amount` - optional amount to withdraw. Defaults to all credited fees
+/// * `token` - optional SNIP-20 token whose credited fees are withdrawn. Defaults to uscrt
+pub fn try_withdraw_referral_fees<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    amount: Option<Uint128>,
+    token: Option<ContractInfo>,
+) -> HandleResult {
+    let referrer_raw = deps.api.canonical_address(&env.message.sender)?;
+    let mut referral_store = PrefixedStorage::new(PREFIX_REFERRALS, &mut deps.storage);
+    let mut stats: ReferralStats =
+        may_load(&referral_store, referrer_raw.as_slice())?.unwrap_or_default();
+    let (credited, unit) = match &token {
+        Some(token) => (
+            stats
+                .token_earned
+                .iter()
+                .find(|earned| earned.token == token.address)
+                .map_or(0, |earned| earned.amount.u128()),
+            " tokens",
+        ),
+        None => (stats.earned.u128(), NATIVE_DENOM),
+    };
+    let withdrawn = amount.map(|amount| amount.u128()).unwrap_or(credited);
+    if withdrawn == 0 || withdrawn > credited {
+        return Err(StdError::generic_err(format!(
+            "Withdrawals must be between 1 and the {}{} of credited referral fees",
+            credited, unit
+        )));
+    }
+    let remaining = credited - withdrawn;
+    let message = match token {
+        Some(token) => {
+            stats.token_earned.retain(|earned| earned.token != token.address);
+            if remaining > 0 {
+                stats.token_earned.push(TokenEarnings {
+                    token: token.address.clone(),
+                    amount: Uint128(remaining),
+                });
+            }
+            Snip20HandleMsg::Transfer {
+                recipient: env.message.sender.clone(),
+                amount: Uint128(withdrawn),
+            }
+            .to_cosmos_msg(token.code_hash, token.address, None)?
+        }
+        None => {
+            stats.earned = Uint128(remaining);
+            CosmosMsg::Bank(BankMsg::Send {
+                from_address: env.contract.address.clone(),
+                to_address: env.message.sender.clone(),
+                amount: vec![Coin {
+                    denom: NATIVE_DENOM.to_string(),
+                    amount: Uint128(withdrawn),
+                }],
+            })
+        }
+    };
+    save(&mut referral_store, referrer_raw.as_slice(), &stats)?;
+
+    ok_with_log(
+        vec![message],
+        vec![],
+        Some(HandleAnswer::WithdrawReferralFees {
+            status: Success,
+            withdrawn: Uint128(withdrawn),
+            remaining: Uint128(remaining),
+        }),
+    )
+}
+
+/// Returns StdResult<()>
+///
+/// increments the referral count of a referrer
+///
+/// # Arguments
+///
+/// * `storage` - mutable reference to the contract's storage
+/// * `api` - reference to the Api used to convert human and canonical addresses
+/// * `referrer` - reference to the address of the referrer
+pub fn record_referral<S: Storage, A: Api>(
+    storage: &mut S,
+    api: &A,
+    referrer: &HumanAddr,
+) -> StdResult<()> {
+    let referrer_raw = api.canonical_address(referrer)?;
+    let mut referral_store = PrefixedStorage::new(PREFIX_REFERRALS, storage);
+    let mut stats: ReferralStats =
+        may_load(&referral_store, referrer_raw.as_slice())?.unwrap_or_default();
+    stats.referrals += 1;
+    save(&mut referral_store, referrer_raw.as_slice(), &stats)
+}
+
+/// Returns StdResult<()>
+///
+/// increments the number of creations made with a creation context
+///
+/// # Arguments
+///
+/// * `storage` - mutable reference to the contract's storage
+/// * `context` - the creation context
+pub fn record_context<S: Storage>(storage: &mut S, context: String) -> StdResult<()> {
+    let mut context_store: CashMap<ContextStats, _> = CashMap::init(CONTEXTS_KEY, storage);
+    let mut stats = context_store
+        .get(context.as_bytes())
+        .unwrap_or(ContextStats {
+            context: context.clone(),
+            creations: 0,
+        });
+    stats.creations += 1;
+    context_store.insert(context.as_bytes(), stats)
+}
+
+/// Returns bool indicating whether offspring may be created from the given code id
+///
+/// # Arguments
+///
+/// * `storage` - a reference to the contract's storage
+/// * `code_id` - code id of the offspring version
+pub fn is_version_enabled<S: ReadonlyStorage>(storage: &S, code_id: u64) -> bool {
+    ReadonlyPrefixedStorage::new(PREFIX_DISABLED_VERSIONS, storage)
+        .get(&code_id.to_be_bytes())
+        .is_none()
+}
+
+/// Returns HandleResult
+///
+/// forgets the sender's creations that did not register yet, so they no longer count against the
+/// pending limit. Their offspring can not register anymore
+///
+/// # Arguments
+///
+/// * `deps` - mutable reference to Extern containing all the contract's external dependencies
+/// * `env` - Env of contract's environment
+pub fn try_cancel_pending_creations<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+) -> HandleResult {
+    let creator = deps.api.canonical_address(&env.message.sender)?;
+    let cancelled = take_pending_creations(&mut deps.storage, &creator)?;
+    if cancelled.is_empty() {
+        return Err(StdError::generic_err(
+            "You have no creations waiting for registration",
+        ));
+    }
+    for creation in cancelled.iter() {
+        let mut pending_store: CashMap<PendingOffspring, _> =
+            CashMap::init(PENDING_KEY, &mut deps.storage);
+        if let Some(pending) = pending_store.get(&creation.key) {
+            pending_store.remove(&creation.key)?;
+            release_label(&mut deps.storage, &pending.label, &creation.key)?;
+        }
+    }
+
+    ok_answer(HandleAnswer::Status {
+        status: Success,
+        message: Some(format!("Cancelled {} pending creations", cancelled.len())),
+    })
+}
+
+/// Returns StdResult<PendingOffspring> with the creation the password belongs to, and forgets
+/// it so the offspring can only register once
+///
+/// the password must belong to an offspring that did not register yet and whose registration
+/// window has not passed
+///
+/// # Arguments
+///
+/// * `storage` - mutable reference to contract's storage
+/// * `password` - a reference to the password the offspring registered with
+/// * `height` - current block height
+fn take_pending<S: Storage>(
+    storage: &mut S,
+    password: &[u8; 32],
+    height: u64,
+) -> StdResult<PendingOffspring> {
+    let key = sha_256(password);
+    let mut pending_store: CashMap<PendingOffspring, _> = CashMap::init(PENDING_KEY, storage);
+    let pending = pending_store
+        .get(&key)
+        .ok_or_else(|| StdError::generic_err("Unable to authenticate registration."))?;
+    pending_store.remove(&key)?;
+    release_label(storage, &pending.label, &key)?;
+    if height > pending.expires_at {
+        return Err(StdError::generic_err(
+            "The time to register this offspring has passed",
+        ));
+    }
+    Ok(pending)
+}
+
+/// Returns HandleResult
+///
+/// Registers the calling offspring by saving its info and adding it to the appropriate lists
+///
+/// # Arguments
+///
+/// * `deps` - mutable reference to Extern containing all the contract's external dependencies
+/// * `env` - Env of contract's environment
+/// * `owner` - reference to the address of the offspring's owner
+/// * `reg_offspring` - reference to RegisterOffspringInfo of the offspring that is trying to register
+pub fn try_register_offspring<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    owner: HumanAddr,
+    reg_offspring: &RegisterOffspringInfo,
+) -> HandleResult {
+    // verify this is one of the offspring we are waiting for
+    let config: Config = load(&deps.storage, CONFIG_KEY)?;
+    let pending = match take_pending(&mut deps.storage, &reg_offspring.password, env.block.height) {
+        Ok(pending) => pending,
+        Err(_) if config.quarantine_registrations => {
+            return quarantine_registration(deps, env, owner, reg_offspring);
+        }
+        Err(e) => return Err(e),
+    };
+    release_pending(&mut deps.storage, &reg_offspring.password)?;
+    if config.require_registration_same_tx {
+        if pending.height != env.block.height {
+            return Err(StdError::generic_err(
+                "Registration must happen in the same transaction as the creation",
+            ));
+        }
+        if pending.owner != deps.api.canonical_address(&owner)? {
+            return Err(StdError::generic_err(
+                "owner does not match the offspring we are creating",
+            ));
+        }
+    }
+
+    // convert register offspring info to storage format
+    let offspring_addr = deps.api.canonical_address(&env.message.sender)?;
+    enforce_not_blocked(&deps.storage, &offspring_addr)?;
+    let mut offspring =
+        reg_offspring.to_store_offspring_info(env.message.sender.clone(), &env.block);
+    let (description, truncated) =
+        seal_description(&deps.storage, offspring_addr.as_slice(), offspring.description)?;
+    offspring.description = description;
+    offspring.description_truncated = truncated;
+
+    // save the offspring info and add it to the owner's list
+    let confirm_msg = complete_registration(
+        &mut deps.storage,
+        &deps.api,
+        &config,
+        &owner,
+        offspring,
+        &pending.code_hash,
+        &pending.key,
+        env.block.height,
+    )?;
+    let messages = vec![confirm_msg];
+    let mut log = lifecycle_log(
+        "register",
+        Some(&env.message.sender),
+        &owner,
+        &reg_offspring.label,
+        &env,
+    );
+    log.extend(checkpoint_log(&deps.storage, &config)?);
+
+    ok_with_log(messages, log, None)
+}
+
+/// Returns StdResult<CosmosMsg> with the message confirming the registration to the offspring
+///
+/// adds an authenticated offspring to the registry, numbers its registration, and remembers the
+/// code hash of the version it was created from
+///
+/// # Arguments
+///
+/// * `storage` - mutable reference to contract's storage
+/// * `api` - reference to the Api used to convert human and canonical addresses
+/// * `config` - a reference to the factory config
+/// * `owner` - a reference to the address of the offspring's owner
+/// * `offspring` - the offspring's info in storage format
+/// * `code_hash` - code hash of the version the offspring was created from
+/// * `key` - hash of the password the offspring registered with
+/// * `height` - current block height
+#[allow(clippy::too_many_arguments)]
+pub fn complete_registration<S: Storage, A: Api>(
+    storage: &mut S,
+    api: &A,
+    config: &Config,
+    owner: &HumanAddr,
+    offspring: StoreOffspringInfo,
+    code_hash: &str,
+    key: &[u8; 32],
+    height: u64,
+) -> StdResult<CosmosMsg> {
+    let address = offspring.address.clone();
+    let offspring_addr = api.canonical_address(&address)?;
+    registry::register(storage, &offspring_addr, owner, offspring)?;
+    record_usage(storage, api, owner, Usage::Created, height)?;
+
+    // remember the code hash for messages the factory sends the offspring later
+    let mut code_hash_store = PrefixedStorage::new(PREFIX_OFFSPRING_CODE_HASH, storage);
+    save(&mut code_hash_store, offspring_addr.as_slice(), &code_hash.to_string())?;
+
+    // remember the password's hash so the offspring can authenticate if it reactivates
+    let mut password_store = PrefixedStorage::new(PREFIX_OFFSPRING_PASSWORDS, storage);
+    save(&mut password_store, offspring_addr.as_slice(), key)?;
+
+    // number the registration so the factory and the offspring agree on it
+    let index: u64 = may_load(storage, REGISTRATION_COUNT_KEY)?.unwrap_or(0);
+    save(storage, REGISTRATION_COUNT_KEY, &(index + 1))?;
+    let mut index_store = PrefixedStorage::new(PREFIX_REGISTRATION_INDEX, storage);
+    save(&mut index_store, offspring_addr.as_slice(), &index)?;
+
+    // let the offspring know the handshake completed
+    OffspringHandleMsg::ConfirmRegistration { index }.to_cosmos_msg(
+        code_hash.to_string(),
+        address,
+        config.callback_policy.confirm_registration,
+    )
+}
+
+/// Returns HandleResult
+///
+/// deactivates the offspring by saving its info and adding/removing it to/from the
+/// appropriate lists
+///
+/// # Arguments
+///
+/// * `deps` - mutable reference to Extern containing all the contract's external dependencies
+/// * `env` - Env of contract's environment
+/// * `owner` - offspring's owner
+/// * `expired` - true if the offspring expired from inactivity rather than being deactivated by
+///   its owner
+pub fn try_deactivate_offspring<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    owner: &HumanAddr,
+    expired: bool,
+) -> HandleResult {
+
+    let offspring_addr = &deps.api.canonical_address(&env.message.sender)?;
+
+    // verify offspring is in active list, and not a spam attempt
+    let offspring_info = authenticate_offspring(&deps.storage, offspring_addr)?;
+    // move the offspring from the active lists to the inactive lists
+    let deactivated_by = if expired {
+        DeactivationSource::Expired
+    } else {
+        DeactivationSource::Owner
+    };
+    registry::deactivate(
+        &mut deps.storage,
+        offspring_addr,
+        owner,
+        &offspring_info,
+        &env.block,
+        deactivated_by,
+    )?;
+    record_usage(&mut deps.storage, &deps.api, owner, Usage::Deactivated, env.block.height)?;
+
+    ok_with_log(
+        vec![],
+        lifecycle_log(
+            "deactivate",
+            Some(&env.message.sender),
+            owner,
+            &offspring_info.label,
+            &env,
+        ),
+        None,
+    )
+}
+
+/// Returns HandleResult
+///
+/// moves the calling offspring to the inactive lists after its owner repointed it to another
+/// factory, so this factory's registry no longer lists it as active
+///
+/// # Arguments
+///
+/// * `deps` - mutable reference to Extern containing all the contract's external dependencies
+/// * `env` - Env of contract's environment
+pub fn try_detach_offspring<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+) -> HandleResult {
+    let offspring_addr = &deps.api.canonical_address(&env.message.sender)?;
+
+    // verify offspring is in active list, and not a spam attempt
+    let offspring_info = authenticate_offspring(&deps.storage, offspring_addr)?;
+    let owner: HumanAddr = load(
+        &ReadonlyPrefixedStorage::new(PREFIX_OFFSPRING_OWNER, &deps.storage),
+        offspring_addr.as_slice(),
+    )?;
+    registry::deactivate(
+        &mut deps.storage,
+        offspring_addr,
+        &owner,
+        &offspring_info,
+        &env.block,
+        DeactivationSource::Detached,
+    )?;
+    record_usage(&mut deps.storage, &deps.api, &owner, Usage::Deactivated, env.block.height)?;
+
+    ok_with_log(
+        vec![],
+        lifecycle_log(
+            "deactivate",
+            Some(&env.message.sender),
+            &owner,
+            &offspring_info.label,
+            &env,
+        ),
+        None,
+    )
+}
+
+/// Returns HandleResult
+///
+/// reactivates the calling offspring by moving it from the inactive lists back to the active
+/// lists. The offspring authenticates with the password it registered with. Offspring that the
+/// admin deactivated can not reactivate
+///
+/// # Arguments
+///
+/// * `deps` - mutable reference to Extern containing all the contract's external dependencies
+/// * `env` - Env of contract's environment
+/// * `password` - a reference to the password the offspring registered with
+pub fn try_reactivate_offspring<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    password: &[u8; 32],
+) -> HandleResult {
+    let offspring_addr = deps.api.canonical_address(&env.message.sender)?;
+    let inactive_store: ReadOnlyCashMap<StoreInactiveOffspringInfo, _> =
+        ReadOnlyCashMap::init(INACTIVE_KEY, &deps.storage);
+    let inactive_info = inactive_store
+        .get(offspring_addr.as_slice())
+        .ok_or_else(|| {
+            StdError::generic_err("This is not an inactive offspring registered with factory.")
+        })
+        .and_then(verify)?;
+    let key: Option<[u8; 32]> = may_load(
+        &ReadonlyPrefixedStorage::new(PREFIX_OFFSPRING_PASSWORDS, &deps.storage),
+        offspring_addr.as_slice(),
+    )?;
+    if key != Some(sha_256(password)) {
+        return Err(StdError::generic_err("Unable to authenticate reactivation."));
+    }
+    if inactive_info.deactivated_by == DeactivationSource::Admin {
+        return Err(StdError::generic_err(
+            "This offspring was deactivated by the admin and can not be reactivated",
+        ));
+    }
+    if inactive_info.deactivated_by == DeactivationSource::Detached {
+        return Err(StdError::generic_err(
+            "This offspring was repointed to another factory and can not be reactivated",
+        ));
+    }
+    enforce_not_blocked(&deps.storage, &offspring_addr)?;
+    let owner: HumanAddr = load(
+        &ReadonlyPrefixedStorage::new(PREFIX_OFFSPRING_OWNER, &deps.storage),
+        offspring_addr.as_slice(),
+    )?;
+    let label = inactive_info.label.clone();
+    registry::reactivate(&mut deps.storage, &offspring_addr, &owner, inactive_info)?;
+
+    ok_with_log(
+        vec![],
+        lifecycle_log("reactivate", Some(&env.message.sender), &owner, &label, &env),
+        None,
+    )
+}
+
+/// Returns HandleResult
+///
+/// updates the registry copy of the calling offspring's description
+///
+/// # Arguments
+///
+/// * `deps` - mutable reference to Extern containing all the contract's external dependencies
+/// * `env` - Env of contract's environment
+/// * `description` - the offspring's new description
+pub fn try_update_offspring_description<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    description: Option<String>,
+) -> HandleResult {
+    let offspring_addr = deps.api.canonical_address(&env.message.sender)?;
+
+    // verify offspring is in active list, and not a spam attempt
+    let mut offspring_info = authenticate_offspring(&deps.storage, &offspring_addr)?;
+    let (description, truncated) =
+        seal_description(&deps.storage, offspring_addr.as_slice(), description)?;
+    offspring_info.description = description;
+    offspring_info.description_truncated = truncated;
+    let owner: HumanAddr = load(
+        &ReadonlyPrefixedStorage::new(PREFIX_OFFSPRING_OWNER, &deps.storage),
+        offspring_addr.as_slice(),
+    )?;
+    registry::update_active(&mut deps.storage, &offspring_addr, &owner, offspring_info)?;
+
+    ok_empty()
+}
+
+/// Returns HandleResult
+///
+/// updates the registry copy of the privacy level of the calling offspring's count
+///
+/// # Arguments
+///
+/// * `deps` - mutable reference to Extern containing all the contract's external dependencies
+/// * `env` - Env of contract's environment
+/// * `count_privacy` - the offspring's new count privacy level
+pub fn try_update_offspring_count_privacy<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    count_privacy: Option<CountPrivacy>,
+) -> HandleResult {
+    let offspring_addr = deps.api.canonical_address(&env.message.sender)?;
+
+    // verify offspring is in active list, and not a spam attempt
+    let mut offspring_info = authenticate_offspring(&deps.storage, &offspring_addr)?;
+    offspring_info.count_privacy = count_privacy;
+    let owner: HumanAddr = load(
+        &ReadonlyPrefixedStorage::new(PREFIX_OFFSPRING_OWNER, &deps.storage),
+        offspring_addr.as_slice(),
+    )?;
+    registry::update_active(&mut deps.storage, &offspring_addr, &owner, offspring_info)?;
+
+    ok_empty()
+}
+
+/// Returns HandleResult
+///
+/// replaces the custom attributes of the calling offspring in the registry
+///
+/// # Arguments
+///
+/// * `deps` - mutable reference to Extern containing all the contract's external dependencies
+/// * `env` - Env of contract's environment
+/// * `attributes` - the offspring's new attributes
+pub fn try_update_offspring_attributes<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    attributes: Vec<OffspringAttribute>,
+) -> HandleResult {
+    if attributes.len() > MAX_OFFSPRING_ATTRIBUTES {
+        return Err(StdError::generic_err(format!(
+            "An offspring can have at most {} attributes",
+            MAX_OFFSPRING_ATTRIBUTES
+        )));
+    }
+    let mut keys = HashSet::new();
+    for attribute in attributes.iter() {
+        if attribute.key.is_empty() || attribute.key.len() > MAX_ATTRIBUTE_KEY_LEN {
+            return Err(StdError::generic_err(format!(
+                "Attribute keys must be between 1 and {} bytes long",
+                MAX_ATTRIBUTE_KEY_LEN
+            )));
+        }
+        if attribute.value.len() > MAX_ATTRIBUTE_VALUE_LEN {
+            return Err(StdError::generic_err(format!(
+                "Attribute values can be at most {} bytes long",
+                MAX_ATTRIBUTE_VALUE_LEN
+            )));
+        }
+        if !keys.insert(attribute.key.as_str()) {
+            return Err(StdError::generic_err(format!(
+                "Attribute {} is listed more than once",
+                attribute.key
+            )));
+        }
+    }
+
+    let offspring_addr = deps.api.canonical_address(&env.message.sender)?;
+
+    // verify offspring is in active list, and not a spam attempt
+    let mut offspring_info = authenticate_offspring(&deps.storage, &offspring_addr)?;
+    offspring_info.attributes = attributes;
+    let owner: HumanAddr = load(
+        &ReadonlyPrefixedStorage::new(PREFIX_OFFSPRING_OWNER, &deps.storage),
+        offspring_addr.as_slice(),
+    )?;
+    registry::update_active(&mut deps.storage, &offspring_addr, &owner, offspring_info)?;
+
+    ok_empty()
+}
+
+/// Returns HandleResult
+///
+/// sets or clears the link to off-chain metadata about one of the sender's active offspring
+///
+/// # Arguments
+///
+/// * `deps` - mutable reference to Extern containing all the contract's external dependencies
+/// * `env` - Env of contract's environment
+/// * `offspring` - address of the offspring
+/// * `url` - optional url of the metadata. If not specified, the link is removed
+pub fn try_set_metadata_url<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    offspring: HumanAddr,
+    url: Option<String>,
+) -> HandleResult {
+    if let Some(link) = url.as_ref() {
+        if link.is_empty() || link.len() > MAX_METADATA_URL_LEN {
+            return Err(StdError::generic_err(format!(
+                "Metadata urls must be between 1 and {} bytes long",
+                MAX_METADATA_URL_LEN
+            )));
+        }
+        if link.chars().any(char::is_whitespace) || !link.contains("://") {
+            return Err(StdError::generic_err(
+                "Metadata urls must include a scheme, like ipfs:// or https://, and no whitespace",
+            ));
+        }
+    }
+    let offspring_addr = deps.api.canonical_address(&offspring)?;
+    let mut offspring_info = authenticate_offspring(&deps.storage, &offspring_addr)?;
+    let owner: HumanAddr = load(
+        &ReadonlyPrefixedStorage::new(PREFIX_OFFSPRING_OWNER, &deps.storage),
+        offspring_addr.as_slice(),
+    )?;
+    if owner != env.message.sender {
+        return Err(StdError::generic_err(
+            "Only the owner of an offspring may set its metadata url",
+        ));
+    }
+    offspring_info.metadata_url = url;
+    registry::update_active(&mut deps.storage, &offspring_addr, &owner, offspring_info)?;
+
+    ok_status()
+}
+
+/// Returns StdResult<(StoreOffspringInfo)>
+///
+/// verifies that the offspring is in the active list, and returns the active offspring info
+///
+/// # Arguments
+///
+/// * `storage` - a reference to contract's storage
+/// * `offspring` - a reference to the offspring's address
+pub fn authenticate_offspring<S: ReadonlyStorage>(
+    storage: &S,
+    offspring: &CanonicalAddr,
+) -> StdResult<StoreOffspringInfo> {
+    let info_store: ReadOnlyCashMap<StoreOffspringInfo, _, _> = ReadOnlyCashMap::init(ACTIVE_KEY, storage);
+
+    let info = info_store.get(offspring.as_slice());
+
+    if let Some(offspring_info) = info {
+        verify(offspring_info)
+    } else {
+        return Err(not_registered_offspring());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::msg::{CallbackPolicy, CreationRules, OffspringContractInfo};
+    use cosmwasm_std::testing::MockStorage;
+    use cosmwasm_std::CanonicalAddr;
+
+    fn config() -> Config {
+        Config {
+            version: OffspringContractInfo {
+                code_id: 1,
+                code_hash: "hash".to_string(),
+            },
+            previous_version: None,
+            stopped: false,
+            admin: CanonicalAddr::from(b"admin".to_vec()),
+            pending_admin: None,
+            tombstone_retention: 0,
+            default_page_size: 1,
+            private_listing: false,
+            auto_viewing_key: false,
+            require_registration_same_tx: false,
+            require_owner_is_sender: false,
+            min_entropy_len: 8,
+            min_entropy_distinct: 4,
+            maintenance_budget: 0,
+            contract_address: HumanAddr("factory".to_string()),
+            callback_policy: CallbackPolicy::default(),
+            registration_retry_blocks: 0,
+            quarantine_registrations: false,
+            creation_rules: CreationRules::default(),
+            address_prefix: None,
+            description_limit: None,
+            features: 0,
+            stats_checkpoint_interval: 0,
+        }
+    }
+
+    /// This test checks the password and viewing key derivations against golden values. If it
+    /// fails, a refactor changed the outputs and already deployed offspring would no longer
+    /// authenticate.
+    #[test]
+    fn test_derivation_golden_values() {
+        let seed = derive_init_seed("factory entropy");
+        let expected_seed: [u8; 32] = [
+            242, 47, 231, 22, 49, 189, 189, 98, 168, 64, 55, 38, 177, 114, 239, 238, 2, 71, 13, 49,
+            84, 183, 5, 100, 24, 69, 51, 38, 21, 238, 102, 85,
+        ];
+        assert_eq!(seed, expected_seed.to_vec());
+
+        let prng_bytes =
+            derive_entropy(12345, 1_600_000_000, "secret1owner", &seed, b"offspring entropy");
+        let expected_prng_bytes: [u8; 32] = [
+            222, 20, 187, 172, 172, 253, 101, 205, 87, 54, 158, 133, 233, 132, 105, 87, 70, 43, 49,
+            240, 139, 227, 126, 111, 232, 128, 24, 85, 107, 182, 65, 163,
+        ];
+        assert_eq!(prng_bytes, expected_prng_bytes);
+
+        let expected_password: [u8; 32] = [
+            193, 181, 66, 52, 216, 1, 122, 242, 28, 221, 54, 220, 89, 104, 152, 64, 160, 38, 54,
+            123, 224, 150, 204, 111, 41, 50, 166, 200, 137, 47, 77, 125,
+        ];
+        assert_eq!(derive_password(&prng_bytes), expected_password);
+
+        let expected_key_entropy: [u8; 32] = [
+            158, 164, 75, 219, 104, 108, 86, 8, 21, 177, 112, 141, 8, 71, 255, 150, 240, 92, 229,
+            58, 87, 198, 79, 9, 252, 249, 91, 60, 47, 59, 47, 154,
+        ];
+        assert_eq!(derive_key_entropy(&prng_bytes), expected_key_entropy);
+    }
+
+    /// This test checks that short or constant entropy is rejected.
+    #[test]
+    fn test_enforce_entropy() {
+        let config = config();
+        assert!(enforce_entropy(&config, "").is_err());
+        assert!(enforce_entropy(&config, "abc").is_err());
+        assert!(enforce_entropy(&config, "aaaaaaaaaaaa").is_err());
+        assert!(enforce_entropy(&config, "random_words").is_ok());
+    }
+
+    /// This test checks that offspring created in the same block can register in any order, each
+    /// only once, and not after their registration window has passed.
+    #[test]
+    fn test_concurrent_pending() {
+        let mut storage = MockStorage::default();
+        let mut pending_store = CashMap::init(PENDING_KEY, &mut storage);
+        for (password, expires_at) in [([1u8; 32], 100), ([2u8; 32], 100), ([3u8; 32], 99)].iter()
+        {
+            let key = sha_256(password);
+            let pending = PendingOffspring {
+                key,
+                label: "counter".to_string(),
+                height: 99,
+                expires_at: *expires_at,
+                owner: CanonicalAddr::from(b"alice".to_vec()),
+                code_hash: "hash".to_string(),
+            };
+            pending_store.insert(&key, pending).unwrap();
+        }
+
+        assert!(take_pending(&mut storage, &[2u8; 32], 100).is_ok());
+        assert!(take_pending(&mut storage, &[1u8; 32], 100).is_ok());
+        assert!(take_pending(&mut storage, &[1u8; 32], 100).is_err());
+        assert!(take_pending(&mut storage, &[3u8; 32], 100).is_err());
+    }
+}
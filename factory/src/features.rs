@@ -0,0 +1,167 @@
+use cosmwasm_std::{
+    to_binary, Api, Env, Extern, HandleResult, Querier, QueryResult, StdError, StdResult, Storage,
+};
+
+use crate::{
+    admin::{enforce_admin, record_audit},
+    msg::{Feature, HandleMsg, QueryAnswer},
+    response::ok_status,
+    state::{load, save, Config, CONFIG_KEY},
+};
+
+/// every optional subsystem that can be switched on or off, in the order of their bits
+pub const FEATURES: &[Feature] = &[
+    Feature::BatchCreation,
+    Feature::TokenFees,
+    Feature::Referrals,
+    Feature::Delegation,
+    Feature::Transfers,
+    Feature::IssueReports,
+    Feature::Recovery,
+];
+
+/// Returns u32 with the bit of the feature in the factory's feature set
+///
+/// # Arguments
+///
+/// * `feature` - the feature
+fn bit(feature: Feature) -> u32 {
+    1 << feature as u32
+}
+
+/// Returns u32 with the feature set of a new factory: every feature except the disabled ones and
+/// recovery, which has to be allowed explicitly
+///
+/// # Arguments
+///
+/// * `disabled` - features to leave out
+/// * `allow_recovery` - true if recovery is enabled
+pub fn initial_features(disabled: &[Feature], allow_recovery: bool) -> u32 {
+    FEATURES
+        .iter()
+        .filter(|feature| !disabled.contains(feature))
+        .filter(|feature| **feature != Feature::Recovery || allow_recovery)
+        .fold(0, |features, feature| features | bit(*feature))
+}
+
+/// Returns bool indicating whether the feature is enabled
+///
+/// # Arguments
+///
+/// * `config` - a reference to the factory config
+/// * `feature` - the feature
+pub fn is_enabled(config: &Config, feature: Feature) -> bool {
+    config.features & bit(feature) != 0
+}
+
+/// Returns Option<Feature> with the feature a handle message uses, if it uses one. Messages that
+/// withdraw from a feature, such as cancelling a transfer offer, do not need it, so they still
+/// work after it was disabled. Referrals are checked by the creation itself, because a referrer
+/// can also arrive embedded in a token payment
+///
+/// # Arguments
+///
+/// * `msg` - a reference to the handle message
+fn required_feature(msg: &HandleMsg) -> Option<Feature> {
+    match msg {
+        HandleMsg::BatchCreateOffspring { .. } => Some(Feature::BatchCreation),
+        HandleMsg::Receive { .. } => Some(Feature::TokenFees),
+        HandleMsg::SetDelegate { allowed: true, .. } | HandleMsg::GrantCreate { .. } => {
+            Some(Feature::Delegation)
+        }
+        HandleMsg::OfferTransfer { .. }
+        | HandleMsg::AcceptTransfer { .. }
+        | HandleMsg::UpdateOffspringOwner { .. } => Some(Feature::Transfers),
+        HandleMsg::FlagIssue { .. } => Some(Feature::IssueReports),
+        HandleMsg::RequestRecovery { .. } | HandleMsg::ApproveRecovery { .. } => {
+            Some(Feature::Recovery)
+        }
+        _ => None,
+    }
+}
+
+/// Returns StdResult<()>
+///
+/// makes sure the feature a handle message uses, if any, is enabled
+///
+/// # Arguments
+///
+/// * `storage` - a reference to the contract's storage
+/// * `msg` - a reference to the handle message
+pub fn enforce_feature<S: Storage>(storage: &S, msg: &HandleMsg) -> StdResult<()> {
+    let feature = match required_feature(msg) {
+        Some(feature) => feature,
+        None => return Ok(()),
+    };
+    let config: Config = load(storage, CONFIG_KEY)?;
+    enforce_enabled(&config, feature)
+}
+
+/// Returns StdResult<()>
+///
+/// makes sure the feature is enabled
+///
+/// # Arguments
+///
+/// * `config` - a reference to the factory config
+/// * `feature` - the feature
+pub fn enforce_enabled(config: &Config, feature: Feature) -> StdResult<()> {
+    if !is_enabled(config, feature) {
+        return Err(StdError::generic_err(format!(
+            "The {} feature is disabled on this factory",
+            feature.name()
+        )));
+    }
+    Ok(())
+}
+
+/// Returns HandleResult
+///
+/// allows admin to enable or disable an optional feature
+///
+/// # Arguments
+///
+/// * `deps` - mutable reference to Extern containing all the contract's external dependencies
+/// * `env` - Env of contract's environment
+/// * `feature` - the feature
+/// * `enabled` - true if the feature should be enabled
+pub fn try_set_feature<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    feature: Feature,
+    enabled: bool,
+) -> HandleResult {
+    // only allow admin to do this
+    let mut config = enforce_admin(deps, &env)?;
+    if enabled {
+        config.features |= bit(feature);
+    } else {
+        config.features &= !bit(feature);
+    }
+    save(&mut deps.storage, CONFIG_KEY, &config)?;
+    let action = if enabled { "enable" } else { "disable" };
+    record_audit(
+        &mut deps.storage,
+        &format!("{}_{}", action, feature.name()),
+        &env,
+        None,
+    )?;
+
+    ok_status()
+}
+
+/// Returns QueryResult listing the enabled features
+///
+/// # Arguments
+///
+/// * `deps` - reference to Extern containing all the contract's external dependencies
+pub fn try_features<S: Storage, A: Api, Q: Querier>(deps: &Extern<S, A, Q>) -> QueryResult {
+    let config: Config = load(&deps.storage, CONFIG_KEY)?;
+    to_binary(&QueryAnswer::Features {
+        enabled: FEATURES
+            .iter()
+            .copied()
+            .filter(|feature| is_enabled(&config, *feature))
+            .collect(),
+    })
+}
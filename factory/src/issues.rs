@@ -0,0 +1,337 @@
+use cosmwasm_std::{
+    to_binary, Api, CanonicalAddr, Env, Extern, HandleResult, HumanAddr, Querier, QueryResult,
+    StdError, StdResult, Storage,
+};
+
+use cosmwasm_storage::{PrefixedStorage, ReadonlyPrefixedStorage};
+
+use secret_toolkit::storage::{AppendStore, AppendStoreMut};
+
+use crate::state::{
+    load, may_load, MAX_ISSUE_CATEGORY_LEN, MAX_ISSUE_DETAILS_LEN, PREFIX_ISSUES,
+    PREFIX_OFFSPRING_EVENTS, PREFIX_OFFSPRING_OWNER,
+};
+
+use crate::{
+    addr::normalize_address,
+    admin::{enforce_admin, record_audit},
+    auth::{is_admin_key_valid, is_key_valid},
+    creation::authenticate_offspring,
+    msg::{Issue, IssueStatus, OffspringEvent, QueryAnswer},
+    queries::resolve_page_size,
+    response::{ok_empty, ok_status},
+};
+
+/// Returns HandleResult
+///
+/// adds an issue the calling offspring's owner flagged to the admin's issue queue, along with the
+/// offspring's registry context
+///
+/// # Arguments
+///
+/// * `deps` - mutable reference to Extern containing all the contract's external dependencies
+/// * `env` - Env of contract's environment
+/// * `category` - short category of the issue
+/// * `details` - description of the issue
+pub fn try_flag_issue<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    category: String,
+    details: String,
+) -> HandleResult {
+    if category.is_empty() || category.len() > MAX_ISSUE_CATEGORY_LEN {
+        return Err(StdError::generic_err(format!(
+            "Issue categories must be between 1 and {} bytes long",
+            MAX_ISSUE_CATEGORY_LEN
+        )));
+    }
+    if details.len() > MAX_ISSUE_DETAILS_LEN {
+        return Err(StdError::generic_err(format!(
+            "Issue details can be at most {} bytes long",
+            MAX_ISSUE_DETAILS_LEN
+        )));
+    }
+    let offspring_addr = deps.api.canonical_address(&env.message.sender)?;
+
+    // verify offspring is in active list, and not a spam attempt
+    let offspring_info = authenticate_offspring(&deps.storage, &offspring_addr)?;
+    open_issue(
+        &mut deps.storage,
+        &offspring_addr,
+        env.message.sender.clone(),
+        offspring_info.label,
+        category,
+        details,
+        &env,
+    )?;
+
+    ok_empty()
+}
+
+/// Returns StdResult<u32> with the id of the new issue
+///
+/// adds an open issue about a registered offspring to the issue queue, and to the offspring's
+/// history
+///
+/// # Arguments
+///
+/// * `storage` - mutable reference to contract's storage
+/// * `offspring_addr` - a reference to the canonical address of the offspring
+/// * `offspring` - address of the offspring
+/// * `label` - label of the offspring
+/// * `category` - short category of the issue
+/// * `details` - description of the issue
+/// * `env` - reference to the Env of the message
+pub fn open_issue<S: Storage>(
+    storage: &mut S,
+    offspring_addr: &CanonicalAddr,
+    offspring: HumanAddr,
+    label: String,
+    category: String,
+    details: String,
+    env: &Env,
+) -> StdResult<u32> {
+    let owner: HumanAddr = load(
+        &ReadonlyPrefixedStorage::new(PREFIX_OFFSPRING_OWNER, storage),
+        offspring_addr.as_slice(),
+    )?;
+    let mut issue_store = PrefixedStorage::new(PREFIX_ISSUES, storage);
+    let mut issues = AppendStoreMut::attach_or_create(&mut issue_store)?;
+    let id = issues.len();
+    issues.push(&Issue {
+        id,
+        offspring,
+        label,
+        owner,
+        category,
+        details,
+        height: env.block.height,
+        status: IssueStatus::Open,
+    })?;
+    record_event(storage, offspring_addr, "issue_flagged", id, None, env)?;
+    Ok(id)
+}
+
+/// Returns HandleResult
+///
+/// allows admin to acknowledge an open issue
+///
+/// # Arguments
+///
+/// * `deps` - mutable reference to Extern containing all the contract's external dependencies
+/// * `env` - Env of contract's environment
+/// * `id` - id of the issue
+pub fn try_acknowledge_issue<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    id: u32,
+) -> HandleResult {
+    // only allow admin to do this
+    enforce_admin(deps, &env)?;
+    let offspring = set_issue_status(deps, id, IssueStatus::Acknowledged)?;
+    let offspring_addr = deps.api.canonical_address(&offspring)?;
+    record_event(&mut deps.storage, &offspring_addr, "issue_acknowledged", id, None, &env)?;
+    record_audit(&mut deps.storage, "acknowledge_issue", &env, Some(offspring))?;
+
+    ok_status()
+}
+
+/// Returns HandleResult
+///
+/// allows admin to resolve an issue, adding the resolution note to the offspring's history
+///
+/// # Arguments
+///
+/// * `deps` - mutable reference to Extern containing all the contract's external dependencies
+/// * `env` - Env of contract's environment
+/// * `id` - id of the issue
+/// * `note` - optional description of the resolution
+pub fn try_resolve_issue<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    id: u32,
+    note: Option<String>,
+) -> HandleResult {
+    // only allow admin to do this
+    enforce_admin(deps, &env)?;
+    if note.as_ref().map(String::len).unwrap_or(0) > MAX_ISSUE_DETAILS_LEN {
+        return Err(StdError::generic_err(format!(
+            "Resolution notes can be at most {} bytes long",
+            MAX_ISSUE_DETAILS_LEN
+        )));
+    }
+    let offspring = set_issue_status(deps, id, IssueStatus::Resolved)?;
+    let offspring_addr = deps.api.canonical_address(&offspring)?;
+    record_event(&mut deps.storage, &offspring_addr, "issue_resolved", id, note, &env)?;
+    record_audit(&mut deps.storage, "resolve_issue", &env, Some(offspring))?;
+
+    ok_status()
+}
+
+/// Returns StdResult<HumanAddr> with the address of the offspring the issue was flagged from
+///
+/// moves an issue forward to the new status. Issues can not move back, and resolved issues can
+/// not be changed
+///
+/// # Arguments
+///
+/// * `deps` - mutable reference to Extern containing all the contract's external dependencies
+/// * `id` - id of the issue
+/// * `status` - the issue's new status
+fn set_issue_status<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    id: u32,
+    status: IssueStatus,
+) -> StdResult<HumanAddr> {
+    let mut issue_store = PrefixedStorage::new(PREFIX_ISSUES, &mut deps.storage);
+    let mut issues = AppendStoreMut::<Issue, _>::attach_or_create(&mut issue_store)?;
+    if id >= issues.len() {
+        return Err(StdError::generic_err(format!("There is no issue {}", id)));
+    }
+    let mut issue = issues.get_at(id)?;
+    let allowed = match status {
+        IssueStatus::Acknowledged => issue.status == IssueStatus::Open,
+        IssueStatus::Resolved => issue.status != IssueStatus::Resolved,
+        IssueStatus::Open => false,
+    };
+    if !allowed {
+        return Err(StdError::generic_err(format!(
+            "Issue {} is already {:?}",
+            id, issue.status
+        )));
+    }
+    issue.status = status;
+    issues.set_at(id, &issue)?;
+    Ok(issue.offspring)
+}
+
+/// Returns StdResult<()>
+///
+/// appends an issue event to the offspring's history
+///
+/// # Arguments
+///
+/// * `storage` - mutable reference to contract's storage
+/// * `offspring` - a reference to the canonical address of the offspring
+/// * `action` - name of the event
+/// * `issue` - id of the issue the event belongs to
+/// * `note` - optional note
+/// * `env` - reference to the Env of the message
+fn record_event<S: Storage>(
+    storage: &mut S,
+    offspring: &CanonicalAddr,
+    action: &str,
+    issue: u32,
+    note: Option<String>,
+    env: &Env,
+) -> StdResult<()> {
+    let mut event_store =
+        PrefixedStorage::multilevel(&[PREFIX_OFFSPRING_EVENTS, offspring.as_slice()], storage);
+    let mut events = AppendStoreMut::attach_or_create(&mut event_store)?;
+    events.push(&OffspringEvent {
+        action: action.to_string(),
+        issue,
+        note,
+        height: env.block.height,
+    })
+}
+
+/// Returns QueryResult listing the issues that are not resolved yet, oldest first
+///
+/// # Arguments
+///
+/// * `deps` - reference to Extern containing all the contract's external dependencies
+/// * `address` - a reference to the admin's address
+/// * `viewing_key` - String key used to authenticate the query
+/// * `start_page` - optional start page for the issues returned and listed
+/// * `page_size` - optional number of issues to return in this page
+pub fn try_issue_queue<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    address: &HumanAddr,
+    viewing_key: String,
+    start_page: Option<u32>,
+    page_size: Option<u32>,
+) -> QueryResult {
+    let address = &normalize_address(&deps.api, address)?;
+    if !is_admin_key_valid(deps, address, viewing_key)? {
+        return to_binary(&QueryAnswer::ViewingKeyError {
+            error: "Wrong viewing key for this address or viewing key not set".to_string(),
+        });
+    }
+    let size = resolve_page_size(deps, Some(address), page_size)?;
+    let issue_store = ReadonlyPrefixedStorage::new(PREFIX_ISSUES, &deps.storage);
+    let open = match AppendStore::<Issue, _>::attach(&issue_store) {
+        Some(issue_log) => issue_log?
+            .iter()
+            .filter(|issue| {
+                issue
+                    .as_ref()
+                    .map_or(true, |issue| issue.status != IssueStatus::Resolved)
+            })
+            .collect::<StdResult<Vec<Issue>>>()?,
+        None => vec![],
+    };
+    let total = open.len() as u32;
+    let issues = open
+        .into_iter()
+        .skip((start_page.unwrap_or(0) * size) as usize)
+        .take(size as usize)
+        .collect();
+    to_binary(&QueryAnswer::IssueQueue { issues, total })
+}
+
+/// Returns QueryResult listing the issue history of an offspring, most recent first. Can be
+/// viewed by the admin and by the offspring's owner
+///
+/// # Arguments
+///
+/// * `deps` - reference to Extern containing all the contract's external dependencies
+/// * `offspring` - a reference to the address of the offspring
+/// * `address` - a reference to the address of the admin or owner
+/// * `viewing_key` - String key used to authenticate the query
+/// * `start_page` - optional start page for the events returned and listed
+/// * `page_size` - optional number of events to return in this page
+pub fn try_offspring_history<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    offspring: &HumanAddr,
+    address: &HumanAddr,
+    viewing_key: String,
+    start_page: Option<u32>,
+    page_size: Option<u32>,
+) -> QueryResult {
+    let address = &normalize_address(&deps.api, address)?;
+    let offspring_addr = deps.api.canonical_address(offspring)?;
+    let owner: Option<HumanAddr> = may_load(
+        &ReadonlyPrefixedStorage::new(PREFIX_OFFSPRING_OWNER, &deps.storage),
+        offspring_addr.as_slice(),
+    )?;
+    let authorized = if owner.as_ref() == Some(address) {
+        is_key_valid(&deps.storage, address, viewing_key)
+    } else {
+        is_admin_key_valid(deps, address, viewing_key)?
+    };
+    if !authorized {
+        return to_binary(&QueryAnswer::ViewingKeyError {
+            error: "Wrong viewing key for this address or viewing key not set".to_string(),
+        });
+    }
+    let size = resolve_page_size(deps, Some(address), page_size)?;
+    let event_store = ReadonlyPrefixedStorage::multilevel(
+        &[PREFIX_OFFSPRING_EVENTS, offspring_addr.as_slice()],
+        &deps.storage,
+    );
+    let (events, total) = match AppendStore::<OffspringEvent, _>::attach(&event_store) {
+        Some(event_log) => {
+            let event_log = event_log?;
+            let events = event_log
+                .iter()
+                .rev()
+                .skip((start_page.unwrap_or(0) * size) as usize)
+                .take(size as usize)
+                .collect::<StdResult<Vec<OffspringEvent>>>()?;
+            (events, event_log.len())
+        }
+        None => (vec![], 0),
+    };
+    to_binary(&QueryAnswer::OffspringHistory { events, total })
+}
@@ -1,10 +1,39 @@
+mod addr;
+mod admin;
+mod api_version;
+mod auth;
+mod checksum;
 pub mod contract;
+mod creation;
+mod features;
+mod issues;
+mod maintenance;
 pub mod msg;
 mod rand;
+mod quarantine;
+mod queries;
+mod recovery;
+mod registry;
+mod response;
+mod sealing;
+mod snapshot;
+mod stats;
 pub mod state;
+mod transfer;
+mod validators;
+mod versions;
+#[cfg(feature = "testing")]
+pub mod testing;
 mod offspring_msg;
+mod permits;
+mod snip20_msg;
 
-#[cfg(target_arch = "wasm32")]
+/// the contract's entry points, for crates that link the factory natively with the library
+/// feature
+#[cfg(feature = "library")]
+pub use crate::contract::{handle, init, query};
+
+#[cfg(all(target_arch = "wasm32", not(feature = "library")))]
 mod wasm {
     use super::contract;
     use cosmwasm_std::{
@@ -0,0 +1,118 @@
+use cosmwasm_std::{Api, StdResult, Storage};
+
+use crate::state::{
+    load, may_load, save, Config, PendingOffspring, COMPACT_CURSOR_KEY, CONFIG_KEY,
+    MAINTENANCE_CURSOR_KEY, MAINTENANCE_HEIGHT_KEY, PENDING_CURSOR_KEY, PENDING_KEY,
+};
+
+use secret_toolkit_incubator::CashMap;
+
+use crate::registry::{compact_entry, save_compact_cursor, sweep_tombstones};
+use crate::validators::release_label;
+
+/// kinds of deferred maintenance work
+#[derive(Clone, Copy)]
+enum Task {
+    /// discard pending offspring that can no longer register
+    ExpirePending,
+    /// discard expired tombstones
+    SweepTombstones,
+    /// remove stale active entries of offspring that are listed as inactive
+    Compact,
+}
+
+/// the work queue. The task that goes first rotates with every handle, so no kind of work can
+/// use up the budget every time
+const TASKS: [Task; 3] = [Task::ExpirePending, Task::SweepTombstones, Task::Compact];
+
+/// Returns StdResult<u32> with the number of units of work performed
+///
+/// performs up to the configured maintenance budget of deferred maintenance work. Each pending
+/// offspring, tombstone, or registry entry examined is one unit of work
+///
+/// # Arguments
+///
+/// * `storage` - mutable reference to contract's storage
+/// * `api` - reference to the Api used to convert human and canonical addresses
+/// * `height` - current block height
+pub fn run_maintenance<S: Storage, A: Api>(storage: &mut S, api: &A, height: u64) -> StdResult<u32> {
+    let config: Config = load(storage, CONFIG_KEY)?;
+    if config.maintenance_budget == 0 {
+        return Ok(0);
+    }
+    let first: u32 = may_load(storage, MAINTENANCE_CURSOR_KEY)?.unwrap_or(0);
+    let mut budget = config.maintenance_budget;
+    for i in 0..TASKS.len() {
+        if budget == 0 {
+            break;
+        }
+        let used = match TASKS[(first as usize + i) % TASKS.len()] {
+            Task::ExpirePending => expire_pending(storage, height, budget)?,
+            Task::SweepTombstones => sweep_tombstones(storage, api, height, budget)?,
+            Task::Compact => compact(storage, api, budget)?,
+        };
+        budget -= used.min(budget);
+    }
+    save(storage, MAINTENANCE_CURSOR_KEY, &((first + 1) % TASKS.len() as u32))?;
+    save(storage, MAINTENANCE_HEIGHT_KEY, &height)?;
+
+    Ok(config.maintenance_budget - budget)
+}
+
+/// Returns StdResult<u32> with the number of units of work performed
+///
+/// discards pending offspring whose registration window has passed, because they can no longer
+/// register. Each run examines the next pending offspring after the ones the previous run
+/// examined, starting over once all of them have been examined
+///
+/// # Arguments
+///
+/// * `storage` - mutable reference to contract's storage
+/// * `height` - current block height
+/// * `limit` - maximum number of pending offspring to examine
+fn expire_pending<S: Storage>(storage: &mut S, height: u64, limit: u32) -> StdResult<u32> {
+    let mut position: u32 = may_load(storage, PENDING_CURSOR_KEY)?.unwrap_or(0);
+    let mut pending_store: CashMap<PendingOffspring, _> = CashMap::init(PENDING_KEY, storage);
+    let mut examined: u32 = 0;
+    while examined < limit {
+        // paging with a page size of 1 reads the pending offspring at `position`
+        let pending = match pending_store.paging(position, 1)?.pop() {
+            Some(pending) => pending,
+            None => break,
+        };
+        examined += 1;
+        if pending.expires_at < height {
+            // removing swaps the last entry into this position, so the position is not advanced
+            pending_store.remove(&pending.key)?;
+            release_label(storage, &pending.label, &pending.key)?;
+            pending_store = CashMap::init(PENDING_KEY, storage);
+        } else {
+            position += 1;
+        }
+    }
+    let cursor = if position >= pending_store.len() { 0 } else { position };
+    save(storage, PENDING_CURSOR_KEY, &cursor)?;
+    Ok(examined)
+}
+
+/// Returns StdResult<u32> with the number of units of work performed
+///
+/// continues the registry compaction where it last stopped
+///
+/// # Arguments
+///
+/// * `storage` - mutable reference to contract's storage
+/// * `api` - reference to the Api used to convert human and canonical addresses
+/// * `budget` - maximum number of registry entries to examine
+fn compact<S: Storage, A: Api>(storage: &mut S, api: &A, budget: u32) -> StdResult<u32> {
+    let mut position: u32 = may_load(storage, COMPACT_CURSOR_KEY)?.unwrap_or(0);
+    let mut processed: u32 = 0;
+    while processed < budget {
+        if compact_entry(storage, api, &mut position)?.is_none() {
+            break;
+        }
+        processed += 1;
+    }
+    save_compact_cursor(storage, position)?;
+    Ok(processed)
+}
@@ -1,7 +1,11 @@
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
-use cosmwasm_std::{HumanAddr};
+use cosmwasm_std::{Binary, BlockInfo, HumanAddr, Uint128};
+
+use secret_toolkit::permit::Permit;
+
+use crate::state::{FACTORY_COMMIT, FACTORY_VERSION};
 
 /// Instantiation message
 #[derive(Serialize, Deserialize, JsonSchema)]
@@ -10,6 +14,82 @@ pub struct InitMsg {
     pub entropy: String,
     /// offspring contract info
     pub offspring_contract: OffspringContractInfo,
+    /// optional number of blocks a tombstone is kept after its offspring is purged.
+    /// Default: DEFAULT_TOMBSTONE_RETENTION
+    #[serde(default)]
+    pub tombstone_retention: Option<u64>,
+    /// optional number of entries listed by queries that do not specify a page size.
+    /// Default: DEFAULT_PAGE_SIZE
+    #[serde(default)]
+    pub default_page_size: Option<u32>,
+    /// optionally set to true so that listing active/inactive offspring only returns counts unless
+    /// a valid viewing key is supplied. Default: false
+    #[serde(default)]
+    pub private_listing: Option<bool>,
+    /// optionally set to true so that an owner creating their own offspring without having a
+    /// viewing key is given one in the creation response. Default: false
+    #[serde(default)]
+    pub auto_viewing_key: Option<bool>,
+    /// optionally set to true so that an offspring is only registered if its registration arrives
+    /// in the same transaction it was created in, for the owner it was created for. Default: false
+    #[serde(default)]
+    pub require_registration_same_tx: Option<bool>,
+    /// optionally set to true so that offspring can only be created by their owner, or by an
+    /// address the owner granted creation rights to with GrantCreate. Default: false
+    #[serde(default)]
+    pub require_owner_is_sender: Option<bool>,
+    /// optionally set to true so that an address can ask to take over an offspring from an owner
+    /// who lost their key, subject to the admin's approval with ApproveRecovery. Default: false
+    #[serde(default)]
+    pub allow_recovery: Option<bool>,
+    /// optional features to disable from the start. Default: only Recovery is disabled, unless
+    /// allow_recovery is set
+    #[serde(default)]
+    pub disabled_features: Option<Vec<Feature>>,
+    /// optional minimum length of the entropy strings passed to init, CreateOffspring and
+    /// CreateViewingKey. Default: DEFAULT_MIN_ENTROPY_LEN
+    #[serde(default)]
+    pub min_entropy_len: Option<u32>,
+    /// optional minimum number of distinct characters in those entropy strings.
+    /// Default: DEFAULT_MIN_ENTROPY_DISTINCT
+    #[serde(default)]
+    pub min_entropy_distinct: Option<u32>,
+    /// optional number of units of deferred maintenance work, such as discarding expired
+    /// tombstones, performed by each handle. 0 disables it. Default: DEFAULT_MAINTENANCE_BUDGET
+    #[serde(default)]
+    pub maintenance_budget: Option<u32>,
+    /// optional funds the factory attaches to the messages it sends offspring. Default: none
+    #[serde(default)]
+    pub callback_policy: Option<CallbackPolicy>,
+    /// optional number of blocks after its creation an offspring whose registration failed may
+    /// retry it. 0 disables retries. Default: DEFAULT_REGISTRATION_RETRY_BLOCKS
+    #[serde(default)]
+    pub registration_retry_blocks: Option<u64>,
+    /// optional flag to hold registrations with an unknown or expired password for the admin's
+    /// review instead of rejecting them. Default: false
+    #[serde(default)]
+    pub quarantine_registrations: Option<bool>,
+    /// optional settings of the creation pipeline's allow list, fee, rate limit and label rules.
+    /// Default: all of them disabled
+    #[serde(default)]
+    pub creation_rules: Option<CreationRules>,
+    /// optional bech32 prefix, such as "secret", that addresses the factory stores must have.
+    /// Default: any address the chain accepts
+    #[serde(default)]
+    pub address_prefix: Option<String>,
+    /// optional number of characters of an offspring's description the registry keeps. Longer
+    /// descriptions are stored as a truncated preview, and their full text is only kept by the
+    /// offspring. Default: no limit
+    #[serde(default)]
+    pub description_limit: Option<u32>,
+    /// optional number of registrations between two checkpoints of the registry's totals in the
+    /// registration logs. 0 disables them. Default: DEFAULT_STATS_CHECKPOINT_INTERVAL
+    #[serde(default)]
+    pub stats_checkpoint_interval: Option<u64>,
+    /// optional offspring to create right away, at most MAX_BOOTSTRAP_OFFSPRING. They are all
+    /// instantiated by the init, and each registers on its own
+    #[serde(default)]
+    pub bootstrap: Option<Vec<OffspringSpec>>,
 }
 
 /// Handle messages
@@ -18,8 +98,10 @@ pub struct InitMsg {
 pub enum HandleMsg {
     /// CreateOffspring will instantiate a new offspring contract
     CreateOffspring {
-        /// String used to label when instantiating offspring contract.
-        label: String,
+        /// String used to label when instantiating offspring contract. If not specified, a label is
+        /// generated from the factory address, the owner and the owner's creation nonce
+        #[serde(default)]
+        label: Option<String>,
         /// Used to generate the password for the offspring contract
         entropy: String,
         //  the rest are meant to be contract specific data
@@ -29,6 +111,48 @@ pub enum HandleMsg {
         count: i32,
         #[serde(default)]
         description: Option<String>,
+        /// optional names of address book entries the offspring should resolve at init
+        #[serde(default)]
+        dependencies: Option<Vec<String>>,
+        /// optional address credited with referring this creation
+        #[serde(default)]
+        referrer: Option<HumanAddr>,
+        /// optional level of privacy of the offspring's count. Default: owner only
+        #[serde(default)]
+        count_privacy: Option<CountPrivacy>,
+        /// optional id of the registered offspring version to create the offspring from.
+        /// Default: the current version
+        #[serde(default)]
+        version: Option<u32>,
+        /// optional context of the creation, such as the id of the client app or a campaign tag.
+        /// It is only counted in the creation counters the admin can view
+        #[serde(default)]
+        context: Option<String>,
+    },
+
+    /// BatchCreateOffspring creates up to MAX_BATCH_CREATE offspring in one transaction. Every
+    /// offspring is checked like CreateOffspring, and the ones that fail are skipped and reported
+    /// in the answer instead of failing the whole batch
+    BatchCreateOffspring {
+        /// Used to generate the passwords for the offspring contracts
+        entropy: String,
+        /// the offspring to create
+        offspring: Vec<OffspringSpec>,
+    },
+
+    /// Receive implements the SNIP-20 receiver interface. The token the creation rules accept
+    /// fees in calls it when a creator sends it the token fee, with a CreateOffspring message
+    /// embedded in msg. The offspring is then created as if `from` had sent that message
+    Receive {
+        /// address that sent the tokens
+        sender: HumanAddr,
+        /// owner of the tokens
+        from: HumanAddr,
+        /// amount of tokens received
+        amount: Uint128,
+        /// embedded CreateOffspring message
+        #[serde(default)]
+        msg: Option<Binary>,
     },
 
     /// RegisterOffspring saves the offspring info of a newly instantiated contract and adds it to the list
@@ -46,13 +170,133 @@ pub enum HandleMsg {
     DeactivateOffspring {
         /// offspring's owner
         owner: HumanAddr,
+        /// true if the offspring expired from inactivity rather than being deactivated by its
+        /// owner
+        #[serde(default)]
+        expired: bool,
+    },
+
+    /// DetachOffspring tells the factory that the owner repointed the offspring to another
+    /// factory, so it is moved to the inactive list.
+    DetachOffspring {},
+
+    /// ReactivateOffspring tells the factory that the inactive offspring is active again. The
+    /// offspring authenticates with the password it registered with
+    ///
+    /// Only offspring will use this function
+    ReactivateOffspring {
+        /// password the offspring registered with
+        password: [u8; 32],
     },
 
-    /// Allows the admin to add a new offspring contract version
+    /// UpdateOffspringDescription tells the factory that the offspring's description changed so
+    /// the registry copy stays in sync
+    ///
+    /// Only offspring will use this function
+    UpdateOffspringDescription {
+        /// the offspring's new description
+        #[serde(default)]
+        description: Option<String>,
+    },
+
+    /// UpdateOffspringCountPrivacy tells the factory that the privacy level of the offspring's
+    /// count changed so the registry copy stays in sync
+    ///
+    /// Only offspring will use this function
+    UpdateOffspringCountPrivacy {
+        /// the offspring's new count privacy level
+        #[serde(default)]
+        count_privacy: Option<CountPrivacy>,
+    },
+
+    /// UpdateOffspringAttributes replaces the custom attributes shown with the offspring in the
+    /// registry, letting template forks surface contract-specific summary data
+    ///
+    /// Only offspring will use this function
+    UpdateOffspringAttributes {
+        /// the offspring's attributes, at most MAX_OFFSPRING_ATTRIBUTES with unique keys
+        attributes: Vec<OffspringAttribute>,
+    },
+
+    /// FlagIssue adds an issue the offspring's owner reported to the admin's issue queue
+    ///
+    /// Only offspring will use this function
+    FlagIssue {
+        /// short category of the issue, at most MAX_ISSUE_CATEGORY_LEN bytes
+        category: String,
+        /// description of the issue, at most MAX_ISSUE_DETAILS_LEN bytes
+        details: String,
+    },
+
+    /// UpdateOffspringOwner moves the calling offspring to its new owner's lists after its owner
+    /// transferred it
+    ///
+    /// Only offspring will use this function
+    UpdateOffspringOwner {
+        /// the offspring's new owner
+        new_owner: HumanAddr,
+    },
+
+    /// RequestRecovery records a claimant's request to become the owner of the calling offspring,
+    /// to be approved or rejected by the admin
+    ///
+    /// Only offspring will use this function
+    RequestRecovery {
+        /// address asking to become the owner
+        claimant: HumanAddr,
+    },
+
+    /// Allows the admin to approve a recovery request, making the claimant the offspring's owner
+    ApproveRecovery {
+        /// address of the offspring
+        offspring: HumanAddr,
+    },
+
+    /// Allows the admin to reject a recovery request
+    RejectRecovery {
+        /// address of the offspring
+        offspring: HumanAddr,
+    },
+
+    /// Forgets the sender's creations that did not register yet, so they no longer count against
+    /// the pending limit of the creation rules. The cancelled offspring can not register anymore
+    CancelPendingCreations {},
+
+    /// Offers one of the sender's active offspring to another address, optionally for a price in
+    /// uscrt. Replaces any previous offer of the same offspring
+    OfferTransfer {
+        /// address of the offspring
+        offspring: HumanAddr,
+        /// address the offspring is offered to
+        recipient: HumanAddr,
+        /// optional price in uscrt the recipient must pay when accepting
+        #[serde(default)]
+        price: Option<Uint128>,
+    },
+
+    /// Accepts a transfer offer made to the sender. The price, if any, must be sent along and is
+    /// paid to the previous owner
+    AcceptTransfer {
+        /// address of the offspring
+        offspring: HumanAddr,
+    },
+
+    /// Cancels a transfer offer. Can be called by the owner or the recipient of the offer
+    CancelTransfer {
+        /// address of the offspring
+        offspring: HumanAddr,
+    },
+
+    /// Allows the admin to add a new offspring contract version to the registered versions and
+    /// make it the current version
     NewOffspringContract {
         offspring_contract: OffspringContractInfo,
     },
 
+    /// Allows the admin to switch back to the offspring contract version replaced by the last
+    /// NewOffspringContract. Rolling back again restores the newer version
+    RollbackVersion {},
+
     /// Create a viewing key to be used with all factory and offspring authenticated queries
     CreateViewingKey { entropy: String },
 
@@ -62,58 +306,1041 @@ pub enum HandleMsg {
         // optional padding can be used so message length doesn't betray key length
         padding: Option<String>,
     },
-
-    /// Allows an admin to start/stop all offspring creation
-    SetStatus { stop: bool },
+
+    /// Allows an admin to start/stop all offspring creation
+    SetStatus { stop: bool },
+
+    /// Allows an admin to change the number of entries listed by queries that do not specify a
+    /// page size
+    SetDefaultPageSize { page_size: u32 },
+
+    /// Allows an admin to change the number of characters of an offspring's description the
+    /// registry keeps, or to remove the limit if limit is not specified. Descriptions already
+    /// stored are not affected
+    SetDescriptionLimit {
+        #[serde(default)]
+        limit: Option<u32>,
+    },
+
+    /// Allows an admin to change the number of units of deferred maintenance work performed by
+    /// each handle. 0 disables it
+    SetMaintenanceBudget { budget: u32 },
+
+    /// Allows an admin to change the funds the factory attaches to the messages it sends
+    /// offspring
+    SetCallbackPolicy { policy: CallbackPolicy },
+
+    /// Allows an admin to hand the admin role over to another address. The change only takes
+    /// effect once the new admin accepts it with AcceptAdmin
+    TransferAdmin {
+        /// address of the new admin. Transferring to the current admin cancels a pending transfer
+        new_admin: HumanAddr,
+    },
+
+    /// Accepts the admin role handed over with TransferAdmin. Can only be called by the address
+    /// the role was transferred to
+    AcceptAdmin {},
+
+    /// Allows an admin to enable or disable an optional feature
+    SetFeature {
+        /// the feature
+        feature: Feature,
+        /// true to enable the feature, false to disable it
+        enabled: bool,
+    },
+
+    /// Allows an admin to change the settings of the creation pipeline
+    SetCreationRules { rules: CreationRules },
+
+    /// Allows an admin to send collected creation fees to a treasury address
+    WithdrawFees {
+        /// address the fees are sent to
+        treasury: HumanAddr,
+        /// optional amount to withdraw. Default: all collected fees
+        #[serde(default)]
+        amount: Option<Uint128>,
+        /// optional SNIP-20 token whose collected fees are withdrawn. Default: uscrt
+        #[serde(default)]
+        token: Option<ContractInfo>,
+    },
+
+    /// Allows a referrer to withdraw the creation fees credited to them
+    WithdrawReferralFees {
+        /// optional amount to withdraw. Default: all credited fees
+        #[serde(default)]
+        amount: Option<Uint128>,
+        /// optional SNIP-20 token whose credited fees are withdrawn. Default: uscrt
+        #[serde(default)]
+        token: Option<ContractInfo>,
+    },
+
+    /// Allows an admin to allow or disallow an address to create offspring when the creation
+    /// rules use an allow list
+    SetCreatorAllowed {
+        /// address of the creator
+        address: HumanAddr,
+        /// true if the address may create offspring
+        allowed: bool,
+    },
+
+    /// Allows an admin to deny an address creating offspring, whether or not the creation rules
+    /// use an allow list, or to lift the denial
+    SetCreatorDenied {
+        /// address of the creator
+        address: HumanAddr,
+        /// true if the address may not create offspring
+        denied: bool,
+    },
+
+    /// Sets or clears the link to off-chain metadata about one of the sender's active offspring
+    SetMetadataUrl {
+        /// address of the offspring
+        offspring: HumanAddr,
+        /// the url, at most MAX_METADATA_URL_LEN bytes. If not specified, the link is removed
+        #[serde(default)]
+        url: Option<String>,
+    },
+
+    /// Sets the sender's own settings
+    SetOwnerSettings {
+        /// optional number of entries listed by the sender's queries that do not specify a page
+        /// size. If not specified, the factory's default page size is used
+        #[serde(default)]
+        page_size: Option<u32>,
+    },
+
+    /// Authorizes or revokes a delegate that may act for the sender. Offspring can check
+    /// delegation with the AuthenticateRole query
+    SetDelegate {
+        /// address of the delegate
+        delegate: HumanAddr,
+        /// true to authorize the delegate, false to revoke it
+        allowed: bool,
+    },
+
+    /// Authorizes an address to create offspring owned by the sender. Only checked if the factory
+    /// requires the owner to be the sender
+    GrantCreate {
+        /// address allowed to create offspring on the sender's behalf
+        creator: HumanAddr,
+    },
+
+    /// Revokes an address' right to create offspring owned by the sender
+    RevokeCreate {
+        /// address no longer allowed to create offspring on the sender's behalf
+        creator: HumanAddr,
+    },
+
+    /// Revokes the sender's query permit with the given name, both for the factory and for its
+    /// offspring
+    RevokePermit {
+        /// name of the permit to revoke
+        permit_name: String,
+    },
+
+    /// Allows an admin to add, update, or remove a named entry in the address book of related
+    /// system contracts
+    SetAddressBookEntry {
+        /// name the contract is resolved by, e.g. "fee_collector"
+        name: String,
+        /// code hash and address of the contract. Removes the entry if not specified
+        #[serde(default)]
+        contract: Option<ContractInfo>,
+    },
+
+    /// Allows an admin to enable/disable creation of offspring from a specific code id
+    /// without pausing the whole factory
+    SetVersionStatus {
+        /// code id of the offspring version
+        code_id: u64,
+        /// true if offspring of this version may be created
+        enabled: bool,
+    },
+
+    /// Allows an admin to attach a deprecation notice to an offspring version, so owners of
+    /// offspring created from it know they should migrate, or to remove the notice
+    SetVersionDeprecation {
+        /// code hash of the offspring version
+        code_hash: String,
+        /// the deprecation notice, or None to remove it
+        #[serde(default)]
+        deprecation: Option<VersionDeprecation>,
+    },
+
+    /// Allows an admin to block an offspring address from registering, or to unblock it.
+    /// Blocking an address that is already registered flags it in the issue queue
+    SetAddressBlocked {
+        /// address of the offspring contract
+        address: HumanAddr,
+        /// true to block the address, false to unblock it
+        blocked: bool,
+        /// optional reason for blocking, at most MAX_ISSUE_DETAILS_LEN bytes
+        #[serde(default)]
+        reason: Option<String>,
+    },
+
+    /// Allows the admin to attach a private support note to an owner address, replacing any
+    /// previous note. Notes are only shown to the admin
+    SetOwnerNote {
+        /// address of the owner
+        owner: HumanAddr,
+        /// the note, at most MAX_OWNER_NOTE_LEN bytes
+        note: String,
+    },
+
+    /// Allows the admin to remove the note about an owner
+    ClearOwnerNote {
+        /// address of the owner
+        owner: HumanAddr,
+    },
+
+    /// Allows the admin to execute several admin actions in order within one transaction. If any
+    /// action fails, none of them take effect
+    AdminBatch {
+        /// the actions to execute, at most MAX_ADMIN_BATCH
+        actions: Vec<AdminAction>,
+    },
+
+    /// Allows the admin to run a bounded slice of registry compaction. Each call resumes where
+    /// the previous one stopped, so compaction can be spread over several transactions
+    Compact {
+        /// maximum number of registry entries to process in this call (capped at MAX_COMPACT_ITEMS)
+        max_items: u32,
+    },
+
+    /// Allows the admin to acknowledge an open issue
+    AcknowledgeIssue {
+        /// id of the issue
+        id: u32,
+    },
+
+    /// Allows the admin to resolve an issue, adding the resolution note to the offspring's history
+    ResolveIssue {
+        /// id of the issue
+        id: u32,
+        /// optional description of the resolution, at most MAX_ISSUE_DETAILS_LEN bytes
+        #[serde(default)]
+        note: Option<String>,
+    },
+
+    /// Allows the admin to snapshot the active registry as a merkle root that can be published
+    /// and verified externally. The snapshot is built over as many calls as needed, each resuming
+    /// where the previous one stopped, and replaces the previous snapshot once it is complete
+    SnapshotRegistry {
+        /// optional maximum number of registry entries and tree nodes to process in this call
+        /// (capped at MAX_SNAPSHOT_ITEMS). Default: MAX_SNAPSHOT_ITEMS
+        #[serde(default)]
+        max_items: Option<u32>,
+    },
+
+    /// Allows the admin to deactivate an active offspring, e.g. one that abuses the registry. The
+    /// offspring is told to deactivate itself
+    DeactivateOffspringAdmin {
+        /// address of the offspring
+        offspring: HumanAddr,
+    },
+
+    /// Allows the admin to see the offspring listing of an owner the way the owner sees it with
+    /// ListMyOffspring. This is a handle rather than a query so that every use is recorded in the
+    /// audit log
+    ListOffspringOfOwnerAdmin {
+        /// address whose offspring to display
+        owner: HumanAddr,
+        /// optional filter for only active or inactive offspring.  If not specified, lists all
+        #[serde(default)]
+        filter: Option<FilterTypes>,
+        /// start page for the offsprings returned and listed (applies to both active and inactive). Default: 0
+        #[serde(default)]
+        start_page: Option<u32>,
+        /// optional number of offspring to return in this page (applies to both active and inactive). Default: the owner's page size setting, or the factory's default page size
+        #[serde(default)]
+        page_size: Option<u32>,
+    },
+
+    /// Allows the admin to import a batch of registry records exported from another factory with
+    /// ExportRegistry. Batches must be applied in order and exactly once
+    ImportRegistry {
+        /// continuation token the batch was exported with. None for the first batch
+        #[serde(default)]
+        token: Option<String>,
+        /// continuation token returned with the batch. None for the last batch
+        #[serde(default)]
+        next_token: Option<String>,
+        /// exported registry records
+        records: Vec<RegistryRecord>,
+    },
+
+    /// Allows the admin to approve a quarantined registration, registering the offspring, or to
+    /// deny it, discarding it
+    ReviewQuarantine {
+        /// address of the quarantined offspring
+        address: HumanAddr,
+        /// true to register the offspring, false to discard its registration
+        approve: bool,
+    },
+
+    /// Allows the admin to permanently remove an inactive offspring from the registry. A
+    /// tombstone is kept in its place for the configured retention period
+    PurgeOffspring {
+        /// address of the inactive offspring to purge
+        address: HumanAddr,
+        /// optional reason recorded in the tombstone
+        #[serde(default)]
+        reason: Option<String>,
+    },
+
+    /// Permanently removes inactive offspring from the registry to reclaim storage. Each
+    /// offspring can be removed by its owner or by the admin. A tombstone is kept in its place
+    /// for the configured retention period. Addresses that are not listed as inactive are
+    /// skipped
+    RemoveOffspring {
+        /// addresses of the inactive offspring to remove, at most MAX_REMOVE_BATCH
+        addresses: Vec<HumanAddr>,
+    },
+}
+
+/// Queries
+#[derive(Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum QueryMsg {
+    /// lists all offspring whose owner is the given address.
+    ListMyOffspring {
+        // address whose activity to display
+        address: HumanAddr,
+        /// viewing key
+        viewing_key: String,
+        /// optional filter for only active or inactive offspring.  If not specified, lists all
+        #[serde(default)]
+        filter: Option<FilterTypes>,
+        /// start page for the offsprings returned and listed (applies to both active and inactive). Default: 0
+        #[serde(default)]
+        start_page: Option<u32>,
+        /// optional number of offspring to return in this page (applies to both active and inactive). Default: the factory's default page size
+        #[serde(default)]
+        page_size: Option<u32>,
+        /// optionally set to true to list the newest offspring first (applies to both active and inactive). Default: false
+        #[serde(default)]
+        reverse: Option<bool>,
+        /// optional text the labels of the listed offspring must contain, ignoring case. Pages are
+        /// counted among the matching offspring only
+        #[serde(default)]
+        label_contains: Option<String>,
+        /// optional registry revision of the client's last answer. If the lists did not change
+        /// since, a NotModified answer is returned instead of the lists
+        #[serde(default)]
+        if_modified_since: Option<u64>,
+    },
+    /// lists all active offspring, newest first if requested. If the factory uses private
+    /// listing, only the number of active offspring is returned unless a valid viewing key is supplied
+    ListActiveOffspring {
+        /// start page for the offsprings returned and listed. Default: 0
+        #[serde(default)]
+        start_page: Option<u32>,
+        /// optional number of offspring to return in this page. Default: the factory's default page size
+        #[serde(default)]
+        page_size: Option<u32>,
+        /// optional address to authenticate as when the factory uses private listing
+        #[serde(default)]
+        address: Option<HumanAddr>,
+        /// optional viewing key of the address
+        #[serde(default)]
+        viewing_key: Option<String>,
+        /// optional registry revision of the client's last answer. If the lists did not change
+        /// since, a NotModified answer is returned instead of the lists
+        #[serde(default)]
+        if_modified_since: Option<u64>,
+        /// optionally set to true to list the most recently activated offspring first. Default: false
+        #[serde(default)]
+        newest_first: Option<bool>,
+    },
+    /// lists inactive offspring, newest first if requested. If the factory uses private
+    /// listing, only the number of inactive offspring is returned unless a valid viewing key is supplied
+    ListInactiveOffspring {
+        /// start page for the offsprings returned and listed. Default: 0
+        #[serde(default)]
+        start_page: Option<u32>,
+        /// optional number of offspring to return in this page. Default: the factory's default page size
+        #[serde(default)]
+        page_size: Option<u32>,
+        /// optional address to authenticate as when the factory uses private listing
+        #[serde(default)]
+        address: Option<HumanAddr>,
+        /// optional viewing key of the address
+        #[serde(default)]
+        viewing_key: Option<String>,
+        /// optional registry revision of the client's last answer. If the lists did not change
+        /// since, a NotModified answer is returned instead of the lists
+        #[serde(default)]
+        if_modified_since: Option<u64>,
+        /// optionally set to true to list the most recently deactivated offspring first. Default: false
+        #[serde(default)]
+        newest_first: Option<bool>,
+    },
+    /// authenticates the supplied address/viewing key. This should be called by offspring.
+    IsKeyValid {
+        /// address whose viewing key is being authenticated
+        address: HumanAddr,
+        /// viewing key
+        viewing_key: String,
+    },
+    /// authenticates the supplied address/viewing key like IsKeyValid, and also reports the
+    /// roles the address holds. This should be called by offspring that need richer permissioning
+    AuthenticateRole {
+        /// address whose viewing key is being authenticated
+        address: HumanAddr,
+        /// viewing key
+        viewing_key: String,
+        /// optional owner to check whether the address is one of its delegates
+        #[serde(default)]
+        owner: Option<HumanAddr>,
+    },
+    /// lists the named entries of the address book of related system contracts
+    AddressBook {
+        /// start page for the entries returned and listed. Default: 0
+        #[serde(default)]
+        start_page: Option<u32>,
+        /// optional number of entries to return in this page. Default: the factory's default page size
+        #[serde(default)]
+        page_size: Option<u32>,
+    },
+    /// resolves names in the address book. Fails if any name is not in the address book
+    ResolveNames {
+        /// names to resolve
+        names: Vec<String>,
+    },
+    /// reports where an offspring currently is in its lifecycle, as recorded by the factory. If
+    /// the factory uses private listing, only the status is returned unless a valid viewing key
+    /// is supplied
+    OffspringLifecycle {
+        /// address of the offspring contract
+        address: HumanAddr,
+        /// optional address to authenticate as when the factory uses private listing
+        #[serde(default)]
+        viewer: Option<HumanAddr>,
+        /// optional viewing key of the viewer
+        #[serde(default)]
+        viewing_key: Option<String>,
+    },
+    /// looks up the offspring registered under a label, and where it is in its lifecycle. If
+    /// the factory uses private listing, only the status is returned unless a valid viewing key
+    /// is supplied
+    OffspringByLabel {
+        /// label of the offspring
+        label: String,
+        /// optional address to authenticate as when the factory uses private listing
+        #[serde(default)]
+        viewer: Option<HumanAddr>,
+        /// optional viewing key of the viewer
+        #[serde(default)]
+        viewing_key: Option<String>,
+    },
+    /// displays the registry info of a single offspring, as the public lists would show it. If
+    /// the factory uses private listing, only the status is returned unless a valid viewing key
+    /// is supplied
+    OffspringInfo {
+        /// address of the offspring contract
+        address: HumanAddr,
+        /// optional address to authenticate as when the factory uses private listing
+        #[serde(default)]
+        viewer: Option<HumanAddr>,
+        /// optional viewing key of the viewer
+        #[serde(default)]
+        viewing_key: Option<String>,
+    },
+    /// lists the admin audit log, most recent first. Can only be viewed by the admin
+    AuditLog {
+        /// admin address
+        address: HumanAddr,
+        /// admin's viewing key
+        viewing_key: String,
+        /// start page for the entries returned and listed. Default: 0
+        #[serde(default)]
+        start_page: Option<u32>,
+        /// optional number of entries to return in this page. Default: the admin's page size setting, or the factory's default page size
+        #[serde(default)]
+        page_size: Option<u32>,
+    },
+    /// lists the issues flagged by offspring owners that are not resolved yet, oldest first. Can
+    /// only be viewed by the admin
+    IssueQueue {
+        /// admin address
+        address: HumanAddr,
+        /// admin's viewing key
+        viewing_key: String,
+        /// start page for the issues returned and listed. Default: 0
+        #[serde(default)]
+        start_page: Option<u32>,
+        /// optional number of issues to return in this page. Default: the admin's page size setting, or the factory's default page size
+        #[serde(default)]
+        page_size: Option<u32>,
+    },
+    /// lists the registrations waiting for the admin's review. Can only be viewed by the admin
+    Quarantine {
+        /// admin address
+        address: HumanAddr,
+        /// admin's viewing key
+        viewing_key: String,
+        /// start page for the registrations returned and listed. Default: 0
+        #[serde(default)]
+        start_page: Option<u32>,
+        /// optional number of registrations to return in this page. Default: the admin's page size setting, or the factory's default page size
+        #[serde(default)]
+        page_size: Option<u32>,
+    },
+    /// lists the issue history of an offspring, most recent first. Can be viewed by the admin and
+    /// by the offspring's owner
+    OffspringHistory {
+        /// address of the offspring
+        offspring: HumanAddr,
+        /// address of the admin or the offspring's owner
+        address: HumanAddr,
+        /// viewing key
+        viewing_key: String,
+        /// start page for the events returned and listed. Default: 0
+        #[serde(default)]
+        start_page: Option<u32>,
+        /// optional number of events to return in this page. Default: the querier's page size setting, or the factory's default page size
+        #[serde(default)]
+        page_size: Option<u32>,
+    },
+    /// exports a batch of registry records along with a continuation token for the next batch.
+    /// Can only be viewed by the admin
+    ExportRegistry {
+        /// admin address
+        address: HumanAddr,
+        /// admin's viewing key
+        viewing_key: String,
+        /// continuation token returned with the previous batch. None to start the export
+        #[serde(default)]
+        token: Option<String>,
+        /// optional number of records in the batch. Default and maximum: MAX_EXPORT_BATCH
+        #[serde(default)]
+        batch_size: Option<u32>,
+    },
+    /// exports a batch of the owner's offspring records, including their private fields, along
+    /// with a continuation token for the next batch
+    ExportMyOffspring {
+        /// address of the owner
+        address: HumanAddr,
+        /// viewing key
+        viewing_key: String,
+        /// continuation token returned with the previous batch. None to start the export
+        #[serde(default)]
+        token: Option<String>,
+        /// optional number of records in the batch. Default and maximum: MAX_EXPORT_BATCH
+        #[serde(default)]
+        batch_size: Option<u32>,
+    },
+    /// describes the registry's storage layout so migration tooling can choose how to move the
+    /// registry between factory deployments
+    RegistrySchema {},
+    /// displays the version and commit this factory was built from
+    BuildInfo {},
+    /// lists the optional features enabled on this factory
+    Features {},
+    /// displays the offspring version the factory creates, whether creation is stopped, the
+    /// admin, and the number of listed offspring
+    FactoryConfig {},
+    /// displays the results of the factory's health checks and when maintenance last ran, so
+    /// monitoring systems can poll a single query
+    Health {},
+    /// displays the number of active and inactive offspring, and also the owner's numbers if an
+    /// owner's address and viewing key are given
+    OffspringCount {
+        /// optional address of the owner
+        address: Option<HumanAddr>,
+        /// optional viewing key of the owner
+        viewing_key: Option<String>,
+    },
+    /// lists the registered offspring versions, and whether each is current, enabled and
+    /// deprecated
+    OffspringVersions {},
+    /// displays the pending transfer offer of an offspring, if the given address made or
+    /// received it
+    TransferOffer {
+        /// address of the offspring
+        offspring: HumanAddr,
+        /// address of the owner or recipient of the offer
+        address: HumanAddr,
+        /// viewing key
+        viewing_key: String,
+    },
+    /// displays the referral stats of the given address
+    ReferralStats {
+        /// address of the referrer
+        address: HumanAddr,
+        /// viewing key
+        viewing_key: String,
+    },
+    /// lists the number of creations made with each creation context. Can only be viewed by the
+    /// admin
+    CreationContexts {
+        /// admin address
+        address: HumanAddr,
+        /// admin's viewing key
+        viewing_key: String,
+        /// start page for the contexts returned and listed. Default: 0
+        #[serde(default)]
+        start_page: Option<u32>,
+        /// optional number of contexts to return in this page. Default: the admin's page size setting, or the factory's default page size
+        #[serde(default)]
+        page_size: Option<u32>,
+    },
+    /// lists the addresses on the creator allow list or deny list. Can only be viewed by the
+    /// admin
+    CreatorList {
+        /// admin address
+        address: HumanAddr,
+        /// admin's viewing key
+        viewing_key: String,
+        /// which list to display
+        list: CreatorListType,
+        /// start page for the addresses returned and listed. Default: 0
+        #[serde(default)]
+        start_page: Option<u32>,
+        /// optional number of addresses to return in this page. Default: the admin's page size setting, or the factory's default page size
+        #[serde(default)]
+        page_size: Option<u32>,
+    },
+    /// lists the recovery requests waiting for the admin's decision. Can only be viewed by the
+    /// admin
+    RecoveryRequests {
+        /// admin address
+        address: HumanAddr,
+        /// admin's viewing key
+        viewing_key: String,
+        /// start page for the requests returned and listed. Default: 0
+        #[serde(default)]
+        start_page: Option<u32>,
+        /// optional number of requests to return in this page. Default: the admin's page size setting, or the factory's default page size
+        #[serde(default)]
+        page_size: Option<u32>,
+    },
+    /// displays the owner's offspring creations and deactivations per bucket of
+    /// STATS_BUCKET_BLOCKS blocks, most recent bucket first
+    UsageStats {
+        /// address of the owner
+        address: HumanAddr,
+        /// viewing key
+        viewing_key: String,
+        /// start page for the buckets returned and listed. Default: 0
+        #[serde(default)]
+        start_page: Option<u32>,
+        /// optional number of buckets to return in this page. Default: the owner's page size setting, or the factory's default page size
+        #[serde(default)]
+        page_size: Option<u32>,
+    },
+    /// displays the owner's next creation nonce and the label it generates
+    CreationNonce {
+        /// address of the owner
+        address: HumanAddr,
+        /// viewing key
+        viewing_key: String,
+    },
+    /// displays whether the address may create an offspring right now, and if not, why
+    CanICreate {
+        /// address of the creator
+        address: HumanAddr,
+        /// viewing key
+        viewing_key: String,
+    },
+    /// displays how many more offspring the address may create in its current rate limit window,
+    /// and when the window ends
+    CreationQuota {
+        /// address of the creator
+        address: HumanAddr,
+        /// viewing key
+        viewing_key: String,
+    },
+    /// displays the root and height of the latest registry snapshot
+    RegistrySnapshot {},
+    /// displays the merkle proof that an offspring is included in the latest registry snapshot.
+    /// If the factory lists privately, only the offspring's owner can query it
+    MembershipProof {
+        /// address of the offspring
+        offspring: HumanAddr,
+        /// address of the offspring's owner, required if the factory lists privately
+        #[serde(default)]
+        address: Option<HumanAddr>,
+        /// viewing key of the offspring's owner, required if the factory lists privately
+        #[serde(default)]
+        viewing_key: Option<String>,
+    },
+    /// answers the wrapped query in the format of the requested api version, wrapped in a
+    /// VersionedAnswer
+    Versioned {
+        /// requested api version. Default: API_VERSION
+        #[serde(default)]
+        api_version: Option<u32>,
+        /// the query to answer. Can not be another versioned query
+        query: Box<QueryMsg>,
+    },
+    /// answers a query that needs authentication with a permit signed by the querier instead of
+    /// a viewing key
+    WithPermit {
+        /// permit for this factory with the owner permission
+        permit: Permit,
+        /// the query to answer for the signer of the permit
+        query: QueryWithPermit,
+    },
+    /// validates a permit and reports who signed it. This should be called by offspring that
+    /// accept permits
+    IsPermitValid {
+        /// permit for this factory
+        permit: Permit,
+    },
 }
 
-/// Queries
+/// queries that can be authenticated with a permit
 #[derive(Serialize, Deserialize, JsonSchema)]
 #[serde(rename_all = "snake_case")]
-pub enum QueryMsg {
-    /// lists all offspring whose owner is the given address.
+pub enum QueryWithPermit {
+    /// lists the signer's offspring, like ListMyOffspring
     ListMyOffspring {
-        // address whose activity to display
-        address: HumanAddr,
-        /// viewing key
-        viewing_key: String,
         /// optional filter for only active or inactive offspring.  If not specified, lists all
         #[serde(default)]
         filter: Option<FilterTypes>,
         /// start page for the offsprings returned and listed (applies to both active and inactive). Default: 0
         #[serde(default)]
         start_page: Option<u32>,
-        /// optional number of offspring to return in this page (applies to both active and inactive). Default: DEFAULT_PAGE_SIZE
+        /// optional number of offspring to return in this page (applies to both active and inactive). Default: the factory's default page size
         #[serde(default)]
         page_size: Option<u32>,
-    },
-    /// lists all active offspring in reverse chronological order
-    ListActiveOffspring {
-        /// start page for the offsprings returned and listed. Default: 0
+        /// optionally set to true to list the newest offspring first (applies to both active and inactive). Default: false
         #[serde(default)]
-        start_page: Option<u32>,
-        /// optional number of offspring to return in this page. Default: DEFAULT_PAGE_SIZE
+        reverse: Option<bool>,
+        /// optional text the labels of the listed offspring must contain, ignoring case. Pages are
+        /// counted among the matching offspring only
         #[serde(default)]
-        page_size: Option<u32>,
-    },
-    /// lists inactive offspring in reverse chronological order.
-    ListInactiveOffspring {
-        /// start page for the offsprings returned and listed. Default: 0
+        label_contains: Option<String>,
+        /// optional registry revision of the client's last answer. If the lists did not change
+        /// since, a NotModified answer is returned instead of the lists
         #[serde(default)]
-        start_page: Option<u32>,
-        /// optional number of offspring to return in this page. Default: DEFAULT_PAGE_SIZE
+        if_modified_since: Option<u64>,
+    },
+    /// displays the number of offspring, including the signer's own numbers, like OffspringCount
+    OffspringCount {},
+    /// reports where an offspring is in its lifecycle, like OffspringLifecycle
+    OffspringLifecycle {
+        /// address of the offspring contract
+        address: HumanAddr,
+    },
+    /// looks up the offspring registered under a label, like OffspringByLabel
+    OffspringByLabel {
+        /// label of the offspring
+        label: String,
+    },
+    /// displays the registry info of a single offspring, like OffspringInfo
+    OffspringInfo {
+        /// address of the offspring contract
+        address: HumanAddr,
+    },
+}
+
+/// custom key/value attribute an offspring reported to the registry
+#[derive(Serialize, Deserialize, JsonSchema, Clone, Debug, PartialEq)]
+pub struct OffspringAttribute {
+    /// attribute name, at most MAX_ATTRIBUTE_KEY_LEN bytes
+    pub key: String,
+    /// attribute value, at most MAX_ATTRIBUTE_VALUE_LEN bytes
+    pub value: String,
+}
+
+/// pending offer to transfer an offspring to another owner
+#[derive(Serialize, Deserialize, JsonSchema, Clone, Debug)]
+pub struct TransferOffer {
+    /// address of the offspring
+    pub offspring: HumanAddr,
+    /// address of the owner making the offer
+    pub owner: HumanAddr,
+    /// address the offspring is offered to
+    pub recipient: HumanAddr,
+    /// optional price in uscrt the recipient must pay
+    pub price: Option<Uint128>,
+}
+
+/// the admin's private support note about an owner
+#[derive(Serialize, Deserialize, JsonSchema, Clone, Debug)]
+pub struct OwnerNote {
+    /// the note
+    pub note: String,
+    /// block height the note was last set at
+    pub height: u64,
+    /// block time the note was last set at, in seconds
+    pub time: u64,
+}
+
+/// request to become the owner of an offspring whose owner lost their key
+#[derive(Serialize, Deserialize, JsonSchema, Clone, Debug)]
+pub struct RecoveryRequest {
+    /// address of the offspring
+    pub offspring: HumanAddr,
+    /// owner of the offspring when the request was made
+    pub owner: HumanAddr,
+    /// address asking to become the owner
+    pub claimant: HumanAddr,
+    /// block height the request was made at
+    pub height: u64,
+}
+
+/// version and commit of a factory build
+#[derive(Serialize, Deserialize, JsonSchema, Clone, Debug, PartialEq)]
+pub struct BuildInfo {
+    /// crate version of the factory
+    pub version: String,
+    /// commit the factory was built from, if known
+    pub commit: Option<String>,
+}
+
+impl BuildInfo {
+    /// returns the build info of this factory
+    pub fn current() -> Self {
+        BuildInfo {
+            version: FACTORY_VERSION.to_string(),
+            commit: FACTORY_COMMIT
+                .filter(|commit| !commit.is_empty())
+                .map(|commit| commit.to_string()),
+        }
+    }
+}
+
+/// admin action that can be executed as part of an AdminBatch
+#[derive(Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum AdminAction {
+    /// same as HandleMsg::NewOffspringContract
+    NewOffspringContract {
+        offspring_contract: OffspringContractInfo,
+    },
+    /// same as HandleMsg::RollbackVersion
+    RollbackVersion {},
+    /// same as HandleMsg::SetStatus
+    SetStatus { stop: bool },
+    /// same as HandleMsg::SetDefaultPageSize
+    SetDefaultPageSize { page_size: u32 },
+    /// same as HandleMsg::SetAddressBookEntry
+    SetAddressBookEntry {
+        name: String,
         #[serde(default)]
-        page_size: Option<u32>,
+        contract: Option<ContractInfo>,
     },
-    /// authenticates the supplied address/viewing key. This should be called by offspring.
-    IsKeyValid {
-        /// address whose viewing key is being authenticated
+    /// same as HandleMsg::SetVersionStatus
+    SetVersionStatus { code_id: u64, enabled: bool },
+    /// same as HandleMsg::SetAddressBlocked
+    SetAddressBlocked {
         address: HumanAddr,
-        /// viewing key
-        viewing_key: String,
+        blocked: bool,
+        #[serde(default)]
+        reason: Option<String>,
     },
 }
 
+/// result of creating one offspring of a batch
+#[derive(Serialize, Deserialize, JsonSchema, Debug)]
+pub struct BatchCreateResult {
+    /// label the offspring is instantiated with, if it was created
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+    /// success or failure
+    pub status: ResponseStatus,
+    /// why the offspring was not created
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// offspring to create at init or in a batch
+#[derive(Serialize, Deserialize, JsonSchema, Clone, Debug)]
+pub struct OffspringSpec {
+    /// optional label of the offspring. If not specified, a label is generated from the factory
+    /// address, the owner and the owner's creation nonce
+    #[serde(default)]
+    pub label: Option<String>,
+    /// address of the owner associated to this offspring contract
+    pub owner: HumanAddr,
+    /// the count for the counter offspring template
+    pub count: i32,
+    /// optional text description of the offspring
+    #[serde(default)]
+    pub description: Option<String>,
+    /// optional level of privacy of the offspring's count. Default: owner only
+    #[serde(default)]
+    pub count_privacy: Option<CountPrivacy>,
+    /// optional id of the registered offspring version to create the offspring from. Default:
+    /// the current version
+    #[serde(default)]
+    pub version: Option<u32>,
+}
+
+/// registered offspring version, as listed by the OffspringVersions query
+#[derive(Serialize, Deserialize, JsonSchema, Debug)]
+pub struct OffspringVersion {
+    /// id to pass to CreateOffspring to create offspring from this version
+    pub id: u32,
+    /// code id and code hash of the version
+    pub offspring_contract: OffspringContractInfo,
+    /// true if this is the version offspring are created from by default
+    pub current: bool,
+    /// true unless the admin disabled creation from this version's code id
+    pub enabled: bool,
+    /// true if the admin attached a deprecation notice to this version
+    pub deprecated: bool,
+}
+
+/// uscrt the factory attaches, from its own balance, to each kind of message it sends offspring.
+/// The factory sends at most one message of each kind per transaction, so there is no batching
+/// to configure, and messages sent by a contract always use the gas of the transaction
+#[derive(Serialize, Deserialize, JsonSchema, Clone, Default, Debug, PartialEq)]
+pub struct CallbackPolicy {
+    /// funds attached when instantiating a new offspring
+    #[serde(default)]
+    pub instantiate: Option<Uint128>,
+    /// funds attached when confirming an offspring's registration
+    #[serde(default)]
+    pub confirm_registration: Option<Uint128>,
+    /// funds attached when telling an offspring its ownership was transferred
+    #[serde(default)]
+    pub change_owner: Option<Uint128>,
+    /// funds attached when telling an offspring the admin deactivated it
+    #[serde(default)]
+    pub deactivate: Option<Uint128>,
+}
+
+/// deprecation notice of an offspring version
+#[derive(Serialize, Deserialize, JsonSchema)]
+pub struct VersionDeprecation {
+    /// why the version is deprecated and how to migrate
+    pub note: String,
+    /// optional version owners should migrate to
+    #[serde(default)]
+    pub replacement: Option<OffspringContractInfo>,
+}
+
+/// settings of the optional steps of the creation pipeline. A step whose setting is left out is
+/// skipped
+#[derive(Serialize, Deserialize, JsonSchema, Clone, Default, Debug, PartialEq)]
+pub struct CreationRules {
+    /// true if only addresses the admin allowed may create offspring
+    #[serde(default)]
+    pub allow_list: bool,
+    /// uscrt that must be sent with each CreateOffspring
+    #[serde(default)]
+    pub fee: Option<Uint128>,
+    /// SNIP-20 token fee that can be sent to the factory with an embedded CreateOffspring instead
+    #[serde(default)]
+    pub token_fee: Option<TokenFee>,
+    /// maximum number of offspring an address may create per window of blocks
+    #[serde(default)]
+    pub rate_limit: Option<RateLimit>,
+    /// maximum length of the labels given to CreateOffspring
+    #[serde(default)]
+    pub max_label_len: Option<u32>,
+    /// maximum number of an address's creations that may wait for registration at a time
+    #[serde(default)]
+    pub max_pending: Option<u32>,
+    /// steps of the creation pipeline that are skipped even if their setting is given. The
+    /// status and label steps can not be disabled
+    #[serde(default)]
+    pub disabled_steps: Vec<CreationStep>,
+    /// share of the creation fee, in basis points, credited to the referrer of a creation
+    /// instead of the admin
+    #[serde(default)]
+    pub referral_share: Option<u16>,
+}
+
+/// steps of the creation pipeline
+#[derive(Serialize, Deserialize, JsonSchema, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum CreationStep {
+    /// the factory is not stopped, and creation from the current version is enabled
+    Status,
+    /// the creator is allowed, if the creation rules use an allow list
+    AllowList,
+    /// the creation fee was paid, if the creation rules set one
+    Fee,
+    /// the creator did not use up the rate limit window, if the creation rules set a rate limit
+    RateLimit,
+    /// the creator does not have too many creations waiting for registration, if the creation
+    /// rules set a pending limit
+    PendingLimit,
+    /// the label follows the creation rules and is not used by another offspring
+    LabelRules,
+}
+
+/// creation fee paid in a SNIP-20 token
+#[derive(Serialize, Deserialize, JsonSchema, Clone, Debug, PartialEq)]
+pub struct TokenFee {
+    /// code hash and address of the token
+    pub token: ContractInfo,
+    /// amount of the token each creation costs
+    pub amount: Uint128,
+}
+
+/// maximum number of creations per window of blocks
+#[derive(Serialize, Deserialize, JsonSchema, Clone, Copy, Debug, PartialEq)]
+pub struct RateLimit {
+    /// maximum number of offspring created in a window
+    pub max_creations: u32,
+    /// number of blocks in a window
+    pub window_blocks: u64,
+}
+
+/// who may view the count of a counter offspring
+#[derive(Serialize, Deserialize, JsonSchema, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum CountPrivacy {
+    /// only the owner
+    Owner,
+    /// the owner and the viewers the owner chose
+    Viewers,
+    /// anyone
+    Public,
+}
+
+/// optional subsystems a deployment can switch on or off
+#[derive(Serialize, Deserialize, JsonSchema, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum Feature {
+    /// BatchCreateOffspring
+    BatchCreation,
+    /// paying the creation fee in a SNIP-20 token through Receive
+    TokenFees,
+    /// crediting a referrer when creating offspring
+    Referrals,
+    /// SetDelegate and GrantCreate
+    Delegation,
+    /// OfferTransfer and AcceptTransfer
+    Transfers,
+    /// issue reports sent by offspring with FlagIssue
+    IssueReports,
+    /// ownership recovery approved by the admin
+    Recovery,
+}
+
+impl Feature {
+    /// Returns &str with the name of the feature, as it is serialized
+    pub fn name(&self) -> &'static str {
+        match self {
+            Feature::BatchCreation => "batch_creation",
+            Feature::TokenFees => "token_fees",
+            Feature::Referrals => "referrals",
+            Feature::Delegation => "delegation",
+            Feature::Transfers => "transfers",
+            Feature::IssueReports => "issue_reports",
+            Feature::Recovery => "recovery",
+        }
+    }
+}
+
+/// the lists of creators the admin manages
+#[derive(Serialize, Deserialize, JsonSchema, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum CreatorListType {
+    /// addresses allowed to create offspring when the creation rules use an allow list
+    Allowed,
+    /// addresses that may not create offspring
+    Denied,
+}
+
 /// the filter types when viewing an address' offspring
 #[derive(Serialize, Deserialize, JsonSchema, PartialEq)]
 #[serde(rename_all = "snake_case")]
@@ -123,6 +1350,26 @@ pub enum FilterTypes {
     All,
 }
 
+/// result of one of the factory's health checks
+#[derive(Serialize, Deserialize, JsonSchema, Debug)]
+pub struct HealthCheck {
+    /// name of the check
+    pub name: String,
+    /// true if the check passed
+    pub passed: bool,
+    /// what the check found
+    pub detail: String,
+}
+
+/// envelope of the answer to a versioned query
+#[derive(Serialize, Deserialize, JsonSchema)]
+pub struct VersionedAnswer<T> {
+    /// api version the answer is formatted in
+    pub api_version: u32,
+    /// the answer
+    pub body: T,
+}
+
 /// responses to queries
 #[derive(Serialize, Deserialize, JsonSchema)]
 #[serde(rename_all = "snake_case")]
@@ -135,21 +1382,551 @@ pub enum QueryAnswer {
         /// lists of the address' inactive offspring
         #[serde(skip_serializing_if = "Option::is_none")]
         inactive: Option<Vec<StoreInactiveOffspringInfo>>,
+        /// addresses of the listed offspring created from a deprecated version
+        #[serde(default)]
+        deprecated: Vec<HumanAddr>,
+        /// registry revision the lists were read at
+        revision: u64,
+        /// number of offspring pages are counted among, in all listed lists
+        total_count: u32,
+        /// page listed
+        page: u32,
+        /// number of offspring listed per page
+        page_size: u32,
+        /// true if either listed list continues after this page
+        has_more: bool,
     },
     /// List active offspring
     ListActiveOffspring {
-        /// active offspring
+        /// active offspring, most recently activated first if newest_first was set
         active: Vec<StoreOffspringInfo>,
+        /// addresses of the listed offspring created from a deprecated version
+        #[serde(default)]
+        deprecated: Vec<HumanAddr>,
+        /// registry revision the lists were read at
+        revision: u64,
+        /// number of offspring pages are counted among
+        total_count: u32,
+        /// page listed
+        page: u32,
+        /// number of offspring listed per page
+        page_size: u32,
+        /// true if the list continues after this page
+        has_more: bool,
     },
-    /// List inactive offspring in no particular order
+    /// List inactive offspring
     ListInactiveOffspring {
-        /// inactive offspring in no particular order
+        /// inactive offspring, most recently deactivated first if newest_first was set
         inactive: Vec<StoreInactiveOffspringInfo>,
+        /// addresses of the listed offspring created from a deprecated version
+        #[serde(default)]
+        deprecated: Vec<HumanAddr>,
+        /// registry revision the lists were read at
+        revision: u64,
+        /// number of offspring pages are counted among
+        total_count: u32,
+        /// page listed
+        page: u32,
+        /// number of offspring listed per page
+        page_size: u32,
+        /// true if the list continues after this page
+        has_more: bool,
+    },
+    /// returned instead of a list if the offspring lists did not change since the client's
+    /// if_modified_since revision
+    NotModified {
+        /// current registry revision
+        revision: u64,
     },
+    /// number of listed offspring, returned instead of the list when the factory uses private
+    /// listing and no valid viewing key was supplied
+    OffspringCount { count: u32 },
+    /// which list an offspring is in, returned instead of its registry info when the factory
+    /// uses private listing and no valid viewing key was supplied
+    OffspringStatus { status: OffspringStatus },
     /// Viewing Key Error
     ViewingKeyError { error: String },
     /// result of authenticating address/key pair
     IsKeyValid { is_valid: bool },
+    /// result of validating a permit
+    IsPermitValid {
+        /// true if the permit is for this factory, signed, and not revoked
+        is_valid: bool,
+        /// address that signed the permit, if it is valid
+        #[serde(skip_serializing_if = "Option::is_none")]
+        address: Option<HumanAddr>,
+    },
+    /// result of authenticating an address/key pair and its roles
+    AuthenticateRole {
+        /// true if the address/key pair is valid
+        is_valid: bool,
+        /// true if the key is valid and the address is the factory admin
+        is_admin: bool,
+        /// true if the key is valid and the address is a delegate of the specified owner
+        is_delegate: bool,
+    },
+    /// entries of the address book
+    AddressBook {
+        /// address book entries
+        entries: Vec<AddressBookEntry>,
+    },
+    /// resolved address book entries in the order they were requested
+    ResolveNames {
+        /// resolved entries
+        entries: Vec<AddressBookEntry>,
+    },
+    /// lifecycle status of an offspring
+    OffspringLifecycle {
+        /// address of the offspring contract
+        address: HumanAddr,
+        /// the offspring's lifecycle status
+        status: LifecycleStatus,
+        /// sequence number of the offspring's registration, if it is registered
+        registration_index: Option<u64>,
+        /// true if the admin blocked this address from registering
+        blocked: bool,
+        /// deprecation notice of the version the offspring was created from, if it is deprecated
+        deprecation: Option<VersionDeprecation>,
+    },
+    /// offspring registered under a label
+    OffspringByLabel {
+        /// the label looked up
+        label: String,
+        /// address of the offspring using the label, if any
+        address: Option<HumanAddr>,
+        /// the offspring's lifecycle status
+        status: LifecycleStatus,
+    },
+    /// registry info of a single offspring
+    OffspringInfo {
+        /// address of the offspring contract
+        address: HumanAddr,
+        /// which list the offspring is in, if any
+        status: OffspringStatus,
+        /// info of the offspring if it is active
+        active: Option<StoreOffspringInfo>,
+        /// info of the offspring if it is inactive
+        inactive: Option<StoreInactiveOffspringInfo>,
+        /// true if the offspring was created from a deprecated version
+        deprecated: bool,
+    },
+    /// admin audit log entries
+    AuditLog {
+        /// audit log entries, most recent first
+        entries: Vec<AuditEntry>,
+        /// total number of audit log entries
+        total: u32,
+    },
+    /// issues flagged by offspring owners that are not resolved yet
+    IssueQueue {
+        /// issues, oldest first
+        issues: Vec<Issue>,
+        /// total number of issues that are not resolved yet
+        total: u32,
+    },
+    /// registrations waiting for the admin's review
+    Quarantine {
+        /// quarantined registrations
+        registrations: Vec<QuarantinedOffspring>,
+        /// total number of quarantined registrations
+        total: u32,
+    },
+    /// issue history of an offspring
+    OffspringHistory {
+        /// events, most recent first
+        events: Vec<OffspringEvent>,
+        /// total number of events
+        total: u32,
+    },
+    /// batch of exported registry records
+    ExportRegistry {
+        /// exported registry records
+        records: Vec<RegistryRecord>,
+        /// continuation token to request the next batch with. None if this is the last batch
+        next_token: Option<String>,
+    },
+    /// batch of an owner's exported offspring records
+    ExportMyOffspring {
+        /// exported offspring records
+        records: Vec<RegistryRecord>,
+        /// continuation token to request the next batch with. None if this is the last batch
+        next_token: Option<String>,
+    },
+    /// description of the registry's storage layout
+    RegistrySchema {
+        /// version of the registry's storage layout
+        version: u32,
+        /// identifiers of the registry indexes
+        indexes: Vec<String>,
+        /// supported ways to migrate the registry
+        migration_paths: Vec<String>,
+    },
+    /// version and commit this factory was built from
+    BuildInfo {
+        build: BuildInfo,
+    },
+    /// optional features enabled on this factory
+    Features {
+        enabled: Vec<Feature>,
+    },
+    /// current config and status of the factory
+    FactoryConfig {
+        /// code id and code hash of the offspring version new offspring are created from
+        offspring_contract: OffspringContractInfo,
+        /// true if the creation of new offspring is stopped
+        stopped: bool,
+        /// address of the admin
+        admin: HumanAddr,
+        /// number of active offspring
+        active_count: u32,
+        /// number of inactive offspring
+        inactive_count: u32,
+    },
+    /// results of the factory's health checks
+    Health {
+        /// true if every check passed
+        healthy: bool,
+        /// results of the individual checks
+        checks: Vec<HealthCheck>,
+        /// block height of the last maintenance run, if maintenance ever ran
+        last_maintenance_height: Option<u64>,
+    },
+    /// number of listed offspring, globally and of the given owner
+    OffspringCounts {
+        /// number of active offspring
+        active: u32,
+        /// number of inactive offspring
+        inactive: u32,
+        /// number of the owner's active offspring, if an owner was given
+        #[serde(skip_serializing_if = "Option::is_none")]
+        my_active: Option<u32>,
+        /// number of the owner's inactive offspring, if an owner was given
+        #[serde(skip_serializing_if = "Option::is_none")]
+        my_inactive: Option<u32>,
+    },
+    /// registered offspring versions, oldest first
+    OffspringVersions {
+        versions: Vec<OffspringVersion>,
+    },
+    /// pending transfer offer of an offspring
+    TransferOffer {
+        /// the offer, if there is one the querier made or received
+        offer: Option<TransferOffer>,
+    },
+    /// referral stats of an address
+    ReferralStats {
+        /// aggregate referral stats
+        stats: ReferralStats,
+    },
+    /// number of creations per creation context
+    CreationContexts {
+        /// creation counters, in no particular order
+        contexts: Vec<ContextStats>,
+        /// total number of contexts
+        total: u32,
+    },
+    /// addresses on a creator list
+    CreatorList {
+        /// addresses on the list, in no particular order
+        creators: Vec<HumanAddr>,
+        /// total number of addresses on the list
+        total: u32,
+    },
+    /// recovery requests waiting for the admin's decision
+    RecoveryRequests {
+        /// recovery requests, in no particular order
+        requests: Vec<RecoveryRequest>,
+        /// total number of requests
+        total: u32,
+    },
+    /// usage statistics of an owner
+    UsageStats {
+        /// number of blocks in a bucket
+        bucket_blocks: u64,
+        /// buckets with activity, most recent first
+        buckets: Vec<UsageBucket>,
+    },
+    /// next creation nonce of an owner
+    CreationNonce {
+        /// nonce of the next offspring created for the owner
+        nonce: u64,
+        /// label generated for the next offspring created for the owner without a label
+        next_label: String,
+    },
+    /// whether an address may create an offspring right now
+    CanICreate {
+        /// true if a CreateOffspring sent now would pass the factory's creation checks
+        allowed: bool,
+        /// reasons creation is not allowed, empty if it is
+        reasons: Vec<String>,
+    },
+    /// remaining creations of an address in its rate limit window
+    CreationQuota {
+        /// the rate limit, if the creation rules set one
+        rate_limit: Option<RateLimit>,
+        /// number of offspring the address may still create in the current window, if there is a
+        /// rate limit
+        remaining: Option<u32>,
+        /// block height at which the current window ends and the quota is reset, if the address
+        /// created offspring in it
+        resets_at: Option<u64>,
+    },
+    /// latest registry snapshot
+    RegistrySnapshot {
+        /// the snapshot, if one was taken
+        snapshot: Option<SnapshotInfo>,
+    },
+    /// merkle proof of an offspring's inclusion in the latest registry snapshot
+    MembershipProof {
+        /// the proof, if the offspring is included in the latest snapshot
+        proof: Option<MembershipProof>,
+    },
+}
+
+/// root and height of a registry snapshot
+#[derive(Serialize, Deserialize, JsonSchema)]
+pub struct SnapshotInfo {
+    /// merkle root over the active registry
+    pub root: Binary,
+    /// block height the snapshot was taken at
+    pub height: u64,
+    /// number of active offspring included
+    pub leaf_count: u32,
+}
+
+/// sibling hash on the path from a snapshot leaf to its root
+#[derive(Serialize, Deserialize, JsonSchema, Clone, Debug, PartialEq)]
+pub struct ProofNode {
+    /// the sibling's hash
+    pub hash: Binary,
+    /// true if the sibling is hashed on the left
+    pub is_left: bool,
+}
+
+/// merkle proof that an offspring is included in a registry snapshot. The leaf is the sha256 of
+/// a 0x00 byte followed by the address, label, and owner, each prefixed with its length as a 4
+/// byte big endian integer. Each node is the sha256 of a 0x01 byte followed by its children
+#[derive(Serialize, Deserialize, JsonSchema)]
+pub struct MembershipProof {
+    /// merkle root the proof leads to
+    pub root: Binary,
+    /// block height the snapshot was taken at
+    pub height: u64,
+    /// position of the offspring in the snapshot
+    pub index: u32,
+    /// label of the offspring when the snapshot was taken
+    pub label: String,
+    /// owner of the offspring when the snapshot was taken
+    pub owner: HumanAddr,
+    /// sibling hashes from the leaf level up to the root. Levels where the path has no sibling
+    /// are skipped
+    pub siblings: Vec<ProofNode>,
+}
+
+/// registry record of an offspring as exported/imported during a migration
+#[derive(Serialize, Deserialize, JsonSchema)]
+pub struct RegistryRecord {
+    /// offspring address
+    pub address: HumanAddr,
+    /// label used when initializing offspring
+    pub label: String,
+    /// optional text description of the offspring
+    pub description: Option<String>,
+    /// true if the description is a preview cut at the exporting factory's description limit
+    #[serde(default)]
+    pub description_truncated: bool,
+    /// optional link to off-chain metadata about the offspring
+    #[serde(default)]
+    pub metadata_url: Option<String>,
+    /// optional privacy level of the offspring's count
+    #[serde(default)]
+    pub count_privacy: Option<CountPrivacy>,
+    /// custom attributes the offspring reported
+    #[serde(default)]
+    pub attributes: Vec<OffspringAttribute>,
+    /// address of the offspring's owner
+    pub owner: HumanAddr,
+    /// true if the offspring is active
+    pub active: bool,
+    /// block height the offspring registered at
+    #[serde(default)]
+    pub created_height: u64,
+    /// block time the offspring registered at, in seconds since epoch
+    #[serde(default)]
+    pub created_time: u64,
+    /// block height the offspring was deactivated at, if it is inactive
+    #[serde(default)]
+    pub deactivated_height: Option<u64>,
+    /// block time the offspring was deactivated at, if it is inactive
+    #[serde(default)]
+    pub deactivated_time: Option<u64>,
+    /// who or what deactivated the offspring, if it is inactive
+    #[serde(default)]
+    pub deactivated_by: Option<DeactivationSource>,
+}
+
+/// record of an admin action kept for accountability
+#[derive(Serialize, Deserialize, JsonSchema)]
+pub struct AuditEntry {
+    /// name of the admin action
+    pub action: String,
+    /// address of the admin that performed the action
+    pub admin: HumanAddr,
+    /// optional address the action was performed on
+    pub subject: Option<HumanAddr>,
+    /// block height of the action
+    pub height: u64,
+}
+
+/// issue an offspring's owner flagged for the admin
+#[derive(Serialize, Deserialize, JsonSchema)]
+pub struct Issue {
+    /// position of the issue in the queue
+    pub id: u32,
+    /// address of the offspring the issue was flagged from
+    pub offspring: HumanAddr,
+    /// label of the offspring
+    pub label: String,
+    /// address of the offspring's owner
+    pub owner: HumanAddr,
+    /// short category of the issue
+    pub category: String,
+    /// description of the issue
+    pub details: String,
+    /// block height the issue was flagged at
+    pub height: u64,
+    /// how far the admin handled the issue
+    pub status: IssueStatus,
+}
+
+/// how far the admin handled a flagged issue
+#[derive(Serialize, Deserialize, JsonSchema, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum IssueStatus {
+    /// the admin has not handled the issue yet
+    Open,
+    /// the admin has seen the issue
+    Acknowledged,
+    /// the admin resolved the issue
+    Resolved,
+}
+
+/// event in an offspring's issue history
+#[derive(Serialize, Deserialize, JsonSchema)]
+pub struct OffspringEvent {
+    /// name of the event
+    pub action: String,
+    /// id of the issue the event belongs to
+    pub issue: u32,
+    /// optional note, such as the resolution of the issue
+    pub note: Option<String>,
+    /// block height of the event
+    pub height: u64,
+}
+
+/// aggregate referral stats of a referrer
+#[derive(Serialize, Deserialize, JsonSchema, Default)]
+pub struct ReferralStats {
+    /// number of offspring creations referred
+    pub referrals: u64,
+    /// uscrt of creation fees credited to the referrer and not withdrawn yet
+    #[serde(default)]
+    pub earned: Uint128,
+    /// creation fees paid in SNIP-20 tokens credited to the referrer and not withdrawn yet
+    #[serde(default)]
+    pub token_earned: Vec<TokenEarnings>,
+}
+
+/// amount of a SNIP-20 token credited to a referrer
+#[derive(Serialize, Deserialize, JsonSchema, Clone)]
+pub struct TokenEarnings {
+    /// address of the token
+    pub token: HumanAddr,
+    /// amount of the token not withdrawn yet
+    pub amount: Uint128,
+}
+
+/// number of creations made with a creation context
+#[derive(Serialize, Deserialize, JsonSchema)]
+pub struct ContextStats {
+    /// the creation context
+    pub context: String,
+    /// number of offspring created with the context
+    pub creations: u64,
+}
+
+/// an owner's activity during a bucket of blocks
+#[derive(Serialize, Deserialize, JsonSchema)]
+pub struct UsageBucket {
+    /// first block height of the bucket
+    pub start_height: u64,
+    /// number of the owner's offspring registered during the bucket
+    pub created: u32,
+    /// number of the owner's offspring deactivated during the bucket
+    pub deactivated: u32,
+}
+
+/// which registry list an offspring is in
+#[derive(Serialize, Deserialize, JsonSchema, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum OffspringStatus {
+    /// the offspring is in the active list
+    Active,
+    /// the offspring is in the inactive list
+    Inactive,
+    /// the offspring was purged from the registry
+    Purged,
+    /// the address is not known to the factory
+    Unknown,
+}
+
+/// lifecycle status of an offspring as seen by the factory's indexes
+#[derive(Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum LifecycleStatus {
+    /// the offspring is registered and listed as active
+    Active {
+        /// stored info of the active offspring
+        info: StoreOffspringInfo,
+    },
+    /// the offspring has been deactivated
+    Inactive {
+        /// stored info of the inactive offspring
+        info: StoreInactiveOffspringInfo,
+    },
+    /// the offspring was purged from the registry and only its tombstone remains
+    Purged {
+        /// tombstone left by the purged offspring
+        tombstone: Tombstone,
+    },
+    /// the address is not known to the factory
+    Unknown {},
+}
+
+/// registration held for the admin's review because the factory could not authenticate it
+#[derive(Serialize, Deserialize, JsonSchema, Clone)]
+pub struct QuarantinedOffspring {
+    /// the offspring's info as it would be registered
+    pub info: StoreOffspringInfo,
+    /// owner named in the registration
+    pub owner: HumanAddr,
+    /// block height the registration arrived at
+    pub height: u64,
+    /// hash of the password the offspring registered with, kept so it can reactivate later
+    pub key: [u8; 32],
+}
+
+/// minimal record kept for a purged offspring so references to it can still be resolved
+#[derive(Serialize, Deserialize, JsonSchema, Clone)]
+pub struct Tombstone {
+    /// address of the purged offspring
+    pub address: HumanAddr,
+    /// block height at which the offspring was purged
+    pub purged_at: u64,
+    /// block height after which the tombstone may be discarded
+    pub expires_at: u64,
+    /// optional reason given for the purge
+    pub reason: Option<String>,
 }
 
 /// success or failure response
@@ -165,6 +1942,103 @@ pub enum ResponseStatus {
 pub enum HandleAnswer {
     /// response from creating a viewing key
     ViewingKey { key: String },
+    /// response from creating an offspring
+    CreateOffspring {
+        /// success or failure
+        status: ResponseStatus,
+        /// viewing key generated for an owner that did not have one, if the factory provisions
+        /// viewing keys automatically
+        #[serde(skip_serializing_if = "Option::is_none")]
+        viewing_key: Option<String>,
+    },
+    /// response from creating a batch of offspring
+    BatchCreateOffspring {
+        /// result of each offspring, in the order they were given
+        results: Vec<BatchCreateResult>,
+    },
+    /// response from a compaction pass
+    Compact {
+        /// number of registry entries processed in this call
+        processed: u32,
+        /// number of stale duplicate entries removed in this call
+        removed: u32,
+        /// true if this call finished a full pass over the registry
+        done: bool,
+    },
+    /// response from taking a registry snapshot
+    SnapshotRegistry {
+        /// number of registry entries and tree nodes processed in this call
+        processed: u32,
+        /// true if the registry changed since the build began, so it started over
+        restarted: bool,
+        /// true if this call completed the snapshot
+        done: bool,
+        /// merkle root over the active registry, once the snapshot is complete
+        root: Option<Binary>,
+        /// number of active offspring included, once the snapshot is complete
+        leaf_count: Option<u32>,
+    },
+    /// response from removing inactive offspring
+    RemoveOffspring {
+        /// success or failure
+        status: ResponseStatus,
+        /// number of offspring removed
+        removed: u32,
+    },
+    /// response from executing an admin batch
+    AdminBatch {
+        /// success or failure
+        status: ResponseStatus,
+        /// number of actions executed
+        executed: u32,
+    },
+    /// owner's offspring listing as seen by the admin
+    ListOffspringOfOwnerAdmin {
+        /// lists of the owner's active offspring
+        #[serde(skip_serializing_if = "Option::is_none")]
+        active: Option<Vec<StoreOffspringInfo>>,
+        /// lists of the owner's inactive offspring
+        #[serde(skip_serializing_if = "Option::is_none")]
+        inactive: Option<Vec<StoreInactiveOffspringInfo>>,
+        /// addresses of the listed offspring created from a deprecated version
+        #[serde(default)]
+        deprecated: Vec<HumanAddr>,
+        /// the admin's note about the owner, if any
+        #[serde(default)]
+        note: Option<OwnerNote>,
+    },
+    /// response from starting an admin transfer
+    TransferAdmin {
+        /// success or failure
+        status: ResponseStatus,
+        /// address that must accept the transfer, or None if a pending transfer was cancelled
+        pending_admin: Option<HumanAddr>,
+    },
+    /// response from accepting an admin transfer
+    AcceptAdmin {
+        /// success or failure
+        status: ResponseStatus,
+        /// address of the new admin
+        admin: HumanAddr,
+    },
+    /// response from withdrawing creation fees
+    WithdrawFees {
+        /// success or failure
+        status: ResponseStatus,
+        /// amount sent to the treasury
+        withdrawn: Uint128,
+        /// collected fees of the same denomination or token left after the withdrawal
+        remaining: Uint128,
+    },
+    /// response from withdrawing referral fees
+    WithdrawReferralFees {
+        /// success or failure
+        status: ResponseStatus,
+        /// amount sent to the referrer
+        withdrawn: Uint128,
+        /// credited fees of the same denomination or token left after the withdrawal
+        remaining: Uint128,
+    },
     /// generic status response
     Status {
         /// success or failure
@@ -176,7 +2050,7 @@ pub enum HandleAnswer {
 }
 
 /// code hash and address of a contract
-#[derive(Serialize, Deserialize, JsonSchema)]
+#[derive(Serialize, Deserialize, JsonSchema, Clone, Debug, PartialEq)]
 pub struct ContractInfo {
     /// contract's code hash string
     pub code_hash: String,
@@ -184,8 +2058,17 @@ pub struct ContractInfo {
     pub address: HumanAddr,
 }
 
+/// named entry of the address book of related system contracts
+#[derive(Serialize, Deserialize, JsonSchema, Clone)]
+pub struct AddressBookEntry {
+    /// name the contract is resolved by
+    pub name: String,
+    /// code hash and address of the contract
+    pub contract: ContractInfo,
+}
+
 /// Info needed to instantiate an offspring
-#[derive(Serialize, Deserialize, JsonSchema)]
+#[derive(Serialize, Deserialize, JsonSchema, Clone, Debug, PartialEq)]
 pub struct OffspringContractInfo {
     /// code id of the stored offspring contract
     pub code_id: u64,
@@ -209,14 +2092,33 @@ pub struct RegisterOffspringInfo {
     pub label: String,
     /// offspring password
     pub password: [u8; 32],
+    /// optional text description of the offspring
+    #[serde(default)]
+    pub description: Option<String>,
+    /// optional privacy level of the offspring's count
+    #[serde(default)]
+    pub count_privacy: Option<CountPrivacy>,
 }
 
 impl RegisterOffspringInfo {
-    /// takes the register offspring information and creates a store offspring info struct
-    pub fn to_store_offspring_info(&self, address: HumanAddr) -> StoreOffspringInfo {
+    /// takes the register offspring information and the block it registered in, and creates a
+    /// store offspring info struct
+    pub fn to_store_offspring_info(
+        &self,
+        address: HumanAddr,
+        block: &BlockInfo,
+    ) -> StoreOffspringInfo {
         StoreOffspringInfo {
             address,
             label: self.label.clone(),
+            description: self.description.clone(),
+            description_truncated: false,
+            metadata_url: None,
+            count_privacy: self.count_privacy,
+            attributes: vec![],
+            created_height: block.height,
+            created_time: block.time,
+            checksum: 0,
         }
     }
 }
@@ -231,20 +2133,68 @@ pub struct StoreOffspringInfo {
     pub address: HumanAddr,
     /// label used when initializing offspring
     pub label: String,
+    /// optional text description of the offspring
+    pub description: Option<String>,
+    /// true if the description is a preview cut at the factory's description limit. The full
+    /// text is only kept by the offspring
+    pub description_truncated: bool,
+    /// optional link to off-chain metadata about the offspring, set by its owner. Public lists
+    /// only show it if the count is public
+    pub metadata_url: Option<String>,
+    /// optional privacy level of the offspring's count
+    pub count_privacy: Option<CountPrivacy>,
+    /// custom attributes the offspring reported
+    pub attributes: Vec<OffspringAttribute>,
+    /// block height the offspring registered at
+    pub created_height: u64,
+    /// block time the offspring registered at, in seconds since epoch
+    pub created_time: u64,
+    /// crc32 of the other fields, set when the record is stored and verified when it is read
+    pub checksum: u32,
 }
 
 impl StoreOffspringInfo {
-    /// takes the active offspring information and creates a inactive offspring info struct
+    /// takes the active offspring information, the block it was deactivated in, and who or what
+    /// deactivated it, and creates a inactive offspring info struct
     pub fn to_store_inactive_offspring_info(
         &self,
+        block: &BlockInfo,
+        deactivated_by: DeactivationSource,
     ) -> StoreInactiveOffspringInfo {
         StoreInactiveOffspringInfo {
             address: self.address.clone(),
             label: self.label.clone(),
+            description: self.description.clone(),
+            description_truncated: self.description_truncated,
+            metadata_url: self.metadata_url.clone(),
+            count_privacy: self.count_privacy,
+            attributes: self.attributes.clone(),
+            created_height: self.created_height,
+            created_time: self.created_time,
+            deactivated_height: block.height,
+            deactivated_time: block.time,
+            deactivated_by,
+            checksum: self.checksum,
         }
     }
 }
 
+/// who or what deactivated an offspring
+#[derive(Serialize, Deserialize, JsonSchema, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum DeactivationSource {
+    /// the owner deactivated the offspring
+    Owner,
+    /// the factory admin deactivated the offspring
+    Admin,
+    /// the offspring expired from inactivity
+    Expired,
+    /// the owner repointed the offspring to another factory
+    Detached,
+    /// the offspring was imported from a registry export that did not record it
+    Unknown,
+}
+
 // in general, when an offspring contract is deactivated, it may require
 // different data to be stored with it, and thus, in theory InactiveOffspringInfo
 // could be different to OffspringInfo. That's why we have InactiveOffspringInfo.
@@ -259,10 +2209,53 @@ pub struct InactiveOffspringInfo {
 }
 
 /// inactive offspring storage/display format
-#[derive(Serialize, Deserialize, JsonSchema, Clone)]
+#[derive(Serialize, Deserialize, JsonSchema, Clone, Debug)]
 pub struct StoreInactiveOffspringInfo {
     /// offspring address
     pub address: HumanAddr,
     /// label used when initializing offspring
     pub label: String,
+    /// optional text description of the offspring
+    pub description: Option<String>,
+    /// true if the description is a preview cut at the factory's description limit. The full
+    /// text is only kept by the offspring
+    pub description_truncated: bool,
+    /// optional link to off-chain metadata about the offspring, set by its owner. Public lists
+    /// only show it if the count is public
+    pub metadata_url: Option<String>,
+    /// optional privacy level of the offspring's count
+    pub count_privacy: Option<CountPrivacy>,
+    /// custom attributes the offspring reported
+    pub attributes: Vec<OffspringAttribute>,
+    /// block height the offspring registered at
+    pub created_height: u64,
+    /// block time the offspring registered at, in seconds since epoch
+    pub created_time: u64,
+    /// block height the offspring was deactivated at
+    pub deactivated_height: u64,
+    /// block time the offspring was deactivated at, in seconds since epoch
+    pub deactivated_time: u64,
+    /// who or what deactivated the offspring
+    pub deactivated_by: DeactivationSource,
+    /// crc32 of the other fields, set when the record is stored and verified when it is read
+    pub checksum: u32,
+}
+
+impl StoreInactiveOffspringInfo {
+    /// takes the inactive offspring information and creates an active offspring info struct for
+    /// a reactivated offspring
+    pub fn to_store_offspring_info(&self) -> StoreOffspringInfo {
+        StoreOffspringInfo {
+            address: self.address.clone(),
+            label: self.label.clone(),
+            description: self.description.clone(),
+            description_truncated: self.description_truncated,
+            metadata_url: self.metadata_url.clone(),
+            count_privacy: self.count_privacy,
+            attributes: self.attributes.clone(),
+            created_height: self.created_height,
+            created_time: self.created_time,
+            checksum: self.checksum,
+        }
+    }
 }
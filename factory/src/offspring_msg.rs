@@ -1,9 +1,12 @@
-use secret_toolkit::utils::InitCallback;
+use secret_toolkit::utils::{HandleCallback, InitCallback};
 use serde::{Deserialize, Serialize};
 
 use cosmwasm_std::HumanAddr;
 
-use crate::{msg::ContractInfo, state::BLOCK_SIZE};
+use crate::{
+    msg::{BuildInfo, ContractInfo, CountPrivacy},
+    state::BLOCK_SIZE,
+};
 
 /// Instantiation message
 #[derive(Serialize, Deserialize)]
@@ -19,8 +22,39 @@ pub struct OffspringInitMsg {
     pub count: i32,
     #[serde(default)]
     pub description: Option<String>,
+    /// names of address book entries the offspring should resolve at init
+    #[serde(default)]
+    pub dependencies: Option<Vec<String>>,
+    /// privacy level of the offspring's count
+    #[serde(default)]
+    pub count_privacy: Option<CountPrivacy>,
+    /// version and commit of the factory creating the offspring
+    #[serde(default)]
+    pub factory_build: Option<BuildInfo>,
 }
 
 impl InitCallback for OffspringInitMsg {
     const BLOCK_SIZE: usize = BLOCK_SIZE;
 }
+
+/// Offspring handle messages to be used by the factory
+#[derive(Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OffspringHandleMsg {
+    /// tells the offspring the factory registered it
+    ConfirmRegistration {
+        /// sequence number of the offspring's registration
+        index: u64,
+    },
+    /// tells the offspring its ownership was transferred
+    ChangeOwner {
+        /// address of the new owner
+        owner: HumanAddr,
+    },
+    /// tells the offspring the admin deactivated it
+    DeactivateByFactory {},
+}
+
+impl HandleCallback for OffspringHandleMsg {
+    const BLOCK_SIZE: usize = BLOCK_SIZE;
+}
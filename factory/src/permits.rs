@@ -0,0 +1,123 @@
+use cosmwasm_std::{
+    to_binary, Api, Env, Extern, HandleResult, HumanAddr, Querier, QueryResult, StdError,
+    StdResult, Storage,
+};
+
+use secret_toolkit::permit::{validate, Permit, RevokedPermits, TokenPermissions};
+
+use crate::state::{load, Config, CONFIG_KEY, PREFIX_REVOKED_PERMITS};
+
+use crate::{
+    msg::{QueryAnswer, QueryWithPermit},
+    queries::{lifecycle, list_my, lookup_label, offspring_count, offspring_info},
+    response::ok_status,
+};
+
+/// Returns HandleResult
+///
+/// revokes the sender's permit with the given name. Revoked permits are refused by the factory
+/// and by the offspring that validate permits with the factory
+///
+/// # Arguments
+///
+/// * `deps` - mutable reference to Extern containing all the contract's external dependencies
+/// * `env` - Env of contract's environment
+/// * `permit_name` - name of the permit to revoke
+pub fn try_revoke_permit<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    permit_name: &str,
+) -> HandleResult {
+    RevokedPermits::revoke_permit(
+        &mut deps.storage,
+        PREFIX_REVOKED_PERMITS,
+        &env.message.sender,
+        permit_name,
+    );
+
+    ok_status()
+}
+
+/// Returns StdResult<HumanAddr> with the address that signed the permit
+///
+/// makes sure the permit is for this factory, correctly signed, and not revoked
+///
+/// # Arguments
+///
+/// * `deps` - reference to Extern containing all the contract's external dependencies
+/// * `permit` - a reference to the permit
+pub fn validate_permit<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    permit: &Permit,
+) -> StdResult<HumanAddr> {
+    // queries have no Env, so the permit is checked against the remembered factory address
+    let config: Config = load(&deps.storage, CONFIG_KEY)?;
+    let signer = validate(
+        deps,
+        PREFIX_REVOKED_PERMITS,
+        permit,
+        config.contract_address,
+        config.address_prefix.as_deref(),
+    )?;
+    Ok(HumanAddr(signer))
+}
+
+/// Returns QueryResult answering a query for the signer of the permit
+///
+/// # Arguments
+///
+/// * `deps` - reference to Extern containing all the contract's external dependencies
+/// * `permit` - the permit authenticating the query
+/// * `query` - the query to answer
+pub fn try_with_permit<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    permit: Permit,
+    query: QueryWithPermit,
+) -> QueryResult {
+    let address = validate_permit(deps, &permit)?;
+    if !permit.check_permission(&TokenPermissions::Owner) {
+        return Err(StdError::generic_err(
+            "This query needs a permit with the owner permission",
+        ));
+    }
+    match query {
+        QueryWithPermit::ListMyOffspring {
+            filter,
+            start_page,
+            page_size,
+            reverse,
+            label_contains,
+            if_modified_since,
+        } => list_my(
+            deps,
+            &address,
+            filter,
+            start_page,
+            page_size,
+            reverse.unwrap_or(false),
+            label_contains,
+            if_modified_since,
+        ),
+        QueryWithPermit::OffspringCount {} => offspring_count(deps, Some(&address)),
+        QueryWithPermit::OffspringLifecycle { address } => lifecycle(deps, &address, true),
+        QueryWithPermit::OffspringByLabel { label } => lookup_label(deps, label, true),
+        QueryWithPermit::OffspringInfo { address } => offspring_info(deps, &address, true),
+    }
+}
+
+/// Returns QueryResult indicating whether the permit is valid, and if so, who signed it
+///
+/// # Arguments
+///
+/// * `deps` - reference to Extern containing all the contract's external dependencies
+/// * `permit` - a reference to the permit
+pub fn try_validate_permit<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    permit: &Permit,
+) -> QueryResult {
+    let address = validate_permit(deps, permit).ok();
+    to_binary(&QueryAnswer::IsPermitValid {
+        is_valid: address.is_some(),
+        address,
+    })
+}
@@ -0,0 +1,160 @@
+use cosmwasm_std::{
+    log, to_binary, Api, Env, Extern, HandleResult, HumanAddr, Querier, QueryResult, StdError,
+    StdResult, Storage,
+};
+
+use secret_toolkit_incubator::{CashMap, ReadOnlyCashMap};
+
+use crate::state::{Config, MAX_QUARANTINE, QUARANTINE_KEY};
+
+use crate::{
+    addr::normalize_address,
+    admin::{enforce_admin, enforce_not_blocked, record_audit},
+    auth::is_admin_key_valid,
+    creation::complete_registration,
+    msg::{QuarantinedOffspring, QueryAnswer, RegisterOffspringInfo},
+    queries::resolve_page_size,
+    rand::sha_256,
+    response::{ok_status, ok_with_log},
+    sealing::{open, seal_description},
+    stats::checkpoint_log,
+};
+
+/// Returns HandleResult
+///
+/// holds a registration the factory could not authenticate for the admin's review. A later
+/// registration from the same offspring replaces the held one
+///
+/// # Arguments
+///
+/// * `deps` - mutable reference to Extern containing all the contract's external dependencies
+/// * `env` - Env of contract's environment
+/// * `owner` - address of the owner named in the registration
+/// * `reg_offspring` - reference to RegisterOffspringInfo of the offspring that is trying to register
+pub fn quarantine_registration<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    owner: HumanAddr,
+    reg_offspring: &RegisterOffspringInfo,
+) -> HandleResult {
+    let offspring_addr = deps.api.canonical_address(&env.message.sender)?;
+    enforce_not_blocked(&deps.storage, &offspring_addr)?;
+    deps.api.canonical_address(&owner)?;
+    let mut info = reg_offspring.to_store_offspring_info(env.message.sender.clone(), &env.block);
+    let (description, truncated) =
+        seal_description(&deps.storage, offspring_addr.as_slice(), info.description)?;
+    info.description = description;
+    info.description_truncated = truncated;
+
+    let mut quarantine: CashMap<QuarantinedOffspring, _> =
+        CashMap::init(QUARANTINE_KEY, &mut deps.storage);
+    if !quarantine.contains(offspring_addr.as_slice()) && quarantine.len() >= MAX_QUARANTINE {
+        return Err(StdError::generic_err(
+            "Unable to authenticate registration, and the quarantine is full.",
+        ));
+    }
+    quarantine.insert(
+        offspring_addr.as_slice(),
+        QuarantinedOffspring {
+            info,
+            owner,
+            height: env.block.height,
+            key: sha_256(&reg_offspring.password),
+        },
+    )?;
+
+    ok_with_log(
+        vec![],
+        vec![log("quarantined_address", env.message.sender)],
+        None,
+    )
+}
+
+/// Returns HandleResult
+///
+/// allows admin to approve a quarantined registration, registering the offspring as if it had
+/// authenticated, or to deny it, discarding it
+///
+/// # Arguments
+///
+/// * `deps` - mutable reference to Extern containing all the contract's external dependencies
+/// * `env` - Env of contract's environment
+/// * `address` - address of the quarantined offspring
+/// * `approve` - true to register the offspring, false to discard its registration
+pub fn try_review_quarantine<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    address: HumanAddr,
+    approve: bool,
+) -> HandleResult {
+    // only allow admin to do this
+    let config: Config = enforce_admin(deps, &env)?;
+    let offspring_addr = deps.api.canonical_address(&address)?;
+    let mut quarantine: CashMap<QuarantinedOffspring, _> =
+        CashMap::init(QUARANTINE_KEY, &mut deps.storage);
+    let held = quarantine.get(offspring_addr.as_slice()).ok_or_else(|| {
+        StdError::generic_err(format!("There is no quarantined registration of {}", address))
+    })?;
+    quarantine.remove(offspring_addr.as_slice())?;
+
+    if !approve {
+        record_audit(&mut deps.storage, "deny_quarantined", &env, Some(address))?;
+        return ok_status();
+    }
+    enforce_not_blocked(&deps.storage, &offspring_addr)?;
+    let confirm_msg = complete_registration(
+        &mut deps.storage,
+        &deps.api,
+        &config,
+        &held.owner,
+        held.info,
+        &config.version.code_hash,
+        &held.key,
+        env.block.height,
+    )?;
+    record_audit(&mut deps.storage, "approve_quarantined", &env, Some(address.clone()))?;
+
+    let mut log = vec![log("offspring_address", address)];
+    log.extend(checkpoint_log(&deps.storage, &config)?);
+
+    ok_with_log(vec![confirm_msg], log, None)
+}
+
+/// Returns QueryResult listing the registrations waiting for the admin's review
+///
+/// # Arguments
+///
+/// * `deps` - reference to Extern containing all the contract's external dependencies
+/// * `address` - a reference to the admin's address
+/// * `viewing_key` - String key used to authenticate the query
+/// * `start_page` - optional start page for the registrations returned and listed
+/// * `page_size` - optional number of registrations to return in this page
+pub fn try_quarantine<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    address: &HumanAddr,
+    viewing_key: String,
+    start_page: Option<u32>,
+    page_size: Option<u32>,
+) -> QueryResult {
+    let address = &normalize_address(&deps.api, address)?;
+    if !is_admin_key_valid(deps, address, viewing_key)? {
+        return to_binary(&QueryAnswer::ViewingKeyError {
+            error: "Wrong viewing key for this address or viewing key not set".to_string(),
+        });
+    }
+    let size = resolve_page_size(deps, Some(address), page_size)?;
+    let quarantine: ReadOnlyCashMap<QuarantinedOffspring, _> =
+        ReadOnlyCashMap::init(QUARANTINE_KEY, &deps.storage);
+    let registrations = quarantine
+        .paging(start_page.unwrap_or(0), size)?
+        .into_iter()
+        .map(|mut held| {
+            held.info.description = open(&deps.storage, held.info.description)?;
+            Ok(held)
+        })
+        .collect::<StdResult<Vec<QuarantinedOffspring>>>()?;
+    to_binary(&QueryAnswer::Quarantine {
+        registrations,
+        total: quarantine.len(),
+    })
+}
@@ -0,0 +1,1621 @@
+use std::collections::HashSet;
+
+use cosmwasm_std::{
+    to_binary, Api, Binary, CanonicalAddr, Extern, HumanAddr, Querier, QueryResult, ReadonlyStorage, StdError,
+    StdResult, Storage,
+};
+
+use cosmwasm_storage::ReadonlyPrefixedStorage;
+
+use serde::{de::DeserializeOwned, Serialize};
+
+use secret_toolkit::{
+    serialization::{Bincode2, Serde},
+    storage::AppendStore,
+};
+
+use secret_toolkit_incubator::ReadOnlyCashMap;
+
+use crate::state::{
+    load, may_load, Config, ExportCursor, OwnerSettings, PendingOffspring, RegistrySnapshot,
+    ACTIVE_KEY, CONTEXTS_KEY, HEALTHY_PENDING_LIMIT, MAINTENANCE_HEIGHT_KEY, PENDING_KEY, SCHEMA_VERSION_KEY,
+    ADDRESS_BOOK_KEY, CONFIG_KEY, INACTIVE_KEY, MAX_EXPORT_BATCH, MIGRATION_PATHS, PREFIX_AUDIT_LOG,
+    PREFIX_OFFSPRING_OWNER, PREFIX_OWNERS_ACTIVE, PREFIX_OWNERS_INACTIVE, PREFIX_OWNER_SETTINGS,
+    PREFIX_REFERRALS, PREFIX_REGISTRATION_INDEX, REGISTRY_INDEXES, REGISTRY_SCHEMA_VERSION,
+    SNAPSHOT_KEY, ALLOWED_CREATORS_KEY, DENIED_CREATORS_KEY,
+};
+
+use crate::{
+    addr::{normalize_address, owner_key},
+    admin::is_blocked,
+    auth::{is_admin_key_valid, is_delegate, is_key_valid, may_view_full_list},
+    checksum::retain_intact,
+    creation::{auto_label, load_creation_nonce},
+    msg::{
+        AddressBookEntry, AuditEntry, BuildInfo, ContextStats, CountPrivacy, CreatorListType, FilterTypes, HealthCheck, LifecycleStatus, MembershipProof, OffspringStatus, QueryAnswer,
+        ReferralStats, RegistryRecord, SnapshotInfo, StoreInactiveOffspringInfo, StoreOffspringInfo,
+    },
+    registry::{
+        current_height, lifecycle_status, offspring_by_label, order_namespace, position_namespace,
+        registry_revision, version_deprecation,
+    },
+    sealing::{open, open_active, open_inactive},
+    snapshot::{merkle_proof, snapshot_entry},
+    transfer::load_offer,
+    validators::{run_pipeline, CreationRequest},
+};
+
+/// Returns StdResult<u32>
+///
+/// determines how many entries a list query should return. An explicit page size is used as
+/// is, otherwise the owner's page size setting applies, falling back to the factory's default
+///
+/// # Arguments
+///
+/// * `deps` - reference to Extern containing all the contract's external dependencies
+/// * `owner` - optional reference to the address whose settings apply
+/// * `page_size` - optional page size specified by the query
+pub fn resolve_page_size<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    owner: Option<&HumanAddr>,
+    page_size: Option<u32>,
+) -> StdResult<u32> {
+    if let Some(size) = page_size {
+        return Ok(size);
+    }
+    if let Some(owner) = owner {
+        let owner_raw = deps.api.canonical_address(owner)?;
+        let settings_store = ReadonlyPrefixedStorage::new(PREFIX_OWNER_SETTINGS, &deps.storage);
+        let settings: Option<OwnerSettings> = may_load(&settings_store, owner_raw.as_slice())?;
+        if let Some(size) = settings.and_then(|s| s.page_size) {
+            return Ok(size);
+        }
+    }
+    let config: Config = load(&deps.storage, CONFIG_KEY)?;
+    Ok(config.default_page_size)
+}
+
+/// Returns StdResult<u32>
+///
+/// makes sure a page size lists at least one entry
+///
+/// # Arguments
+///
+/// * `page_size` - the page size to validate
+pub fn valid_page_size(page_size: u32) -> StdResult<u32> {
+    if page_size == 0 {
+        return Err(StdError::generic_err("Page size must be greater than zero"));
+    }
+    Ok(page_size)
+}
+
+/// Returns QueryResult listing the active offspring
+///
+/// # Arguments
+///
+/// * `deps` - reference to Extern containing all the contract's external dependencies
+/// * `start_page` - optional start page for the offsprings returned and listed
+/// * `page_size` - optional number of offspring to return in this page
+/// * `address` - optional address to authenticate as when the factory uses private listing
+/// * `viewing_key` - optional viewing key of the address
+/// * `if_modified_since` - optional registry revision of the client's last answer
+/// * `newest_first` - true if the most recently activated offspring should be listed first
+pub fn try_list_active<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    start_page: Option<u32>,
+    page_size: Option<u32>,
+    address: Option<HumanAddr>,
+    viewing_key: Option<String>,
+    if_modified_since: Option<u64>,
+    newest_first: bool,
+) -> QueryResult {
+    let address = address
+        .map(|address| normalize_address(&deps.api, &address))
+        .transpose()?;
+    if !may_view_full_list(&deps.storage, address, viewing_key)? {
+        let info_store: ReadOnlyCashMap<StoreOffspringInfo, _> = ReadOnlyCashMap::init(ACTIVE_KEY, &deps.storage);
+        return to_binary(&QueryAnswer::OffspringCount {
+            count: info_store.len(),
+        });
+    }
+    let revision = registry_revision(&deps.storage)?;
+    if is_unmodified(revision, if_modified_since) {
+        return to_binary(&QueryAnswer::NotModified { revision });
+    }
+    let size = resolve_page_size(deps, None, page_size)?;
+    let (active, total) =
+        display_active_list(&deps.storage, None, ACTIVE_KEY, start_page, size, newest_first, None)?;
+    let deprecated = deprecated_among(deps, &Some(active.clone()), &None)?;
+    let active = active.into_iter().map(public_active).collect();
+    to_binary(&QueryAnswer::ListActiveOffspring {
+        active,
+        deprecated,
+        revision,
+        total_count: total,
+        page: start_page.unwrap_or(0),
+        page_size: size,
+        has_more: continues_after(total, start_page, size),
+    })
+}
+
+/// Returns QueryResult listing the inactive offspring
+///
+/// # Arguments
+///
+/// * `deps` - reference to Extern containing all the contract's external dependencies
+/// * `start_page` - optional start page for the offsprings returned and listed
+/// * `page_size` - optional number of offspring to display
+/// * `address` - optional address to authenticate as when the factory uses private listing
+/// * `viewing_key` - optional viewing key of the address
+/// * `if_modified_since` - optional registry revision of the client's last answer
+/// * `newest_first` - true if the most recently deactivated offspring should be listed first
+pub fn try_list_inactive<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    start_page: Option<u32>,
+    page_size: Option<u32>,
+    address: Option<HumanAddr>,
+    viewing_key: Option<String>,
+    if_modified_since: Option<u64>,
+    newest_first: bool,
+) -> QueryResult {
+    let address = address
+        .map(|address| normalize_address(&deps.api, &address))
+        .transpose()?;
+    if !may_view_full_list(&deps.storage, address, viewing_key)? {
+        let inactive_store: ReadOnlyCashMap<StoreInactiveOffspringInfo, _> = ReadOnlyCashMap::init(INACTIVE_KEY, &deps.storage);
+        return to_binary(&QueryAnswer::OffspringCount {
+            count: inactive_store.len(),
+        });
+    }
+    let revision = registry_revision(&deps.storage)?;
+    if is_unmodified(revision, if_modified_since) {
+        return to_binary(&QueryAnswer::NotModified { revision });
+    }
+    let size = resolve_page_size(deps, None, page_size)?;
+    let (inactive, total) = display_inactive_list(
+        &deps.storage,
+        None,
+        INACTIVE_KEY,
+        start_page,
+        size,
+        newest_first,
+        None,
+    )?;
+    let deprecated = deprecated_among(deps, &None, &Some(inactive.clone()))?;
+    let inactive = inactive.into_iter().map(public_inactive).collect();
+    to_binary(&QueryAnswer::ListInactiveOffspring {
+        inactive,
+        deprecated,
+        revision,
+        total_count: total,
+        page: start_page.unwrap_or(0),
+        page_size: size,
+        has_more: continues_after(total, start_page, size),
+    })
+}
+
+/// Returns Option<String> with the metadata url an offspring shows in public answers. The url
+/// is only shown if the offspring's count is public
+///
+/// # Arguments
+///
+/// * `url` - the offspring's optional metadata url
+/// * `count_privacy` - the offspring's optional count privacy
+fn public_metadata_url(url: Option<String>, count_privacy: Option<CountPrivacy>) -> Option<String> {
+    url.filter(|_| count_privacy == Some(CountPrivacy::Public))
+}
+
+/// Returns StoreOffspringInfo as shown in public answers
+///
+/// # Arguments
+///
+/// * `info` - the active offspring's info
+fn public_active(mut info: StoreOffspringInfo) -> StoreOffspringInfo {
+    info.metadata_url = public_metadata_url(info.metadata_url, info.count_privacy);
+    info
+}
+
+/// Returns StoreInactiveOffspringInfo as shown in public answers
+///
+/// # Arguments
+///
+/// * `info` - the inactive offspring's info
+fn public_inactive(mut info: StoreInactiveOffspringInfo) -> StoreInactiveOffspringInfo {
+    info.metadata_url = public_metadata_url(info.metadata_url, info.count_privacy);
+    info
+}
+
+/// Returns StdResult<LifecycleStatus> as shown in public answers, with sealed fields opened
+///
+/// # Arguments
+///
+/// * `storage` - a reference to the contract's storage
+/// * `status` - the offspring's lifecycle status
+fn public_status<S: ReadonlyStorage>(
+    storage: &S,
+    status: LifecycleStatus,
+) -> StdResult<LifecycleStatus> {
+    Ok(match status {
+        LifecycleStatus::Active { mut info } => {
+            info.description = open(storage, info.description)?;
+            LifecycleStatus::Active {
+                info: public_active(info),
+            }
+        }
+        LifecycleStatus::Inactive { mut info } => {
+            info.description = open(storage, info.description)?;
+            LifecycleStatus::Inactive {
+                info: public_inactive(info),
+            }
+        }
+        other => other,
+    })
+}
+
+/// Returns OffspringStatus naming the list an offspring is in, without its info
+///
+/// # Arguments
+///
+/// * `status` - a reference to the offspring's lifecycle status
+fn list_status(status: &LifecycleStatus) -> OffspringStatus {
+    match status {
+        LifecycleStatus::Active { .. } => OffspringStatus::Active,
+        LifecycleStatus::Inactive { .. } => OffspringStatus::Inactive,
+        LifecycleStatus::Purged { .. } => OffspringStatus::Purged,
+        LifecycleStatus::Unknown {} => OffspringStatus::Unknown,
+    }
+}
+
+/// Returns StdResult<bool>
+///
+/// determines whether a single offspring's registry info may be displayed, which follows the
+/// same rules as the full lists
+///
+/// # Arguments
+///
+/// * `deps` - reference to Extern containing all the contract's external dependencies
+/// * `viewer` - optional address to authenticate as
+/// * `viewing_key` - optional viewing key of the viewer
+fn may_view_info<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    viewer: Option<HumanAddr>,
+    viewing_key: Option<String>,
+) -> StdResult<bool> {
+    let viewer = viewer
+        .map(|viewer| normalize_address(&deps.api, &viewer))
+        .transpose()?;
+    may_view_full_list(&deps.storage, viewer, viewing_key)
+}
+
+/// Returns QueryResult listing the offspring with the address as its owner
+///
+/// # Arguments
+///
+/// * `deps` - reference to Extern containing all the contract's external dependencies
+/// * `address` - a reference to the address whose offspring should be listed
+/// * `viewing_key` - String key used to authenticate the query
+/// * `filter` - optional choice of display filters
+/// * `start_page` - optional start page for the offsprings returned and listed
+/// * `page_size` - optional number of offspring to return in this page
+/// * `reverse` - true if the newest offspring should be listed first
+/// * `label_contains` - optional text the labels of the listed offspring must contain
+/// * `if_modified_since` - optional registry revision of the client's last answer
+#[allow(clippy::too_many_arguments)]
+pub fn try_list_my<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    address: &HumanAddr,
+    viewing_key: String,
+    filter: Option<FilterTypes>,
+    start_page: Option<u32>,
+    page_size: Option<u32>,
+    reverse: bool,
+    label_contains: Option<String>,
+    if_modified_since: Option<u64>,
+) -> QueryResult {
+    let address = &normalize_address(&deps.api, address)?;
+    // if key matches
+    if !is_key_valid(&deps.storage, address, viewing_key) {
+        return to_binary(&QueryAnswer::ViewingKeyError {
+            error: "Wrong viewing key for this address or viewing key not set".to_string(),
+        });
+    }
+    list_my(
+        deps,
+        address,
+        filter,
+        start_page,
+        page_size,
+        reverse,
+        label_contains,
+        if_modified_since,
+    )
+}
+
+/// Returns QueryResult listing the offspring of an authenticated owner
+///
+/// # Arguments
+///
+/// * `deps` - reference to Extern containing all the contract's external dependencies
+/// * `address` - a reference to the authenticated address whose offspring to list
+/// * `filter` - optional choice of display of active or inactive offspring (or both)
+/// * `start_page` - optional start page for the offsprings returned and listed
+/// * `page_size` - optional number of offspring to return in this page
+/// * `reverse` - true to list the newest offspring first
+/// * `label_contains` - optional text the labels of the listed offspring must contain
+/// * `if_modified_since` - optional registry revision of the client's last answer
+#[allow(clippy::too_many_arguments)]
+pub fn list_my<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    address: &HumanAddr,
+    filter: Option<FilterTypes>,
+    start_page: Option<u32>,
+    page_size: Option<u32>,
+    reverse: bool,
+    label_contains: Option<String>,
+    if_modified_since: Option<u64>,
+) -> QueryResult {
+    let revision = registry_revision(&deps.storage)?;
+    if is_unmodified(revision, if_modified_since) {
+        return to_binary(&QueryAnswer::NotModified { revision });
+    }
+    let page = list_owner_offspring(
+        deps,
+        address,
+        filter,
+        start_page,
+        page_size,
+        reverse,
+        label_contains.as_deref(),
+    )?;
+    let deprecated = deprecated_among(deps, &page.active, &page.inactive)?;
+
+    to_binary(&QueryAnswer::ListMyOffspring {
+        active: page.active,
+        inactive: page.inactive,
+        deprecated,
+        revision,
+        total_count: page.total,
+        page: start_page.unwrap_or(0),
+        page_size: page.page_size,
+        has_more: page.has_more,
+    })
+}
+
+/// Returns bool indicating whether the offspring lists did not change since the client's last
+/// answer
+///
+/// # Arguments
+///
+/// * `revision` - the current registry revision
+/// * `if_modified_since` - optional registry revision of the client's last answer
+fn is_unmodified(revision: u64, if_modified_since: Option<u64>) -> bool {
+    matches!(if_modified_since, Some(since) if revision <= since)
+}
+
+/// Returns StdResult<Vec<HumanAddr>> with the addresses of the listed offspring that were
+/// created from a deprecated version
+///
+/// # Arguments
+///
+/// * `deps` - reference to Extern containing all the contract's external dependencies
+/// * `active` - a reference to the optional list of active offspring
+/// * `inactive` - a reference to the optional list of inactive offspring
+pub fn deprecated_among<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    active: &Option<Vec<StoreOffspringInfo>>,
+    inactive: &Option<Vec<StoreInactiveOffspringInfo>>,
+) -> StdResult<Vec<HumanAddr>> {
+    let addresses = active
+        .iter()
+        .flatten()
+        .map(|info| &info.address)
+        .chain(inactive.iter().flatten().map(|info| &info.address));
+    let mut deprecated = Vec::new();
+    for address in addresses {
+        let offspring_addr = deps.api.canonical_address(address)?;
+        if version_deprecation(&deps.storage, &offspring_addr)?.is_some() {
+            deprecated.push(address.clone());
+        }
+    }
+    Ok(deprecated)
+}
+
+/// one page of an owner's lists of active and inactive offspring
+pub struct OwnerPage {
+    /// page of the owner's active offspring, if the filter lists them
+    pub active: Option<Vec<StoreOffspringInfo>>,
+    /// page of the owner's inactive offspring, if the filter lists them
+    pub inactive: Option<Vec<StoreInactiveOffspringInfo>>,
+    /// number of offspring in the listed lists that pages are counted among
+    pub total: u32,
+    /// number of offspring listed per page
+    pub page_size: u32,
+    /// true if either listed list continues after this page
+    pub has_more: bool,
+}
+
+/// Returns StdResult<OwnerPage>
+///
+/// provide a page of the owner's lists of active and inactive offspring allowed by the filter
+///
+/// # Arguments
+///
+/// * `deps` - reference to Extern containing all the contract's external dependencies
+/// * `address` - a reference to the address whose offspring should be listed
+/// * `filter` - optional choice of display filters
+/// * `start_page` - optional start page for the offsprings returned and listed
+/// * `page_size` - optional number of offspring to return in this page
+/// * `reverse` - true if the newest offspring should be listed first
+/// * `label_contains` - optional text the labels of the listed offspring must contain, ignoring
+///   case. Pages are counted among the matching offspring only
+pub fn list_owner_offspring<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    address: &HumanAddr,
+    filter: Option<FilterTypes>,
+    start_page: Option<u32>,
+    page_size: Option<u32>,
+    reverse: bool,
+    label_contains: Option<&str>,
+) -> StdResult<OwnerPage> {
+    let size = resolve_page_size(deps, Some(address), page_size)?;
+    let mut active_list: Option<Vec<StoreOffspringInfo>> = None;
+    let mut inactive_list: Option<Vec<StoreInactiveOffspringInfo>> = None;
+    let mut total = 0;
+    let mut has_more = false;
+    // if no filter default to ALL
+    let types = filter.unwrap_or(FilterTypes::All);
+
+    // list the active offspring
+    if types == FilterTypes::Active || types == FilterTypes::All {
+        let (list, count) = display_active_list(
+            &deps.storage,
+            Some( PREFIX_OWNERS_ACTIVE ),
+            owner_key(address),
+            start_page,
+            size,
+            reverse,
+            label_contains,
+        )?;
+        active_list = Some(list);
+        total += count;
+        has_more |= continues_after(count, start_page, size);
+    }
+    // list the inactive offspring
+    if types == FilterTypes::Inactive || types == FilterTypes::All {
+        let (list, count) = display_inactive_list(
+            &deps.storage,
+            Some( PREFIX_OWNERS_INACTIVE ),
+            owner_key(address),
+            start_page,
+            size,
+            reverse,
+            label_contains,
+        )?;
+        inactive_list = Some(list);
+        total += count;
+        has_more |= continues_after(count, start_page, size);
+    }
+
+    Ok(OwnerPage {
+        active: active_list,
+        inactive: inactive_list,
+        total,
+        page_size: size,
+        has_more,
+    })
+}
+
+/// Returns bool indicating whether a list has entries after the given page
+///
+/// # Arguments
+///
+/// * `total` - number of entries pages are counted among
+/// * `start_page` - optional page that was listed
+/// * `size` - number of entries in a page
+fn continues_after(total: u32, start_page: Option<u32>, size: u32) -> bool {
+    (u64::from(start_page.unwrap_or(0)) + 1) * u64::from(size) < u64::from(total)
+}
+
+/// Returns StdResult<(Vec<StoreOffspringInfo>, u32)>
+///
+/// provide the appropriate page of active offspring, and the number of offspring pages are
+/// counted among
+///
+/// # Arguments
+///
+/// * `api` - reference to the Api used to convert canonical and human addresses
+/// * `storage` - a reference to the contract's storage
+/// * `prefix` - optional storage prefix to load from
+/// * `key` - storage key to read (user addr byte)
+/// * `start_page` - optional start page for the offsprings returned and listed
+/// * `size` - number of offspring to return in this page
+/// * `reverse` - true if the list should be paged newest first, by its chronological index
+/// * `label_contains` - optional text the labels of the listed offspring must contain
+pub fn display_active_list<S: ReadonlyStorage>(
+    storage: &S,
+    prefix: Option<&[u8]>,
+    key: &[u8],
+    start_page: Option<u32>,
+    size: u32,
+    reverse: bool,
+    label_contains: Option<&str>,
+) -> StdResult<(Vec<StoreOffspringInfo>, u32)> {
+    let page_number = start_page.unwrap_or(0);
+    let order = ReadonlyPrefixedStorage::multilevel(&order_namespace(prefix, key), storage);
+    let positions = ReadonlyPrefixedStorage::multilevel(&position_namespace(prefix, key), storage);
+    let index = (&order, &positions);
+    let (list, total) = match prefix {
+        Some(pref) => {
+            // get owner's active list
+            let read = &ReadonlyPrefixedStorage::new(pref, storage);
+            let user_store: ReadOnlyCashMap<StoreOffspringInfo, _> = ReadOnlyCashMap::init(key, read);
+            page_of(&user_store, index, page_number, size, reverse, label_contains)?
+        },
+        None => {
+            // get factory's active list
+            let active_store: ReadOnlyCashMap<StoreOffspringInfo, _> = ReadOnlyCashMap::init(key, storage);
+            page_of(&active_store, index, page_number, size, reverse, label_contains)?
+        }
+    };
+    Ok((open_active(storage, retain_intact(list))?, total))
+}
+
+/// Returns StdResult<(Vec<InactiveOffspringInfo>, u32)>
+///
+/// provide the appropriate page of inactive offspring, and the number of offspring pages are
+/// counted among
+///
+/// # Arguments
+///
+/// * `storage` - a reference to the contract's storage
+/// * `prefix` - optional storage prefix to load from
+/// * `key` - storage key to read
+/// * `start_page` - optional start page for the offsprings returned and listed
+/// * `size` - number of offspring to return in this page
+/// * `reverse` - true if the list should be paged newest first, by its chronological index
+/// * `label_contains` - optional text the labels of the listed offspring must contain
+pub fn display_inactive_list<S: ReadonlyStorage>(
+    storage: &S,
+    prefix: Option<&[u8]>,
+    key: &[u8],
+    start_page: Option<u32>,
+    size: u32,
+    reverse: bool,
+    label_contains: Option<&str>,
+) -> StdResult<(Vec<StoreInactiveOffspringInfo>, u32)> {
+    let page_number = start_page.unwrap_or(0);
+    let order = ReadonlyPrefixedStorage::multilevel(&order_namespace(prefix, key), storage);
+    let positions = ReadonlyPrefixedStorage::multilevel(&position_namespace(prefix, key), storage);
+    let index = (&order, &positions);
+    let (list, total) = match prefix {
+        Some(pref) => {
+            // get owner's inactive list
+            let read = &ReadonlyPrefixedStorage::new(pref, storage);
+            let user_store: ReadOnlyCashMap<StoreInactiveOffspringInfo, _> = ReadOnlyCashMap::init(key, read);
+            page_of(&user_store, index, page_number, size, reverse, label_contains)?
+        },
+        None => {
+            // get factory's inactive list
+            let active_store: ReadOnlyCashMap<StoreInactiveOffspringInfo, _> = ReadOnlyCashMap::init(key, storage);
+            page_of(&active_store, index, page_number, size, reverse, label_contains)?
+        }
+    };
+    Ok((open_inactive(storage, retain_intact(list))?, total))
+}
+
+/// registry entries that can be searched by label
+trait Labeled {
+    /// Returns the label of the offspring the entry belongs to
+    fn label(&self) -> &str;
+}
+
+impl Labeled for StoreOffspringInfo {
+    fn label(&self) -> &str {
+        &self.label
+    }
+}
+
+impl Labeled for StoreInactiveOffspringInfo {
+    fn label(&self) -> &str {
+        &self.label
+    }
+}
+
+/// Returns StdResult<(Vec<T>, u32)> with one page of a list and the number of entries pages are
+/// counted among. When reversed, the list is paged newest first, in the order of its
+/// chronological index
+///
+/// # Arguments
+///
+/// * `store` - a reference to the list being paged
+/// * `index` - references to the storage of the list's chronological index and of the latest
+///   position of each offspring in it
+/// * `start_page` - the page to return
+/// * `size` - number of entries in a page
+/// * `reverse` - true if the list should be paged newest first
+/// * `label_contains` - optional text the labels of the entries must contain, ignoring case.
+///   Pages are counted among the matching entries only
+fn page_of<T: Serialize + DeserializeOwned + Labeled, S: ReadonlyStorage, O: ReadonlyStorage>(
+    store: &ReadOnlyCashMap<T, S>,
+    index: (&O, &O),
+    start_page: u32,
+    size: u32,
+    reverse: bool,
+    label_contains: Option<&str>,
+) -> StdResult<(Vec<T>, u32)> {
+    if reverse {
+        return newest_page_of(store, index, start_page, size, label_contains);
+    }
+    if let Some(text) = label_contains {
+        // labels are not indexed, so the whole list is searched before paging
+        let text = text.to_lowercase();
+        let matching: Vec<T> = store
+            .paging(0, store.len())?
+            .into_iter()
+            .filter(|entry| entry.label().to_lowercase().contains(&text))
+            .collect();
+        let total = matching.len() as u32;
+        let page = matching
+            .into_iter()
+            .skip(start_page.saturating_mul(size) as usize)
+            .take(size as usize)
+            .collect();
+        return Ok((page, total));
+    }
+    Ok((store.paging(start_page, size)?, store.len()))
+}
+
+/// Returns StdResult<(Vec<T>, u32)> with one page of a list ordered newest first and the number
+/// of entries pages are counted among. The list's chronological index is read from its end, and
+/// only the newest entry of an offspring that is still in the list is counted
+///
+/// # Arguments
+///
+/// * `store` - a reference to the list being paged
+/// * `index` - references to the storage of the list's chronological index and of the latest
+///   position of each offspring in it
+/// * `start_page` - the page to return
+/// * `size` - number of entries in a page
+/// * `label_contains` - optional text the labels of the entries must contain, ignoring case.
+///   Pages are counted among the matching entries only
+fn newest_page_of<T: Serialize + DeserializeOwned + Labeled, S: ReadonlyStorage, O: ReadonlyStorage>(
+    store: &ReadOnlyCashMap<T, S>,
+    index: (&O, &O),
+    start_page: u32,
+    size: u32,
+    label_contains: Option<&str>,
+) -> StdResult<(Vec<T>, u32)> {
+    let (order, positions) = index;
+    let index = match AppendStore::<CanonicalAddr, _>::attach(order) {
+        Some(index) => index?,
+        None => return Ok((Vec::new(), 0)),
+    };
+    let text = label_contains.map(|text| text.to_lowercase());
+    let skip = start_page.saturating_mul(size);
+    let mut seen = HashSet::new();
+    let mut matched = 0u32;
+    let mut page = Vec::new();
+    for pos in (0..index.len()).rev() {
+        // without a label search the total is known, so reading can stop with a full page
+        if text.is_none() && page.len() as u32 >= size {
+            break;
+        }
+        let offspring_addr = index.get_at(pos)?;
+        // entries a compaction moved or dropped are not at the offspring's latest position
+        let latest: Option<u32> = may_load(positions, offspring_addr.as_slice())?;
+        if matches!(latest, Some(latest) if latest != pos) || !seen.insert(offspring_addr.clone()) {
+            continue;
+        }
+        let entry = match store.get(offspring_addr.as_slice()) {
+            Some(entry) => entry,
+            None => continue,
+        };
+        if let Some(text) = &text {
+            if !entry.label().to_lowercase().contains(text) {
+                continue;
+            }
+        }
+        if matched >= skip && (page.len() as u32) < size {
+            page.push(entry);
+        }
+        matched += 1;
+    }
+    let total = if text.is_some() { matched } else { store.len() };
+    Ok((page, total))
+}
+
+/// Returns QueryResult indicating whether the address/key pair is valid
+///
+/// # Arguments
+///
+/// * `deps` - reference to Extern containing all the contract's external dependencies
+/// * `address` - a reference to the address whose key should be validated
+/// * `viewing_key` - String key used for authentication
+pub fn try_validate_key<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    address: &HumanAddr,
+    viewing_key: String,
+) -> QueryResult {
+    let address = &normalize_address(&deps.api, address)?;
+    to_binary(&QueryAnswer::IsKeyValid {
+        is_valid: is_key_valid(&deps.storage, address, viewing_key),
+    })
+}
+
+/// Returns QueryResult indicating whether the address/key pair is valid, and if so, whether
+/// the address is the admin and whether it is a delegate of the specified owner
+///
+/// # Arguments
+///
+/// * `deps` - reference to Extern containing all the contract's external dependencies
+/// * `address` - a reference to the address whose key should be validated
+/// * `viewing_key` - String key used for authentication
+/// * `owner` - optional owner to check delegation for
+pub fn try_authenticate_role<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    address: &HumanAddr,
+    viewing_key: String,
+    owner: Option<HumanAddr>,
+) -> QueryResult {
+    let address = &normalize_address(&deps.api, address)?;
+    let owner = owner.map(|o| normalize_address(&deps.api, &o)).transpose()?;
+    let is_valid = is_key_valid(&deps.storage, address, viewing_key);
+    let mut is_admin = false;
+    let mut delegate = false;
+    if is_valid {
+        let config: Config = load(&deps.storage, CONFIG_KEY)?;
+        is_admin = config.admin == deps.api.canonical_address(address)?;
+        if let Some(owner) = owner {
+            delegate = is_delegate(&deps.storage, &deps.api, &owner, address)?;
+        }
+    }
+    to_binary(&QueryAnswer::AuthenticateRole {
+        is_valid,
+        is_admin,
+        is_delegate: delegate,
+    })
+}
+
+/// Returns QueryResult listing the address book entries
+///
+/// # Arguments
+///
+/// * `deps` - reference to Extern containing all the contract's external dependencies
+/// * `start_page` - optional start page for the entries returned and listed
+/// * `page_size` - optional number of entries to return in this page
+pub fn try_list_address_book<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    start_page: Option<u32>,
+    page_size: Option<u32>,
+) -> QueryResult {
+    let size = resolve_page_size(deps, None, page_size)?;
+    let book: ReadOnlyCashMap<AddressBookEntry, _> = ReadOnlyCashMap::init(ADDRESS_BOOK_KEY, &deps.storage);
+    to_binary(&QueryAnswer::AddressBook {
+        entries: book.paging(start_page.unwrap_or(0), size)?,
+    })
+}
+
+/// Returns QueryResult resolving names in the address book
+///
+/// # Arguments
+///
+/// * `deps` - reference to Extern containing all the contract's external dependencies
+/// * `names` - names to resolve
+pub fn try_resolve_names<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    names: &[String],
+) -> QueryResult {
+    let book: ReadOnlyCashMap<AddressBookEntry, _> = ReadOnlyCashMap::init(ADDRESS_BOOK_KEY, &deps.storage);
+    let entries = names
+        .iter()
+        .map(|name| {
+            book.get(name.as_bytes()).ok_or_else(|| {
+                StdError::generic_err(format!("{} is not in the address book", name))
+            })
+        })
+        .collect::<StdResult<Vec<AddressBookEntry>>>()?;
+    to_binary(&QueryAnswer::ResolveNames { entries })
+}
+
+/// Returns QueryResult describing the lifecycle status of an offspring
+///
+/// # Arguments
+///
+/// * `deps` - reference to Extern containing all the contract's external dependencies
+/// * `address` - a reference to the address of the offspring
+/// * `viewer` - optional address to authenticate as when the factory uses private listing
+/// * `viewing_key` - optional viewing key of the viewer
+pub fn try_lifecycle<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    address: &HumanAddr,
+    viewer: Option<HumanAddr>,
+    viewing_key: Option<String>,
+) -> QueryResult {
+    let authorized = may_view_info(deps, viewer, viewing_key)?;
+    lifecycle(deps, address, authorized)
+}
+
+/// Returns QueryResult describing the lifecycle status of an offspring, or only which list it
+/// is in if the caller is not authorized to view it
+///
+/// # Arguments
+///
+/// * `deps` - reference to Extern containing all the contract's external dependencies
+/// * `address` - a reference to the address of the offspring
+/// * `authorized` - true if the caller may view the offspring's registry info
+pub fn lifecycle<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    address: &HumanAddr,
+    authorized: bool,
+) -> QueryResult {
+    let address = &normalize_address(&deps.api, address)?;
+    let offspring_addr = deps.api.canonical_address(address)?;
+    let status = lifecycle_status(&deps.storage, &offspring_addr, current_height(&deps.storage)?);
+    if !authorized {
+        return to_binary(&QueryAnswer::OffspringStatus {
+            status: list_status(&status),
+        });
+    }
+    let status = public_status(&deps.storage, status)?;
+    // only registered offspring have a version
+    let deprecation = match status {
+        LifecycleStatus::Active { .. } | LifecycleStatus::Inactive { .. } => {
+            version_deprecation(&deps.storage, &offspring_addr)?
+        }
+        _ => None,
+    };
+    to_binary(&QueryAnswer::OffspringLifecycle {
+        address: address.clone(),
+        status,
+        registration_index: may_load(
+            &ReadonlyPrefixedStorage::new(PREFIX_REGISTRATION_INDEX, &deps.storage),
+            offspring_addr.as_slice(),
+        )?,
+        blocked: is_blocked(&deps.storage, &offspring_addr)?,
+        deprecation,
+    })
+}
+
+/// Returns QueryResult with the address and lifecycle status of the offspring registered under
+/// a label
+///
+/// # Arguments
+///
+/// * `deps` - reference to Extern containing all the contract's external dependencies
+/// * `label` - the label to look up
+/// * `viewer` - optional address to authenticate as when the factory uses private listing
+/// * `viewing_key` - optional viewing key of the viewer
+pub fn try_offspring_by_label<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    label: String,
+    viewer: Option<HumanAddr>,
+    viewing_key: Option<String>,
+) -> QueryResult {
+    let authorized = may_view_info(deps, viewer, viewing_key)?;
+    lookup_label(deps, label, authorized)
+}
+
+/// Returns QueryResult with the address and lifecycle status of the offspring registered under
+/// a label, or only which list it is in if the caller is not authorized to view it
+///
+/// # Arguments
+///
+/// * `deps` - reference to Extern containing all the contract's external dependencies
+/// * `label` - the label to look up
+/// * `authorized` - true if the caller may view the offspring's registry info
+pub fn lookup_label<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    label: String,
+    authorized: bool,
+) -> QueryResult {
+    let address = offspring_by_label(&deps.storage, &label)?;
+    let status = match &address {
+        Some(address) => lifecycle_status(
+            &deps.storage,
+            &deps.api.canonical_address(address)?,
+            current_height(&deps.storage)?,
+        ),
+        None => LifecycleStatus::Unknown {},
+    };
+    if !authorized {
+        return to_binary(&QueryAnswer::OffspringStatus {
+            status: list_status(&status),
+        });
+    }
+    let status = public_status(&deps.storage, status)?;
+    to_binary(&QueryAnswer::OffspringByLabel {
+        label,
+        address,
+        status,
+    })
+}
+
+/// Returns QueryResult displaying the registry info of a single offspring, as the public lists
+/// would show it
+///
+/// # Arguments
+///
+/// * `deps` - reference to Extern containing all the contract's external dependencies
+/// * `address` - a reference to the address of the offspring
+/// * `viewer` - optional address to authenticate as when the factory uses private listing
+/// * `viewing_key` - optional viewing key of the viewer
+pub fn try_offspring_info<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    address: &HumanAddr,
+    viewer: Option<HumanAddr>,
+    viewing_key: Option<String>,
+) -> QueryResult {
+    let authorized = may_view_info(deps, viewer, viewing_key)?;
+    offspring_info(deps, address, authorized)
+}
+
+/// Returns QueryResult displaying the registry info of a single offspring, or only which list
+/// it is in if the caller is not authorized to view it
+///
+/// # Arguments
+///
+/// * `deps` - reference to Extern containing all the contract's external dependencies
+/// * `address` - a reference to the address of the offspring
+/// * `authorized` - true if the caller may view the offspring's registry info
+pub fn offspring_info<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    address: &HumanAddr,
+    authorized: bool,
+) -> QueryResult {
+    let address = normalize_address(&deps.api, address)?;
+    let offspring_addr = deps.api.canonical_address(&address)?;
+    let status = lifecycle_status(&deps.storage, &offspring_addr, current_height(&deps.storage)?);
+    if !authorized {
+        return to_binary(&QueryAnswer::OffspringStatus {
+            status: list_status(&status),
+        });
+    }
+    let (status, active, inactive) = match public_status(&deps.storage, status)? {
+        LifecycleStatus::Active { info } => (OffspringStatus::Active, Some(info), None),
+        LifecycleStatus::Inactive { info } => (OffspringStatus::Inactive, None, Some(info)),
+        LifecycleStatus::Purged { .. } => (OffspringStatus::Purged, None, None),
+        LifecycleStatus::Unknown {} => (OffspringStatus::Unknown, None, None),
+    };
+    // only registered offspring have a version
+    let deprecated = match status {
+        OffspringStatus::Active | OffspringStatus::Inactive => {
+            version_deprecation(&deps.storage, &offspring_addr)?.is_some()
+        }
+        _ => false,
+    };
+    to_binary(&QueryAnswer::OffspringInfo {
+        address,
+        status,
+        active,
+        inactive,
+        deprecated,
+    })
+}
+
+/// Returns QueryResult listing the admin audit log, most recent first
+///
+/// # Arguments
+///
+/// * `deps` - reference to Extern containing all the contract's external dependencies
+/// * `address` - a reference to the admin's address
+/// * `viewing_key` - String key used to authenticate the query
+/// * `start_page` - optional start page for the entries returned and listed
+/// * `page_size` - optional number of entries to return in this page
+pub fn try_audit_log<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    address: &HumanAddr,
+    viewing_key: String,
+    start_page: Option<u32>,
+    page_size: Option<u32>,
+) -> QueryResult {
+    let address = &normalize_address(&deps.api, address)?;
+    if !is_admin_key_valid(deps, address, viewing_key)? {
+        return to_binary(&QueryAnswer::ViewingKeyError {
+            error: "Wrong viewing key for this address or viewing key not set".to_string(),
+        });
+    }
+    let size = resolve_page_size(deps, Some(address), page_size)?;
+    let audit_store = ReadonlyPrefixedStorage::new(PREFIX_AUDIT_LOG, &deps.storage);
+    let (entries, total) = match AppendStore::<AuditEntry, _>::attach(&audit_store) {
+        Some(audit_log) => {
+            let audit_log = audit_log?;
+            let entries = audit_log
+                .iter()
+                .rev()
+                .skip((start_page.unwrap_or(0) * size) as usize)
+                .take(size as usize)
+                .collect::<StdResult<Vec<AuditEntry>>>()?;
+            (entries, audit_log.len())
+        }
+        None => (vec![], 0),
+    };
+    to_binary(&QueryAnswer::AuditLog { entries, total })
+}
+
+/// Returns QueryResult describing the registry's storage layout
+pub fn try_registry_schema() -> QueryResult {
+    to_binary(&QueryAnswer::RegistrySchema {
+        version: REGISTRY_SCHEMA_VERSION,
+        indexes: REGISTRY_INDEXES
+            .iter()
+            .map(|key| String::from_utf8_lossy(key).to_string())
+            .collect(),
+        migration_paths: MIGRATION_PATHS.iter().map(|path| path.to_string()).collect(),
+    })
+}
+
+/// Returns QueryResult displaying the pending transfer offer of an offspring, if the querier made
+/// or received it
+///
+/// # Arguments
+///
+/// * `deps` - reference to Extern containing all the contract's external dependencies
+/// * `offspring` - a reference to the address of the offspring
+/// * `address` - a reference to the address of the owner or recipient of the offer
+/// * `viewing_key` - String key used to authenticate the query
+pub fn try_transfer_offer<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    offspring: &HumanAddr,
+    address: &HumanAddr,
+    viewing_key: String,
+) -> QueryResult {
+    let offspring = &normalize_address(&deps.api, offspring)?;
+    let address = &normalize_address(&deps.api, address)?;
+    if !is_key_valid(&deps.storage, address, viewing_key) {
+        return to_binary(&QueryAnswer::ViewingKeyError {
+            error: "Wrong viewing key for this address or viewing key not set".to_string(),
+        });
+    }
+    let offspring_addr = deps.api.canonical_address(offspring)?;
+    let offer = load_offer(&deps.storage, &offspring_addr)?
+        .filter(|offer| offer.owner == *address || offer.recipient == *address);
+    to_binary(&QueryAnswer::TransferOffer { offer })
+}
+
+/// Returns QueryResult displaying the version and commit this factory was built from
+pub fn try_build_info() -> QueryResult {
+    to_binary(&QueryAnswer::BuildInfo {
+        build: BuildInfo::current(),
+    })
+}
+
+/// Returns QueryResult displaying the offspring version the factory creates, whether creation is
+/// stopped, the admin, and the number of listed offspring
+///
+/// # Arguments
+///
+/// * `deps` - reference to Extern containing all the contract's external dependencies
+pub fn try_factory_config<S: Storage, A: Api, Q: Querier>(deps: &Extern<S, A, Q>) -> QueryResult {
+    let config: Config = load(&deps.storage, CONFIG_KEY)?;
+    let active_store: ReadOnlyCashMap<StoreOffspringInfo, _> =
+        ReadOnlyCashMap::init(ACTIVE_KEY, &deps.storage);
+    let inactive_store: ReadOnlyCashMap<StoreInactiveOffspringInfo, _> =
+        ReadOnlyCashMap::init(INACTIVE_KEY, &deps.storage);
+    to_binary(&QueryAnswer::FactoryConfig {
+        offspring_contract: config.version,
+        stopped: config.stopped,
+        admin: deps.api.human_address(&config.admin)?,
+        active_count: active_store.len(),
+        inactive_count: inactive_store.len(),
+    })
+}
+
+/// Returns QueryResult displaying the number of active and inactive offspring, and also the
+/// owner's numbers if an owner's address and viewing key are given. The lists keep their own
+/// length, so nothing is paged through to count them
+///
+/// # Arguments
+///
+/// * `deps` - reference to Extern containing all the contract's external dependencies
+/// * `address` - optional address of the owner
+/// * `viewing_key` - optional viewing key of the owner
+pub fn try_offspring_count<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    address: Option<HumanAddr>,
+    viewing_key: Option<String>,
+) -> QueryResult {
+    let address = match address {
+        Some(address) => {
+            let address = normalize_address(&deps.api, &address)?;
+            let key = viewing_key.unwrap_or_default();
+            if !is_key_valid(&deps.storage, &address, key) {
+                return to_binary(&QueryAnswer::ViewingKeyError {
+                    error: "Wrong viewing key for this address or viewing key not set".to_string(),
+                });
+            }
+            Some(address)
+        }
+        None => None,
+    };
+    offspring_count(deps, address.as_ref())
+}
+
+/// Returns QueryResult displaying the number of active and inactive offspring, and also the
+/// owner's numbers if an authenticated owner is given
+///
+/// # Arguments
+///
+/// * `deps` - reference to Extern containing all the contract's external dependencies
+/// * `address` - optional reference to the authenticated address of the owner
+pub fn offspring_count<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    address: Option<&HumanAddr>,
+) -> QueryResult {
+    let active_store: ReadOnlyCashMap<StoreOffspringInfo, _> =
+        ReadOnlyCashMap::init(ACTIVE_KEY, &deps.storage);
+    let inactive_store: ReadOnlyCashMap<StoreInactiveOffspringInfo, _> =
+        ReadOnlyCashMap::init(INACTIVE_KEY, &deps.storage);
+    let (my_active, my_inactive) = match address {
+        Some(address) => {
+            let owners_active = ReadonlyPrefixedStorage::new(PREFIX_OWNERS_ACTIVE, &deps.storage);
+            let my_active_store: ReadOnlyCashMap<StoreOffspringInfo, _, _> =
+                ReadOnlyCashMap::init(owner_key(address), &owners_active);
+            let owners_inactive =
+                ReadonlyPrefixedStorage::new(PREFIX_OWNERS_INACTIVE, &deps.storage);
+            let my_inactive_store: ReadOnlyCashMap<StoreInactiveOffspringInfo, _, _> =
+                ReadOnlyCashMap::init(owner_key(address), &owners_inactive);
+            (Some(my_active_store.len()), Some(my_inactive_store.len()))
+        }
+        None => (None, None),
+    };
+    to_binary(&QueryAnswer::OffspringCounts {
+        active: active_store.len(),
+        inactive: inactive_store.len(),
+        my_active,
+        my_inactive,
+    })
+}
+
+/// Returns QueryResult displaying the results of the factory's health checks and when
+/// maintenance last ran
+///
+/// # Arguments
+///
+/// * `deps` - reference to Extern containing all the contract's external dependencies
+pub fn try_health<S: Storage, A: Api, Q: Querier>(deps: &Extern<S, A, Q>) -> QueryResult {
+    let config: Config = load(&deps.storage, CONFIG_KEY)?;
+    let pending_store: ReadOnlyCashMap<PendingOffspring, _> =
+        ReadOnlyCashMap::init(PENDING_KEY, &deps.storage);
+    let pending = pending_store.len();
+    let schema_version: Option<u32> = may_load(&deps.storage, SCHEMA_VERSION_KEY)?;
+    let checks = vec![
+        HealthCheck {
+            name: "pending_below_threshold".to_string(),
+            passed: pending < HEALTHY_PENDING_LIMIT,
+            detail: format!(
+                "{} creations wait for registration, the threshold is {}",
+                pending, HEALTHY_PENDING_LIMIT
+            ),
+        },
+        HealthCheck {
+            name: "storage_schema_current".to_string(),
+            passed: schema_version == Some(REGISTRY_SCHEMA_VERSION),
+            detail: format!(
+                "storage schema version {}, the current version is {}",
+                schema_version.map_or_else(|| "unknown".to_string(), |v| v.to_string()),
+                REGISTRY_SCHEMA_VERSION
+            ),
+        },
+        HealthCheck {
+            name: "creation_not_stopped".to_string(),
+            passed: !config.stopped,
+            detail: if config.stopped {
+                "the admin stopped the creation of new offspring".to_string()
+            } else {
+                "new offspring can be created".to_string()
+            },
+        },
+    ];
+    to_binary(&QueryAnswer::Health {
+        healthy: checks.iter().all(|check| check.passed),
+        checks,
+        last_maintenance_height: may_load(&deps.storage, MAINTENANCE_HEIGHT_KEY)?,
+    })
+}
+
+/// Returns QueryResult exporting a batch of registry records, active offspring first, along with
+/// the continuation token for the next batch
+///
+/// # Arguments
+///
+/// * `deps` - reference to Extern containing all the contract's external dependencies
+/// * `address` - a reference to the admin's address
+/// * `viewing_key` - String key used to authenticate the query
+/// * `token` - optional continuation token returned with the previous batch
+/// * `batch_size` - optional number of records in the batch
+pub fn try_export_registry<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    address: &HumanAddr,
+    viewing_key: String,
+    token: Option<String>,
+    batch_size: Option<u32>,
+) -> QueryResult {
+    let address = &normalize_address(&deps.api, address)?;
+    if !is_admin_key_valid(deps, address, viewing_key)? {
+        return to_binary(&QueryAnswer::ViewingKeyError {
+            error: "Wrong viewing key for this address or viewing key not set".to_string(),
+        });
+    }
+    let size = batch_size.unwrap_or(MAX_EXPORT_BATCH).min(MAX_EXPORT_BATCH);
+    let active_store: ReadOnlyCashMap<StoreOffspringInfo, _> = ReadOnlyCashMap::init(ACTIVE_KEY, &deps.storage);
+    let inactive_store: ReadOnlyCashMap<StoreInactiveOffspringInfo, _> = ReadOnlyCashMap::init(INACTIVE_KEY, &deps.storage);
+    let revision = Some(registry_revision(&deps.storage)?);
+    let (records, next_token) =
+        export_batch(deps, &active_store, &inactive_store, token, size, revision)?;
+    to_binary(&QueryAnswer::ExportRegistry { records, next_token })
+}
+
+/// Returns StdResult<(Vec<RegistryRecord>, Option<String>)> with a batch of records exported
+/// from the lists, active offspring first, and the continuation token for the next batch
+///
+/// # Arguments
+///
+/// * `deps` - reference to Extern containing all the contract's external dependencies
+/// * `active_store` - a reference to the list of active offspring to export
+/// * `inactive_store` - a reference to the list of inactive offspring to export
+/// * `token` - optional continuation token returned with the previous batch
+/// * `size` - number of records in the batch
+/// * `revision` - current registry revision when exporting the registry. The export fails if
+///   the registry changed since its first batch, because the positions it resumes from moved
+fn export_batch<S: Storage, A: Api, Q: Querier, L: ReadonlyStorage>(
+    deps: &Extern<S, A, Q>,
+    active_store: &ReadOnlyCashMap<StoreOffspringInfo, L>,
+    inactive_store: &ReadOnlyCashMap<StoreInactiveOffspringInfo, L>,
+    token: Option<String>,
+    size: u32,
+    revision: Option<u64>,
+) -> StdResult<(Vec<RegistryRecord>, Option<String>)> {
+    let mut cursor = match token {
+        Some(token) => decode_export_token(&token)?,
+        None => ExportCursor {
+            batch: 0,
+            inactive: false,
+            position: 0,
+            revision,
+        },
+    };
+    if cursor.revision != revision {
+        return Err(StdError::generic_err(
+            "The registry changed since this export started. Start the export over",
+        ));
+    }
+    let owner_store = ReadonlyPrefixedStorage::new(PREFIX_OFFSPRING_OWNER, &deps.storage);
+    let mut records: Vec<RegistryRecord> = Vec::new();
+    let mut finished = false;
+
+    while (records.len() as u32) < size {
+        // paging with a page size of 1 reads the entry at `position`
+        let (info, deactivated) = if !cursor.inactive {
+            match active_store.paging(cursor.position, 1)?.pop() {
+                Some(info) => (info, None),
+                None => {
+                    cursor.inactive = true;
+                    cursor.position = 0;
+                    continue;
+                }
+            }
+        } else {
+            match inactive_store.paging(cursor.position, 1)?.pop() {
+                Some(info) => (
+                    StoreOffspringInfo {
+                        address: info.address,
+                        label: info.label,
+                        description: info.description,
+                        description_truncated: info.description_truncated,
+                        metadata_url: info.metadata_url,
+                        count_privacy: info.count_privacy,
+                        attributes: info.attributes,
+                        created_height: info.created_height,
+                        created_time: info.created_time,
+                        checksum: info.checksum,
+                    },
+                    Some((info.deactivated_height, info.deactivated_time, info.deactivated_by)),
+                ),
+                None => {
+                    finished = true;
+                    break;
+                }
+            }
+        };
+        cursor.position += 1;
+        let offspring_addr = deps.api.canonical_address(&info.address)?;
+        let owner: HumanAddr = load(&owner_store, offspring_addr.as_slice())?;
+        records.push(RegistryRecord {
+            address: info.address,
+            label: info.label,
+            description: open(&deps.storage, info.description)?,
+            description_truncated: info.description_truncated,
+            metadata_url: info.metadata_url,
+            count_privacy: info.count_privacy,
+            attributes: info.attributes,
+            owner,
+            active: deactivated.is_none(),
+            created_height: info.created_height,
+            created_time: info.created_time,
+            deactivated_height: deactivated.map(|(height, _, _)| height),
+            deactivated_time: deactivated.map(|(_, time, _)| time),
+            deactivated_by: deactivated.map(|(_, _, source)| source),
+        });
+    }
+
+    let next_token = if finished {
+        None
+    } else {
+        cursor.batch += 1;
+        Some(base64::encode(Bincode2::serialize(&cursor)?))
+    };
+    Ok((records, next_token))
+}
+
+/// Returns QueryResult exporting a batch of the owner's offspring records, active offspring
+/// first, along with the continuation token for the next batch
+///
+/// # Arguments
+///
+/// * `deps` - reference to Extern containing all the contract's external dependencies
+/// * `address` - a reference to the owner's address
+/// * `viewing_key` - String key used to authenticate the query
+/// * `token` - optional continuation token returned with the previous batch
+/// * `batch_size` - optional number of records in the batch
+pub fn try_export_my_offspring<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    address: &HumanAddr,
+    viewing_key: String,
+    token: Option<String>,
+    batch_size: Option<u32>,
+) -> QueryResult {
+    let address = &normalize_address(&deps.api, address)?;
+    if !is_key_valid(&deps.storage, address, viewing_key) {
+        return to_binary(&QueryAnswer::ViewingKeyError {
+            error: "Wrong viewing key for this address or viewing key not set".to_string(),
+        });
+    }
+    let size = batch_size.unwrap_or(MAX_EXPORT_BATCH).min(MAX_EXPORT_BATCH);
+    let active_read = ReadonlyPrefixedStorage::new(PREFIX_OWNERS_ACTIVE, &deps.storage);
+    let inactive_read = ReadonlyPrefixedStorage::new(PREFIX_OWNERS_INACTIVE, &deps.storage);
+    let active_store: ReadOnlyCashMap<StoreOffspringInfo, _> =
+        ReadOnlyCashMap::init(owner_key(address), &active_read);
+    let inactive_store: ReadOnlyCashMap<StoreInactiveOffspringInfo, _> =
+        ReadOnlyCashMap::init(owner_key(address), &inactive_read);
+    let (records, next_token) =
+        export_batch(deps, &active_store, &inactive_store, token, size, None)?;
+    to_binary(&QueryAnswer::ExportMyOffspring { records, next_token })
+}
+
+/// Returns StdResult<ExportCursor> decoded from a continuation token
+///
+/// # Arguments
+///
+/// * `token` - a reference to the continuation token
+pub fn decode_export_token(token: &str) -> StdResult<ExportCursor> {
+    let bytes = base64::decode(token)
+        .map_err(|_| StdError::generic_err("Invalid continuation token"))?;
+    Bincode2::deserialize(&bytes).map_err(|_| StdError::generic_err("Invalid continuation token"))
+}
+
+/// Returns QueryResult displaying the owner's next creation nonce and the label it generates
+///
+/// # Arguments
+///
+/// * `deps` - reference to Extern containing all the contract's external dependencies
+/// * `address` - a reference to the address of the owner
+/// * `viewing_key` - String key used to authenticate the query
+pub fn try_creation_nonce<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    address: &HumanAddr,
+    viewing_key: String,
+) -> QueryResult {
+    let address = &normalize_address(&deps.api, address)?;
+    if !is_key_valid(&deps.storage, address, viewing_key) {
+        return to_binary(&QueryAnswer::ViewingKeyError {
+            error: "Wrong viewing key for this address or viewing key not set".to_string(),
+        });
+    }
+    let config: Config = load(&deps.storage, CONFIG_KEY)?;
+    let nonce = load_creation_nonce(&deps.storage, &deps.api, address)?;
+    to_binary(&QueryAnswer::CreationNonce {
+        nonce,
+        next_label: auto_label(&config.contract_address, address, nonce),
+    })
+}
+
+/// Returns QueryResult displaying whether the address may create an offspring right now, with
+/// the reasons if it may not
+///
+/// # Arguments
+///
+/// * `deps` - reference to Extern containing all the contract's external dependencies
+/// * `address` - a reference to the address of the creator
+/// * `viewing_key` - String key used to authenticate the query
+pub fn try_can_i_create<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    address: &HumanAddr,
+    viewing_key: String,
+) -> QueryResult {
+    let address = &normalize_address(&deps.api, address)?;
+    if !is_key_valid(&deps.storage, address, viewing_key) {
+        return to_binary(&QueryAnswer::ViewingKeyError {
+            error: "Wrong viewing key for this address or viewing key not set".to_string(),
+        });
+    }
+    let config: Config = load(&deps.storage, CONFIG_KEY)?;
+    let creator = deps.api.canonical_address(address)?;
+    // the fee and label can only be checked once the creation is sent
+    let request = CreationRequest {
+        creator: &creator,
+        sent_funds: None,
+        token_fee_paid: false,
+        label: None,
+        height: Some(current_height(&deps.storage)?),
+        code_id: config.version.code_id,
+    };
+    let reasons = run_pipeline(&deps.storage, &config, &request);
+    to_binary(&QueryAnswer::CanICreate {
+        allowed: reasons.is_empty(),
+        reasons,
+    })
+}
+
+/// Returns QueryResult displaying the root and height of the latest registry snapshot
+///
+/// # Arguments
+///
+/// * `deps` - reference to Extern containing all the contract's external dependencies
+pub fn try_registry_snapshot<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+) -> QueryResult {
+    let snapshot: Option<RegistrySnapshot> = may_load(&deps.storage, SNAPSHOT_KEY)?;
+    to_binary(&QueryAnswer::RegistrySnapshot {
+        snapshot: snapshot.map(|s| SnapshotInfo {
+            root: Binary::from(s.root.to_vec()),
+            height: s.height,
+            leaf_count: s.leaf_count,
+        }),
+    })
+}
+
+/// Returns QueryResult displaying the merkle proof that an offspring is included in the latest
+/// registry snapshot
+///
+/// # Arguments
+///
+/// * `deps` - reference to Extern containing all the contract's external dependencies
+/// * `offspring` - a reference to the address of the offspring
+/// * `address` - optional address of the offspring's owner
+/// * `viewing_key` - optional key used to authenticate the owner
+pub fn try_membership_proof<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    offspring: &HumanAddr,
+    address: Option<HumanAddr>,
+    viewing_key: Option<String>,
+) -> QueryResult {
+    let offspring_addr = deps.api.canonical_address(&normalize_address(&deps.api, offspring)?)?;
+    let snapshot: Option<RegistrySnapshot> = may_load(&deps.storage, SNAPSHOT_KEY)?;
+    let snapshot = match snapshot {
+        Some(s) => s,
+        None => return to_binary(&QueryAnswer::MembershipProof { proof: None }),
+    };
+    let entry = snapshot_entry(&deps.storage, &snapshot, &offspring_addr)?;
+    let config: Config = load(&deps.storage, CONFIG_KEY)?;
+    if config.private_listing {
+        // only the owner may learn whether the offspring is included, so a missing entry and a
+        // wrong key give the same answer
+        let owner = entry.as_ref().map(|e| &e.owner);
+        let authorized = match (owner, address, viewing_key) {
+            (Some(owner), Some(address), Some(key)) => {
+                let address = normalize_address(&deps.api, &address)?;
+                address == *owner && is_key_valid(&deps.storage, &address, key)
+            }
+            _ => false,
+        };
+        if !authorized {
+            return to_binary(&QueryAnswer::ViewingKeyError {
+                error: "Wrong viewing key for this address or viewing key not set".to_string(),
+            });
+        }
+    }
+    let proof = match entry {
+        Some(entry) => Some(MembershipProof {
+            root: Binary::from(snapshot.root.to_vec()),
+            height: snapshot.height,
+            index: entry.index,
+            siblings: merkle_proof(&deps.storage, &snapshot, entry.index)?,
+            label: entry.label,
+            owner: entry.owner,
+        }),
+        None => None,
+    };
+    to_binary(&QueryAnswer::MembershipProof { proof })
+}
+
+/// Returns QueryResult listing the number of creations made with each creation context
+///
+/// # Arguments
+///
+/// * `deps` - reference to Extern containing all the contract's external dependencies
+/// * `address` - a reference to the admin's address
+/// * `viewing_key` - String key used to authenticate the query
+/// * `start_page` - optional start page for the contexts returned and listed
+/// * `page_size` - optional number of contexts to return in this page
+pub fn try_creation_contexts<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    address: &HumanAddr,
+    viewing_key: String,
+    start_page: Option<u32>,
+    page_size: Option<u32>,
+) -> QueryResult {
+    let address = &normalize_address(&deps.api, address)?;
+    if !is_admin_key_valid(deps, address, viewing_key)? {
+        return to_binary(&QueryAnswer::ViewingKeyError {
+            error: "Wrong viewing key for this address or viewing key not set".to_string(),
+        });
+    }
+    let size = resolve_page_size(deps, Some(address), page_size)?;
+    let context_store: ReadOnlyCashMap<ContextStats, _> =
+        ReadOnlyCashMap::init(CONTEXTS_KEY, &deps.storage);
+    to_binary(&QueryAnswer::CreationContexts {
+        contexts: context_store.paging(start_page.unwrap_or(0), size)?,
+        total: context_store.len(),
+    })
+}
+
+/// Returns QueryResult listing the addresses on a creator list
+///
+/// # Arguments
+///
+/// * `deps` - reference to Extern containing all the contract's external dependencies
+/// * `address` - a reference to the admin's address
+/// * `viewing_key` - String key used to authenticate the query
+/// * `list` - which creator list to display
+/// * `start_page` - optional start page for the addresses returned and listed
+/// * `page_size` - optional number of addresses to return in this page
+pub fn try_creator_list<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    address: &HumanAddr,
+    viewing_key: String,
+    list: CreatorListType,
+    start_page: Option<u32>,
+    page_size: Option<u32>,
+) -> QueryResult {
+    let address = &normalize_address(&deps.api, address)?;
+    if !is_admin_key_valid(deps, address, viewing_key)? {
+        return to_binary(&QueryAnswer::ViewingKeyError {
+            error: "Wrong viewing key for this address or viewing key not set".to_string(),
+        });
+    }
+    let size = resolve_page_size(deps, Some(address), page_size)?;
+    let list_key = match list {
+        CreatorListType::Allowed => ALLOWED_CREATORS_KEY,
+        CreatorListType::Denied => DENIED_CREATORS_KEY,
+    };
+    let creator_store: ReadOnlyCashMap<HumanAddr, _> = ReadOnlyCashMap::init(list_key, &deps.storage);
+    to_binary(&QueryAnswer::CreatorList {
+        creators: creator_store.paging(start_page.unwrap_or(0), size)?,
+        total: creator_store.len(),
+    })
+}
+
+/// Returns QueryResult displaying the referral stats of an address
+///
+/// # Arguments
+///
+/// * `deps` - reference to Extern containing all the contract's external dependencies
+/// * `address` - a reference to the address whose referral stats should be displayed
+/// * `viewing_key` - String key used to authenticate the query
+pub fn try_referral_stats<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    address: &HumanAddr,
+    viewing_key: String,
+) -> QueryResult {
+    let address = &normalize_address(&deps.api, address)?;
+    if !is_key_valid(&deps.storage, address, viewing_key) {
+        return to_binary(&QueryAnswer::ViewingKeyError {
+            error: "Wrong viewing key for this address or viewing key not set".to_string(),
+        });
+    }
+    let address_raw = deps.api.canonical_address(address)?;
+    let referral_store = ReadonlyPrefixedStorage::new(PREFIX_REFERRALS, &deps.storage);
+    let stats: ReferralStats =
+        may_load(&referral_store, address_raw.as_slice())?.unwrap_or_default();
+    to_binary(&QueryAnswer::ReferralStats { stats })
+}
@@ -0,0 +1,191 @@
+use cosmwasm_std::{
+    to_binary, Api, Env, Extern, HandleResult, HumanAddr, Querier, QueryResult, StdError,
+    StdResult, Storage,
+};
+
+use cosmwasm_storage::{PrefixedStorage, ReadonlyPrefixedStorage};
+
+use secret_toolkit::utils::HandleCallback;
+
+use secret_toolkit_incubator::{CashMap, ReadOnlyCashMap};
+
+use crate::state::{load, remove, PREFIX_OFFSPRING_OWNER, PREFIX_TRANSFER_OFFERS, RECOVERY_KEY};
+
+use crate::{
+    addr::{normalize_address, validate_address},
+    admin::{enforce_admin, record_audit},
+    auth::is_admin_key_valid,
+    creation::authenticate_offspring,
+    msg::{HandleAnswer, QueryAnswer, RecoveryRequest, ResponseStatus::Success},
+    offspring_msg::OffspringHandleMsg,
+    queries::resolve_page_size,
+    registry,
+    response::{ok_empty, ok_status, ok_with_msgs},
+};
+
+/// Returns HandleResult
+///
+/// records a claimant's request to become the owner of the calling offspring. Only one request
+/// per offspring can wait for the admin's decision at a time
+///
+/// # Arguments
+///
+/// * `deps` - mutable reference to Extern containing all the contract's external dependencies
+/// * `env` - Env of contract's environment
+/// * `claimant` - address asking to become the owner
+pub fn try_request_recovery<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    claimant: HumanAddr,
+) -> HandleResult {
+    let offspring_addr = deps.api.canonical_address(&env.message.sender)?;
+
+    // verify offspring is in active list, and not a spam attempt
+    authenticate_offspring(&deps.storage, &offspring_addr)?;
+    let claimant = validate_address(&deps.storage, &deps.api, &claimant)?;
+    let owner: HumanAddr = load(
+        &ReadonlyPrefixedStorage::new(PREFIX_OFFSPRING_OWNER, &deps.storage),
+        offspring_addr.as_slice(),
+    )?;
+    if claimant == owner {
+        return Err(StdError::generic_err("The claimant already owns this offspring"));
+    }
+    let mut request_store: CashMap<RecoveryRequest, _> =
+        CashMap::init(RECOVERY_KEY, &mut deps.storage);
+    if request_store.get(offspring_addr.as_slice()).is_some() {
+        return Err(StdError::generic_err(
+            "There is already a recovery request for this offspring",
+        ));
+    }
+    request_store.insert(
+        offspring_addr.as_slice(),
+        RecoveryRequest {
+            offspring: env.message.sender,
+            owner,
+            claimant,
+            height: env.block.height,
+        },
+    )?;
+
+    ok_empty()
+}
+
+/// Returns HandleResult
+///
+/// allows admin to approve a recovery request. The offspring moves to the claimant's lists, and
+/// the offspring is told about its new owner
+///
+/// # Arguments
+///
+/// * `deps` - mutable reference to Extern containing all the contract's external dependencies
+/// * `env` - Env of contract's environment
+/// * `offspring` - address of the offspring
+pub fn try_approve_recovery<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    offspring: HumanAddr,
+) -> HandleResult {
+    // only allow admin to do this
+    let config = enforce_admin(deps, &env)?;
+    let offspring_addr = deps.api.canonical_address(&offspring)?;
+    let request = take_request(&mut deps.storage, offspring_addr.as_slice())?;
+    authenticate_offspring(&deps.storage, &offspring_addr)?;
+    let owner: HumanAddr = load(
+        &ReadonlyPrefixedStorage::new(PREFIX_OFFSPRING_OWNER, &deps.storage),
+        offspring_addr.as_slice(),
+    )?;
+    if owner != request.owner {
+        return Err(StdError::generic_err(
+            "The offspring changed owners since the recovery was requested",
+        ));
+    }
+    registry::transfer(&mut deps.storage, &offspring_addr, &owner, &request.claimant)?;
+    // a transfer offer made by the previous owner is void
+    let mut offer_store = PrefixedStorage::new(PREFIX_TRANSFER_OFFERS, &mut deps.storage);
+    remove(&mut offer_store, offspring_addr.as_slice());
+    record_audit(&mut deps.storage, "approve_recovery", &env, Some(offspring.clone()))?;
+
+    // let the offspring know its new owner
+    let code_hash = registry::code_hash(&deps.storage, &offspring_addr)?;
+    let message = OffspringHandleMsg::ChangeOwner {
+        owner: request.claimant,
+    }
+    .to_cosmos_msg(code_hash, offspring, config.callback_policy.change_owner)?;
+    ok_with_msgs(
+        vec![message],
+        HandleAnswer::Status {
+            status: Success,
+            message: None,
+        },
+    )
+}
+
+/// Returns HandleResult
+///
+/// allows admin to reject a recovery request
+///
+/// # Arguments
+///
+/// * `deps` - mutable reference to Extern containing all the contract's external dependencies
+/// * `env` - Env of contract's environment
+/// * `offspring` - address of the offspring
+pub fn try_reject_recovery<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    offspring: HumanAddr,
+) -> HandleResult {
+    // only allow admin to do this
+    enforce_admin(deps, &env)?;
+    let offspring_addr = deps.api.canonical_address(&offspring)?;
+    take_request(&mut deps.storage, offspring_addr.as_slice())?;
+    record_audit(&mut deps.storage, "reject_recovery", &env, Some(offspring))?;
+
+    ok_status()
+}
+
+/// Returns StdResult<RecoveryRequest> with the recovery request of an offspring, after removing
+/// it from the requests waiting for a decision
+///
+/// # Arguments
+///
+/// * `storage` - mutable reference to contract's storage
+/// * `key` - canonical address bytes of the offspring
+fn take_request<S: Storage>(storage: &mut S, key: &[u8]) -> StdResult<RecoveryRequest> {
+    let mut request_store: CashMap<RecoveryRequest, _> = CashMap::init(RECOVERY_KEY, storage);
+    let request = request_store.get(key).ok_or_else(|| {
+        StdError::generic_err("There is no recovery request for this offspring")
+    })?;
+    request_store.remove(key)?;
+    Ok(request)
+}
+
+/// Returns QueryResult listing the recovery requests waiting for the admin's decision
+///
+/// # Arguments
+///
+/// * `deps` - reference to Extern containing all the contract's external dependencies
+/// * `address` - a reference to the admin's address
+/// * `viewing_key` - String key used to authenticate the query
+/// * `start_page` - optional start page for the requests returned and listed
+/// * `page_size` - optional number of requests to return in this page
+pub fn try_recovery_requests<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    address: &HumanAddr,
+    viewing_key: String,
+    start_page: Option<u32>,
+    page_size: Option<u32>,
+) -> QueryResult {
+    let address = &normalize_address(&deps.api, address)?;
+    if !is_admin_key_valid(deps, address, viewing_key)? {
+        return to_binary(&QueryAnswer::ViewingKeyError {
+            error: "Wrong viewing key for this address or viewing key not set".to_string(),
+        });
+    }
+    let size = resolve_page_size(deps, Some(address), page_size)?;
+    let request_store: ReadOnlyCashMap<RecoveryRequest, _> =
+        ReadOnlyCashMap::init(RECOVERY_KEY, &deps.storage);
+    to_binary(&QueryAnswer::RecoveryRequests {
+        requests: request_store.paging(start_page.unwrap_or(0), size)?,
+        total: request_store.len(),
+    })
+}
@@ -0,0 +1,1216 @@
+
+use cosmwasm_std::{
+    Api, BlockInfo, CanonicalAddr, Env, Extern, HandleResult, HumanAddr, Querier, ReadonlyStorage,
+    StdError, StdResult, Storage,
+};
+
+use cosmwasm_storage::{PrefixedStorage, ReadonlyPrefixedStorage};
+
+use serde::{de::DeserializeOwned, Serialize};
+
+use secret_toolkit::storage::{AppendStore, AppendStoreMut};
+
+use secret_toolkit_incubator::{CashMap, ReadOnlyCashMap};
+
+use crate::state::{
+    load, may_load, remove, save, Config, ImportProgress, ACTIVE_KEY, COMPACT_CURSOR_KEY,
+    CONFIG_KEY, IMPORT_PROGRESS_KEY, INACTIVE_KEY, MAX_COMPACT_ITEMS, PREFIX_DEPRECATED_VERSIONS,
+    OrderCompaction, PREFIX_LABELS, PREFIX_LIST_ORDER, PREFIX_LIST_POSITIONS, PREFIX_OFFSPRING_CODE_HASH,
+    PREFIX_ORDER_COMPACTIONS, ORDER_COMPACTION_KEY, ORDER_COMPACTION_STEPS, PREFIX_OFFSPRING_PASSWORDS,
+    PREFIX_OFFSPRING_OWNER, PREFIX_OWNERS_ACTIVE, PREFIX_OWNERS_INACTIVE, PREFIX_REGISTRATION_INDEX,
+    PREFIX_TRANSFER_OFFERS, REGISTRY_REVISION_KEY, LAST_HEIGHT_KEY,
+    MAX_REMOVE_BATCH, ORDER_INDEX_SLACK, TOMBSTONE_CURSOR_KEY, TOMBSTONE_KEY, TOMBSTONE_SWEEP_LIMIT,
+};
+
+use crate::{
+    addr::owner_key,
+    admin::{enforce_admin, enforce_not_blocked, record_audit},
+    checksum::stamp,
+    msg::{
+        DeactivationSource, HandleAnswer, LifecycleStatus, RegistryRecord, ResponseStatus::Success,
+        StoreInactiveOffspringInfo,
+        StoreOffspringInfo, Tombstone, VersionDeprecation,
+    },
+    queries::decode_export_token,
+    response::{ok_answer, ok_status},
+    sealing::seal_description,
+};
+
+/// where an offspring is expected to be listed after a registry mutation
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Placement {
+    /// listed in the global and owner's active lists only
+    Active,
+    /// listed in the global and owner's inactive lists only
+    Inactive,
+    /// only a tombstone remains
+    Purged,
+}
+
+/// Returns StdResult<()>
+///
+/// adds a newly registered offspring to the global and owner's active lists and remembers its
+/// owner and label, then verifies the indexes agree
+///
+/// # Arguments
+///
+/// * `storage` - mutable reference to contract's storage
+/// * `offspring_addr` - a reference to the canonical address of the offspring
+/// * `owner` - a reference to the address of the offspring's owner
+/// * `info` - active offspring info to store
+pub fn register<S: Storage>(
+    storage: &mut S,
+    offspring_addr: &CanonicalAddr,
+    owner: &HumanAddr,
+    info: StoreOffspringInfo,
+) -> StdResult<()> {
+    // a label can only be used by one offspring
+    if let Some(existing) = offspring_by_label(storage, &info.label)? {
+        if existing != info.address {
+            return Err(StdError::generic_err(format!(
+                "The label {} is already used by {}",
+                info.label, existing
+            )));
+        }
+    }
+    let mut label_store = PrefixedStorage::new(PREFIX_LABELS, storage);
+    save(&mut label_store, info.label.as_bytes(), &info.address)?;
+
+    // save the offspring info
+    let info = stamp(info)?;
+    let mut info_store: CashMap<StoreOffspringInfo, _> = CashMap::init(ACTIVE_KEY, storage);
+    info_store.insert(offspring_addr.as_slice(), info.clone())?;
+
+    // add this offspring to owner's list
+    let mut owners_store = PrefixedStorage::new(PREFIX_OWNERS_ACTIVE, storage);
+    let mut my_active_store: CashMap<StoreOffspringInfo, _, _> = CashMap::init(owner_key(owner), &mut owners_store);
+    my_active_store.insert(offspring_addr.as_slice(), info)?;
+    append_order::<StoreOffspringInfo, _>(storage, None, ACTIVE_KEY, offspring_addr)?;
+    append_order::<StoreOffspringInfo, _>(
+        storage,
+        Some(PREFIX_OWNERS_ACTIVE),
+        owner_key(owner),
+        offspring_addr,
+    )?;
+
+    // remember who owns this offspring
+    let mut offspring_owner_store = PrefixedStorage::new(PREFIX_OFFSPRING_OWNER, storage);
+    save(&mut offspring_owner_store, offspring_addr.as_slice(), owner)?;
+
+    bump_revision(storage)?;
+    verify_placement(storage, offspring_addr, Some(owner), Placement::Active)
+}
+
+/// Returns StdResult<()>
+///
+/// moves an offspring from the global and owner's active lists to the inactive lists, then
+/// verifies the indexes agree
+///
+/// # Arguments
+///
+/// * `storage` - mutable reference to contract's storage
+/// * `offspring_addr` - a reference to the canonical address of the offspring
+/// * `owner` - a reference to the address of the offspring's owner
+/// * `info` - the offspring's active info
+/// * `block` - a reference to the block the offspring was deactivated in
+/// * `deactivated_by` - who or what deactivated the offspring
+pub fn deactivate<S: Storage>(
+    storage: &mut S,
+    offspring_addr: &CanonicalAddr,
+    owner: &HumanAddr,
+    info: &StoreOffspringInfo,
+    block: &BlockInfo,
+    deactivated_by: DeactivationSource,
+) -> StdResult<()> {
+    // delete the active offspring info
+    let mut info_store: CashMap<StoreOffspringInfo, _, _> = CashMap::init(ACTIVE_KEY, storage);
+    info_store.remove(offspring_addr.as_slice())?;
+
+    // save owner's inactive offspring info
+    let inactive_info = stamp(info.to_store_inactive_offspring_info(block, deactivated_by))?;
+    let mut owners_inactive_store = PrefixedStorage::new(PREFIX_OWNERS_INACTIVE, storage);
+    let mut inactive_store = CashMap::init(owner_key(owner), &mut owners_inactive_store);
+    inactive_store.insert(offspring_addr.as_slice(), inactive_info.clone())?;
+
+    // save inactive offspring info
+    let mut inactive_store = CashMap::init(INACTIVE_KEY, storage);
+    inactive_store.insert(offspring_addr.as_slice(), inactive_info)?;
+    append_order::<StoreInactiveOffspringInfo, _>(storage, None, INACTIVE_KEY, offspring_addr)?;
+    append_order::<StoreInactiveOffspringInfo, _>(
+        storage,
+        Some(PREFIX_OWNERS_INACTIVE),
+        owner_key(owner),
+        offspring_addr,
+    )?;
+
+    // remove offspring from owner's active list
+    remove_from_persons_active(storage, PREFIX_OWNERS_ACTIVE, owner, offspring_addr)?;
+
+    bump_revision(storage)?;
+    verify_placement(storage, offspring_addr, Some(owner), Placement::Inactive)
+}
+
+/// Returns StdResult<()>
+///
+/// replaces an active offspring's info in the global and owner's active lists, then verifies
+/// the indexes agree
+///
+/// # Arguments
+///
+/// * `storage` - mutable reference to contract's storage
+/// * `offspring_addr` - a reference to the canonical address of the offspring
+/// * `owner` - a reference to the address of the offspring's owner
+/// * `info` - updated active offspring info
+pub fn update_active<S: Storage>(
+    storage: &mut S,
+    offspring_addr: &CanonicalAddr,
+    owner: &HumanAddr,
+    info: StoreOffspringInfo,
+) -> StdResult<()> {
+    let info = stamp(info)?;
+    let mut info_store: CashMap<StoreOffspringInfo, _> = CashMap::init(ACTIVE_KEY, storage);
+    info_store.insert(offspring_addr.as_slice(), info.clone())?;
+
+    let mut owners_store = PrefixedStorage::new(PREFIX_OWNERS_ACTIVE, storage);
+    let mut my_active_store: CashMap<StoreOffspringInfo, _, _> = CashMap::init(owner_key(owner), &mut owners_store);
+    my_active_store.insert(offspring_addr.as_slice(), info)?;
+
+    bump_revision(storage)?;
+    verify_placement(storage, offspring_addr, Some(owner), Placement::Active)
+}
+
+/// Returns StdResult<()>
+///
+/// moves an active offspring from its owner's active list to the new owner's active list and
+/// remembers its new owner, then verifies the indexes agree
+///
+/// # Arguments
+///
+/// * `storage` - mutable reference to contract's storage
+/// * `offspring_addr` - a reference to the canonical address of the offspring
+/// * `owner` - a reference to the address of the offspring's current owner
+/// * `new_owner` - a reference to the address of the offspring's new owner
+pub fn transfer<S: Storage>(
+    storage: &mut S,
+    offspring_addr: &CanonicalAddr,
+    owner: &HumanAddr,
+    new_owner: &HumanAddr,
+) -> StdResult<()> {
+    let info_store: ReadOnlyCashMap<StoreOffspringInfo, _> = ReadOnlyCashMap::init(ACTIVE_KEY, storage);
+    let info = info_store.get(offspring_addr.as_slice()).ok_or_else(|| {
+        StdError::generic_err("Only active offspring registered with factory can be transferred.")
+    })?;
+
+    // move the offspring between the owners' lists
+    remove_from_persons_active(storage, PREFIX_OWNERS_ACTIVE, owner, offspring_addr)?;
+    let mut owners_store = PrefixedStorage::new(PREFIX_OWNERS_ACTIVE, storage);
+    let mut new_active_store: CashMap<StoreOffspringInfo, _, _> = CashMap::init(owner_key(new_owner), &mut owners_store);
+    new_active_store.insert(offspring_addr.as_slice(), info)?;
+    append_order::<StoreOffspringInfo, _>(
+        storage,
+        Some(PREFIX_OWNERS_ACTIVE),
+        owner_key(new_owner),
+        offspring_addr,
+    )?;
+
+    // remember who owns this offspring now
+    let mut offspring_owner_store = PrefixedStorage::new(PREFIX_OFFSPRING_OWNER, storage);
+    save(&mut offspring_owner_store, offspring_addr.as_slice(), new_owner)?;
+
+    bump_revision(storage)?;
+    verify_placement(storage, offspring_addr, Some(new_owner), Placement::Active)
+}
+
+/// Returns Vec<&[u8]> with the storage namespaces of a registry list's chronological index
+///
+/// # Arguments
+///
+/// * `prefix` - optional storage prefix of the list, given for the owners' lists
+/// * `key` - storage key of the list
+pub fn order_namespace<'a>(prefix: Option<&'a [u8]>, key: &'a [u8]) -> Vec<&'a [u8]> {
+    list_namespace(PREFIX_LIST_ORDER, prefix, key)
+}
+
+/// Returns Vec<&[u8]> with the storage namespaces of the latest position of each offspring in a
+/// registry list's chronological index
+///
+/// # Arguments
+///
+/// * `prefix` - optional storage prefix of the list, given for the owners' lists
+/// * `key` - storage key of the list
+pub fn position_namespace<'a>(prefix: Option<&'a [u8]>, key: &'a [u8]) -> Vec<&'a [u8]> {
+    list_namespace(PREFIX_LIST_POSITIONS, prefix, key)
+}
+
+/// Returns Vec<&[u8]> with the storage namespaces of data kept about a registry list
+///
+/// # Arguments
+///
+/// * `base` - storage prefix of the kind of data
+/// * `prefix` - optional storage prefix of the list, given for the owners' lists
+/// * `key` - storage key of the list
+fn list_namespace<'a>(base: &'a [u8], prefix: Option<&'a [u8]>, key: &'a [u8]) -> Vec<&'a [u8]> {
+    match prefix {
+        Some(pref) => vec![base, pref, key],
+        None => vec![base, key],
+    }
+}
+
+/// Returns StdResult<()>
+///
+/// appends an offspring that joined a registry list to the list's chronological index. Entries
+/// of offspring that left the list, or joined it again later, are skipped by readers. Once the
+/// index grows past twice the list's length plus ORDER_INDEX_SLACK, a compaction starts, and
+/// each append advances it by ORDER_COMPACTION_STEPS entries, so no single append pays for
+/// compacting the whole index
+///
+/// # Arguments
+///
+/// * `storage` - mutable reference to contract's storage
+/// * `prefix` - optional storage prefix of the list, given for the owners' lists
+/// * `key` - storage key of the list
+/// * `offspring_addr` - a reference to the canonical address of the offspring
+fn append_order<T: Serialize + DeserializeOwned, S: Storage>(
+    storage: &mut S,
+    prefix: Option<&[u8]>,
+    key: &[u8],
+    offspring_addr: &CanonicalAddr,
+) -> StdResult<()> {
+    let indexed = {
+        let mut order_store = PrefixedStorage::multilevel(&order_namespace(prefix, key), storage);
+        let mut order = AppendStoreMut::attach_or_create(&mut order_store)?;
+        order.push(offspring_addr)?;
+        order.len()
+    };
+    let mut position_store =
+        PrefixedStorage::multilevel(&position_namespace(prefix, key), storage);
+    save(&mut position_store, offspring_addr.as_slice(), &(indexed - 1))?;
+
+    let compaction_namespace = list_namespace(PREFIX_ORDER_COMPACTIONS, prefix, key);
+    let compaction_store = ReadonlyPrefixedStorage::multilevel(&compaction_namespace, storage);
+    let compaction = match may_load(&compaction_store, ORDER_COMPACTION_KEY)? {
+        Some(compaction) => compaction,
+        None => {
+            let listed = list_entry::<T, _>(storage, prefix, key, None).0;
+            if indexed <= listed.saturating_mul(2).saturating_add(ORDER_INDEX_SLACK) {
+                return Ok(());
+            }
+            OrderCompaction { read: 0, write: 0 }
+        }
+    };
+    compact_order::<T, _>(storage, prefix, key, compaction, ORDER_COMPACTION_STEPS)
+}
+
+/// Returns (u32, bool) with the length of a registry list, and whether the offspring is in it
+/// if one is given
+///
+/// # Arguments
+///
+/// * `storage` - a reference to contract's storage
+/// * `prefix` - optional storage prefix of the list, given for the owners' lists
+/// * `key` - storage key of the list
+/// * `offspring_addr` - optional reference to the canonical address of the offspring to look for
+fn list_entry<T: Serialize + DeserializeOwned, S: ReadonlyStorage>(
+    storage: &S,
+    prefix: Option<&[u8]>,
+    key: &[u8],
+    offspring_addr: Option<&CanonicalAddr>,
+) -> (u32, bool) {
+    match prefix {
+        Some(pref) => {
+            let read = ReadonlyPrefixedStorage::new(pref, storage);
+            let list: ReadOnlyCashMap<T, _> = ReadOnlyCashMap::init(key, &read);
+            (list.len(), matches!(offspring_addr, Some(addr) if list.contains(addr.as_slice())))
+        }
+        None => {
+            let list: ReadOnlyCashMap<T, _> = ReadOnlyCashMap::init(key, storage);
+            (list.len(), matches!(offspring_addr, Some(addr) if list.contains(addr.as_slice())))
+        }
+    }
+}
+
+/// Returns StdResult<()>
+///
+/// continues compacting a registry list's chronological index for at most `steps` entries.
+/// Reading forward, the newest entry of each offspring that is still in the list is moved down
+/// to the compacted part of the index, and other entries are dropped. Once every entry is read,
+/// the rest of the index is dropped from its end. Readers skip the entries that are not at an
+/// offspring's latest position, so the index reads the same while a compaction is under way
+///
+/// # Arguments
+///
+/// * `storage` - mutable reference to contract's storage
+/// * `prefix` - optional storage prefix of the list, given for the owners' lists
+/// * `key` - storage key of the list
+/// * `compaction` - progress of the compaction
+/// * `steps` - maximum number of index entries to process
+fn compact_order<T: Serialize + DeserializeOwned, S: Storage>(
+    storage: &mut S,
+    prefix: Option<&[u8]>,
+    key: &[u8],
+    mut compaction: OrderCompaction,
+    steps: u32,
+) -> StdResult<()> {
+    let order_ns = order_namespace(prefix, key);
+    let position_ns = position_namespace(prefix, key);
+    let compaction_ns = list_namespace(PREFIX_ORDER_COMPACTIONS, prefix, key);
+    for _ in 0..steps {
+        let (len, entry) = {
+            let order_store = ReadonlyPrefixedStorage::multilevel(&order_ns, storage);
+            let order = match AppendStore::<CanonicalAddr, _>::attach(&order_store) {
+                Some(order) => order?,
+                None => break,
+            };
+            let entry = if compaction.read < order.len() {
+                Some(order.get_at(compaction.read)?)
+            } else {
+                None
+            };
+            (order.len(), entry)
+        };
+        match entry {
+            Some(addr) => {
+                let position_store = ReadonlyPrefixedStorage::multilevel(&position_ns, storage);
+                let latest: Option<u32> = may_load(&position_store, addr.as_slice())?;
+                if !matches!(latest, Some(pos) if pos != compaction.read) {
+                    let listed = list_entry::<T, _>(storage, prefix, key, Some(&addr)).1;
+                    let mut position_store = PrefixedStorage::multilevel(&position_ns, storage);
+                    if listed {
+                        save(&mut position_store, addr.as_slice(), &compaction.write)?;
+                        if compaction.write != compaction.read {
+                            let mut order_store = PrefixedStorage::multilevel(&order_ns, storage);
+                            let mut order = AppendStoreMut::attach_or_create(&mut order_store)?;
+                            order.set_at(compaction.write, &addr)?;
+                        }
+                        compaction.write += 1;
+                    } else {
+                        remove(&mut position_store, addr.as_slice());
+                    }
+                }
+                compaction.read += 1;
+            }
+            None if len > compaction.write => {
+                let mut order_store = PrefixedStorage::multilevel(&order_ns, storage);
+                let mut order = AppendStoreMut::<CanonicalAddr, _>::attach_or_create(&mut order_store)?;
+                order.pop()?;
+                // offspring appended after entries were dropped from the end are read too
+                compaction.read = order.len();
+            }
+            None => {
+                let mut compaction_store = PrefixedStorage::multilevel(&compaction_ns, storage);
+                remove(&mut compaction_store, ORDER_COMPACTION_KEY);
+                return Ok(());
+            }
+        }
+    }
+    let mut compaction_store = PrefixedStorage::multilevel(&compaction_ns, storage);
+    save(&mut compaction_store, ORDER_COMPACTION_KEY, &compaction)
+}
+
+/// Returns StdResult<String> with the code hash of a registered offspring. Offspring registered
+/// before code hashes were recorded are assumed to run the current version
+///
+/// # Arguments
+///
+/// * `storage` - a reference to contract's storage
+/// * `offspring_addr` - a reference to the canonical address of the offspring
+pub fn code_hash<S: ReadonlyStorage>(storage: &S, offspring_addr: &CanonicalAddr) -> StdResult<String> {
+    let may_hash: Option<String> = may_load(
+        &ReadonlyPrefixedStorage::new(PREFIX_OFFSPRING_CODE_HASH, storage),
+        offspring_addr.as_slice(),
+    )?;
+    match may_hash {
+        Some(hash) => Ok(hash),
+        None => {
+            let config: Config = load(storage, CONFIG_KEY)?;
+            Ok(config.version.code_hash)
+        }
+    }
+}
+
+/// Returns StdResult<Option<VersionDeprecation>> with the deprecation notice of the version a
+/// registered offspring was created from, if that version is deprecated
+///
+/// # Arguments
+///
+/// * `storage` - a reference to contract's storage
+/// * `offspring_addr` - a reference to the canonical address of the offspring
+pub fn version_deprecation<S: ReadonlyStorage>(
+    storage: &S,
+    offspring_addr: &CanonicalAddr,
+) -> StdResult<Option<VersionDeprecation>> {
+    let hash = code_hash(storage, offspring_addr)?;
+    may_load(
+        &ReadonlyPrefixedStorage::new(PREFIX_DEPRECATED_VERSIONS, storage),
+        hash.as_bytes(),
+    )
+}
+
+/// Returns StdResult<()>
+///
+/// moves a reactivated offspring from the global and owner's inactive lists back to the active
+/// lists, then verifies the indexes agree
+///
+/// # Arguments
+///
+/// * `storage` - mutable reference to contract's storage
+/// * `offspring_addr` - a reference to the canonical address of the offspring
+/// * `owner` - a reference to the address of the offspring's owner
+/// * `info` - the offspring's inactive info
+pub fn reactivate<S: Storage>(
+    storage: &mut S,
+    offspring_addr: &CanonicalAddr,
+    owner: &HumanAddr,
+    info: StoreInactiveOffspringInfo,
+) -> StdResult<()> {
+    // delete the inactive offspring info
+    let mut inactive_store: CashMap<StoreInactiveOffspringInfo, _> =
+        CashMap::init(INACTIVE_KEY, storage);
+    inactive_store.remove(offspring_addr.as_slice())?;
+    let mut owners_inactive_store = PrefixedStorage::new(PREFIX_OWNERS_INACTIVE, storage);
+    let mut my_inactive_store: CashMap<StoreInactiveOffspringInfo, _, _> =
+        CashMap::init(owner_key(owner), &mut owners_inactive_store);
+    my_inactive_store.remove(offspring_addr.as_slice())?;
+
+    // save the active offspring info in the global and owner's active lists
+    let info = stamp(info.to_store_offspring_info())?;
+    let mut info_store: CashMap<StoreOffspringInfo, _> = CashMap::init(ACTIVE_KEY, storage);
+    info_store.insert(offspring_addr.as_slice(), info.clone())?;
+    let mut owners_store = PrefixedStorage::new(PREFIX_OWNERS_ACTIVE, storage);
+    let mut my_active_store: CashMap<StoreOffspringInfo, _, _> =
+        CashMap::init(owner_key(owner), &mut owners_store);
+    my_active_store.insert(offspring_addr.as_slice(), info)?;
+    append_order::<StoreOffspringInfo, _>(storage, None, ACTIVE_KEY, offspring_addr)?;
+    append_order::<StoreOffspringInfo, _>(
+        storage,
+        Some(PREFIX_OWNERS_ACTIVE),
+        owner_key(owner),
+        offspring_addr,
+    )?;
+
+    bump_revision(storage)?;
+    verify_placement(storage, offspring_addr, Some(owner), Placement::Active)
+}
+
+/// Returns StdResult<()>
+///
+/// removes an inactive offspring from the global and owner's inactive lists and leaves a
+/// tombstone in its place, then verifies the indexes agree
+///
+/// # Arguments
+///
+/// * `storage` - mutable reference to contract's storage
+/// * `offspring_addr` - a reference to the canonical address of the offspring
+/// * `tombstone` - tombstone to leave behind
+pub fn purge<S: Storage>(
+    storage: &mut S,
+    offspring_addr: &CanonicalAddr,
+    tombstone: Tombstone,
+) -> StdResult<()> {
+    // only inactive offspring can be purged
+    let mut inactive_store: CashMap<StoreInactiveOffspringInfo, _> = CashMap::init(INACTIVE_KEY, storage);
+    let info = inactive_store.get(offspring_addr.as_slice()).ok_or_else(|| {
+        StdError::generic_err("Only inactive offspring registered with factory can be purged.")
+    })?;
+    inactive_store.remove(offspring_addr.as_slice())?;
+
+    // free the label for new offspring
+    let mut label_store = PrefixedStorage::new(PREFIX_LABELS, storage);
+    let label_holder: Option<HumanAddr> = may_load(&label_store, info.label.as_bytes())?;
+    if label_holder.as_ref() == Some(&info.address) {
+        remove(&mut label_store, info.label.as_bytes());
+    }
+
+    // remove the offspring from its owner's inactive list
+    let owner: HumanAddr = load(
+        &ReadonlyPrefixedStorage::new(PREFIX_OFFSPRING_OWNER, storage),
+        offspring_addr.as_slice(),
+    )?;
+    let mut owners_inactive_store = PrefixedStorage::new(PREFIX_OWNERS_INACTIVE, storage);
+    let mut my_inactive_store: CashMap<StoreInactiveOffspringInfo, _, _> = CashMap::init(owner_key(&owner), &mut owners_inactive_store);
+    my_inactive_store.remove(offspring_addr.as_slice())?;
+    let mut offspring_owner_store = PrefixedStorage::new(PREFIX_OFFSPRING_OWNER, storage);
+    remove(&mut offspring_owner_store, offspring_addr.as_slice());
+    let mut code_hash_store = PrefixedStorage::new(PREFIX_OFFSPRING_CODE_HASH, storage);
+    remove(&mut code_hash_store, offspring_addr.as_slice());
+    let mut password_store = PrefixedStorage::new(PREFIX_OFFSPRING_PASSWORDS, storage);
+    remove(&mut password_store, offspring_addr.as_slice());
+    let mut offer_store = PrefixedStorage::new(PREFIX_TRANSFER_OFFERS, storage);
+    remove(&mut offer_store, offspring_addr.as_slice());
+    let mut index_store = PrefixedStorage::new(PREFIX_REGISTRATION_INDEX, storage);
+    remove(&mut index_store, offspring_addr.as_slice());
+
+    // leave a tombstone behind
+    let mut tombstone_store: CashMap<Tombstone, _> = CashMap::init(TOMBSTONE_KEY, storage);
+    tombstone_store.insert(offspring_addr.as_slice(), tombstone)?;
+
+    bump_revision(storage)?;
+    verify_placement(storage, offspring_addr, Some(&owner), Placement::Purged)
+}
+
+/// Returns StdResult<()>
+///
+/// remove an offspring from a person's list of active offspring. (This helper is implemented
+/// in case there are multiple users associated to an offspring)
+///
+/// # Arguments
+///
+/// * `storage` - mutable reference to contract's storage
+/// * `prefix` - prefix to storage of a person's active offspring list
+/// * `person` - a reference to the canonical address of the person the list belongs to
+/// * `offspring_addr` - a reference to the canonical address of the offspring to remove
+pub fn remove_from_persons_active<S: Storage>(
+    storage: &mut S,
+    prefix: &[u8],
+    person: &HumanAddr,
+    offspring_addr: &CanonicalAddr,
+) -> StdResult<()> {
+    let mut store = PrefixedStorage::new(prefix, storage);
+    let mut load_active: CashMap<StoreOffspringInfo, _, _> = CashMap::init(owner_key(person), &mut store);
+    load_active.remove(offspring_addr.as_slice())?;
+    Ok(())
+}
+
+/// Returns StdResult<()>
+///
+/// verifies that an offspring is listed in exactly the indexes expected for its placement,
+/// turning silent drift between the global and owner's indexes into an error
+///
+/// # Arguments
+///
+/// * `storage` - a reference to contract's storage
+/// * `offspring_addr` - a reference to the canonical address of the offspring
+/// * `owner` - optional reference to the offspring's owner. The owner's lists are only checked if
+///   the owner is known
+/// * `expected` - where the offspring is expected to be listed
+pub fn verify_placement<S: ReadonlyStorage>(
+    storage: &S,
+    offspring_addr: &CanonicalAddr,
+    owner: Option<&HumanAddr>,
+    expected: Placement,
+) -> StdResult<()> {
+    let key = offspring_addr.as_slice();
+    let active_store: ReadOnlyCashMap<StoreOffspringInfo, _> = ReadOnlyCashMap::init(ACTIVE_KEY, storage);
+    let inactive_store: ReadOnlyCashMap<StoreInactiveOffspringInfo, _> = ReadOnlyCashMap::init(INACTIVE_KEY, storage);
+    let tombstone_store: ReadOnlyCashMap<Tombstone, _> = ReadOnlyCashMap::init(TOMBSTONE_KEY, storage);
+
+    let mut checks = vec![
+        ("active list", active_store.contains(key), expected == Placement::Active),
+        ("inactive list", inactive_store.contains(key), expected == Placement::Inactive),
+        ("tombstones", tombstone_store.contains(key), expected == Placement::Purged),
+    ];
+    if let Some(owner) = owner {
+        let owners_active = ReadonlyPrefixedStorage::new(PREFIX_OWNERS_ACTIVE, storage);
+        let my_active_store: ReadOnlyCashMap<StoreOffspringInfo, _, _> = ReadOnlyCashMap::init(owner_key(owner), &owners_active);
+        let owners_inactive = ReadonlyPrefixedStorage::new(PREFIX_OWNERS_INACTIVE, storage);
+        let my_inactive_store: ReadOnlyCashMap<StoreInactiveOffspringInfo, _, _> = ReadOnlyCashMap::init(owner_key(owner), &owners_inactive);
+        let may_owner: Option<HumanAddr> = may_load(
+            &ReadonlyPrefixedStorage::new(PREFIX_OFFSPRING_OWNER, storage),
+            key,
+        )?;
+        checks.push(("owner's active list", my_active_store.contains(key), expected == Placement::Active));
+        checks.push(("owner's inactive list", my_inactive_store.contains(key), expected == Placement::Inactive));
+        checks.push((
+            "owner index",
+            may_owner.as_ref() == Some(owner),
+            expected != Placement::Purged,
+        ));
+    }
+
+    if let Some((index, found, _)) = checks.iter().find(|(_, found, wanted)| found != wanted) {
+        return Err(StdError::generic_err(format!(
+            "Registry index drift: offspring expected to be {:?} but was {} in the {}",
+            expected,
+            if *found { "found" } else { "not found" },
+            index,
+        )));
+    }
+    Ok(())
+}
+
+/// Returns HandleResult
+///
+/// allows admin to permanently remove an inactive offspring from the registry, leaving a
+/// tombstone in its place
+///
+/// # Arguments
+///
+/// * `deps` - mutable reference to Extern containing all the contract's external dependencies
+/// * `env` - Env of contract's environment
+/// * `address` - a reference to the address of the offspring to purge
+/// * `reason` - optional reason recorded in the tombstone
+pub fn try_purge_offspring<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    address: &HumanAddr,
+    reason: Option<String>,
+) -> HandleResult {
+    // only allow admin to do this
+    let config = enforce_admin(deps, &env)?;
+    let offspring_addr = deps.api.canonical_address(address)?;
+
+    // remove the offspring from the inactive lists and leave a tombstone behind
+    sweep_tombstones(&mut deps.storage, &deps.api, env.block.height, TOMBSTONE_SWEEP_LIMIT)?;
+    let tombstone = Tombstone {
+        address: address.clone(),
+        purged_at: env.block.height,
+        expires_at: env.block.height.saturating_add(config.tombstone_retention),
+        reason,
+    };
+    purge(&mut deps.storage, &offspring_addr, tombstone)?;
+
+    ok_status()
+}
+
+/// Returns HandleResult
+///
+/// allows owners and the admin to permanently remove inactive offspring from the registry,
+/// leaving tombstones in their place. Addresses that are not listed as inactive are skipped
+///
+/// # Arguments
+///
+/// * `deps` - mutable reference to Extern containing all the contract's external dependencies
+/// * `env` - Env of contract's environment
+/// * `addresses` - addresses of the offspring to remove
+pub fn try_remove_offspring<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    addresses: Vec<HumanAddr>,
+) -> HandleResult {
+    if addresses.len() > MAX_REMOVE_BATCH {
+        return Err(StdError::generic_err(format!(
+            "At most {} offspring can be removed at a time",
+            MAX_REMOVE_BATCH
+        )));
+    }
+    let config: Config = load(&deps.storage, CONFIG_KEY)?;
+    let is_admin = config.admin == deps.api.canonical_address(&env.message.sender)?;
+    sweep_tombstones(&mut deps.storage, &deps.api, env.block.height, TOMBSTONE_SWEEP_LIMIT)?;
+    let reason = if is_admin {
+        "removed by admin"
+    } else {
+        "removed by owner"
+    };
+    let mut removed: u32 = 0;
+    for address in addresses {
+        let offspring_addr = deps.api.canonical_address(&address)?;
+        let inactive_store: ReadOnlyCashMap<StoreInactiveOffspringInfo, _> =
+            ReadOnlyCashMap::init(INACTIVE_KEY, &deps.storage);
+        if !inactive_store.contains(offspring_addr.as_slice()) {
+            continue;
+        }
+        let owner: HumanAddr = load(
+            &ReadonlyPrefixedStorage::new(PREFIX_OFFSPRING_OWNER, &deps.storage),
+            offspring_addr.as_slice(),
+        )?;
+        if !is_admin && owner != env.message.sender {
+            return Err(StdError::generic_err(format!(
+                "Only the owner or the admin can remove offspring {}",
+                address
+            )));
+        }
+        let tombstone = Tombstone {
+            address,
+            purged_at: env.block.height,
+            expires_at: env.block.height.saturating_add(config.tombstone_retention),
+            reason: Some(reason.to_string()),
+        };
+        purge(&mut deps.storage, &offspring_addr, tombstone)?;
+        removed += 1;
+    }
+
+    ok_answer(HandleAnswer::RemoveOffspring {
+        status: Success,
+        removed,
+    })
+}
+
+/// Returns HandleResult
+///
+/// allows admin to compact a bounded slice of the registry. An offspring that is listed as
+/// inactive must not also be listed as active, so any such stale active entry is removed
+/// from the factory's active list and from its owner's active list
+///
+/// # Arguments
+///
+/// * `deps` - mutable reference to Extern containing all the contract's external dependencies
+/// * `env` - Env of contract's environment
+/// * `max_items` - maximum number of registry entries to process in this call
+pub fn try_compact<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    max_items: u32,
+) -> HandleResult {
+    // only allow admin to do this
+    let _config = enforce_admin(deps, &env)?;
+    let limit = max_items.min(MAX_COMPACT_ITEMS);
+    let mut position: u32 = may_load(&deps.storage, COMPACT_CURSOR_KEY)?.unwrap_or(0);
+    let mut processed: u32 = 0;
+    let mut removed: u32 = 0;
+
+    while processed < limit {
+        match compact_entry(&mut deps.storage, &deps.api, &mut position)? {
+            Some(was_removed) => {
+                processed += 1;
+                if was_removed {
+                    removed += 1;
+                }
+            }
+            None => break,
+        }
+    }
+    let done = save_compact_cursor(&mut deps.storage, position)?;
+
+    ok_answer(HandleAnswer::Compact {
+        processed,
+        removed,
+        done,
+    })
+}
+
+/// Returns StdResult<Option<bool>>
+///
+/// compacts the active list entry at `position`, advancing the position past it unless it was
+/// removed. Returns None if there is no entry at `position`, otherwise whether it was removed
+///
+/// # Arguments
+///
+/// * `storage` - mutable reference to contract's storage
+/// * `api` - reference to the Api used to convert human and canonical addresses
+/// * `position` - mutable reference to the position in the active list
+pub fn compact_entry<S: Storage, A: Api>(
+    storage: &mut S,
+    api: &A,
+    position: &mut u32,
+) -> StdResult<Option<bool>> {
+    let info_store: ReadOnlyCashMap<StoreOffspringInfo, _> = ReadOnlyCashMap::init(ACTIVE_KEY, storage);
+    // paging with a page size of 1 reads the entry at `position`
+    let entry = match info_store.paging(*position, 1)?.pop() {
+        Some(info) => info,
+        None => return Ok(None),
+    };
+    let offspring_addr = api.canonical_address(&entry.address)?;
+    let inactive_store: ReadOnlyCashMap<StoreInactiveOffspringInfo, _> = ReadOnlyCashMap::init(INACTIVE_KEY, storage);
+    if !inactive_store.contains(offspring_addr.as_slice()) {
+        *position += 1;
+        return Ok(Some(false));
+    }
+    // removing swaps the last entry into this position, so the position is not advanced
+    let mut info_store: CashMap<StoreOffspringInfo, _> = CashMap::init(ACTIVE_KEY, storage);
+    info_store.remove(offspring_addr.as_slice())?;
+    let may_owner: Option<HumanAddr> = may_load(
+        &ReadonlyPrefixedStorage::new(PREFIX_OFFSPRING_OWNER, storage),
+        offspring_addr.as_slice(),
+    )?;
+    if let Some(owner) = may_owner.as_ref() {
+        let owners_store = ReadonlyPrefixedStorage::new(PREFIX_OWNERS_ACTIVE, storage);
+        let my_active_store: ReadOnlyCashMap<StoreOffspringInfo, _, _> = ReadOnlyCashMap::init(owner_key(owner), &owners_store);
+        if my_active_store.contains(offspring_addr.as_slice()) {
+            remove_from_persons_active(storage, PREFIX_OWNERS_ACTIVE, owner, &offspring_addr)?;
+        }
+    }
+    bump_revision(storage)?;
+    verify_placement(storage, &offspring_addr, may_owner.as_ref(), Placement::Inactive)?;
+    Ok(Some(true))
+}
+
+/// Returns StdResult<bool> indicating whether a full compaction pass has finished
+///
+/// saves the position where the next compaction resumes, starting over once the whole
+/// registry has been covered
+///
+/// # Arguments
+///
+/// * `storage` - mutable reference to contract's storage
+/// * `position` - position in the active list reached by this compaction
+pub fn save_compact_cursor<S: Storage>(storage: &mut S, position: u32) -> StdResult<bool> {
+    let info_store: ReadOnlyCashMap<StoreOffspringInfo, _> = ReadOnlyCashMap::init(ACTIVE_KEY, storage);
+    let done = position >= info_store.len();
+    let cursor = if done { 0 } else { position };
+    save(storage, COMPACT_CURSOR_KEY, &cursor)?;
+    Ok(done)
+}
+
+/// Returns StdResult<u32> with the number of tombstones checked
+///
+/// discards expired tombstones among the next `limit` stored ones. Each sweep resumes where the
+/// previous one stopped, starting over once all tombstones have been checked
+///
+/// # Arguments
+///
+/// * `storage` - mutable reference to contract's storage
+/// * `api` - reference to the Api used to convert human and canonical addresses
+/// * `height` - current block height
+/// * `limit` - maximum number of tombstones to check
+pub fn sweep_tombstones<S: Storage, A: Api>(
+    storage: &mut S,
+    api: &A,
+    height: u64,
+    limit: u32,
+) -> StdResult<u32> {
+    let mut position: u32 = may_load(storage, TOMBSTONE_CURSOR_KEY)?.unwrap_or(0);
+    let mut tombstone_store: CashMap<Tombstone, _> = CashMap::init(TOMBSTONE_KEY, storage);
+    let mut checked: u32 = 0;
+    while checked < limit {
+        // paging with a page size of 1 reads the tombstone at `position`
+        let tombstone = match tombstone_store.paging(position, 1)?.pop() {
+            Some(tombstone) => tombstone,
+            None => break,
+        };
+        checked += 1;
+        if tombstone.expires_at <= height {
+            // removing swaps the last tombstone into this position, so the position is not advanced
+            let addr = api.canonical_address(&tombstone.address)?;
+            tombstone_store.remove(addr.as_slice())?;
+        } else {
+            position += 1;
+        }
+    }
+    let cursor = if position >= tombstone_store.len() { 0 } else { position };
+    save(storage, TOMBSTONE_CURSOR_KEY, &cursor)?;
+    Ok(checked)
+}
+
+/// Returns StdResult<u64> with the block height of the factory's latest handle. Queries have no
+/// Env, so they use it as the current height
+///
+/// # Arguments
+///
+/// * `storage` - a reference to the contract's storage
+pub fn current_height<S: ReadonlyStorage>(storage: &S) -> StdResult<u64> {
+    Ok(may_load(storage, LAST_HEIGHT_KEY)?.unwrap_or(0))
+}
+
+/// Returns HandleResult
+///
+/// allows admin to import a batch of registry records exported from another factory. The
+/// continuation tokens make sure batches are applied exactly once and in order
+///
+/// # Arguments
+///
+/// * `deps` - mutable reference to Extern containing all the contract's external dependencies
+/// * `env` - Env of contract's environment
+/// * `token` - continuation token the batch was exported with
+/// * `next_token` - continuation token returned with the batch
+/// * `records` - exported registry records
+pub fn try_import_registry<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    token: Option<String>,
+    next_token: Option<String>,
+    records: Vec<RegistryRecord>,
+) -> HandleResult {
+    // only allow admin to do this
+    let _config = enforce_admin(deps, &env)?;
+    let progress: ImportProgress = may_load(&deps.storage, IMPORT_PROGRESS_KEY)?.unwrap_or_default();
+    if progress.done {
+        return Err(StdError::generic_err("The registry import has already completed"));
+    }
+    if token != progress.expected_token {
+        return Err(StdError::generic_err(
+            "This is not the next batch of the registry import",
+        ));
+    }
+    // the next token must continue from this batch
+    let batch = match &token {
+        Some(token) => decode_export_token(token)?.batch,
+        None => 0,
+    };
+    if let Some(next) = &next_token {
+        if decode_export_token(next)?.batch != batch + 1 {
+            return Err(StdError::generic_err(
+                "The next continuation token does not follow this batch",
+            ));
+        }
+    }
+
+    for record in records {
+        let offspring_addr = deps.api.canonical_address(&record.address)?;
+        enforce_not_blocked(&deps.storage, &offspring_addr)?;
+        match lifecycle_status(&deps.storage, &offspring_addr, env.block.height) {
+            LifecycleStatus::Unknown {} => {
+                // an expired tombstone that was not swept yet no longer reserves the address
+                let mut tombstone_store: CashMap<Tombstone, _> = CashMap::init(TOMBSTONE_KEY, &mut deps.storage);
+                if tombstone_store.contains(offspring_addr.as_slice()) {
+                    tombstone_store.remove(offspring_addr.as_slice())?;
+                }
+            }
+            _ => {
+                return Err(StdError::generic_err(format!(
+                    "{} is already known to the factory",
+                    record.address
+                )))
+            }
+        }
+        let (description, truncated) =
+            seal_description(&deps.storage, offspring_addr.as_slice(), record.description)?;
+        let info = StoreOffspringInfo {
+            address: record.address,
+            label: record.label,
+            description,
+            description_truncated: truncated || record.description_truncated,
+            metadata_url: record.metadata_url,
+            count_privacy: record.count_privacy,
+            attributes: record.attributes,
+            created_height: record.created_height,
+            created_time: record.created_time,
+            checksum: 0,
+        };
+        register(&mut deps.storage, &offspring_addr, &record.owner, info.clone())?;
+        if !record.active {
+            // records exported before deactivations were timed count as deactivated now
+            let deactivated = BlockInfo {
+                height: record.deactivated_height.unwrap_or(env.block.height),
+                time: record.deactivated_time.unwrap_or(env.block.time),
+                chain_id: env.block.chain_id.clone(),
+            };
+            deactivate(
+                &mut deps.storage,
+                &offspring_addr,
+                &record.owner,
+                &info,
+                &deactivated,
+                record.deactivated_by.unwrap_or(DeactivationSource::Unknown),
+            )?;
+        }
+    }
+
+    let progress = ImportProgress {
+        done: next_token.is_none(),
+        expected_token: next_token,
+    };
+    save(&mut deps.storage, IMPORT_PROGRESS_KEY, &progress)?;
+    record_audit(&mut deps.storage, "import_registry", &env, None)?;
+
+    ok_status()
+}
+
+/// Returns StdResult<()>
+///
+/// bumps the registry revision, so clients polling the lists know they changed
+///
+/// # Arguments
+///
+/// * `storage` - mutable reference to contract's storage
+pub fn bump_revision<S: Storage>(storage: &mut S) -> StdResult<()> {
+    let revision = registry_revision(storage)?;
+    save(storage, REGISTRY_REVISION_KEY, &revision.wrapping_add(1))
+}
+
+/// Returns StdResult<u64> with the registry revision, the number of changes made to the
+/// offspring lists
+///
+/// # Arguments
+///
+/// * `storage` - a reference to the contract's storage
+pub fn registry_revision<S: ReadonlyStorage>(storage: &S) -> StdResult<u64> {
+    Ok(may_load(storage, REGISTRY_REVISION_KEY)?.unwrap_or(0))
+}
+
+/// Returns StdResult<Option<HumanAddr>> with the address of the offspring registered under a
+/// label, if any. Purged offspring keep their label
+///
+/// # Arguments
+///
+/// * `storage` - a reference to the contract's storage
+/// * `label` - the label to look up
+pub fn offspring_by_label<S: ReadonlyStorage>(
+    storage: &S,
+    label: &str,
+) -> StdResult<Option<HumanAddr>> {
+    may_load(&ReadonlyPrefixedStorage::new(PREFIX_LABELS, storage), label.as_bytes())
+}
+
+/// Returns LifecycleStatus of an offspring assembled from the factory's indexes. A tombstone
+/// past its expiration counts as gone even if it was not swept yet
+///
+/// # Arguments
+///
+/// * `storage` - a reference to the contract's storage
+/// * `offspring_addr` - a reference to the canonical address of the offspring
+/// * `height` - current block height
+pub fn lifecycle_status<S: ReadonlyStorage>(
+    storage: &S,
+    offspring_addr: &CanonicalAddr,
+    height: u64,
+) -> LifecycleStatus {
+    let active_store: ReadOnlyCashMap<StoreOffspringInfo, _> = ReadOnlyCashMap::init(ACTIVE_KEY, storage);
+    if let Some(info) = active_store.get(offspring_addr.as_slice()) {
+        return LifecycleStatus::Active { info };
+    }
+    let inactive_store: ReadOnlyCashMap<StoreInactiveOffspringInfo, _> = ReadOnlyCashMap::init(INACTIVE_KEY, storage);
+    if let Some(info) = inactive_store.get(offspring_addr.as_slice()) {
+        return LifecycleStatus::Inactive { info };
+    }
+    let tombstone_store: ReadOnlyCashMap<Tombstone, _> = ReadOnlyCashMap::init(TOMBSTONE_KEY, storage);
+    match tombstone_store.get(offspring_addr.as_slice()) {
+        Some(tombstone) if tombstone.expires_at > height => LifecycleStatus::Purged { tombstone },
+        _ => LifecycleStatus::Unknown {},
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cosmwasm_std::testing::{mock_env, MockApi, MockStorage};
+
+    fn offspring() -> (CanonicalAddr, HumanAddr, StoreOffspringInfo) {
+        let addr = CanonicalAddr::from(b"offspring".to_vec());
+        let owner = HumanAddr("owner".to_string());
+        let info = StoreOffspringInfo {
+            address: HumanAddr("offspring".to_string()),
+            label: "label".to_string(),
+            description: None,
+            description_truncated: false,
+            metadata_url: None,
+            count_privacy: None,
+            attributes: vec![],
+            created_height: 1,
+            created_time: 1,
+            checksum: 0,
+        };
+        (addr, owner, info)
+    }
+
+    fn block() -> BlockInfo {
+        mock_env("offspring", &[]).block
+    }
+
+    fn tombstone() -> Tombstone {
+        Tombstone {
+            address: HumanAddr("offspring".to_string()),
+            purged_at: 1,
+            expires_at: 2,
+            reason: None,
+        }
+    }
+
+    /// This test checks that each mutation leaves the offspring in exactly the expected indexes.
+    #[test]
+    fn test_lifecycle_placements() {
+        let mut storage = MockStorage::default();
+        let (addr, owner, info) = offspring();
+
+        register(&mut storage, &addr, &owner, info.clone()).unwrap();
+        deactivate(&mut storage, &addr, &owner, &info, &block(), DeactivationSource::Owner).unwrap();
+        purge(&mut storage, &addr, tombstone()).unwrap();
+        verify_placement(&storage, &addr, Some(&owner), Placement::Purged).unwrap();
+    }
+
+    /// This test checks that an offspring listed in both the active and inactive lists is
+    /// reported as drift.
+    #[test]
+    fn test_detects_drift() {
+        let mut storage = MockStorage::default();
+        let (addr, owner, info) = offspring();
+
+        register(&mut storage, &addr, &owner, info.clone()).unwrap();
+        let mut inactive_store = CashMap::init(INACTIVE_KEY, &mut storage);
+        inactive_store
+            .insert(
+                addr.as_slice(),
+                info.to_store_inactive_offspring_info(&block(), DeactivationSource::Owner),
+            )
+            .unwrap();
+
+        assert!(verify_placement(&storage, &addr, Some(&owner), Placement::Active).is_err());
+        assert!(verify_placement(&storage, &addr, None, Placement::Inactive).is_err());
+    }
+
+    /// This test checks that a transfer moves the offspring from the previous owner's lists to
+    /// the new owner's lists.
+    #[test]
+    fn test_transfer_moves_owner_lists() {
+        let mut storage = MockStorage::default();
+        let (addr, owner, info) = offspring();
+        let new_owner = HumanAddr("new_owner".to_string());
+
+        register(&mut storage, &addr, &owner, info.clone()).unwrap();
+        transfer(&mut storage, &addr, &owner, &new_owner).unwrap();
+        assert!(verify_placement(&storage, &addr, Some(&owner), Placement::Active).is_err());
+
+        deactivate(&mut storage, &addr, &new_owner, &info, &block(), DeactivationSource::Owner)
+            .unwrap();
+        assert!(transfer(&mut storage, &addr, &new_owner, &owner).is_err());
+    }
+
+    /// This test checks that the chronological index of a list is compacted a few entries at a
+    /// time as offspring keep leaving and rejoining it, and that the entries at the offspring's
+    /// latest positions keep the order of the listed offspring while it is.
+    #[test]
+    fn test_order_index_is_compacted() {
+        let mut storage = MockStorage::default();
+        let (addr, owner, info) = offspring();
+        let other = CanonicalAddr::from(b"other".to_vec());
+        let mut other_info = info.clone();
+        other_info.address = HumanAddr("other".to_string());
+        other_info.label = "other".to_string();
+
+        register(&mut storage, &addr, &owner, info.clone()).unwrap();
+        register(&mut storage, &other, &owner, other_info).unwrap();
+        let lists = [(None, ACTIVE_KEY), (Some(PREFIX_OWNERS_ACTIVE), owner_key(&owner))];
+        let mut shortest = u32::MAX;
+        for _ in 0..50 {
+            deactivate(&mut storage, &addr, &owner, &info, &block(), DeactivationSource::Owner)
+                .unwrap();
+            let inactive =
+                info.to_store_inactive_offspring_info(&block(), DeactivationSource::Owner);
+            reactivate(&mut storage, &addr, &owner, inactive).unwrap();
+            for (prefix, key) in &lists {
+                let order_ns = order_namespace(*prefix, key);
+                let order_store = ReadonlyPrefixedStorage::multilevel(&order_ns, &storage);
+                let order = AppendStore::<CanonicalAddr, _>::attach(&order_store).unwrap().unwrap();
+                assert!(order.len() <= 2 * (2 * 2 + ORDER_INDEX_SLACK));
+                shortest = shortest.min(order.len());
+                // the reactivated offspring is the newest, and the other one keeps its place
+                let position_ns = position_namespace(*prefix, key);
+                let position_store = ReadonlyPrefixedStorage::multilevel(&position_ns, &storage);
+                let latest: Vec<CanonicalAddr> = (0..order.len())
+                    .map(|pos| order.get_at(pos).unwrap())
+                    .enumerate()
+                    .filter(|(pos, e)| {
+                        may_load(&position_store, e.as_slice()).unwrap() == Some(*pos as u32)
+                    })
+                    .map(|(_, e)| e)
+                    .collect();
+                assert_eq!(latest, vec![other.clone(), addr.clone()]);
+            }
+        }
+        assert!(shortest <= 2 + ORDER_COMPACTION_STEPS);
+    }
+
+    /// This test checks that an expired tombstone is reported as unknown before it is swept, and
+    /// that each sweep resumes after the tombstones the previous one kept.
+    #[test]
+    fn test_tombstone_sweep_resumes() {
+        let mut storage = MockStorage::default();
+        let api = MockApi::new(20);
+        let mut tombstone_store: CashMap<Tombstone, _> = CashMap::init(TOMBSTONE_KEY, &mut storage);
+        for (name, expires_at) in &[("kept1", 100), ("kept2", 100), ("expired", 5)] {
+            let address = HumanAddr(name.to_string());
+            let addr = api.canonical_address(&address).unwrap();
+            let tombstone = Tombstone {
+                address,
+                purged_at: 1,
+                expires_at: *expires_at,
+                reason: None,
+            };
+            tombstone_store.insert(addr.as_slice(), tombstone).unwrap();
+        }
+        let expired = api.canonical_address(&HumanAddr::from("expired")).unwrap();
+        assert!(matches!(lifecycle_status(&storage, &expired, 4), LifecycleStatus::Purged { .. }));
+        assert!(matches!(lifecycle_status(&storage, &expired, 5), LifecycleStatus::Unknown {}));
+
+        // the first sweep only reaches the tombstones that are kept
+        assert_eq!(sweep_tombstones(&mut storage, &api, 10, 2).unwrap(), 2);
+        let tombstone_store: ReadOnlyCashMap<Tombstone, _> = ReadOnlyCashMap::init(TOMBSTONE_KEY, &storage);
+        assert!(tombstone_store.contains(expired.as_slice()));
+        assert_eq!(sweep_tombstones(&mut storage, &api, 10, 2).unwrap(), 1);
+        let tombstone_store: ReadOnlyCashMap<Tombstone, _> = ReadOnlyCashMap::init(TOMBSTONE_KEY, &storage);
+        assert!(!tombstone_store.contains(expired.as_slice()));
+        assert_eq!(tombstone_store.len(), 2);
+        // the whole list was covered, so the next sweep starts over
+        let cursor: u32 = load(&storage, TOMBSTONE_CURSOR_KEY).unwrap();
+        assert_eq!(cursor, 0);
+    }
+}
@@ -0,0 +1,102 @@
+use cosmwasm_std::{
+    log, to_binary, CosmosMsg, Env, HandleResponse, HandleResult, HumanAddr, LogAttribute,
+    StdError,
+};
+
+use crate::msg::{HandleAnswer, ResponseStatus::Success};
+
+// Responses built here are padded to BLOCK_SIZE by `contract::handle`, so every handler gets the
+// same padding no matter which of these helpers it uses.
+
+/// Returns HandleResult with the answer as response data
+///
+/// # Arguments
+///
+/// * `answer` - the HandleAnswer to return
+pub fn ok_answer(answer: HandleAnswer) -> HandleResult {
+    ok_with_msgs(vec![], answer)
+}
+
+/// Returns HandleResult with the answer as response data that also sends messages
+///
+/// # Arguments
+///
+/// * `messages` - messages to send
+/// * `answer` - the HandleAnswer to return
+pub fn ok_with_msgs(messages: Vec<CosmosMsg>, answer: HandleAnswer) -> HandleResult {
+    ok_with_log(messages, vec![], Some(answer))
+}
+
+/// Returns HandleResult with a Success status as response data
+pub fn ok_status() -> HandleResult {
+    ok_answer(HandleAnswer::Status {
+        status: Success,
+        message: None,
+    })
+}
+
+/// Returns HandleResult without response data, used to reply to the offspring's callbacks
+pub fn ok_empty() -> HandleResult {
+    ok_with_log(vec![], vec![], None)
+}
+
+/// Returns HandleResult that sends messages and logs attributes, with an optional answer as
+/// response data
+///
+/// # Arguments
+///
+/// * `messages` - messages to send
+/// * `log` - attributes to log
+/// * `answer` - optional HandleAnswer to return
+pub fn ok_with_log(
+    messages: Vec<CosmosMsg>,
+    log: Vec<LogAttribute>,
+    answer: Option<HandleAnswer>,
+) -> HandleResult {
+    Ok(HandleResponse {
+        messages,
+        log,
+        data: answer.map(|a| to_binary(&a)).transpose()?,
+    })
+}
+
+/// Returns Vec<LogAttribute> describing a change in an offspring's lifecycle, so indexers can
+/// follow the registry without querying it
+///
+/// # Arguments
+///
+/// * `action` - name of the change
+/// * `offspring` - optional address of the offspring. It is not known yet when it is created
+/// * `owner` - a reference to the address of the offspring's owner
+/// * `label` - label of the offspring
+/// * `env` - reference to the Env of the message
+pub fn lifecycle_log(
+    action: &str,
+    offspring: Option<&HumanAddr>,
+    owner: &HumanAddr,
+    label: &str,
+    env: &Env,
+) -> Vec<LogAttribute> {
+    let mut attributes = vec![log("action", action)];
+    if let Some(offspring) = offspring {
+        attributes.push(log("offspring_address", offspring));
+    }
+    attributes.extend(vec![
+        log("owner", owner),
+        log("label", label),
+        log("block_time", env.block.time),
+    ]);
+    attributes
+}
+
+/// Returns StdError for a non-admin sender of an admin command
+pub fn not_admin() -> StdError {
+    StdError::generic_err(
+        "This is an admin command. Admin commands can only be run from admin address",
+    )
+}
+
+/// Returns StdError for a sender that is not an active offspring registered with the factory
+pub fn not_registered_offspring() -> StdError {
+    StdError::generic_err("This is not an active offspring registered with factory.")
+}
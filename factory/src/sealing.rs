@@ -0,0 +1,179 @@
+use chacha20poly1305::{
+    aead::{Aead, NewAead},
+    ChaCha20Poly1305, Key, Nonce,
+};
+
+use cosmwasm_std::{ReadonlyStorage, StdError, StdResult, Storage};
+
+use crate::msg::{StoreInactiveOffspringInfo, StoreOffspringInfo};
+use crate::rand::sha_256;
+use crate::state::{load, may_load, save, Config, CONFIG_KEY, SEALING_KEY};
+
+/// marks a field sealed with version 1 of the scheme: ChaCha20-Poly1305 with a key derived from
+/// the factory's initial prng seed. A later scheme must use a new marker, so fields sealed with
+/// either can be told apart while they are migrated
+const SEALED_V1: &str = "sealed1:";
+
+/// Returns StdResult<()>
+///
+/// derives the key private registry fields are sealed with from the initial prng seed, and
+/// saves it unless a key was already saved. The prng seed changes with every creation, so the
+/// key must not be derived from it again later
+///
+/// # Arguments
+///
+/// * `storage` - mutable reference to the contract's storage
+/// * `prng_seed` - the factory's initial prng seed
+pub fn init_sealing_key<S: Storage>(storage: &mut S, prng_seed: &[u8]) -> StdResult<()> {
+    let existing: Option<[u8; 32]> = may_load(storage, SEALING_KEY)?;
+    if existing.is_none() {
+        let mut seed = prng_seed.to_vec();
+        seed.extend_from_slice(b"registry field sealing v1");
+        save(storage, SEALING_KEY, &sha_256(&seed))?;
+    }
+    Ok(())
+}
+
+/// Returns StdResult<[u8; 32]> with the key private registry fields are sealed with
+///
+/// # Arguments
+///
+/// * `storage` - a reference to the contract's storage
+fn sealing_key<S: ReadonlyStorage>(storage: &S) -> StdResult<[u8; 32]> {
+    load(storage, SEALING_KEY)
+}
+
+/// Returns StdResult<Option<String>> with the field sealed if the factory lists privately, or
+/// unchanged otherwise
+///
+/// # Arguments
+///
+/// * `storage` - a reference to the contract's storage
+/// * `context` - bytes the field belongs to, such as the offspring's address
+/// * `field` - the field to seal
+pub fn seal<S: ReadonlyStorage>(
+    storage: &S,
+    context: &[u8],
+    field: Option<String>,
+) -> StdResult<Option<String>> {
+    let config: Config = load(storage, CONFIG_KEY)?;
+    match field {
+        Some(plaintext) if config.private_listing => seal_text(storage, context, &plaintext).map(Some),
+        other => Ok(other),
+    }
+}
+
+/// Returns StdResult<String> with the text sealed, whether or not the factory lists privately.
+/// Used for fields that are always private, like the admin's notes about owners
+///
+/// # Arguments
+///
+/// * `storage` - a reference to the contract's storage
+/// * `context` - bytes the text belongs to, such as the address it describes
+/// * `plaintext` - the text to seal
+pub fn seal_text<S: ReadonlyStorage>(
+    storage: &S,
+    context: &[u8],
+    plaintext: &str,
+) -> StdResult<String> {
+    let key = sealing_key(storage)?;
+    // the nonce only repeats for the same field of the same context, which can only reveal that
+    // a field was set to a value it had before
+    let mut nonce_input = key.to_vec();
+    nonce_input.extend_from_slice(context);
+    nonce_input.extend_from_slice(plaintext.as_bytes());
+    let nonce = sha_256(&nonce_input);
+    let ciphertext = ChaCha20Poly1305::new(Key::from_slice(&key))
+        .encrypt(Nonce::from_slice(&nonce[..12]), plaintext.as_bytes())
+        .map_err(|_| StdError::generic_err("Unable to seal registry field"))?;
+    let mut sealed = nonce[..12].to_vec();
+    sealed.extend_from_slice(&ciphertext);
+    Ok(format!("{}{}", SEALED_V1, base64::encode(&sealed)))
+}
+
+/// Returns StdResult<(Option<String>, bool)> with the description to store in the registry, and
+/// whether it was truncated to the factory's description limit. A truncated description is only
+/// a preview, and the full text is only kept by the offspring
+///
+/// # Arguments
+///
+/// * `storage` - a reference to the contract's storage
+/// * `context` - bytes the description belongs to, such as the offspring's address
+/// * `description` - the offspring's description
+pub fn seal_description<S: ReadonlyStorage>(
+    storage: &S,
+    context: &[u8],
+    description: Option<String>,
+) -> StdResult<(Option<String>, bool)> {
+    let config: Config = load(storage, CONFIG_KEY)?;
+    let (description, truncated) = match (description, config.description_limit) {
+        (Some(text), Some(limit)) => match text.char_indices().nth(limit as usize) {
+            Some((end, _)) => (Some(text[..end].to_string()), true),
+            None => (Some(text), false),
+        },
+        (other, _) => (other, false),
+    };
+    Ok((seal(storage, context, description)?, truncated))
+}
+
+/// Returns StdResult<Option<String>> with the field opened if it is sealed, or unchanged
+/// otherwise. Only call this on paths that already authenticated the querier
+///
+/// # Arguments
+///
+/// * `storage` - a reference to the contract's storage
+/// * `field` - the field to open
+pub fn open<S: ReadonlyStorage>(storage: &S, field: Option<String>) -> StdResult<Option<String>> {
+    let encoded = match field {
+        Some(field) if field.starts_with(SEALED_V1) => field[SEALED_V1.len()..].to_string(),
+        other => return Ok(other),
+    };
+    let sealed = base64::decode(&encoded)
+        .map_err(|_| StdError::generic_err("Sealed registry field is corrupted"))?;
+    if sealed.len() < 12 {
+        return Err(StdError::generic_err("Sealed registry field is corrupted"));
+    }
+    let key = sealing_key(storage)?;
+    let plaintext = ChaCha20Poly1305::new(Key::from_slice(&key))
+        .decrypt(Nonce::from_slice(&sealed[..12]), &sealed[12..])
+        .map_err(|_| StdError::generic_err("Unable to open sealed registry field"))?;
+    String::from_utf8(plaintext)
+        .map(Some)
+        .map_err(|_| StdError::generic_err("Sealed registry field is corrupted"))
+}
+
+/// Returns StdResult<Vec<StoreOffspringInfo>> with the sealed fields of the list opened
+///
+/// # Arguments
+///
+/// * `storage` - a reference to the contract's storage
+/// * `list` - the active offspring to open
+pub fn open_active<S: ReadonlyStorage>(
+    storage: &S,
+    list: Vec<StoreOffspringInfo>,
+) -> StdResult<Vec<StoreOffspringInfo>> {
+    list.into_iter()
+        .map(|mut info| {
+            info.description = open(storage, info.description)?;
+            Ok(info)
+        })
+        .collect()
+}
+
+/// Returns StdResult<Vec<StoreInactiveOffspringInfo>> with the sealed fields of the list opened
+///
+/// # Arguments
+///
+/// * `storage` - a reference to the contract's storage
+/// * `list` - the inactive offspring to open
+pub fn open_inactive<S: ReadonlyStorage>(
+    storage: &S,
+    list: Vec<StoreInactiveOffspringInfo>,
+) -> StdResult<Vec<StoreInactiveOffspringInfo>> {
+    list.into_iter()
+        .map(|mut info| {
+            info.description = open(storage, info.description)?;
+            Ok(info)
+        })
+        .collect()
+}
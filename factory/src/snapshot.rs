@@ -0,0 +1,481 @@
+use cosmwasm_std::{
+    Api, Binary, CanonicalAddr, Env, Extern, HandleResult, HumanAddr, Querier, ReadonlyStorage,
+    StdError, StdResult, Storage,
+};
+
+use cosmwasm_storage::{PrefixedStorage, ReadonlyPrefixedStorage};
+
+use secret_toolkit_incubator::ReadOnlyCashMap;
+
+use crate::state::{
+    load, may_load, remove, save, RegistrySnapshot, SnapshotBuild, SnapshotEntry, ACTIVE_KEY,
+    MAX_SNAPSHOT_ITEMS, PREFIX_OFFSPRING_OWNER, PREFIX_SNAPSHOT_ENTRIES, PREFIX_SNAPSHOT_NODES,
+    SNAPSHOT_BUILD_KEY, SNAPSHOT_KEY,
+};
+
+use crate::{
+    admin::enforce_admin,
+    msg::{HandleAnswer, ProofNode, StoreOffspringInfo},
+    rand::sha_256,
+    registry::registry_revision,
+    response::ok_answer,
+};
+
+/// domain separator of leaf hashes
+const LEAF_PREFIX: u8 = 0;
+/// domain separator of inner node hashes
+const NODE_PREFIX: u8 = 1;
+
+/// Returns [u8;32] with the merkle leaf of a registry entry. Each field is prefixed with its
+/// length as a 4 byte big endian integer, so that fields can not be shifted into each other
+///
+/// # Arguments
+///
+/// * `entry` - a reference to the registry entry
+pub fn leaf_hash(entry: &SnapshotEntry) -> [u8; 32] {
+    let mut data = vec![LEAF_PREFIX];
+    for field in [&entry.address.0, &entry.label, &entry.owner.0].iter() {
+        data.extend_from_slice(&(field.len() as u32).to_be_bytes());
+        data.extend_from_slice(field.as_bytes());
+    }
+    sha_256(&data)
+}
+
+/// Returns [u8;32] with the merkle node of two child nodes
+///
+/// # Arguments
+///
+/// * `left` - a reference to the left child
+/// * `right` - a reference to the right child
+pub fn node_hash(left: &[u8; 32], right: &[u8; 32]) -> [u8; 32] {
+    let mut data = Vec::with_capacity(65);
+    data.push(NODE_PREFIX);
+    data.extend_from_slice(left);
+    data.extend_from_slice(right);
+    sha_256(&data)
+}
+
+/// Returns [u8;1] with the storage slot of a snapshot. Consecutive snapshots use different
+/// slots, so the latest snapshot stays provable while the next one is built
+///
+/// # Arguments
+///
+/// * `id` - id of the snapshot
+fn slot(id: u64) -> [u8; 1] {
+    [(id % 2) as u8]
+}
+
+/// Returns Vec<u8> with the storage key of a tree node
+///
+/// # Arguments
+///
+/// * `level` - level of the node, where level 0 holds the leaves
+/// * `position` - position of the node in its level
+fn node_key(level: u32, position: u32) -> Vec<u8> {
+    let mut key = level.to_be_bytes().to_vec();
+    key.extend_from_slice(&position.to_be_bytes());
+    key
+}
+
+/// Returns StdResult<[u8;32]> with a tree node of a snapshot
+///
+/// # Arguments
+///
+/// * `storage` - a reference to the contract's storage
+/// * `id` - id of the snapshot
+/// * `level` - level of the node, where level 0 holds the leaves
+/// * `position` - position of the node in its level
+fn load_node<S: ReadonlyStorage>(
+    storage: &S,
+    id: u64,
+    level: u32,
+    position: u32,
+) -> StdResult<[u8; 32]> {
+    let node_store =
+        ReadonlyPrefixedStorage::multilevel(&[PREFIX_SNAPSHOT_NODES, &slot(id)], storage);
+    load(&node_store, &node_key(level, position))
+}
+
+/// Returns StdResult<()>
+///
+/// stores a tree node of a snapshot
+///
+/// # Arguments
+///
+/// * `storage` - mutable reference to the contract's storage
+/// * `id` - id of the snapshot
+/// * `level` - level of the node, where level 0 holds the leaves
+/// * `position` - position of the node in its level
+/// * `node` - a reference to the node
+fn save_node<S: Storage>(
+    storage: &mut S,
+    id: u64,
+    level: u32,
+    position: u32,
+    node: &[u8; 32],
+) -> StdResult<()> {
+    let mut node_store =
+        PrefixedStorage::multilevel(&[PREFIX_SNAPSHOT_NODES, &slot(id)], storage);
+    save(&mut node_store, &node_key(level, position), node)
+}
+
+/// Returns StdResult<()>
+///
+/// processes the next registry entry or tree node of an unfinished snapshot build. A node
+/// without a sibling is carried up to the next level unchanged
+///
+/// # Arguments
+///
+/// * `storage` - mutable reference to the contract's storage
+/// * `api` - reference to the Api used to convert human and canonical addresses
+/// * `build` - mutable reference to the progress of the build
+fn build_step<S: Storage, A: Api>(
+    storage: &mut S,
+    api: &A,
+    build: &mut SnapshotBuild,
+) -> StdResult<()> {
+    if build.level == 0 {
+        let info_store: ReadOnlyCashMap<StoreOffspringInfo, _> =
+            ReadOnlyCashMap::init(ACTIVE_KEY, storage);
+        // paging with a page size of 1 reads the entry at `position`
+        let info = info_store
+            .paging(build.position, 1)?
+            .pop()
+            .ok_or_else(|| StdError::not_found("StoreOffspringInfo"))?;
+        let offspring_addr = api.canonical_address(&info.address)?;
+        let owner_store = ReadonlyPrefixedStorage::new(PREFIX_OFFSPRING_OWNER, storage);
+        let owner: HumanAddr = load(&owner_store, offspring_addr.as_slice())?;
+        let entry = SnapshotEntry {
+            address: info.address,
+            label: info.label,
+            owner,
+            index: build.position,
+        };
+        save_node(storage, build.id, 0, build.position, &leaf_hash(&entry))?;
+        let mut entry_store =
+            PrefixedStorage::multilevel(&[PREFIX_SNAPSHOT_ENTRIES, &slot(build.id)], storage);
+        save(&mut entry_store, offspring_addr.as_slice(), &entry)?;
+    } else {
+        let below = build.level - 1;
+        let left = load_node(storage, build.id, below, 2 * build.position)?;
+        let node = if 2 * build.position + 1 < build.width {
+            node_hash(&left, &load_node(storage, build.id, below, 2 * build.position + 1)?)
+        } else {
+            left
+        };
+        save_node(storage, build.id, build.level, build.position, &node)?;
+    }
+    build.position += 1;
+    Ok(())
+}
+
+/// Returns StdResult<Option<[u8;32]>> with the merkle root if the build is complete. The root of
+/// an empty tree is all zeros
+///
+/// moves a snapshot build past every level it finished
+///
+/// # Arguments
+///
+/// * `storage` - a reference to the contract's storage
+/// * `build` - mutable reference to the progress of the build
+fn advance<S: ReadonlyStorage>(
+    storage: &S,
+    build: &mut SnapshotBuild,
+) -> StdResult<Option<[u8; 32]>> {
+    if build.level == 0 {
+        let info_store: ReadOnlyCashMap<StoreOffspringInfo, _> =
+            ReadOnlyCashMap::init(ACTIVE_KEY, storage);
+        if build.position < info_store.len() {
+            return Ok(None);
+        }
+        build.leaf_count = build.position;
+        build.width = build.position;
+        build.level = 1;
+        build.position = 0;
+    }
+    loop {
+        match build.width {
+            0 => return Ok(Some([0u8; 32])),
+            1 => return load_node(storage, build.id, build.level - 1, 0).map(Some),
+            width if build.position < width / 2 + width % 2 => return Ok(None),
+            width => {
+                build.width = width / 2 + width % 2;
+                build.level += 1;
+                build.position = 0;
+            }
+        }
+    }
+}
+
+/// Returns StdResult<(u32, Option<[u8;32]>)> with the number of registry entries and tree nodes
+/// processed, and the merkle root if the build is complete
+///
+/// # Arguments
+///
+/// * `storage` - mutable reference to the contract's storage
+/// * `api` - reference to the Api used to convert human and canonical addresses
+/// * `build` - mutable reference to the progress of the build
+/// * `limit` - maximum number of entries and nodes to process
+fn run_build<S: Storage, A: Api>(
+    storage: &mut S,
+    api: &A,
+    build: &mut SnapshotBuild,
+    limit: u32,
+) -> StdResult<(u32, Option<[u8; 32]>)> {
+    let mut processed: u32 = 0;
+    let mut root = advance(storage, build)?;
+    while root.is_none() && processed < limit {
+        build_step(storage, api, build)?;
+        processed += 1;
+        root = advance(storage, build)?;
+    }
+    Ok((processed, root))
+}
+
+/// Returns StdResult<Option<SnapshotEntry>> with an offspring's entry in a snapshot, if the
+/// offspring is included
+///
+/// # Arguments
+///
+/// * `storage` - a reference to the contract's storage
+/// * `snapshot` - a reference to the snapshot
+/// * `offspring_addr` - a reference to the canonical address of the offspring
+pub fn snapshot_entry<S: ReadonlyStorage>(
+    storage: &S,
+    snapshot: &RegistrySnapshot,
+    offspring_addr: &CanonicalAddr,
+) -> StdResult<Option<SnapshotEntry>> {
+    let entry_store =
+        ReadonlyPrefixedStorage::multilevel(&[PREFIX_SNAPSHOT_ENTRIES, &slot(snapshot.id)], storage);
+    let entry: Option<SnapshotEntry> = may_load(&entry_store, offspring_addr.as_slice())?;
+    match entry {
+        // an entry left over from an earlier snapshot in the same slot does not match the leaf
+        // now stored at its position
+        Some(entry)
+            if entry.index < snapshot.leaf_count
+                && load_node(storage, snapshot.id, 0, entry.index)? == leaf_hash(&entry) =>
+        {
+            Ok(Some(entry))
+        }
+        _ => Ok(None),
+    }
+}
+
+/// Returns StdResult<Vec<ProofNode>> with the siblings on the path from a leaf to the merkle
+/// root, starting at the leaf's level. Levels where the path node has no sibling are skipped
+///
+/// # Arguments
+///
+/// * `storage` - a reference to the contract's storage
+/// * `snapshot` - a reference to the snapshot
+/// * `index` - position of the leaf being proven
+pub fn merkle_proof<S: ReadonlyStorage>(
+    storage: &S,
+    snapshot: &RegistrySnapshot,
+    mut index: u32,
+) -> StdResult<Vec<ProofNode>> {
+    let mut proof = Vec::new();
+    let mut level = 0;
+    let mut width = snapshot.leaf_count;
+    while width > 1 {
+        let sibling = index ^ 1;
+        if sibling < width {
+            proof.push(ProofNode {
+                hash: Binary::from(load_node(storage, snapshot.id, level, sibling)?.to_vec()),
+                is_left: sibling < index,
+            });
+        }
+        index /= 2;
+        width = width / 2 + width % 2;
+        level += 1;
+    }
+    Ok(proof)
+}
+
+/// Returns HandleResult
+///
+/// allows admin to snapshot the active registry as a merkle root over the address, label and
+/// owner of every active offspring. The snapshot is built over as many calls as needed, each
+/// processing at most `max_items` registry entries and tree nodes and resuming where the
+/// previous one stopped. The build starts over if the registry changes before it is complete.
+/// Proofs of membership are generated against the latest complete snapshot
+///
+/// # Arguments
+///
+/// * `deps` - mutable reference to Extern containing all the contract's external dependencies
+/// * `env` - Env of contract's environment
+/// * `max_items` - optional maximum number of entries and nodes to process in this call
+pub fn try_snapshot_registry<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    max_items: Option<u32>,
+) -> HandleResult {
+    // only allow admin to do this
+    let _config = enforce_admin(deps, &env)?;
+    let limit = max_items.unwrap_or(MAX_SNAPSHOT_ITEMS).min(MAX_SNAPSHOT_ITEMS);
+    let revision = registry_revision(&deps.storage)?;
+    let may_build: Option<SnapshotBuild> = may_load(&deps.storage, SNAPSHOT_BUILD_KEY)?;
+    let restarted = matches!(&may_build, Some(build) if build.revision != revision);
+    let mut build = match may_build {
+        Some(build) if !restarted => build,
+        _ => {
+            let latest: Option<RegistrySnapshot> = may_load(&deps.storage, SNAPSHOT_KEY)?;
+            SnapshotBuild {
+                id: latest.map(|s| s.id.wrapping_add(1)).unwrap_or(0),
+                revision,
+                level: 0,
+                position: 0,
+                width: 0,
+                leaf_count: 0,
+            }
+        }
+    };
+    let (processed, root) = run_build(&mut deps.storage, &deps.api, &mut build, limit)?;
+    if let Some(root) = root {
+        let snapshot = RegistrySnapshot {
+            id: build.id,
+            root,
+            height: env.block.height,
+            leaf_count: build.leaf_count,
+        };
+        save(&mut deps.storage, SNAPSHOT_KEY, &snapshot)?;
+        remove(&mut deps.storage, SNAPSHOT_BUILD_KEY);
+    } else {
+        save(&mut deps.storage, SNAPSHOT_BUILD_KEY, &build)?;
+    }
+
+    ok_answer(HandleAnswer::SnapshotRegistry {
+        processed,
+        restarted,
+        done: root.is_some(),
+        root: root.map(|root| Binary::from(root.to_vec())),
+        leaf_count: root.map(|_| build.leaf_count),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cosmwasm_std::testing::MockApi;
+    use cosmwasm_std::testing::MockStorage;
+    use secret_toolkit_incubator::CashMap;
+
+    fn entry(n: u32) -> SnapshotEntry {
+        SnapshotEntry {
+            address: HumanAddr(format!("offspring{}", n)),
+            label: format!("label{}", n),
+            owner: HumanAddr("owner".to_string()),
+            index: n,
+        }
+    }
+
+    fn new_build(id: u64) -> SnapshotBuild {
+        SnapshotBuild {
+            id,
+            revision: 0,
+            level: 0,
+            position: 0,
+            width: 0,
+            leaf_count: 0,
+        }
+    }
+
+    /// the merkle root computed in memory, level by level
+    fn expected_root(leaves: &[[u8; 32]]) -> [u8; 32] {
+        if leaves.is_empty() {
+            return [0u8; 32];
+        }
+        let mut level = leaves.to_vec();
+        while level.len() > 1 {
+            level = level
+                .chunks(2)
+                .map(|pair| match pair {
+                    [left, right] => node_hash(left, right),
+                    [single] => *single,
+                    _ => unreachable!(),
+                })
+                .collect();
+        }
+        level[0]
+    }
+
+    /// This test checks that a snapshot built one item per call has the expected root, that the
+    /// proof of every leaf leads to it, including the leaves carried up without a sibling, and
+    /// that the snapshot stays provable while the next one is built.
+    #[test]
+    fn test_merkle_proofs() {
+        let mut storage = MockStorage::default();
+        let api = MockApi::new(20);
+        let mut empty = new_build(0);
+        assert_eq!(run_build(&mut storage, &api, &mut empty, 1).unwrap(), (0, Some([0u8; 32])));
+
+        let mut leaves = Vec::new();
+        for n in 0..5 {
+            let entry = entry(n);
+            let offspring_addr = api.canonical_address(&entry.address).unwrap();
+            let info = StoreOffspringInfo {
+                address: entry.address.clone(),
+                label: entry.label.clone(),
+                description: None,
+                description_truncated: false,
+                metadata_url: None,
+                count_privacy: None,
+                attributes: vec![],
+                created_height: 1,
+                created_time: 1,
+                checksum: 0,
+            };
+            let mut info_store: CashMap<StoreOffspringInfo, _> =
+                CashMap::init(ACTIVE_KEY, &mut storage);
+            info_store.insert(offspring_addr.as_slice(), info).unwrap();
+            let mut owner_store = PrefixedStorage::new(PREFIX_OFFSPRING_OWNER, &mut storage);
+            save(&mut owner_store, offspring_addr.as_slice(), &entry.owner).unwrap();
+            leaves.push(leaf_hash(&entry));
+        }
+
+        // 5 leaves, then 3, 2 and 1 nodes
+        let mut build = new_build(1);
+        let mut calls = 0;
+        let root = loop {
+            calls += 1;
+            match run_build(&mut storage, &api, &mut build, 1).unwrap() {
+                (1, Some(root)) => break root,
+                (1, None) => continue,
+                other => panic!("unexpected progress {:?}", other),
+            }
+        };
+        assert_eq!(calls, 11);
+        assert_eq!(root, expected_root(&leaves));
+        let snapshot = RegistrySnapshot {
+            id: build.id,
+            root,
+            height: 1,
+            leaf_count: build.leaf_count,
+        };
+        assert_eq!(snapshot.leaf_count, 5);
+
+        // start the next snapshot without finishing it
+        let mut next = new_build(2);
+        run_build(&mut storage, &api, &mut next, 3).unwrap();
+        for n in 0..5 {
+            let offspring_addr = api.canonical_address(&entry(n).address).unwrap();
+            let found = snapshot_entry(&storage, &snapshot, &offspring_addr).unwrap().unwrap();
+            assert_eq!(found.index, n);
+            let mut hash = leaves[n as usize];
+            for node in merkle_proof(&storage, &snapshot, n).unwrap() {
+                let mut sibling = [0u8; 32];
+                sibling.copy_from_slice(node.hash.as_slice());
+                hash = if node.is_left {
+                    node_hash(&sibling, &hash)
+                } else {
+                    node_hash(&hash, &sibling)
+                };
+            }
+            assert_eq!(hash, snapshot.root);
+        }
+        // the last leaf has no sibling on the first level
+        assert_eq!(merkle_proof(&storage, &snapshot, 4).unwrap().len(), 1);
+        let stranger = api.canonical_address(&HumanAddr::from("stranger")).unwrap();
+        assert!(snapshot_entry(&storage, &snapshot, &stranger).unwrap().is_none());
+    }
+}
@@ -0,0 +1,28 @@
+use secret_toolkit::utils::HandleCallback;
+use serde::Serialize;
+
+use cosmwasm_std::{HumanAddr, Uint128};
+
+use crate::state::BLOCK_SIZE;
+
+/// SNIP-20 handle messages to be used by the factory
+#[derive(Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Snip20HandleMsg {
+    /// makes the token call the factory's Receive when the factory is sent tokens
+    RegisterReceive {
+        /// code hash of the factory
+        code_hash: String,
+    },
+    /// sends tokens the factory holds
+    Transfer {
+        /// address the tokens are sent to
+        recipient: HumanAddr,
+        /// amount of tokens to send
+        amount: Uint128,
+    },
+}
+
+impl HandleCallback for Snip20HandleMsg {
+    const BLOCK_SIZE: usize = BLOCK_SIZE;
+}
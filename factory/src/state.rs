@@ -2,11 +2,11 @@ use std::any::type_name;
 
 use serde::{de::DeserializeOwned, Deserialize, Serialize};
 
-use cosmwasm_std::{CanonicalAddr, ReadonlyStorage, StdError, StdResult, Storage};
+use cosmwasm_std::{CanonicalAddr, HumanAddr, ReadonlyStorage, StdError, StdResult, Storage};
 
 use secret_toolkit::serialization::{Bincode2, Serde};
 
-use crate::msg::OffspringContractInfo;
+use crate::msg::{CallbackPolicy, CreationRules, OffspringContractInfo};
 
 /// prefix for storage of owners' inactive offspring
 pub const PREFIX_OWNERS_INACTIVE: &[u8] = b"ownersinactive";
@@ -16,29 +16,391 @@ pub const PREFIX_OWNERS_ACTIVE: &[u8] = b"ownersactive";
 pub const PREFIX_ACTIVE_INFO: &[u8] = b"activeinfo";
 /// prefix for storage of a inactive offspring info
 pub const INACTIVE_KEY: &[u8] = b"inactiveinfo";
+/// prefix for storage of the owner of each registered offspring
+pub const PREFIX_OFFSPRING_OWNER: &[u8] = b"offspringowner";
+/// prefix for storage of the append-only chronological indexes of the global and owners' lists
+pub const PREFIX_LIST_ORDER: &[u8] = b"listorder";
+/// the number of entries a list's chronological index may hold beyond twice the list's length
+/// before it is compacted
+pub const ORDER_INDEX_SLACK: u32 = 16;
+/// prefix for storage of the latest position of each offspring in a list's chronological index
+pub const PREFIX_LIST_POSITIONS: &[u8] = b"listpositions";
+/// prefix for storage of the compaction under way of a list's chronological index
+pub const PREFIX_ORDER_COMPACTIONS: &[u8] = b"ordercompactions";
+/// storage key of a list's compaction, within the list's namespace
+pub const ORDER_COMPACTION_KEY: &[u8] = b"compaction";
+/// the number of chronological index entries a compaction processes with each append
+pub const ORDER_COMPACTION_STEPS: u32 = 4;
+/// storage key for the tombstones of purged offspring
+pub const TOMBSTONE_KEY: &[u8] = b"tombstones";
+/// prefix for storage of the offspring code ids whose creation has been disabled
+pub const PREFIX_DISABLED_VERSIONS: &[u8] = b"disabledversions";
+/// prefix for storage of the deprecation notices of offspring versions, by code hash
+pub const PREFIX_DEPRECATED_VERSIONS: &[u8] = b"deprecatedversions";
+/// storage key for the position in the active list where the next compaction pass resumes
+pub const COMPACT_CURSOR_KEY: &[u8] = b"compactcursor";
+/// storage key for the position in the pending offspring where the next expiry pass resumes
+pub const PENDING_CURSOR_KEY: &[u8] = b"pendingcursor";
+/// storage key for the position in the tombstones where the next sweep resumes
+pub const TOMBSTONE_CURSOR_KEY: &[u8] = b"tombstonecursor";
+/// storage key for the block height of the latest handle, which queries use as the current
+/// height because they have no Env
+pub const LAST_HEIGHT_KEY: &[u8] = b"lastheight";
+/// storage key for the task the next maintenance run starts with
+pub const MAINTENANCE_CURSOR_KEY: &[u8] = b"maintenancecursor";
+/// storage key for the block height of the last maintenance run
+pub const MAINTENANCE_HEIGHT_KEY: &[u8] = b"maintenanceheight";
+/// storage key for the version of the registry's storage layout the factory was initialized with
+pub const SCHEMA_VERSION_KEY: &[u8] = b"schemaversion";
+/// storage key for the address book of related system contracts
+pub const ADDRESS_BOOK_KEY: &[u8] = b"addressbook";
+/// prefix for storage of the referral stats of each referrer
+pub const PREFIX_REFERRALS: &[u8] = b"referrals";
+/// prefix for storage of the number of offspring created for each owner
+pub const PREFIX_CREATION_NONCES: &[u8] = b"creationnonces";
+/// prefix for storage of each owner's settings
+pub const PREFIX_OWNER_SETTINGS: &[u8] = b"ownersettings";
+/// prefix for storage of the admin audit log
+pub const PREFIX_AUDIT_LOG: &[u8] = b"auditlog";
+/// storage key for the progress of a registry import
+pub const IMPORT_PROGRESS_KEY: &[u8] = b"importprogress";
+/// prefix for storage of the code hash of each registered offspring
+pub const PREFIX_OFFSPRING_CODE_HASH: &[u8] = b"offspringcodehash";
+/// prefix for storage of the hash of the password each registered offspring registered with
+pub const PREFIX_OFFSPRING_PASSWORDS: &[u8] = b"offspringpasswords";
+/// prefix for storage of the registration index of each registered offspring
+pub const PREFIX_REGISTRATION_INDEX: &[u8] = b"registrationindex";
+/// storage key for the number of offspring registered so far
+pub const REGISTRATION_COUNT_KEY: &[u8] = b"registrationcount";
+/// prefix for storage of the pending transfer offer of each offspring
+pub const PREFIX_TRANSFER_OFFERS: &[u8] = b"transferoffers";
+/// prefix for storage of the delegates each owner has authorized
+pub const PREFIX_DELEGATES: &[u8] = b"delegates";
+/// storage prefix of the permits their signers revoked
+pub const PREFIX_REVOKED_PERMITS: &str = "revoked_permits";
+/// storage key for the number of creations per creation context
+pub const CONTEXTS_KEY: &[u8] = b"creationcontexts";
+/// prefix for storage of the addresses owners authorized to create offspring on their behalf
+pub const PREFIX_CREATE_DELEGATES: &[u8] = b"createdelegates";
+/// storage key for the recovery requests waiting for the admin's decision, by offspring address
+pub const RECOVERY_KEY: &[u8] = b"recovery";
+/// storage key for the creation fees collected and not withdrawn yet
+pub const COLLECTED_FEES_KEY: &[u8] = b"collectedfees";
+/// prefix for storage of the creation fees collected in SNIP-20 tokens, by token address
+pub const PREFIX_TOKEN_FEES: &[u8] = b"tokenfees";
+/// storage key for the registry revision, bumped on every change to the offspring lists
+pub const REGISTRY_REVISION_KEY: &[u8] = b"registryrevision";
+/// storage key for the latest registry snapshot
+pub const SNAPSHOT_KEY: &[u8] = b"snapshot";
+/// storage key for the progress of the registry snapshot being built
+pub const SNAPSHOT_BUILD_KEY: &[u8] = b"snapshotbuild";
+/// prefix for storage of registry snapshot entries, by slot and offspring address
+pub const PREFIX_SNAPSHOT_ENTRIES: &[u8] = b"snapshotentries";
+/// prefix for storage of registry snapshot tree nodes, by slot, level and position
+pub const PREFIX_SNAPSHOT_NODES: &[u8] = b"snapshotnodes";
+/// prefix for storage of the issue queue
+pub const PREFIX_ISSUES: &[u8] = b"issues";
+/// prefix for storage of each offspring's issue history
+pub const PREFIX_OFFSPRING_EVENTS: &[u8] = b"offspringevents";
+/// prefix for storage of each owner's usage statistics
+pub const PREFIX_OWNER_STATS: &[u8] = b"ownerstats";
+/// prefix for storage of the addresses that have a viewing key
+pub const PREFIX_HAS_KEY: &[u8] = b"haskey";
+/// prefix for storage of the registered offspring versions, by version id
+pub const PREFIX_VERSIONS: &[u8] = b"versions";
+/// prefix for storage of each creator's creations that did not register yet
+pub const PREFIX_PENDING_CREATIONS: &[u8] = b"pendingcreations";
+/// prefix for storage of the creator of each creation that did not register yet, by password hash
+pub const PREFIX_PENDING_CREATORS: &[u8] = b"pendingcreators";
+/// storage key for the addresses allowed to create offspring when the allow list is used
+pub const ALLOWED_CREATORS_KEY: &[u8] = b"allowedcreators";
+/// storage key for the addresses denied creating offspring
+pub const DENIED_CREATORS_KEY: &[u8] = b"deniedcreators";
+/// prefix for storage of each creator's current rate limit window
+pub const PREFIX_CREATION_WINDOWS: &[u8] = b"creationwindows";
+/// prefix for storage of the offspring addresses the admin blocked, with the reason
+pub const PREFIX_BLOCKED_ADDRESSES: &[u8] = b"blockedaddresses";
+/// storage prefix for the admin's private notes about owners
+pub const PREFIX_OWNER_NOTES: &[u8] = b"ownernotes";
 /// storage key for prng seed
 pub const PRNG_SEED_KEY: &[u8] = b"prngseed";
+/// storage key for the key private registry fields are sealed with. It is set once at init and
+/// never rewritten, unlike the prng seed
+pub const SEALING_KEY: &[u8] = b"sealingkey";
 /// storage key for the factory config
 pub const CONFIG_KEY: &[u8] = b"config";
 /// storage key for the active offspring list
 pub const ACTIVE_KEY: &[u8] = b"active";
-/// storage key for the password of the offspring we just instantiated
+/// storage key for the offspring created but not registered yet, by password hash
 pub const PENDING_KEY: &[u8] = b"pending";
+/// storage key for the registrations waiting for the admin's review
+pub const QUARANTINE_KEY: &[u8] = b"quarantine";
+/// storage prefix for the address of the offspring registered under each label
+pub const PREFIX_LABELS: &[u8] = b"labels";
+/// storage prefix for the key of the pending offspring each label is reserved for
+pub const PREFIX_RESERVED_LABELS: &[u8] = b"reservedlabels";
+/// denomination of the native coin used to pay for transferred offspring
+pub const NATIVE_DENOM: &str = "uscrt";
 /// pad handle responses and log attributes to blocks of 256 bytes to prevent leaking info based on
 /// response size
 pub const BLOCK_SIZE: usize = 256;
-/// the default number of offspring listed during queries
+/// version of the registry's storage layout. Bump it whenever the layout of the registry indexes
+/// changes
+pub const REGISTRY_SCHEMA_VERSION: u32 = 2;
+/// storage keys of the registry indexes, as reported by the RegistrySchema query
+pub const REGISTRY_INDEXES: &[&[u8]] = &[
+    ACTIVE_KEY,
+    INACTIVE_KEY,
+    PREFIX_OWNERS_ACTIVE,
+    PREFIX_OWNERS_INACTIVE,
+    PREFIX_OFFSPRING_OWNER,
+    TOMBSTONE_KEY,
+    PREFIX_LIST_ORDER,
+];
+/// ways the registry can be migrated to another factory, as reported by the RegistrySchema query
+pub const MIGRATION_PATHS: &[&str] = &["export_registry/import_registry"];
+/// version of this factory build
+pub const FACTORY_VERSION: &str = env!("CARGO_PKG_VERSION");
+/// commit this factory was built from, if GIT_COMMIT was set at build time
+pub const FACTORY_COMMIT: Option<&str> = option_env!("GIT_COMMIT");
+/// the default number of offspring listed during queries, unless configured otherwise at init
 pub const DEFAULT_PAGE_SIZE: u32 = 200;
+/// the default minimum length of user supplied entropy strings
+pub const DEFAULT_MIN_ENTROPY_LEN: u32 = 8;
+/// the default minimum number of distinct characters in user supplied entropy strings
+pub const DEFAULT_MIN_ENTROPY_DISTINCT: u32 = 4;
+/// the default number of units of deferred maintenance work performed by each handle
+pub const DEFAULT_MAINTENANCE_BUDGET: u32 = 3;
+/// the maximum number of units of deferred maintenance work performed by each handle
+pub const MAX_MAINTENANCE_BUDGET: u32 = 20;
+/// the default number of blocks a tombstone is retained after its offspring is purged
+pub const DEFAULT_TOMBSTONE_RETENTION: u64 = 100_000;
+/// the default number of blocks after its creation an offspring may retry its registration
+pub const DEFAULT_REGISTRATION_RETRY_BLOCKS: u64 = 14_400;
+/// the default number of registrations between two stats checkpoints in the logs
+pub const DEFAULT_STATS_CHECKPOINT_INTERVAL: u64 = 100;
+/// the maximum length of an address book name
+pub const MAX_ADDRESS_BOOK_NAME_LEN: usize = 64;
+/// the maximum number of custom attributes an offspring can have in the registry
+pub const MAX_OFFSPRING_ATTRIBUTES: usize = 8;
+/// the maximum length of a custom attribute key
+pub const MAX_ATTRIBUTE_KEY_LEN: usize = 32;
+/// the maximum length of a custom attribute value
+pub const MAX_ATTRIBUTE_VALUE_LEN: usize = 128;
+/// the maximum length of the context of a creation
+pub const MAX_CONTEXT_LEN: usize = 64;
+/// the maximum length of a flagged issue's category
+pub const MAX_ISSUE_CATEGORY_LEN: usize = 32;
+/// the maximum length of a flagged issue's details
+pub const MAX_ISSUE_DETAILS_LEN: usize = 512;
+/// the maximum length of an offspring's metadata url
+pub const MAX_METADATA_URL_LEN: usize = 256;
+/// the maximum length of the admin's note about an owner
+pub const MAX_OWNER_NOTE_LEN: usize = 1024;
+/// the maximum length of a version's deprecation note
+pub const MAX_DEPRECATION_NOTE_LEN: usize = 512;
+/// the maximum number of registry entries processed by a single compaction call
+pub const MAX_COMPACT_ITEMS: u32 = 100;
+/// the maximum number of registry entries and tree nodes processed by a single snapshot call
+pub const MAX_SNAPSHOT_ITEMS: u32 = 100;
+/// the maximum number of registry records in a single export batch
+pub const MAX_EXPORT_BATCH: u32 = 100;
+/// the maximum number of actions in a single admin batch
+pub const MAX_ADMIN_BATCH: usize = 10;
+/// the maximum number of offspring removed by a single RemoveOffspring message
+pub const MAX_REMOVE_BATCH: usize = 20;
+/// the maximum number of offspring created by a single BatchCreateOffspring message
+pub const MAX_BATCH_CREATE: usize = 10;
+/// the maximum number of offspring that can be created at init
+pub const MAX_BOOTSTRAP_OFFSPRING: usize = 5;
+/// the maximum number of registrations waiting for the admin's review
+pub const MAX_QUARANTINE: u32 = 50;
+/// the maximum number of tombstones checked for expiry each time an offspring is purged
+pub const TOMBSTONE_SWEEP_LIMIT: u32 = 10;
+/// the number of blocks in a bucket of the owners' usage statistics, about 30 days of 6 second
+/// blocks
+pub const STATS_BUCKET_BLOCKS: u64 = 432_000;
+/// api version of the current query answer format
+pub const API_VERSION: u32 = 4;
+/// oldest api version versioned queries can still be answered in
+pub const OLDEST_API_VERSION: u32 = 1;
+/// the number of creations waiting for registration above which the Health query reports the
+/// factory as unhealthy
+pub const HEALTHY_PENDING_LIMIT: u32 = 100;
 
 /// grouping the data primarily used when creating a new offspring
 #[derive(Serialize, Deserialize)]
 pub struct Config {
     /// code hash and address of the offspring contract
     pub version: OffspringContractInfo,
+    /// the version replaced by the last NewOffspringContract, which RollbackVersion restores
+    pub previous_version: Option<OffspringContractInfo>,
     /// factory's create offspring status
     pub stopped: bool,
     /// address of the factory admin
     pub admin: CanonicalAddr,
+    /// address the admin role was transferred to, until it accepts
+    pub pending_admin: Option<CanonicalAddr>,
+    /// number of blocks a tombstone is kept after its offspring is purged
+    pub tombstone_retention: u64,
+    /// number of entries listed by queries that do not specify a page size
+    pub default_page_size: u32,
+    /// true if the public offspring lists only show counts unless a valid viewing key is supplied
+    pub private_listing: bool,
+    /// true if owners creating their first offspring without a viewing key are given one
+    pub auto_viewing_key: bool,
+    /// true if an offspring must register in the same block it was created in, on behalf of the
+    /// owner it was created for
+    pub require_registration_same_tx: bool,
+    /// true if offspring can only be created by their owner or by an address the owner granted
+    /// creation rights to
+    pub require_owner_is_sender: bool,
+    /// minimum length of user supplied entropy strings
+    pub min_entropy_len: u32,
+    /// minimum number of distinct characters in user supplied entropy strings
+    pub min_entropy_distinct: u32,
+    /// number of units of deferred maintenance work performed by each handle. 0 disables it
+    pub maintenance_budget: u32,
+    /// address of this factory. Queries have no Env, so it is remembered for the labels they
+    /// generate
+    pub contract_address: HumanAddr,
+    /// funds attached to the messages sent to offspring
+    pub callback_policy: CallbackPolicy,
+    /// number of blocks after its creation an offspring may retry its registration. 0 disables
+    /// retries
+    pub registration_retry_blocks: u64,
+    /// true if registrations the factory can not authenticate are held for the admin's review
+    /// instead of being rejected
+    pub quarantine_registrations: bool,
+    /// settings of the optional steps of the creation pipeline
+    pub creation_rules: CreationRules,
+    /// bech32 prefix that addresses the factory stores must have, if any
+    pub address_prefix: Option<String>,
+    /// number of characters of a description the registry keeps, if limited
+    pub description_limit: Option<u32>,
+    /// bit set of the enabled optional features
+    pub features: u32,
+    /// number of registrations between two stats checkpoints in the logs. 0 disables them
+    pub stats_checkpoint_interval: u64,
+}
+
+/// a creator's current rate limit window
+#[derive(Serialize, Deserialize, Default)]
+pub struct CreationWindow {
+    /// block height the window started at
+    pub start: u64,
+    /// number of offspring created in the window
+    pub count: u32,
+}
+
+/// a creation that did not register yet, counted against its creator's pending limit
+#[derive(Serialize, Deserialize, Clone, Copy)]
+pub struct PendingCreation {
+    /// hash of the password the offspring registers with
+    pub key: [u8; 32],
+    /// block height the offspring was created at
+    pub height: u64,
+}
+
+/// an offspring the factory is waiting to register
+#[derive(Serialize, Deserialize, Clone)]
+pub struct PendingOffspring {
+    /// hash of the password the offspring must return when registering
+    pub key: [u8; 32],
+    /// label the offspring was created with
+    pub label: String,
+    /// block height the offspring was created at
+    pub height: u64,
+    /// last block height the offspring may register at
+    pub expires_at: u64,
+    /// address of the owner the offspring was created for
+    pub owner: CanonicalAddr,
+    /// code hash of the version the offspring was created from
+    pub code_hash: String,
+}
+
+/// position in the registry export encoded in a continuation token
+#[derive(Serialize, Deserialize)]
+pub struct ExportCursor {
+    /// sequence number of the batch this cursor starts
+    pub batch: u32,
+    /// true once the active list has been exported and the inactive list is being exported
+    pub inactive: bool,
+    /// position in the list being exported
+    pub position: u32,
+    /// registry revision a registry export started at. Positions only stay valid while the
+    /// registry does not change
+    pub revision: Option<u64>,
+}
+
+/// progress of importing an exported registry
+#[derive(Serialize, Deserialize, Default)]
+pub struct ImportProgress {
+    /// continuation token the next batch must have been exported with. None if the next batch
+    /// must be the first one
+    pub expected_token: Option<String>,
+    /// true once the last batch has been applied
+    pub done: bool,
+}
+
+/// registry entry committed to by a snapshot
+#[derive(Serialize, Deserialize)]
+pub struct SnapshotEntry {
+    /// address of the offspring
+    pub address: HumanAddr,
+    /// label of the offspring
+    pub label: String,
+    /// address of the offspring's owner
+    pub owner: HumanAddr,
+    /// position of the entry's leaf in the snapshot
+    pub index: u32,
+}
+
+/// merkle snapshot of the active registry
+#[derive(Serialize, Deserialize)]
+pub struct RegistrySnapshot {
+    /// id of the snapshot. Its parity selects the storage slot of the entries and tree nodes
+    pub id: u64,
+    /// merkle root over the entries
+    pub root: [u8; 32],
+    /// block height the snapshot was taken at
+    pub height: u64,
+    /// number of entries, in registry order
+    pub leaf_count: u32,
+}
+
+/// progress of the registry snapshot being built
+#[derive(Serialize, Deserialize)]
+pub struct SnapshotBuild {
+    /// id the snapshot will have
+    pub id: u64,
+    /// registry revision the build started at. The build starts over if the registry changes
+    pub revision: u64,
+    /// tree level being built, where level 0 holds the leaves
+    pub level: u32,
+    /// position of the next registry entry or node of the level
+    pub position: u32,
+    /// number of nodes of the level below, once the leaves are built
+    pub width: u32,
+    /// number of leaves, once they are built
+    pub leaf_count: u32,
+}
+
+/// progress of the compaction of a list's chronological index
+#[derive(Serialize, Deserialize)]
+pub struct OrderCompaction {
+    /// position of the next index entry to read
+    pub read: u32,
+    /// position the next entry that is kept is moved to
+    pub write: u32,
+}
+
+/// settings an owner chose for themselves
+#[derive(Serialize, Deserialize, Default)]
+pub struct OwnerSettings {
+    /// number of entries listed by the owner's queries that do not specify a page size. If not
+    /// set, the factory's default page size is used
+    pub page_size: Option<u32>,
 }
 
 /// Returns StdResult<()> resulting from saving an item to storage
@@ -0,0 +1,145 @@
+use cosmwasm_std::{
+    log, to_binary, Api, Extern, HumanAddr, LogAttribute, Querier, QueryResult, ReadonlyStorage,
+    StdResult, Storage,
+};
+
+use cosmwasm_storage::{PrefixedStorage, ReadonlyPrefixedStorage};
+
+use secret_toolkit::storage::{AppendStore, AppendStoreMut};
+
+use secret_toolkit_incubator::ReadOnlyCashMap;
+
+use crate::state::{
+    may_load, Config, ACTIVE_KEY, PREFIX_OWNER_STATS, REGISTRATION_COUNT_KEY, STATS_BUCKET_BLOCKS,
+};
+
+use crate::{
+    addr::normalize_address,
+    auth::is_key_valid,
+    msg::{QueryAnswer, StoreOffspringInfo, UsageBucket},
+    queries::resolve_page_size,
+};
+
+/// kinds of owner activity counted in the usage statistics
+pub enum Usage {
+    /// an offspring of the owner was registered
+    Created,
+    /// an offspring of the owner was deactivated
+    Deactivated,
+}
+
+/// Returns StdResult<Vec<LogAttribute>> with a checkpoint of the registry's totals if the last
+/// registration was a multiple of the checkpoint interval, or no attributes otherwise. Indexers
+/// compare the checkpoints with the events they followed to notice ones they missed
+///
+/// # Arguments
+///
+/// * `storage` - a reference to the contract's storage
+/// * `config` - a reference to the factory config
+pub fn checkpoint_log<S: ReadonlyStorage>(
+    storage: &S,
+    config: &Config,
+) -> StdResult<Vec<LogAttribute>> {
+    let created: u64 = may_load(storage, REGISTRATION_COUNT_KEY)?.unwrap_or(0);
+    let interval = config.stats_checkpoint_interval;
+    // an interval of 0 has no remainder, which disables checkpoints
+    if created == 0 || created.checked_rem(interval) != Some(0) {
+        return Ok(vec![]);
+    }
+    let active_store: ReadOnlyCashMap<StoreOffspringInfo, _> =
+        ReadOnlyCashMap::init(ACTIVE_KEY, storage);
+    Ok(vec![
+        log("stats_checkpoint", created / interval),
+        log("total_active", active_store.len()),
+        log("total_created", created),
+    ])
+}
+
+/// Returns StdResult<()>
+///
+/// counts an offspring creation or deactivation in the owner's bucket of the current block
+/// height. Buckets without activity are not stored
+///
+/// # Arguments
+///
+/// * `storage` - mutable reference to contract's storage
+/// * `api` - reference to the Api used to convert human and canonical addresses
+/// * `owner` - a reference to the owner's address
+/// * `usage` - the kind of activity
+/// * `height` - current block height
+pub fn record_usage<S: Storage, A: Api>(
+    storage: &mut S,
+    api: &A,
+    owner: &HumanAddr,
+    usage: Usage,
+    height: u64,
+) -> StdResult<()> {
+    let owner_addr = api.canonical_address(owner)?;
+    let mut stats_store =
+        PrefixedStorage::multilevel(&[PREFIX_OWNER_STATS, owner_addr.as_slice()], storage);
+    let mut buckets = AppendStoreMut::<UsageBucket, _>::attach_or_create(&mut stats_store)?;
+    let start_height = height - height % STATS_BUCKET_BLOCKS;
+    let last = buckets.len().checked_sub(1);
+    let (position, mut bucket) = match last.map(|pos| buckets.get_at(pos)).transpose()? {
+        Some(bucket) if bucket.start_height == start_height => (last, bucket),
+        _ => (
+            None,
+            UsageBucket {
+                start_height,
+                created: 0,
+                deactivated: 0,
+            },
+        ),
+    };
+    match usage {
+        Usage::Created => bucket.created += 1,
+        Usage::Deactivated => bucket.deactivated += 1,
+    }
+    match position {
+        Some(pos) => buckets.set_at(pos, &bucket),
+        None => buckets.push(&bucket),
+    }
+}
+
+/// Returns QueryResult listing the owner's usage statistics, most recent bucket first
+///
+/// # Arguments
+///
+/// * `deps` - reference to Extern containing all the contract's external dependencies
+/// * `address` - a reference to the owner's address
+/// * `viewing_key` - String key used to authenticate the query
+/// * `start_page` - optional start page for the buckets returned and listed
+/// * `page_size` - optional number of buckets to return in this page
+pub fn try_usage_stats<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    address: &HumanAddr,
+    viewing_key: String,
+    start_page: Option<u32>,
+    page_size: Option<u32>,
+) -> QueryResult {
+    let address = &normalize_address(&deps.api, address)?;
+    if !is_key_valid(&deps.storage, address, viewing_key) {
+        return to_binary(&QueryAnswer::ViewingKeyError {
+            error: "Wrong viewing key for this address or viewing key not set".to_string(),
+        });
+    }
+    let size = resolve_page_size(deps, Some(address), page_size)?;
+    let owner_addr = deps.api.canonical_address(address)?;
+    let stats_store = ReadonlyPrefixedStorage::multilevel(
+        &[PREFIX_OWNER_STATS, owner_addr.as_slice()],
+        &deps.storage,
+    );
+    let buckets = match AppendStore::<UsageBucket, _>::attach(&stats_store) {
+        Some(bucket_log) => bucket_log?
+            .iter()
+            .rev()
+            .skip((start_page.unwrap_or(0) * size) as usize)
+            .take(size as usize)
+            .collect::<StdResult<Vec<UsageBucket>>>()?,
+        None => vec![],
+    };
+    to_binary(&QueryAnswer::UsageStats {
+        bucket_blocks: STATS_BUCKET_BLOCKS,
+        buckets,
+    })
+}
@@ -0,0 +1,57 @@
+//! Deterministic versions of the factory's password and viewing key derivations. They take
+//! fixed inputs instead of an Env, so refactors of the derivation code can be checked against
+//! outputs recorded from already deployed factories and offspring. Only compiled with the
+//! "testing" feature.
+
+use crate::creation::{derive_entropy, derive_init_seed, derive_key_entropy, derive_password};
+
+/// Returns Vec<u8> with the prng seed a factory initialized with `entropy` starts with
+///
+/// # Arguments
+///
+/// * `entropy` - the entropy string passed to init
+pub fn init_prng_seed(entropy: &str) -> Vec<u8> {
+    derive_init_seed(entropy)
+}
+
+/// Returns ([u8;32], [u8;32]) with the new prng seed and the password of an offspring created
+/// with the given inputs
+///
+/// # Arguments
+///
+/// * `prng_seed` - the factory's prng seed before the creation
+/// * `entropy` - the entropy string passed to CreateOffspring
+/// * `height` - block height of the creation
+/// * `time` - block time of the creation
+/// * `sender` - address that sent CreateOffspring
+pub fn offspring_password(
+    prng_seed: &[u8],
+    entropy: &str,
+    height: u64,
+    time: u64,
+    sender: &str,
+) -> ([u8; 32], [u8; 32]) {
+    let prng_bytes = derive_entropy(height, time, sender, prng_seed, entropy.as_bytes());
+    (prng_bytes, derive_password(&prng_bytes))
+}
+
+/// Returns [u8;32] with the entropy of the viewing key an owner is given when creating their
+/// first offspring with the given inputs
+///
+/// # Arguments
+///
+/// * `prng_seed` - the factory's prng seed before the creation
+/// * `entropy` - the entropy string passed to CreateOffspring
+/// * `height` - block height of the creation
+/// * `time` - block time of the creation
+/// * `sender` - address that sent CreateOffspring
+pub fn auto_viewing_key_entropy(
+    prng_seed: &[u8],
+    entropy: &str,
+    height: u64,
+    time: u64,
+    sender: &str,
+) -> [u8; 32] {
+    let prng_bytes = derive_entropy(height, time, sender, prng_seed, entropy.as_bytes());
+    derive_key_entropy(&prng_bytes)
+}
@@ -0,0 +1,242 @@
+use cosmwasm_std::{
+    Api, BankMsg, CanonicalAddr, Coin, CosmosMsg, Env, Extern, HandleResult, HumanAddr, Querier,
+    ReadonlyStorage, StdError, StdResult, Storage, Uint128,
+};
+
+use cosmwasm_storage::{PrefixedStorage, ReadonlyPrefixedStorage};
+
+use secret_toolkit::utils::HandleCallback;
+
+use crate::state::{
+    load, may_load, remove, save, Config, CONFIG_KEY, NATIVE_DENOM, PREFIX_OFFSPRING_OWNER,
+    PREFIX_TRANSFER_OFFERS,
+};
+
+use crate::{
+    addr::validate_address,
+    creation::authenticate_offspring,
+    msg::{HandleAnswer, ResponseStatus::Success, TransferOffer},
+    offspring_msg::OffspringHandleMsg,
+    registry,
+    response::{ok_empty, ok_status, ok_with_msgs},
+};
+
+/// Returns HandleResult
+///
+/// offers one of the sender's active offspring to another address, replacing any previous offer
+/// of the same offspring
+///
+/// # Arguments
+///
+/// * `deps` - mutable reference to Extern containing all the contract's external dependencies
+/// * `env` - Env of contract's environment
+/// * `offspring` - address of the offspring
+/// * `recipient` - address the offspring is offered to
+/// * `price` - optional price in uscrt the recipient must pay
+pub fn try_offer_transfer<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    offspring: HumanAddr,
+    recipient: HumanAddr,
+    price: Option<Uint128>,
+) -> HandleResult {
+    let offspring_addr = deps.api.canonical_address(&offspring)?;
+    let recipient = validate_address(&deps.storage, &deps.api, &recipient)?;
+    if recipient == env.message.sender {
+        return Err(StdError::generic_err("You can not offer an offspring to yourself"));
+    }
+    enforce_owner(&deps.storage, &offspring_addr, &env.message.sender)?;
+
+    let offer = TransferOffer {
+        offspring,
+        owner: env.message.sender,
+        recipient,
+        price,
+    };
+    let mut offer_store = PrefixedStorage::new(PREFIX_TRANSFER_OFFERS, &mut deps.storage);
+    save(&mut offer_store, offspring_addr.as_slice(), &offer)?;
+
+    ok_status()
+}
+
+/// Returns HandleResult
+///
+/// accepts a transfer offer made to the sender. The offspring moves to the sender's lists, the
+/// offspring is told about its new owner, and the price, if any, is paid to the previous owner
+///
+/// # Arguments
+///
+/// * `deps` - mutable reference to Extern containing all the contract's external dependencies
+/// * `env` - Env of contract's environment
+/// * `offspring` - address of the offspring
+pub fn try_accept_transfer<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    offspring: HumanAddr,
+) -> HandleResult {
+    let offspring_addr = deps.api.canonical_address(&offspring)?;
+    let offer = load_offer(&deps.storage, &offspring_addr)?
+        .filter(|offer| offer.recipient == env.message.sender)
+        .ok_or_else(|| {
+            StdError::generic_err("There is no transfer offer of this offspring to you")
+        })?;
+    // the offer is stale if the offspring changed hands or was deactivated since it was made
+    enforce_owner(&deps.storage, &offspring_addr, &offer.owner)?;
+
+    let mut messages: Vec<CosmosMsg> = vec![];
+    let paid: u128 = env
+        .message
+        .sent_funds
+        .iter()
+        .map(|coin| {
+            if coin.denom == NATIVE_DENOM {
+                Ok(coin.amount.u128())
+            } else {
+                Err(StdError::generic_err(format!("Only {} is accepted", NATIVE_DENOM)))
+            }
+        })
+        .sum::<StdResult<u128>>()?;
+    let price = offer.price.map(|price| price.u128()).unwrap_or(0);
+    if paid != price {
+        return Err(StdError::generic_err(format!(
+            "This transfer costs exactly {}{}",
+            price, NATIVE_DENOM
+        )));
+    }
+    if price > 0 {
+        messages.push(CosmosMsg::Bank(BankMsg::Send {
+            from_address: env.contract.address.clone(),
+            to_address: offer.owner.clone(),
+            amount: vec![Coin {
+                denom: NATIVE_DENOM.to_string(),
+                amount: Uint128(price),
+            }],
+        }));
+    }
+
+    registry::transfer(&mut deps.storage, &offspring_addr, &offer.owner, &offer.recipient)?;
+    let mut offer_store = PrefixedStorage::new(PREFIX_TRANSFER_OFFERS, &mut deps.storage);
+    remove(&mut offer_store, offspring_addr.as_slice());
+
+    // let the offspring know its new owner
+    let config: Config = load(&deps.storage, CONFIG_KEY)?;
+    let code_hash = registry::code_hash(&deps.storage, &offspring_addr)?;
+    messages.push(
+        OffspringHandleMsg::ChangeOwner {
+            owner: offer.recipient,
+        }
+        .to_cosmos_msg(code_hash, offspring, config.callback_policy.change_owner)?,
+    );
+
+    ok_with_msgs(
+        messages,
+        HandleAnswer::Status {
+            status: Success,
+            message: None,
+        },
+    )
+}
+
+/// Returns HandleResult
+///
+/// cancels a transfer offer. Can be called by the owner or the recipient of the offer
+///
+/// # Arguments
+///
+/// * `deps` - mutable reference to Extern containing all the contract's external dependencies
+/// * `env` - Env of contract's environment
+/// * `offspring` - address of the offspring
+pub fn try_cancel_transfer<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    offspring: HumanAddr,
+) -> HandleResult {
+    let offspring_addr = deps.api.canonical_address(&offspring)?;
+    load_offer(&deps.storage, &offspring_addr)?
+        .filter(|offer| offer.owner == env.message.sender || offer.recipient == env.message.sender)
+        .ok_or_else(|| {
+            StdError::generic_err("There is no transfer offer of this offspring for you to cancel")
+        })?;
+    let mut offer_store = PrefixedStorage::new(PREFIX_TRANSFER_OFFERS, &mut deps.storage);
+    remove(&mut offer_store, offspring_addr.as_slice());
+
+    ok_status()
+}
+
+/// Returns HandleResult
+///
+/// moves the calling offspring from its owner's lists to the new owner's lists after its owner
+/// transferred it directly. A pending transfer offer of the offspring is void
+///
+/// # Arguments
+///
+/// * `deps` - mutable reference to Extern containing all the contract's external dependencies
+/// * `env` - Env of contract's environment
+/// * `new_owner` - address of the offspring's new owner
+pub fn try_update_offspring_owner<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    new_owner: HumanAddr,
+) -> HandleResult {
+    let offspring_addr = deps.api.canonical_address(&env.message.sender)?;
+
+    // verify offspring is in active list, and not a spam attempt
+    authenticate_offspring(&deps.storage, &offspring_addr)?;
+    let new_owner = validate_address(&deps.storage, &deps.api, &new_owner)?;
+    let owner: HumanAddr = load(
+        &ReadonlyPrefixedStorage::new(PREFIX_OFFSPRING_OWNER, &deps.storage),
+        offspring_addr.as_slice(),
+    )?;
+    if new_owner == owner {
+        return Err(StdError::generic_err("The new owner already owns this offspring"));
+    }
+    registry::transfer(&mut deps.storage, &offspring_addr, &owner, &new_owner)?;
+    let mut offer_store = PrefixedStorage::new(PREFIX_TRANSFER_OFFERS, &mut deps.storage);
+    remove(&mut offer_store, offspring_addr.as_slice());
+
+    ok_empty()
+}
+
+/// Returns StdResult<Option<TransferOffer>> from retrieving the pending transfer offer of an
+/// offspring
+///
+/// # Arguments
+///
+/// * `storage` - a reference to the contract's storage
+/// * `offspring_addr` - a reference to the canonical address of the offspring
+pub fn load_offer<S: ReadonlyStorage>(
+    storage: &S,
+    offspring_addr: &CanonicalAddr,
+) -> StdResult<Option<TransferOffer>> {
+    may_load(
+        &ReadonlyPrefixedStorage::new(PREFIX_TRANSFER_OFFERS, storage),
+        offspring_addr.as_slice(),
+    )
+}
+
+/// Returns StdResult<()>
+///
+/// makes sure the offspring is active and owned by the given address
+///
+/// # Arguments
+///
+/// * `storage` - a reference to the contract's storage
+/// * `offspring_addr` - a reference to the canonical address of the offspring
+/// * `owner` - a reference to the address expected to own the offspring
+fn enforce_owner<S: ReadonlyStorage>(
+    storage: &S,
+    offspring_addr: &CanonicalAddr,
+    owner: &HumanAddr,
+) -> StdResult<()> {
+    authenticate_offspring(storage, offspring_addr)?;
+    let current_owner: HumanAddr = load(
+        &ReadonlyPrefixedStorage::new(PREFIX_OFFSPRING_OWNER, storage),
+        offspring_addr.as_slice(),
+    )?;
+    if current_owner != *owner {
+        return Err(StdError::generic_err(
+            "This offspring is not active or not owned by the offering address",
+        ));
+    }
+    Ok(())
+}
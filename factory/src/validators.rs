@@ -0,0 +1,990 @@
+use cosmwasm_std::{
+    to_binary, Api, CanonicalAddr, Coin, CosmosMsg, Env, Extern, HumanAddr, Querier, QueryResult,
+    ReadonlyStorage, StdError, StdResult, Storage, Uint128,
+};
+
+use secret_toolkit::utils::HandleCallback;
+
+use cosmwasm_storage::{PrefixedStorage, ReadonlyPrefixedStorage};
+
+use crate::addr::normalize_address;
+use crate::auth::is_key_valid;
+use crate::creation::is_version_enabled;
+use crate::msg::{
+    CreationRules, CreationStep, QueryAnswer, RateLimit, ReferralStats, TokenEarnings,
+};
+use crate::rand::sha_256;
+use crate::registry::{current_height, offspring_by_label};
+use crate::snip20_msg::Snip20HandleMsg;
+use crate::state::{
+    load, may_load, remove, save, Config, CreationWindow, PendingCreation, PendingOffspring,
+    COLLECTED_FEES_KEY, CONFIG_KEY, NATIVE_DENOM, PENDING_KEY, ALLOWED_CREATORS_KEY, DENIED_CREATORS_KEY, PREFIX_CREATION_WINDOWS,
+    PREFIX_PENDING_CREATIONS, PREFIX_PENDING_CREATORS, PREFIX_REFERRALS, PREFIX_RESERVED_LABELS,
+    PREFIX_TOKEN_FEES,
+};
+
+use secret_toolkit_incubator::ReadOnlyCashMap;
+
+/// the creation pipeline, in the order the steps run
+pub const PIPELINE: &[CreationStep] = &[
+    CreationStep::Status,
+    CreationStep::AllowList,
+    CreationStep::Fee,
+    CreationStep::RateLimit,
+    CreationStep::PendingLimit,
+    CreationStep::LabelRules,
+];
+
+/// Returns StdResult<()>
+///
+/// checks that an offspring version's code hash is 64 hexadecimal characters
+///
+/// # Arguments
+///
+/// * `code_hash` - code hash of the offspring version
+pub fn valid_code_hash(code_hash: &str) -> StdResult<()> {
+    if code_hash.len() != 64 || !code_hash.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(StdError::generic_err(
+            "The code hash must be 64 hexadecimal characters",
+        ));
+    }
+    Ok(())
+}
+
+/// Returns StdResult<CreationRules> with the creation rules if they are usable
+///
+/// # Arguments
+///
+/// * `rules` - the creation rules to validate
+pub fn valid_creation_rules(rules: CreationRules) -> StdResult<CreationRules> {
+    if let Some(limit) = rules.rate_limit {
+        if limit.max_creations == 0 || limit.window_blocks == 0 {
+            return Err(StdError::generic_err(
+                "A rate limit must allow at least one creation in a window of at least one block",
+            ));
+        }
+    }
+    if rules.max_pending == Some(0) {
+        return Err(StdError::generic_err(
+            "The pending limit must allow at least one creation",
+        ));
+    }
+    if let Some(fee) = &rules.token_fee {
+        if fee.amount.u128() == 0 {
+            return Err(StdError::generic_err(
+                "A token fee must be greater than zero",
+            ));
+        }
+    }
+    if rules.max_label_len == Some(0) {
+        return Err(StdError::generic_err(
+            "The maximum label length must be at least 1",
+        ));
+    }
+    if matches!(rules.referral_share, Some(share) if share > 10_000) {
+        return Err(StdError::generic_err(
+            "The referral share can be at most 10000 basis points",
+        ));
+    }
+    if rules
+        .disabled_steps
+        .iter()
+        .any(|step| matches!(step, CreationStep::Status | CreationStep::LabelRules))
+    {
+        return Err(StdError::generic_err(
+            "The status and label checks can not be disabled",
+        ));
+    }
+    Ok(rules)
+}
+
+/// what the creation pipeline knows about a creation
+pub struct CreationRequest<'a> {
+    /// canonical address of the creator
+    pub creator: &'a CanonicalAddr,
+    /// funds sent with the creation, or None if they are not known yet
+    pub sent_funds: Option<&'a [Coin]>,
+    /// true if the creation fee was paid in the token of the creation rules' token fee
+    pub token_fee_paid: bool,
+    /// label given to the creation, if any
+    pub label: Option<&'a str>,
+    /// current block height, or None if it is not known yet
+    pub height: Option<u64>,
+    /// code id of the offspring version to create
+    pub code_id: u64,
+}
+
+/// Returns Vec<String> with the reason of every enabled step of the pipeline the creation fails,
+/// which is empty if the creation may go ahead
+///
+/// # Arguments
+///
+/// * `storage` - a reference to the contract's storage
+/// * `config` - a reference to the factory config
+/// * `request` - a reference to the creation being validated
+pub fn run_pipeline<S: ReadonlyStorage>(
+    storage: &S,
+    config: &Config,
+    request: &CreationRequest,
+) -> Vec<String> {
+    PIPELINE
+        .iter()
+        .filter(|step| !config.creation_rules.disabled_steps.contains(step))
+        .filter_map(|step| check(*step, storage, config, request).err())
+        .map(|err| match err {
+            StdError::GenericErr { msg, .. } => msg,
+            other => other.to_string(),
+        })
+        .collect()
+}
+
+/// Returns StdResult<()>
+///
+/// runs one step of the creation pipeline
+///
+/// # Arguments
+///
+/// * `step` - the step to run
+/// * `storage` - a reference to the contract's storage
+/// * `config` - a reference to the factory config
+/// * `request` - a reference to the creation being validated
+pub fn check<S: ReadonlyStorage>(
+    step: CreationStep,
+    storage: &S,
+    config: &Config,
+    request: &CreationRequest,
+) -> StdResult<()> {
+    match step {
+        CreationStep::Status => check_status(storage, config, request),
+        CreationStep::AllowList => check_allow_list(storage, config, request),
+        CreationStep::Fee => check_fee(config, request),
+        CreationStep::RateLimit => check_rate_limit(storage, config, request),
+        CreationStep::PendingLimit => check_pending_limit(storage, config, request),
+        CreationStep::LabelRules => check_label(storage, config, request),
+    }
+}
+
+fn check_status<S: ReadonlyStorage>(
+    storage: &S,
+    config: &Config,
+    request: &CreationRequest,
+) -> StdResult<()> {
+    if config.stopped {
+        return Err(StdError::generic_err(
+            "The factory has been stopped. No new offspring can be created",
+        ));
+    }
+    if !is_version_enabled(storage, request.code_id) {
+        return Err(StdError::generic_err(
+            "Creation of offspring from this version has been disabled",
+        ));
+    }
+    Ok(())
+}
+
+fn check_allow_list<S: ReadonlyStorage>(
+    storage: &S,
+    config: &Config,
+    request: &CreationRequest,
+) -> StdResult<()> {
+    let denied_store: ReadOnlyCashMap<HumanAddr, _> =
+        ReadOnlyCashMap::init(DENIED_CREATORS_KEY, storage);
+    if denied_store.contains(request.creator.as_slice()) {
+        return Err(StdError::generic_err(
+            "The admin denied this address creating offspring",
+        ));
+    }
+    if !config.creation_rules.allow_list {
+        return Ok(());
+    }
+    let allowed_store: ReadOnlyCashMap<HumanAddr, _> =
+        ReadOnlyCashMap::init(ALLOWED_CREATORS_KEY, storage);
+    if !allowed_store.contains(request.creator.as_slice()) {
+        return Err(StdError::generic_err(
+            "Only addresses allowed by the admin can create offspring",
+        ));
+    }
+    Ok(())
+}
+
+fn check_fee(config: &Config, request: &CreationRequest) -> StdResult<()> {
+    // Receive already checked the amount of the token fee
+    if request.token_fee_paid {
+        return Ok(());
+    }
+    // the funds of a creation that is only being previewed are not known
+    let sent_funds = match request.sent_funds {
+        Some(sent_funds) => sent_funds,
+        None => return Ok(()),
+    };
+    let fee = native_fee(config)?;
+    if native_amount(sent_funds)? != fee {
+        return Err(StdError::generic_err(format!(
+            "Creating an offspring costs exactly {}{}",
+            fee, NATIVE_DENOM
+        )));
+    }
+    Ok(())
+}
+
+/// Returns StdResult<Option<CosmosMsg>> with the message registering the factory as a receiver
+/// of the creation rules' fee token, if they set a token fee
+///
+/// # Arguments
+///
+/// * `env` - a reference to the Env of contract's environment
+/// * `rules` - a reference to the creation rules
+pub fn register_fee_token(env: &Env, rules: &CreationRules) -> StdResult<Option<CosmosMsg>> {
+    rules
+        .token_fee
+        .as_ref()
+        .map(|fee| {
+            Snip20HandleMsg::RegisterReceive {
+                code_hash: env.contract_code_hash.clone(),
+            }
+            .to_cosmos_msg(fee.token.code_hash.clone(), fee.token.address.clone(), None)
+        })
+        .transpose()
+}
+
+/// Returns StdResult<u128> with the uscrt each creation costs, failing if the creation rules
+/// only accept the fee in a token
+///
+/// # Arguments
+///
+/// * `config` - a reference to the factory config
+pub fn native_fee(config: &Config) -> StdResult<u128> {
+    match (config.creation_rules.fee, &config.creation_rules.token_fee) {
+        (Some(fee), _) => Ok(fee.u128()),
+        (None, Some(_)) => Err(StdError::generic_err(
+            "The creation fee can only be paid by sending the token fee with an embedded CreateOffspring",
+        )),
+        (None, None) => Ok(0),
+    }
+}
+
+/// Returns StdResult<()>
+///
+/// adds paid creation fees to the fees the admin can withdraw, after crediting the referral
+/// share to the referrer of the creation
+///
+/// # Arguments
+///
+/// * `storage` - mutable reference to contract's storage
+/// * `config` - a reference to the factory config
+/// * `amount` - uscrt paid
+/// * `referrer` - optional canonical address of the referrer of the creation
+pub fn collect_fees<S: Storage>(
+    storage: &mut S,
+    config: &Config,
+    amount: u128,
+    referrer: Option<&CanonicalAddr>,
+) -> StdResult<()> {
+    if amount == 0 {
+        return Ok(());
+    }
+    let credited = credit_referrer(storage, config, amount, referrer, None)?;
+    let collected = collected_fees(storage)?;
+    save(storage, COLLECTED_FEES_KEY, &collected.saturating_add(amount - credited))
+}
+
+/// Returns StdResult<u128> with the uscrt of creation fees collected and not withdrawn yet
+///
+/// # Arguments
+///
+/// * `storage` - a reference to the contract's storage
+pub fn collected_fees<S: ReadonlyStorage>(storage: &S) -> StdResult<u128> {
+    Ok(may_load(storage, COLLECTED_FEES_KEY)?.unwrap_or(0))
+}
+
+/// Returns StdResult<()>
+///
+/// adds creation fees paid in a SNIP-20 token to the fees the admin can withdraw, after
+/// crediting the referral share to the referrer of the creation
+///
+/// # Arguments
+///
+/// * `storage` - mutable reference to contract's storage
+/// * `config` - a reference to the factory config
+/// * `token` - address of the token
+/// * `amount` - amount of the token paid
+/// * `referrer` - optional canonical address of the referrer of the creation
+pub fn collect_token_fees<S: Storage>(
+    storage: &mut S,
+    config: &Config,
+    token: &HumanAddr,
+    amount: u128,
+    referrer: Option<&CanonicalAddr>,
+) -> StdResult<()> {
+    let credited = credit_referrer(storage, config, amount, referrer, Some(token))?;
+    let collected = collected_token_fees(storage, token)?;
+    let mut fee_store = PrefixedStorage::new(PREFIX_TOKEN_FEES, storage);
+    save(
+        &mut fee_store,
+        token.as_str().as_bytes(),
+        &collected.saturating_add(amount - credited),
+    )
+}
+
+/// Returns StdResult<u128> with the part of a creation fee credited to the referrer of the
+/// creation
+///
+/// # Arguments
+///
+/// * `storage` - mutable reference to contract's storage
+/// * `config` - a reference to the factory config
+/// * `amount` - amount of the fee paid
+/// * `referrer` - optional canonical address of the referrer of the creation
+/// * `token` - address of the token the fee was paid in, or None if it was paid in uscrt
+fn credit_referrer<S: Storage>(
+    storage: &mut S,
+    config: &Config,
+    amount: u128,
+    referrer: Option<&CanonicalAddr>,
+    token: Option<&HumanAddr>,
+) -> StdResult<u128> {
+    let (referrer, share) = match (referrer, config.creation_rules.referral_share) {
+        (Some(referrer), Some(share)) => (referrer, u128::from(share)),
+        _ => return Ok(0),
+    };
+    // split the multiplication so large fees can not overflow
+    let credit = amount / 10_000 * share + amount % 10_000 * share / 10_000;
+    if credit == 0 {
+        return Ok(0);
+    }
+    let mut referral_store = PrefixedStorage::new(PREFIX_REFERRALS, storage);
+    let mut stats: ReferralStats =
+        may_load(&referral_store, referrer.as_slice())?.unwrap_or_default();
+    match token {
+        Some(token) => match stats.token_earned.iter_mut().find(|e| &e.token == token) {
+            Some(earned) => earned.amount = Uint128(earned.amount.u128().saturating_add(credit)),
+            None => stats.token_earned.push(TokenEarnings {
+                token: token.clone(),
+                amount: Uint128(credit),
+            }),
+        },
+        None => stats.earned = Uint128(stats.earned.u128().saturating_add(credit)),
+    }
+    save(&mut referral_store, referrer.as_slice(), &stats)?;
+    Ok(credit)
+}
+
+/// Returns StdResult<u128> with the amount of a SNIP-20 token collected as creation fees and not
+/// withdrawn yet
+///
+/// # Arguments
+///
+/// * `storage` - a reference to the contract's storage
+/// * `token` - address of the token
+pub fn collected_token_fees<S: ReadonlyStorage>(storage: &S, token: &HumanAddr) -> StdResult<u128> {
+    let fee_store = ReadonlyPrefixedStorage::new(PREFIX_TOKEN_FEES, storage);
+    Ok(may_load(&fee_store, token.as_str().as_bytes())?.unwrap_or(0))
+}
+
+/// Returns StdResult<u128> with the amount of native coins sent, failing if any other coins
+/// were sent
+///
+/// # Arguments
+///
+/// * `sent_funds` - the funds sent with the message
+pub fn native_amount(sent_funds: &[Coin]) -> StdResult<u128> {
+    sent_funds
+        .iter()
+        .map(|coin| {
+            if coin.denom == NATIVE_DENOM {
+                Ok(coin.amount.u128())
+            } else {
+                Err(StdError::generic_err(format!(
+                    "Only {} is accepted",
+                    NATIVE_DENOM
+                )))
+            }
+        })
+        .sum::<StdResult<u128>>()
+}
+
+fn check_rate_limit<S: ReadonlyStorage>(
+    storage: &S,
+    config: &Config,
+    request: &CreationRequest,
+) -> StdResult<()> {
+    // without the block height, the window can not be checked
+    let (limit, height) = match (config.creation_rules.rate_limit, request.height) {
+        (Some(limit), Some(height)) => (limit, height),
+        _ => return Ok(()),
+    };
+    let window = current_window(storage, &limit, request.creator, height)?;
+    if window.count >= limit.max_creations {
+        return Err(StdError::generic_err(format!(
+            "At most {} offspring can be created every {} blocks. Try again at block {}",
+            limit.max_creations,
+            limit.window_blocks,
+            window.start.saturating_add(limit.window_blocks)
+        )));
+    }
+    Ok(())
+}
+
+fn check_pending_limit<S: ReadonlyStorage>(
+    storage: &S,
+    config: &Config,
+    request: &CreationRequest,
+) -> StdResult<()> {
+    let max_pending = match config.creation_rules.max_pending {
+        Some(max_pending) => max_pending,
+        None => return Ok(()),
+    };
+    let pending = live_pending(storage, request.creator, request.height)?;
+    if pending.len() as u32 >= max_pending {
+        return Err(StdError::generic_err(format!(
+            "At most {} of your creations can wait for registration at a time. Wait until one \
+             registers or expires, or cancel them with cancel_pending_creations",
+            max_pending
+        )));
+    }
+    Ok(())
+}
+
+fn check_label<S: ReadonlyStorage>(
+    storage: &S,
+    config: &Config,
+    request: &CreationRequest,
+) -> StdResult<()> {
+    let label = match request.label {
+        Some(label) => label,
+        None => return Ok(()),
+    };
+    if label.trim().is_empty() {
+        return Err(StdError::generic_err("Labels can not be blank"));
+    }
+    if let Some(max_len) = config.creation_rules.max_label_len {
+        if label.len() > max_len as usize {
+            return Err(StdError::generic_err(format!(
+                "Labels can be at most {} bytes long",
+                max_len
+            )));
+        }
+    }
+    if offspring_by_label(storage, label)?.is_some() {
+        return Err(StdError::generic_err(format!(
+            "The label {} is already used by another offspring",
+            label
+        )));
+    }
+    if is_label_reserved(storage, label, request.height)? {
+        return Err(StdError::generic_err(format!(
+            "The label {} is reserved by an offspring waiting for registration",
+            label
+        )));
+    }
+    Ok(())
+}
+
+/// Returns StdResult<()>
+///
+/// reserves a label for an offspring waiting for registration, so no other creation can use it
+///
+/// # Arguments
+///
+/// * `storage` - mutable reference to the contract's storage
+/// * `label` - the label
+/// * `key` - a reference to the key of the pending offspring
+pub fn reserve_label<S: Storage>(storage: &mut S, label: &str, key: &[u8; 32]) -> StdResult<()> {
+    let mut reserved_store = PrefixedStorage::new(PREFIX_RESERVED_LABELS, storage);
+    save(&mut reserved_store, label.as_bytes(), key)
+}
+
+/// Returns StdResult<()>
+///
+/// releases a label's reservation, if it is held by the pending offspring
+///
+/// # Arguments
+///
+/// * `storage` - mutable reference to the contract's storage
+/// * `label` - the label
+/// * `key` - a reference to the key of the pending offspring
+pub fn release_label<S: Storage>(storage: &mut S, label: &str, key: &[u8; 32]) -> StdResult<()> {
+    let mut reserved_store = PrefixedStorage::new(PREFIX_RESERVED_LABELS, storage);
+    let holder: Option<[u8; 32]> = may_load(&reserved_store, label.as_bytes())?;
+    if holder.as_ref() == Some(key) {
+        remove(&mut reserved_store, label.as_bytes());
+    }
+    Ok(())
+}
+
+/// Returns StdResult<bool> indicating whether a label is reserved for an offspring that may still
+/// register
+///
+/// # Arguments
+///
+/// * `storage` - a reference to the contract's storage
+/// * `label` - the label
+/// * `height` - current block height, or None if it is not known
+pub fn is_label_reserved<S: ReadonlyStorage>(
+    storage: &S,
+    label: &str,
+    height: Option<u64>,
+) -> StdResult<bool> {
+    let reserved_store = ReadonlyPrefixedStorage::new(PREFIX_RESERVED_LABELS, storage);
+    let holder: Option<[u8; 32]> = may_load(&reserved_store, label.as_bytes())?;
+    match holder {
+        Some(key) => may_still_register(storage, &key, height),
+        None => Ok(false),
+    }
+}
+
+/// Returns StdResult<CreationWindow> with the creator's rate limit window at the given height.
+/// A window that has ended is replaced by an empty one starting at the height
+///
+/// # Arguments
+///
+/// * `storage` - a reference to the contract's storage
+/// * `limit` - a reference to the rate limit
+/// * `creator` - a reference to the canonical address of the creator
+/// * `height` - current block height
+pub fn current_window<S: ReadonlyStorage>(
+    storage: &S,
+    limit: &RateLimit,
+    creator: &CanonicalAddr,
+    height: u64,
+) -> StdResult<CreationWindow> {
+    let window_store = ReadonlyPrefixedStorage::new(PREFIX_CREATION_WINDOWS, storage);
+    let window: CreationWindow = may_load(&window_store, creator.as_slice())?.unwrap_or_default();
+    if window.count == 0 || height >= window.start.saturating_add(limit.window_blocks) {
+        return Ok(CreationWindow {
+            start: height,
+            count: 0,
+        });
+    }
+    Ok(window)
+}
+
+/// Returns StdResult<()>
+///
+/// counts a creation in the creator's rate limit window, if the creation rules set a rate limit
+///
+/// # Arguments
+///
+/// * `storage` - mutable reference to the contract's storage
+/// * `config` - a reference to the factory config
+/// * `creator` - a reference to the canonical address of the creator
+/// * `height` - current block height
+pub fn record_creation<S: Storage>(
+    storage: &mut S,
+    config: &Config,
+    creator: &CanonicalAddr,
+    height: u64,
+) -> StdResult<()> {
+    let limit = match config.creation_rules.rate_limit {
+        Some(limit) => limit,
+        None => return Ok(()),
+    };
+    let mut window = current_window(storage, &limit, creator, height)?;
+    window.count += 1;
+    let mut window_store = PrefixedStorage::new(PREFIX_CREATION_WINDOWS, storage);
+    save(&mut window_store, creator.as_slice(), &window)
+}
+
+/// Returns bool indicating whether the offspring of a creation may still register
+///
+/// # Arguments
+///
+/// * `storage` - a reference to the contract's storage
+/// * `key` - a reference to the key of the pending offspring
+/// * `height` - current block height, or None if it is not known
+fn may_still_register<S: ReadonlyStorage>(
+    storage: &S,
+    key: &[u8; 32],
+    height: Option<u64>,
+) -> StdResult<bool> {
+    // without the block height, a creation that is still stored counts as pending
+    let height = height.unwrap_or(u64::MIN);
+    let pending_store: ReadOnlyCashMap<PendingOffspring, _> =
+        ReadOnlyCashMap::init(PENDING_KEY, storage);
+    Ok(pending_store
+        .get(key)
+        .map(|pending| height <= pending.expires_at)
+        .unwrap_or(false))
+}
+
+/// Returns StdResult<Vec<PendingCreation>> with the creator's creations whose offspring did not
+/// register yet but still may
+///
+/// # Arguments
+///
+/// * `storage` - a reference to the contract's storage
+/// * `creator` - a reference to the canonical address of the creator
+/// * `height` - current block height, or None if it is not known
+pub fn live_pending<S: ReadonlyStorage>(
+    storage: &S,
+    creator: &CanonicalAddr,
+    height: Option<u64>,
+) -> StdResult<Vec<PendingCreation>> {
+    let pending_store = ReadonlyPrefixedStorage::new(PREFIX_PENDING_CREATIONS, storage);
+    let pending: Vec<PendingCreation> =
+        may_load(&pending_store, creator.as_slice())?.unwrap_or_default();
+    let mut live = Vec::with_capacity(pending.len());
+    for creation in pending {
+        if may_still_register(storage, &creation.key, height)? {
+            live.push(creation);
+        }
+    }
+    Ok(live)
+}
+
+/// Returns StdResult<()>
+///
+/// counts a creation against the creator's pending limit until its offspring registers, if the
+/// creation rules set a pending limit. Creations that can no longer register are released
+///
+/// # Arguments
+///
+/// * `storage` - mutable reference to the contract's storage
+/// * `config` - a reference to the factory config
+/// * `creator` - a reference to the canonical address of the creator
+/// * `password` - a reference to the password of the new offspring
+/// * `height` - current block height
+pub fn record_pending<S: Storage>(
+    storage: &mut S,
+    config: &Config,
+    creator: &CanonicalAddr,
+    password: &[u8; 32],
+    height: u64,
+) -> StdResult<()> {
+    if config.creation_rules.max_pending.is_none() {
+        return Ok(());
+    }
+    let key = sha_256(password);
+    let mut pending = live_pending(storage, creator, Some(height))?;
+    pending.push(PendingCreation { key, height });
+    let mut pending_store = PrefixedStorage::new(PREFIX_PENDING_CREATIONS, storage);
+    save(&mut pending_store, creator.as_slice(), &pending)?;
+    let mut creator_store = PrefixedStorage::new(PREFIX_PENDING_CREATORS, storage);
+    save(&mut creator_store, &key, creator)
+}
+
+/// Returns StdResult<()>
+///
+/// stops counting a creation against its creator's pending limit, because its offspring
+/// registered
+///
+/// # Arguments
+///
+/// * `storage` - mutable reference to the contract's storage
+/// * `password` - a reference to the password the offspring registered with
+pub fn release_pending<S: Storage>(storage: &mut S, password: &[u8; 32]) -> StdResult<()> {
+    let key = sha_256(password);
+    let mut creator_store = PrefixedStorage::new(PREFIX_PENDING_CREATORS, storage);
+    let creator: Option<CanonicalAddr> = may_load(&creator_store, &key)?;
+    let creator = match creator {
+        Some(creator) => creator,
+        None => return Ok(()),
+    };
+    remove(&mut creator_store, &key);
+    let mut pending_store = PrefixedStorage::new(PREFIX_PENDING_CREATIONS, storage);
+    let mut pending: Vec<PendingCreation> =
+        may_load(&pending_store, creator.as_slice())?.unwrap_or_default();
+    pending.retain(|creation| creation.key != key);
+    if pending.is_empty() {
+        remove(&mut pending_store, creator.as_slice());
+        Ok(())
+    } else {
+        save(&mut pending_store, creator.as_slice(), &pending)
+    }
+}
+
+/// Returns StdResult<Vec<PendingCreation>> with the creator's creations that were counted against
+/// the pending limit, after forgetting them
+///
+/// # Arguments
+///
+/// * `storage` - mutable reference to the contract's storage
+/// * `creator` - a reference to the canonical address of the creator
+pub fn take_pending_creations<S: Storage>(
+    storage: &mut S,
+    creator: &CanonicalAddr,
+) -> StdResult<Vec<PendingCreation>> {
+    let mut pending_store = PrefixedStorage::new(PREFIX_PENDING_CREATIONS, storage);
+    let pending: Vec<PendingCreation> =
+        may_load(&pending_store, creator.as_slice())?.unwrap_or_default();
+    remove(&mut pending_store, creator.as_slice());
+    let mut creator_store = PrefixedStorage::new(PREFIX_PENDING_CREATORS, storage);
+    for creation in pending.iter() {
+        remove(&mut creator_store, &creation.key);
+    }
+    Ok(pending)
+}
+
+/// Returns QueryResult displaying how many more offspring the address may create in its current
+/// rate limit window, and when the window ends. Queries have no block height, so the window is
+/// checked at the height of the factory's latest handle
+///
+/// # Arguments
+///
+/// * `deps` - reference to Extern containing all the contract's external dependencies
+/// * `address` - a reference to the address of the creator
+/// * `viewing_key` - String key used to authenticate the query
+pub fn try_creation_quota<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    address: &HumanAddr,
+    viewing_key: String,
+) -> QueryResult {
+    let address = &normalize_address(&deps.api, address)?;
+    if !is_key_valid(&deps.storage, address, viewing_key) {
+        return to_binary(&QueryAnswer::ViewingKeyError {
+            error: "Wrong viewing key for this address or viewing key not set".to_string(),
+        });
+    }
+    let config: Config = load(&deps.storage, CONFIG_KEY)?;
+    let limit = match config.creation_rules.rate_limit {
+        Some(limit) => limit,
+        None => {
+            return to_binary(&QueryAnswer::CreationQuota {
+                rate_limit: None,
+                remaining: None,
+                resets_at: None,
+            })
+        }
+    };
+    let creator = deps.api.canonical_address(address)?;
+    let window = current_window(&deps.storage, &limit, &creator, current_height(&deps.storage)?)?;
+    // a window only starts with the creator's next creation
+    let resets_at = if window.count == 0 {
+        None
+    } else {
+        Some(window.start.saturating_add(limit.window_blocks))
+    };
+    to_binary(&QueryAnswer::CreationQuota {
+        rate_limit: Some(limit),
+        remaining: Some(limit.max_creations.saturating_sub(window.count)),
+        resets_at,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::msg::{CallbackPolicy, OffspringContractInfo};
+    use crate::state::PREFIX_DISABLED_VERSIONS;
+    use cosmwasm_std::testing::MockStorage;
+    use cosmwasm_std::{coins, Uint128};
+    use secret_toolkit_incubator::CashMap;
+
+    fn config(creation_rules: CreationRules) -> Config {
+        Config {
+            version: OffspringContractInfo {
+                code_id: 1,
+                code_hash: "hash".to_string(),
+            },
+            previous_version: None,
+            stopped: false,
+            admin: CanonicalAddr::from(b"admin".to_vec()),
+            pending_admin: None,
+            tombstone_retention: 0,
+            default_page_size: 1,
+            private_listing: false,
+            auto_viewing_key: false,
+            require_registration_same_tx: false,
+            require_owner_is_sender: false,
+            min_entropy_len: 8,
+            min_entropy_distinct: 4,
+            maintenance_budget: 0,
+            contract_address: HumanAddr("factory".to_string()),
+            callback_policy: CallbackPolicy::default(),
+            registration_retry_blocks: 0,
+            quarantine_registrations: false,
+            creation_rules,
+            address_prefix: None,
+            description_limit: None,
+            features: 0,
+            stats_checkpoint_interval: 0,
+        }
+    }
+
+    fn request<'a>(creator: &'a CanonicalAddr, sent_funds: &'a [Coin]) -> CreationRequest<'a> {
+        CreationRequest {
+            creator,
+            sent_funds: Some(sent_funds),
+            token_fee_paid: false,
+            label: Some("counter"),
+            height: Some(100),
+            code_id: 1,
+        }
+    }
+
+    /// This test checks that creation is refused while the factory is stopped or the current
+    /// version is disabled.
+    #[test]
+    fn test_status() {
+        let mut storage = MockStorage::new();
+        let creator = CanonicalAddr::from(b"alice".to_vec());
+        let mut config = config(CreationRules::default());
+        let check_status = |storage: &MockStorage, config: &Config| {
+            check(
+                CreationStep::Status,
+                storage,
+                config,
+                &request(&creator, &[]),
+            )
+        };
+        assert!(check_status(&storage, &config).is_ok());
+        config.stopped = true;
+        assert!(check_status(&storage, &config).is_err());
+        config.stopped = false;
+        let mut disabled_store = PrefixedStorage::new(PREFIX_DISABLED_VERSIONS, &mut storage);
+        save(&mut disabled_store, &1u64.to_be_bytes(), &true).unwrap();
+        assert!(check_status(&storage, &config).is_err());
+    }
+
+    /// This test checks that only allowed creators pass the allow list, and only when it is used,
+    /// and that denied creators never pass.
+    #[test]
+    fn test_allow_list() {
+        let mut storage = MockStorage::new();
+        let creator = CanonicalAddr::from(b"alice".to_vec());
+        let open = config(CreationRules::default());
+        let closed = config(CreationRules {
+            allow_list: true,
+            ..CreationRules::default()
+        });
+        let step = CreationStep::AllowList;
+        assert!(check(step, &storage, &open, &request(&creator, &[])).is_ok());
+        assert!(check(step, &storage, &closed, &request(&creator, &[])).is_err());
+        let mut allowed_store: CashMap<HumanAddr, _> =
+            CashMap::init(ALLOWED_CREATORS_KEY, &mut storage);
+        allowed_store.insert(creator.as_slice(), HumanAddr::from("alice")).unwrap();
+        assert!(check(step, &storage, &closed, &request(&creator, &[])).is_ok());
+        let mut denied_store: CashMap<HumanAddr, _> =
+            CashMap::init(DENIED_CREATORS_KEY, &mut storage);
+        denied_store.insert(creator.as_slice(), HumanAddr::from("alice")).unwrap();
+        assert!(check(step, &storage, &open, &request(&creator, &[])).is_err());
+    }
+
+    /// This test checks that the fee must be paid exactly, in uscrt, and that a creation without
+    /// known funds is not checked.
+    #[test]
+    fn test_fee() {
+        let storage = MockStorage::new();
+        let creator = CanonicalAddr::from(b"alice".to_vec());
+        let config = config(CreationRules {
+            fee: Some(Uint128(1000)),
+            ..CreationRules::default()
+        });
+        let step = CreationStep::Fee;
+        let paid = coins(1000, NATIVE_DENOM);
+        assert!(check(step, &storage, &config, &request(&creator, &paid)).is_ok());
+        let short = coins(999, NATIVE_DENOM);
+        assert!(check(step, &storage, &config, &request(&creator, &short)).is_err());
+        let other = coins(1000, "uatom");
+        assert!(check(step, &storage, &config, &request(&creator, &other)).is_err());
+        assert!(check(step, &storage, &config, &request(&creator, &[])).is_err());
+        let preview = CreationRequest {
+            sent_funds: None,
+            ..request(&creator, &[])
+        };
+        assert!(check(step, &storage, &config, &preview).is_ok());
+    }
+
+    /// This test checks that a creator is refused once the window is used up, and allowed again
+    /// once it ends.
+    #[test]
+    fn test_rate_limit() {
+        let mut storage = MockStorage::new();
+        let creator = CanonicalAddr::from(b"alice".to_vec());
+        let config = config(CreationRules {
+            rate_limit: Some(RateLimit {
+                max_creations: 2,
+                window_blocks: 10,
+            }),
+            ..CreationRules::default()
+        });
+        let at = |height: u64| CreationRequest {
+            height: Some(height),
+            ..request(&creator, &[])
+        };
+        let step = CreationStep::RateLimit;
+        record_creation(&mut storage, &config, &creator, 100).unwrap();
+        assert!(check(step, &storage, &config, &at(105)).is_ok());
+        record_creation(&mut storage, &config, &creator, 105).unwrap();
+        assert!(check(step, &storage, &config, &at(109)).is_err());
+        assert!(check(step, &storage, &config, &at(110)).is_ok());
+    }
+
+    /// This test checks that blank labels and labels over the maximum length are refused.
+    #[test]
+    fn test_label_rules() {
+        let storage = MockStorage::new();
+        let creator = CanonicalAddr::from(b"alice".to_vec());
+        let config = config(CreationRules {
+            max_label_len: Some(7),
+            ..CreationRules::default()
+        });
+        let labeled = |label: &'static str| CreationRequest {
+            label: Some(label),
+            ..request(&creator, &[])
+        };
+        let step = CreationStep::LabelRules;
+        assert!(check(step, &storage, &config, &labeled("counter")).is_ok());
+        assert!(check(step, &storage, &config, &labeled("counters")).is_err());
+        assert!(check(step, &storage, &config, &labeled("  ")).is_err());
+    }
+
+    /// This test checks that a creator is refused while too many creations wait for
+    /// registration, and allowed again once one registers or the others expire.
+    #[test]
+    fn test_pending_limit() {
+        let mut storage = MockStorage::new();
+        let creator = CanonicalAddr::from(b"alice".to_vec());
+        let config = config(CreationRules {
+            max_pending: Some(2),
+            ..CreationRules::default()
+        });
+        let at = |height: u64| CreationRequest {
+            height: Some(height),
+            ..request(&creator, &[])
+        };
+        let step = CreationStep::PendingLimit;
+        for password in [[1u8; 32], [2u8; 32]].iter() {
+            let key = sha_256(password);
+            let pending = PendingOffspring {
+                key,
+                label: "counter".to_string(),
+                height: 100,
+                expires_at: 110,
+                owner: creator.clone(),
+                code_hash: "hash".to_string(),
+            };
+            let mut pending_store = CashMap::init(PENDING_KEY, &mut storage);
+            pending_store.insert(&key, pending).unwrap();
+            record_pending(&mut storage, &config, &creator, password, 100).unwrap();
+        }
+        assert!(check(step, &storage, &config, &at(105)).is_err());
+        assert!(check(step, &storage, &config, &at(111)).is_ok());
+        release_pending(&mut storage, &[1u8; 32]).unwrap();
+        assert!(check(step, &storage, &config, &at(105)).is_ok());
+    }
+
+    /// This test checks that the pipeline skips disabled steps, and that the status and label
+    /// steps can not be disabled.
+    #[test]
+    fn test_disabled_steps() {
+        let storage = MockStorage::new();
+        let creator = CanonicalAddr::from(b"alice".to_vec());
+        let mut rules = CreationRules {
+            fee: Some(Uint128(1000)),
+            max_label_len: Some(3),
+            ..CreationRules::default()
+        };
+        let reasons = run_pipeline(&storage, &config(rules.clone()), &request(&creator, &[]));
+        assert_eq!(reasons.len(), 2);
+        rules.disabled_steps = vec![CreationStep::Fee];
+        let reasons = run_pipeline(&storage, &config(rules.clone()), &request(&creator, &[]));
+        assert_eq!(reasons.len(), 1);
+        assert!(reasons[0].contains("bytes long"));
+        assert!(valid_creation_rules(rules.clone()).is_ok());
+        for step in [CreationStep::Status, CreationStep::LabelRules].iter() {
+            rules.disabled_steps = vec![*step];
+            assert!(valid_creation_rules(rules.clone()).is_err());
+        }
+    }
+}
@@ -0,0 +1,102 @@
+use cosmwasm_std::{
+    to_binary, Api, Extern, Querier, QueryResult, ReadonlyStorage, StdError, StdResult, Storage,
+};
+
+use cosmwasm_storage::{PrefixedStorage, ReadonlyPrefixedStorage};
+
+use secret_toolkit::storage::{AppendStore, AppendStoreMut};
+
+use crate::creation::is_version_enabled;
+use crate::msg::{OffspringContractInfo, OffspringVersion, QueryAnswer};
+use crate::state::{load, Config, CONFIG_KEY, PREFIX_DEPRECATED_VERSIONS, PREFIX_VERSIONS};
+
+/// Returns StdResult<u32> with the id of the offspring version, after adding it to the registered
+/// versions if it is not registered yet
+///
+/// # Arguments
+///
+/// * `storage` - mutable reference to the contract's storage
+/// * `version` - a reference to the code id and code hash of the version
+pub fn add_version<S: Storage>(storage: &mut S, version: &OffspringContractInfo) -> StdResult<u32> {
+    let mut version_store = PrefixedStorage::new(PREFIX_VERSIONS, storage);
+    let mut versions =
+        AppendStoreMut::<OffspringContractInfo, _>::attach_or_create(&mut version_store)?;
+    for (id, registered) in versions.iter().enumerate() {
+        if registered? == *version {
+            return Ok(id as u32);
+        }
+    }
+    versions.push(version)?;
+    Ok(versions.len() - 1)
+}
+
+/// Returns StdResult<OffspringContractInfo> with the registered offspring version
+///
+/// # Arguments
+///
+/// * `storage` - a reference to the contract's storage
+/// * `id` - id of the version
+pub fn load_version<S: ReadonlyStorage>(storage: &S, id: u32) -> StdResult<OffspringContractInfo> {
+    let version_store = ReadonlyPrefixedStorage::new(PREFIX_VERSIONS, storage);
+    if let Some(versions) = AppendStore::<OffspringContractInfo, _>::attach(&version_store) {
+        let versions = versions?;
+        if id < versions.len() {
+            return versions.get_at(id);
+        }
+    }
+    Err(StdError::generic_err(format!(
+        "There is no offspring version {}",
+        id
+    )))
+}
+
+/// Returns StdResult<OffspringContractInfo> with the version an offspring should be created from
+///
+/// # Arguments
+///
+/// * `storage` - a reference to the contract's storage
+/// * `config` - a reference to the factory config
+/// * `id` - optional id of the version. If not specified, the current version is used
+pub fn resolve_version<S: ReadonlyStorage>(
+    storage: &S,
+    config: &Config,
+    id: Option<u32>,
+) -> StdResult<OffspringContractInfo> {
+    match id {
+        Some(id) => load_version(storage, id),
+        None => Ok(config.version.clone()),
+    }
+}
+
+/// Returns QueryResult listing the registered offspring versions, oldest first
+///
+/// # Arguments
+///
+/// * `deps` - reference to Extern containing all the contract's external dependencies
+pub fn try_offspring_versions<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+) -> QueryResult {
+    let config: Config = load(&deps.storage, CONFIG_KEY)?;
+    let version_store = ReadonlyPrefixedStorage::new(PREFIX_VERSIONS, &deps.storage);
+    let registered = match AppendStore::<OffspringContractInfo, _>::attach(&version_store) {
+        Some(versions) => versions?
+            .iter()
+            .collect::<StdResult<Vec<OffspringContractInfo>>>()?,
+        None => vec![],
+    };
+    let deprecated_store = ReadonlyPrefixedStorage::new(PREFIX_DEPRECATED_VERSIONS, &deps.storage);
+    let versions = registered
+        .into_iter()
+        .enumerate()
+        .map(|(id, offspring_contract)| OffspringVersion {
+            id: id as u32,
+            current: offspring_contract == config.version,
+            enabled: is_version_enabled(&deps.storage, offspring_contract.code_id),
+            deprecated: deprecated_store
+                .get(offspring_contract.code_hash.as_bytes())
+                .is_some(),
+            offspring_contract,
+        })
+        .collect();
+    to_binary(&QueryAnswer::OffspringVersions { versions })
+}
@@ -1,3 +1,2966 @@
+//! Cross-contract scenarios that link the factory and the offspring natively, with the offspring
+//! built with its library feature. Messages returned by either contract are dispatched to the
+//! other one depth-first, the way the chain executes them, so whole flows can be tested with
+//! MockStorage and without compiling to wasm.
+
+use cosmwasm_std::testing::{mock_dependencies, mock_env, MockApi, MockQuerier, MockStorage};
+use cosmwasm_std::{
+    coins, from_binary, from_slice, to_binary, BankMsg, Binary, CosmosMsg, Empty, Extern,
+    HumanAddr, Api, Querier, QuerierResult, QueryRequest, SystemError, Uint128, WasmMsg, WasmQuery,
+};
+use cosmwasm_storage::ReadonlyPrefixedStorage;
+
+use factory::contract;
+use factory::msg::{
+    AdminAction, ContractInfo, CreatorListType, DeactivationSource, Feature, FilterTypes, HandleAnswer, HandleMsg, InitMsg, IssueStatus, LifecycleStatus, OffspringContractInfo, OffspringStatus, QueryAnswer, QueryMsg,
+    QueryWithPermit, OffspringSpec, OwnerNote, ResponseStatus, StoreOffspringInfo, VersionDeprecation, VersionedAnswer,
+};
+use factory::state::{load, ACTIVE_KEY, PREFIX_OWNER_NOTES};
+
+use secret_toolkit_incubator::{CashMap, ReadOnlyCashMap};
+
+const ADMIN: &str = "admin";
+const OWNER: &str = "alice";
+const FACTORY: &str = "factory";
+/// a SNIP-20 token outside the scenario. Messages sent to it are not executed
+const TOKEN: &str = "token";
+
+type Deps = Extern<MockStorage, MockApi, MockQuerier>;
+
+/// the factory and the offspring it created, each with its own storage
+struct Chain {
+    factory: Deps,
+    offspring: Vec<(HumanAddr, Deps)>,
+}
+
+impl Chain {
+    fn new() -> Self {
+        Self::with_init(
+            br#"{"entropy":"scenario entropy","offspring_contract":{"code_id":2,"code_hash":"cdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcd"}}"#,
+        )
+    }
+
+    /// instantiates the factory with the given InitMsg json
+    fn with_init(init: &[u8]) -> Self {
+        let mut factory = mock_dependencies(20, &[]);
+        let msg: InitMsg = from_slice(init).unwrap();
+        let mut env = mock_env(ADMIN, &[]);
+        env.contract.address = HumanAddr::from(FACTORY);
+        let res = contract::init(&mut factory, env, msg).unwrap();
+        let mut chain = Chain {
+            factory,
+            offspring: vec![],
+        };
+        chain.dispatch(HumanAddr::from(FACTORY), res.messages);
+        chain
+    }
+
+    /// executes a factory handle, and then every message it leads to
+    fn handle_factory(&mut self, sender: &str, msg: HandleMsg) {
+        let mut env = mock_env(sender, &[]);
+        env.contract.address = HumanAddr::from(FACTORY);
+        let res = contract::handle(&mut self.factory, env, msg).unwrap();
+        self.dispatch(HumanAddr::from(FACTORY), res.messages);
+    }
+
+    /// executes an offspring handle, and then every message it leads to
+    fn handle_offspring(&mut self, sender: &str, index: usize, msg: offspring::msg::HandleMsg) {
+        let address = self.offspring[index].0.clone();
+        let mut env = mock_env(sender, &[]);
+        env.contract.address = address.clone();
+        let res = offspring::handle(&mut self.offspring[index].1, env, msg).unwrap();
+        self.dispatch(address, res.messages);
+    }
+
+    /// executes a factory handle and instantiates the offspring it creates, but drops the
+    /// offspring's registration as if it had failed
+    fn create_unregistered(&mut self, sender: &str, msg: HandleMsg) {
+        let mut env = mock_env(sender, &[]);
+        env.contract.address = HumanAddr::from(FACTORY);
+        let res = contract::handle(&mut self.factory, env, msg).unwrap();
+        match res.messages.into_iter().next() {
+            Some(CosmosMsg::Wasm(WasmMsg::Instantiate { msg, .. })) => {
+                let address = HumanAddr(format!("offspring{}", self.offspring.len()));
+                let mut deps = mock_dependencies(20, &[]);
+                let mut env = mock_env(FACTORY, &[]);
+                env.contract.address = address.clone();
+                offspring::init(&mut deps, env, from_binary(&msg).unwrap()).unwrap();
+                self.offspring.push((address, deps));
+            }
+            other => panic!("unexpected message {:?}", other),
+        }
+    }
+
+    fn query_factory(&self, msg: QueryMsg) -> QueryAnswer {
+        from_binary(&contract::query(&self.factory, msg).unwrap()).unwrap()
+    }
+
+    /// runs an offspring query, with the offspring's own queries to the factory answered by the
+    /// factory
+    fn query_linked_offspring(
+        &mut self,
+        index: usize,
+        msg: offspring::msg::QueryMsg,
+    ) -> offspring::msg::QueryAnswer {
+        let deps = &mut self.offspring[index].1;
+        let linked = Extern {
+            storage: std::mem::take(&mut deps.storage),
+            api: deps.api,
+            querier: FactoryQuerier {
+                factory: &self.factory,
+            },
+        };
+        let res = offspring::query(&linked, msg);
+        deps.storage = linked.storage;
+        from_binary(&res.unwrap()).unwrap()
+    }
+
+    /// executes the messages sent by `sender` in order, each one followed by the messages it
+    /// sends in turn
+    fn dispatch(&mut self, sender: HumanAddr, messages: Vec<CosmosMsg>) {
+        for message in messages {
+            match message {
+                CosmosMsg::Wasm(WasmMsg::Instantiate { msg, .. }) => {
+                    let address = HumanAddr(format!("offspring{}", self.offspring.len()));
+                    let mut deps = mock_dependencies(20, &[]);
+                    let mut env = mock_env(sender.clone(), &[]);
+                    env.contract.address = address.clone();
+                    let res =
+                        offspring::init(&mut deps, env, from_binary(&msg).unwrap()).unwrap();
+                    self.offspring.push((address.clone(), deps));
+                    self.dispatch(address, res.messages);
+                }
+                CosmosMsg::Wasm(WasmMsg::Execute { contract_addr, .. })
+                    if contract_addr.as_str() == TOKEN => {}
+                CosmosMsg::Wasm(WasmMsg::Execute {
+                    contract_addr, msg, ..
+                }) => {
+                    let mut env = mock_env(sender.clone(), &[]);
+                    env.contract.address = contract_addr.clone();
+                    let res = if contract_addr.as_str() == FACTORY {
+                        contract::handle(&mut self.factory, env, from_binary(&msg).unwrap())
+                    } else {
+                        let deps = self
+                            .offspring
+                            .iter_mut()
+                            .find(|(address, _)| *address == contract_addr)
+                            .map(|(_, deps)| deps)
+                            .unwrap();
+                        offspring::handle(deps, env, from_binary(&msg).unwrap())
+                    };
+                    self.dispatch(contract_addr, res.unwrap().messages);
+                }
+                other => panic!("unexpected message {:?}", other),
+            }
+        }
+    }
+}
+
+/// answers queries sent to the factory by running them on the factory
+struct FactoryQuerier<'a> {
+    factory: &'a Deps,
+}
+
+impl Querier for FactoryQuerier<'_> {
+    fn raw_query(&self, bin_request: &[u8]) -> QuerierResult {
+        match from_slice::<QueryRequest<Empty>>(bin_request) {
+            Ok(QueryRequest::Wasm(WasmQuery::Smart {
+                contract_addr, msg, ..
+            })) if contract_addr.as_str() == FACTORY => {
+                Ok(contract::query(self.factory, from_binary(&msg).unwrap()))
+            }
+            _ => Err(SystemError::Unknown {}),
+        }
+    }
+}
+
+/// This test creates an offspring, lets it register with the factory, deactivates it from the
+/// offspring, and checks that the factory moved it from the active to the inactive list.
 #[test]
-#[ignore]
-fn empty_test() {}
+fn test_create_register_deactivate_list() {
+    let mut chain = Chain::new();
+    chain.handle_factory(
+        OWNER,
+        HandleMsg::CreateOffspring {
+            label: Some("scenario counter".to_string()),
+            entropy: "creation entropy".to_string(),
+            owner: HumanAddr::from(OWNER),
+            count: 3,
+            description: None,
+            dependencies: None,
+            referrer: None,
+            count_privacy: None,
+            version: None,
+            context: None,
+        },
+    );
+    assert_eq!(chain.offspring.len(), 1);
+
+    match chain.query_factory(list_active()) {
+        QueryAnswer::ListActiveOffspring { active, .. } => {
+            assert_eq!(active.len(), 1);
+            assert_eq!(active[0].address, HumanAddr::from("offspring0"));
+            assert_eq!(active[0].label, "scenario counter");
+        }
+        _ => panic!("unexpected answer"),
+    }
+
+    chain.handle_offspring(OWNER, 0, offspring::msg::HandleMsg::Deactivate {});
+
+    match chain.query_factory(list_active()) {
+        QueryAnswer::ListActiveOffspring { active, .. } => assert!(active.is_empty()),
+        _ => panic!("unexpected answer"),
+    }
+    match chain.query_factory(list_inactive()) {
+        QueryAnswer::ListInactiveOffspring { inactive, .. } => {
+            assert_eq!(inactive.len(), 1);
+            assert_eq!(inactive[0].address, HumanAddr::from("offspring0"));
+            let block = mock_env(OWNER, &[]).block;
+            assert_eq!(inactive[0].created_height, block.height);
+            assert_eq!(inactive[0].deactivated_time, block.time);
+            assert_eq!(inactive[0].deactivated_by, DeactivationSource::Owner);
+        }
+        _ => panic!("unexpected answer"),
+    }
+    match chain.query_factory(QueryMsg::FactoryConfig {}) {
+        QueryAnswer::FactoryConfig {
+            offspring_contract,
+            admin,
+            active_count,
+            inactive_count,
+            ..
+        } => {
+            assert_eq!(offspring_contract.code_id, 2);
+            assert_eq!(admin, HumanAddr::from(ADMIN));
+            assert_eq!((active_count, inactive_count), (0, 1));
+        }
+        _ => panic!("unexpected answer"),
+    }
+    match chain.query_factory(QueryMsg::Health {}) {
+        QueryAnswer::Health {
+            healthy,
+            checks,
+            last_maintenance_height,
+        } => {
+            assert!(healthy, "{:?}", checks);
+            assert_eq!(last_maintenance_height, Some(mock_env(OWNER, &[]).block.height));
+        }
+        _ => panic!("unexpected answer"),
+    }
+}
+
+/// Returns the query listing the first page of the active offspring without a viewing key
+fn list_active() -> QueryMsg {
+    QueryMsg::ListActiveOffspring {
+        start_page: None,
+        page_size: None,
+        address: None,
+        viewing_key: None,
+        if_modified_since: None,
+        newest_first: None,
+    }
+}
+
+/// Returns the query listing the first page of the inactive offspring without a viewing key
+fn list_inactive() -> QueryMsg {
+    QueryMsg::ListInactiveOffspring {
+        start_page: None,
+        page_size: None,
+        address: None,
+        viewing_key: None,
+        if_modified_since: None,
+        newest_first: None,
+    }
+}
+
+/// Returns the query listing the first page of all of an owner's offspring
+fn list_my(owner: &str, viewing_key: &str) -> QueryMsg {
+    QueryMsg::ListMyOffspring {
+        address: HumanAddr::from(owner),
+        viewing_key: viewing_key.to_string(),
+        filter: None,
+        start_page: None,
+        page_size: None,
+        reverse: None,
+        label_contains: None,
+        if_modified_since: None,
+    }
+}
+
+fn create_msg(label: &str) -> HandleMsg {
+    HandleMsg::CreateOffspring {
+        label: Some(label.to_string()),
+        entropy: "creation entropy".to_string(),
+        owner: HumanAddr::from(OWNER),
+        count: 0,
+        description: None,
+        dependencies: None,
+        referrer: None,
+        count_privacy: None,
+        version: None,
+        context: None,
+    }
+}
+
+/// With private listing, callers without a valid viewing key only learn how many offspring are
+/// listed, while any address with a valid key sees the lists.
+#[test]
+fn test_private_listing_counts() {
+    let mut chain = Chain::with_init(
+        br#"{"entropy":"scenario entropy","offspring_contract":{"code_id":2,"code_hash":"cdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcd"},"private_listing":true}"#,
+    );
+    for label in &["first", "second"] {
+        chain.handle_factory(OWNER, create_msg(label));
+    }
+    chain.handle_offspring(OWNER, 1, offspring::msg::HandleMsg::Deactivate {});
+    chain.handle_factory(
+        "bob",
+        HandleMsg::SetViewingKey {
+            key: "bob key".to_string(),
+            padding: None,
+        },
+    );
+    let authenticated = |viewing_key: &str| QueryMsg::ListActiveOffspring {
+        start_page: None,
+        page_size: None,
+        address: Some(HumanAddr::from("bob")),
+        viewing_key: Some(viewing_key.to_string()),
+        if_modified_since: None,
+        newest_first: None,
+    };
+
+    assert!(matches!(
+        chain.query_factory(list_active()),
+        QueryAnswer::OffspringCount { count: 1 }
+    ));
+    assert!(matches!(
+        chain.query_factory(list_inactive()),
+        QueryAnswer::OffspringCount { count: 1 }
+    ));
+    assert!(matches!(
+        chain.query_factory(authenticated("wrong key")),
+        QueryAnswer::OffspringCount { count: 1 }
+    ));
+    match chain.query_factory(authenticated("bob key")) {
+        QueryAnswer::ListActiveOffspring { active, .. } => {
+            assert_eq!(active.len(), 1);
+            assert_eq!(active[0].label, "first");
+        }
+        _ => panic!("unexpected answer"),
+    }
+}
+
+/// Descriptions sealed at registration in private mode still open after later creations have
+/// moved the prng seed on, in both the authenticated full lists and the owner's list.
+#[test]
+fn test_sealed_descriptions() {
+    let mut chain = Chain::with_init(
+        br#"{"entropy":"scenario entropy","offspring_contract":{"code_id":2,"code_hash":"cdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcd"},"private_listing":true}"#,
+    );
+    for label in &["first", "second"] {
+        chain.handle_factory(
+            OWNER,
+            HandleMsg::CreateOffspring {
+                label: Some(label.to_string()),
+                entropy: "creation entropy".to_string(),
+                owner: HumanAddr::from(OWNER),
+                count: 0,
+                description: Some(format!("{} description", label)),
+                dependencies: None,
+                referrer: None,
+                count_privacy: None,
+                version: None,
+                context: None,
+            },
+        );
+    }
+    chain.handle_factory(
+        OWNER,
+        HandleMsg::SetViewingKey {
+            key: "owner key".to_string(),
+            padding: None,
+        },
+    );
+    let descriptions = |infos: Vec<Option<String>>| {
+        let mut descriptions: Vec<String> = infos.into_iter().map(Option::unwrap).collect();
+        descriptions.sort();
+        descriptions
+    };
+    let expected = vec!["first description", "second description"];
+
+    match chain.query_factory(QueryMsg::ListActiveOffspring {
+        start_page: None,
+        page_size: None,
+        address: Some(HumanAddr::from(OWNER)),
+        viewing_key: Some("owner key".to_string()),
+        if_modified_since: None,
+        newest_first: None,
+    }) {
+        QueryAnswer::ListActiveOffspring { active, .. } => assert_eq!(
+            descriptions(active.into_iter().map(|info| info.description).collect()),
+            expected
+        ),
+        _ => panic!("unexpected answer"),
+    }
+    match chain.query_factory(list_my(OWNER, "owner key")) {
+        QueryAnswer::ListMyOffspring { active, .. } => assert_eq!(
+            descriptions(active.unwrap().into_iter().map(|info| info.description).collect()),
+            expected
+        ),
+        _ => panic!("unexpected answer"),
+    }
+}
+
+/// The mock querier can not reach the factory, so offspring queries run in degraded mode. The
+/// fallback key authenticates the owner until the offspring changes hands through the factory,
+/// and repointing the offspring tells the old factory it left.
+#[test]
+fn test_fallback_key_and_repointing() {
+    let mut chain = Chain::new();
+    chain.handle_factory(OWNER, create_msg("degraded"));
+    chain.handle_offspring(
+        OWNER,
+        0,
+        offspring::msg::HandleMsg::SetFallbackKey {
+            key: Some("fallback".to_string()),
+        },
+    );
+    let count = |chain: &Chain, address: &str| {
+        offspring::query(
+            &chain.offspring[0].1,
+            offspring::msg::QueryMsg::GetCount {
+                address: Some(HumanAddr::from(address)),
+                viewing_key: Some("fallback".to_string()),
+            },
+        )
+    };
+    assert!(count(&chain, OWNER).is_ok());
+    assert!(count(&chain, "bob").is_err());
+
+    let offspring = chain.offspring[0].0.clone();
+    chain.handle_factory(
+        OWNER,
+        HandleMsg::OfferTransfer {
+            offspring: offspring.clone(),
+            recipient: HumanAddr::from("bob"),
+            price: None,
+        },
+    );
+    chain.handle_factory("bob", HandleMsg::AcceptTransfer { offspring });
+    // the previous owner's key must not authenticate the new owner
+    assert!(count(&chain, "bob").is_err());
+    assert!(count(&chain, OWNER).is_err());
+
+    let repoint = |address: &str| offspring::msg::HandleMsg::SetFactory {
+        factory: offspring::msg::ContractInfo {
+            code_hash: "new factory hash".to_string(),
+            address: HumanAddr::from(address),
+        },
+    };
+    let mut env = mock_env("bob", &[]);
+    env.contract.address = chain.offspring[0].0.clone();
+    assert!(offspring::handle(&mut chain.offspring[0].1, env, repoint(FACTORY)).is_err());
+    chain.handle_offspring("bob", 0, repoint("factory2"));
+    match chain.query_factory(list_inactive()) {
+        QueryAnswer::ListInactiveOffspring { inactive, .. } => {
+            assert_eq!(inactive.len(), 1);
+            assert!(inactive[0].deactivated_by == DeactivationSource::Detached);
+        }
+        _ => panic!("unexpected answer"),
+    }
+}
+
+/// This test checks that an offspring whose registration was lost can still register by
+/// retrying, even after the factory created another offspring in the meantime.
+#[test]
+fn test_retry_registration() {
+    let mut chain = Chain::new();
+    chain.create_unregistered(OWNER, create_msg("orphan"));
+    chain.handle_factory(OWNER, create_msg("sibling"));
+    chain.handle_offspring(OWNER, 0, offspring::msg::HandleMsg::RetryRegistration {});
+
+    match chain.query_factory(list_active()) {
+        QueryAnswer::ListActiveOffspring { active, .. } => {
+            let mut labels: Vec<String> = active.into_iter().map(|info| info.label).collect();
+            labels.sort();
+            assert_eq!(labels, vec!["orphan", "sibling"]);
+        }
+        _ => panic!("unexpected answer"),
+    }
+    // the registration was confirmed, so it can not be retried again
+    let mut env = mock_env(OWNER, &[]);
+    env.contract.address = chain.offspring[0].0.clone();
+    assert!(offspring::handle(
+        &mut chain.offspring[0].1,
+        env,
+        offspring::msg::HandleMsg::RetryRegistration {},
+    )
+    .is_err());
+}
+
+/// This test checks that offspring created from a deprecated version are flagged in the list
+/// queries, and that the flag goes away when the deprecation notice is removed.
+#[test]
+fn test_version_deprecation() {
+    let mut chain = Chain::new();
+    chain.handle_factory(OWNER, create_msg("old counter"));
+    let deprecate = |note: Option<&str>| HandleMsg::SetVersionDeprecation {
+        code_hash: "cdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcd".to_string(),
+        deprecation: note.map(|note| VersionDeprecation {
+            note: note.to_string(),
+            replacement: None,
+        }),
+    };
+
+    chain.handle_factory(ADMIN, deprecate(Some("migrate to the new counter")));
+    match chain.query_factory(list_active()) {
+        QueryAnswer::ListActiveOffspring { deprecated, .. } => {
+            assert_eq!(deprecated, vec![HumanAddr::from("offspring0")]);
+        }
+        _ => panic!("unexpected answer"),
+    }
+
+    chain.handle_factory(ADMIN, deprecate(None));
+    match chain.query_factory(list_active()) {
+        QueryAnswer::ListActiveOffspring { deprecated, .. } => assert!(deprecated.is_empty()),
+        _ => panic!("unexpected answer"),
+    }
+}
+
+/// This test checks that the admin role only changes hands once the new admin accepts it, and
+/// that only the new admin can accept it.
+#[test]
+fn test_admin_transfer() {
+    let mut chain = Chain::new();
+    let new_admin = "bob";
+    chain.handle_factory(
+        ADMIN,
+        HandleMsg::TransferAdmin {
+            new_admin: HumanAddr::from(new_admin),
+        },
+    );
+    let stop = || HandleMsg::SetStatus { stop: true };
+    let mut env = mock_env(new_admin, &[]);
+    env.contract.address = HumanAddr::from(FACTORY);
+    assert!(contract::handle(&mut chain.factory, env.clone(), stop()).is_err());
+    let mut impostor = mock_env(OWNER, &[]);
+    impostor.contract.address = HumanAddr::from(FACTORY);
+    assert!(contract::handle(&mut chain.factory, impostor, HandleMsg::AcceptAdmin {}).is_err());
+
+    chain.handle_factory(new_admin, HandleMsg::AcceptAdmin {});
+    assert!(contract::handle(&mut chain.factory, env, stop()).is_ok());
+    let mut previous = mock_env(ADMIN, &[]);
+    previous.contract.address = HumanAddr::from(FACTORY);
+    assert!(contract::handle(&mut chain.factory, previous, stop()).is_err());
+}
+
+/// This test checks that an owner transferring an offspring directly moves it to the new owner's
+/// list in the factory.
+#[test]
+fn test_offspring_transfer_ownership() {
+    let mut chain = Chain::new();
+    chain.handle_factory(OWNER, create_msg("handed over"));
+    for owner in &[OWNER, "bob"] {
+        chain.handle_factory(
+            owner,
+            HandleMsg::SetViewingKey {
+                key: "key".to_string(),
+                padding: None,
+            },
+        );
+    }
+    let active = |chain: &Chain, owner: &str| {
+        match chain.query_factory(list_my(owner, "key")) {
+            QueryAnswer::ListMyOffspring { active, .. } => active.unwrap_or_default().len(),
+            _ => panic!("unexpected answer"),
+        }
+    };
+    assert_eq!((active(&chain, OWNER), active(&chain, "bob")), (1, 0));
+
+    chain.handle_offspring(
+        OWNER,
+        0,
+        offspring::msg::HandleMsg::TransferOwnership {
+            new_owner: HumanAddr::from("bob"),
+        },
+    );
+    assert_eq!((active(&chain, OWNER), active(&chain, "bob")), (0, 1));
+    // only the new owner can transfer it again
+    let mut env = mock_env(OWNER, &[]);
+    env.contract.address = chain.offspring[0].0.clone();
+    let msg = offspring::msg::HandleMsg::TransferOwnership {
+        new_owner: HumanAddr::from(OWNER),
+    };
+    assert!(offspring::handle(&mut chain.offspring[0].1, env, msg).is_err());
+}
+
+/// This test checks that count changes only queue a notification for the listener once they
+/// reach the minimum delta, and that delivering it sends one message to the listener.
+#[test]
+fn test_listener_notification() {
+    let mut chain = Chain::new();
+    chain.handle_factory(OWNER, create_msg("watched counter"));
+    chain.handle_offspring(
+        OWNER,
+        0,
+        offspring::msg::HandleMsg::SetListener {
+            listener: Some(offspring::msg::ContractInfo {
+                code_hash: "listener hash".to_string(),
+                address: HumanAddr::from("listener"),
+            }),
+            min_delta: Some(2),
+        },
+    );
+    let notify = |chain: &mut Chain| {
+        let mut env = mock_env(OWNER, &[]);
+        env.contract.address = chain.offspring[0].0.clone();
+        offspring::handle(
+            &mut chain.offspring[0].1,
+            env,
+            offspring::msg::HandleMsg::NotifyListener {},
+        )
+    };
+
+    chain.handle_offspring(OWNER, 0, offspring::msg::HandleMsg::Increment { nonce: None });
+    assert!(notify(&mut chain).is_err());
+    chain.handle_offspring(OWNER, 0, offspring::msg::HandleMsg::Increment { nonce: None });
+    match notify(&mut chain).unwrap().messages.as_slice() {
+        [CosmosMsg::Wasm(WasmMsg::Execute { contract_addr, .. })] => {
+            assert_eq!(*contract_addr, HumanAddr::from("listener"));
+        }
+        other => panic!("unexpected messages {:?}", other),
+    }
+    assert!(notify(&mut chain).is_err());
+}
+
+/// This test checks that Increment and Reset only answer with the count once the owner enabled
+/// response data, and that Increment does not reveal a private count to other senders.
+#[test]
+fn test_offspring_response_data() {
+    let mut chain = Chain::new();
+    chain.handle_factory(OWNER, create_msg("answering"));
+    let execute = |chain: &mut Chain, sender: &str, msg: offspring::msg::HandleMsg| {
+        let (address, deps) = &mut chain.offspring[0];
+        let mut env = mock_env(sender, &[]);
+        env.contract.address = address.clone();
+        offspring::handle(deps, env, msg).unwrap().data
+    };
+    let increment = offspring::msg::HandleMsg::Increment { nonce: None };
+    assert!(execute(&mut chain, OWNER, increment.clone()).is_none());
+
+    execute(
+        &mut chain,
+        OWNER,
+        offspring::msg::HandleMsg::SetResponseData { enabled: true },
+    );
+    let count = |data: Option<Binary>| match from_binary(&data.unwrap()).unwrap() {
+        offspring::msg::HandleAnswer::Increment { count } => count,
+        offspring::msg::HandleAnswer::Reset { count } => Some(count),
+    };
+    assert_eq!(count(execute(&mut chain, OWNER, increment.clone())), Some(2));
+    assert_eq!(count(execute(&mut chain, "bob", increment)), None);
+    let reset = offspring::msg::HandleMsg::Reset {
+        count: 7,
+        nonce: None,
+    };
+    assert_eq!(count(execute(&mut chain, OWNER, reset)), Some(7));
+}
+
+/// This test checks that adding an offspring version keeps the earlier one registered, and that
+/// offspring can still be created from it.
+#[test]
+fn test_multiple_versions() {
+    let mut chain = Chain::new();
+    chain.handle_factory(
+        ADMIN,
+        HandleMsg::NewOffspringContract {
+            offspring_contract: OffspringContractInfo {
+                code_id: 3,
+                code_hash: "ab".repeat(32),
+            },
+        },
+    );
+    match chain.query_factory(QueryMsg::OffspringVersions {}) {
+        QueryAnswer::OffspringVersions { versions } => {
+            assert_eq!(versions.len(), 2);
+            assert_eq!(versions[0].offspring_contract.code_id, 2);
+            assert!(!versions[0].current);
+            assert!(versions[1].current && versions[1].enabled);
+        }
+        _ => panic!("unexpected answer"),
+    }
+
+    let mut env = mock_env(OWNER, &[]);
+    env.contract.address = HumanAddr::from(FACTORY);
+    let create = |version: Option<u32>| match create_msg("versioned counter") {
+        HandleMsg::CreateOffspring {
+            label,
+            entropy,
+            owner,
+            count,
+            ..
+        } => HandleMsg::CreateOffspring {
+            label,
+            entropy,
+            owner,
+            count,
+            description: None,
+            dependencies: None,
+            referrer: None,
+            count_privacy: None,
+            version,
+            context: None,
+        },
+        _ => unreachable!(),
+    };
+    let res = contract::handle(&mut chain.factory, env.clone(), create(Some(0))).unwrap();
+    match res.messages.as_slice() {
+        [CosmosMsg::Wasm(WasmMsg::Instantiate { code_id, .. })] => assert_eq!(*code_id, 2),
+        other => panic!("unexpected messages {:?}", other),
+    }
+    assert!(contract::handle(&mut chain.factory, env, create(Some(2))).is_err());
+}
+
+/// This test checks that rolling back restores the replaced offspring version, and that both
+/// version changes are logged and recorded in the audit log.
+#[test]
+fn test_version_rollback() {
+    let init: InitMsg = from_slice(
+        br#"{"entropy":"scenario entropy","offspring_contract":{"code_id":2,"code_hash":"offspring hash"}}"#,
+    )
+    .unwrap();
+    assert!(contract::init(&mut mock_dependencies(20, &[]), mock_env(ADMIN, &[]), init).is_err());
+
+    let mut chain = Chain::new();
+    chain.handle_factory(
+        ADMIN,
+        HandleMsg::NewOffspringContract {
+            offspring_contract: OffspringContractInfo {
+                code_id: 3,
+                code_hash: "ab".repeat(32),
+            },
+        },
+    );
+    let mut env = mock_env(ADMIN, &[]);
+    env.contract.address = HumanAddr::from(FACTORY);
+    let res = contract::handle(&mut chain.factory, env, HandleMsg::RollbackVersion {}).unwrap();
+    let attributes: Vec<(&str, &str)> = res
+        .log
+        .iter()
+        .map(|attribute| (attribute.key.trim_end(), attribute.value.trim_end()))
+        .collect();
+    assert!(attributes.contains(&("action", "rollback_version")));
+    assert!(attributes.contains(&("code_id", "2")));
+    match chain.query_factory(QueryMsg::OffspringVersions {}) {
+        QueryAnswer::OffspringVersions { versions } => {
+            assert!(versions[0].current && !versions[1].current);
+        }
+        _ => panic!("unexpected answer"),
+    }
+
+    chain.handle_factory(
+        ADMIN,
+        HandleMsg::SetViewingKey {
+            key: "admin key".to_string(),
+            padding: None,
+        },
+    );
+    let audit = QueryMsg::AuditLog {
+        address: HumanAddr::from(ADMIN),
+        viewing_key: "admin key".to_string(),
+        start_page: None,
+        page_size: None,
+    };
+    match chain.query_factory(audit) {
+        QueryAnswer::AuditLog { entries, .. } => {
+            let actions: Vec<&str> = entries.iter().map(|e| e.action.as_str()).collect();
+            assert_eq!(actions, vec!["rollback_version", "new_version"]);
+        }
+        _ => panic!("unexpected answer"),
+    }
+}
+
+/// This test checks that the offspring counts follow registrations and deactivations, and that
+/// an owner's own counts are only shown with a valid viewing key.
+#[test]
+fn test_offspring_count() {
+    let mut chain = Chain::new();
+    chain.handle_factory(OWNER, create_msg("first"));
+    chain.handle_factory(OWNER, create_msg("second"));
+    chain.handle_offspring(OWNER, 0, offspring::msg::HandleMsg::Deactivate {});
+    chain.handle_factory(
+        OWNER,
+        HandleMsg::SetViewingKey {
+            key: "owner key".to_string(),
+            padding: None,
+        },
+    );
+    let count = |address: Option<&str>, key: &str| QueryMsg::OffspringCount {
+        address: address.map(HumanAddr::from),
+        viewing_key: Some(key.to_string()),
+    };
+
+    match chain.query_factory(count(None, "")) {
+        QueryAnswer::OffspringCounts {
+            active,
+            inactive,
+            my_active,
+            ..
+        } => {
+            assert_eq!((active, inactive), (1, 1));
+            assert_eq!(my_active, None);
+        }
+        _ => panic!("unexpected answer"),
+    }
+    match chain.query_factory(count(Some(OWNER), "owner key")) {
+        QueryAnswer::OffspringCounts {
+            my_active,
+            my_inactive,
+            ..
+        } => assert_eq!((my_active, my_inactive), (Some(1), Some(1))),
+        _ => panic!("unexpected answer"),
+    }
+    match chain.query_factory(count(Some(OWNER), "wrong key")) {
+        QueryAnswer::ViewingKeyError { .. } => {}
+        _ => panic!("unexpected answer"),
+    }
+}
+
+/// This test checks that owners can remove their inactive offspring, that active offspring are
+/// skipped, and that other addresses can not remove them.
+#[test]
+fn test_remove_offspring() {
+    let mut chain = Chain::new();
+    chain.handle_factory(OWNER, create_msg("first"));
+    chain.handle_factory(OWNER, create_msg("second"));
+    chain.handle_offspring(OWNER, 0, offspring::msg::HandleMsg::Deactivate {});
+    let remove = || HandleMsg::RemoveOffspring {
+        addresses: vec![HumanAddr::from("offspring0"), HumanAddr::from("offspring1")],
+    };
+
+    let mut env = mock_env("stranger", &[]);
+    env.contract.address = HumanAddr::from(FACTORY);
+    assert!(contract::handle(&mut chain.factory, env, remove()).is_err());
+
+    let mut env = mock_env(OWNER, &[]);
+    env.contract.address = HumanAddr::from(FACTORY);
+    let res = contract::handle(&mut chain.factory, env, remove()).unwrap();
+    match from_binary(&res.data.unwrap()).unwrap() {
+        HandleAnswer::RemoveOffspring { removed, .. } => assert_eq!(removed, 1),
+        _ => panic!("unexpected answer"),
+    }
+    match chain.query_factory(QueryMsg::OffspringLifecycle {
+        address: HumanAddr::from("offspring0"),
+        viewer: None,
+        viewing_key: None,
+    }) {
+        QueryAnswer::OffspringLifecycle { status, .. } => {
+            assert!(matches!(status, LifecycleStatus::Purged { .. }))
+        }
+        _ => panic!("unexpected answer"),
+    }
+}
+
+/// This test checks that every bootstrap offspring is instantiated by the init, that one that
+/// never registers does not hold up the others, and that later registrations create nothing.
+#[test]
+fn test_bootstrap() {
+    let mut factory = mock_dependencies(20, &[]);
+    let msg: InitMsg = from_slice(
+        br#"{"entropy":"scenario entropy","offspring_contract":{"code_id":2,"code_hash":"cdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcd"},"bootstrap":[{"owner":"owner","count":0,"label":"first"},{"owner":"owner","count":1,"label":"second"},{"owner":"bob","count":2,"label":"third"}]}"#,
+    )
+    .unwrap();
+    let mut env = mock_env(ADMIN, &[]);
+    env.contract.address = HumanAddr::from(FACTORY);
+    let res = contract::init(&mut factory, env, msg).unwrap();
+    assert_eq!(res.messages.len(), 3);
+    let mut chain = Chain {
+        factory,
+        offspring: vec![],
+    };
+    // the first bootstrap offspring never registers
+    chain.dispatch(HumanAddr::from(FACTORY), res.messages.into_iter().skip(1).collect());
+    let labels = |chain: &Chain| match chain.query_factory(list_active()) {
+        QueryAnswer::ListActiveOffspring { active, .. } => {
+            let mut labels: Vec<String> = active.into_iter().map(|info| info.label).collect();
+            labels.sort();
+            labels
+        }
+        _ => panic!("unexpected answer"),
+    };
+    assert_eq!(labels(&chain), vec!["second", "third"]);
+
+    // a stranger's registration does not instantiate the missing bootstrap offspring
+    chain.handle_factory("stranger", create_msg("later"));
+    assert_eq!(chain.offspring.len(), 3);
+    assert_eq!(labels(&chain), vec!["later", "second", "third"]);
+}
+
+/// This test checks that deferred maintenance moves on through the pending offspring across
+/// handles, so an expired creation is discarded even if an earlier one is still waiting.
+#[test]
+fn test_pending_expiry_advances() {
+    let mut chain = Chain::with_init(
+        br#"{"entropy":"scenario entropy","offspring_contract":{"code_id":2,"code_hash":"cdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcd"},"registration_retry_blocks":10,"maintenance_budget":0}"#,
+    );
+    let handle_at = |chain: &mut Chain, sender: &str, height: u64, msg: HandleMsg| {
+        let mut env = mock_env(sender, &[]);
+        env.contract.address = HumanAddr::from(FACTORY);
+        env.block.height = height;
+        // the offspring never register, so their instantiation is not executed
+        contract::handle(&mut chain.factory, env, msg).unwrap();
+    };
+    handle_at(&mut chain, OWNER, 1000, create_msg("waiting"));
+    handle_at(&mut chain, OWNER, 100, create_msg("abandoned"));
+    let pending = |chain: &Chain| match chain.query_factory(QueryMsg::Health {}) {
+        QueryAnswer::Health { checks, .. } => checks[0].detail.clone(),
+        _ => panic!("unexpected answer"),
+    };
+    assert!(pending(&chain).starts_with("2 creations"));
+
+    // each handle examines one pending offspring: first the one still waiting, then the
+    // abandoned one
+    handle_at(&mut chain, ADMIN, 500, HandleMsg::SetMaintenanceBudget { budget: 1 });
+    assert!(pending(&chain).starts_with("2 creations"));
+    handle_at(&mut chain, ADMIN, 500, HandleMsg::SetMaintenanceBudget { budget: 1 });
+    assert!(pending(&chain).starts_with("1 creations"));
+}
+
+/// This test checks that CanICreate refuses a creator who used up the rate limit window, or whose
+/// creations fill the pending limit, until the window ends or the creations expire.
+#[test]
+fn test_can_i_create_limits() {
+    let handle_at = |chain: &mut Chain, sender: &str, height: u64, msg: HandleMsg| {
+        let mut env = mock_env(sender, &[]);
+        env.contract.address = HumanAddr::from(FACTORY);
+        env.block.height = height;
+        // the offspring never register, so their instantiation is not executed
+        contract::handle(&mut chain.factory, env, msg).unwrap();
+    };
+    let set_key = || HandleMsg::SetViewingKey {
+        key: "owner key".to_string(),
+        padding: None,
+    };
+    let can_i_create = |chain: &Chain| match chain.query_factory(QueryMsg::CanICreate {
+        address: HumanAddr::from(OWNER),
+        viewing_key: "owner key".to_string(),
+    }) {
+        QueryAnswer::CanICreate { allowed, reasons } => (allowed, reasons),
+        _ => panic!("unexpected answer"),
+    };
+
+    let mut chain = Chain::with_init(
+        br#"{"entropy":"scenario entropy","offspring_contract":{"code_id":2,"code_hash":"cdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcd"},"creation_rules":{"rate_limit":{"max_creations":1,"window_blocks":100}},"maintenance_budget":0}"#,
+    );
+    handle_at(&mut chain, OWNER, 1000, set_key());
+    assert_eq!(can_i_create(&chain), (true, vec![]));
+    handle_at(&mut chain, OWNER, 1000, create_msg("first"));
+    let (allowed, reasons) = can_i_create(&chain);
+    assert!(!allowed);
+    assert_eq!(
+        reasons,
+        vec!["At most 1 offspring can be created every 100 blocks. Try again at block 1100"]
+    );
+    handle_at(&mut chain, OWNER, 1100, set_key());
+    assert_eq!(can_i_create(&chain), (true, vec![]));
+
+    let mut chain = Chain::with_init(
+        br#"{"entropy":"scenario entropy","offspring_contract":{"code_id":2,"code_hash":"cdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcd"},"creation_rules":{"max_pending":1},"registration_retry_blocks":10,"maintenance_budget":0}"#,
+    );
+    handle_at(&mut chain, OWNER, 1000, set_key());
+    handle_at(&mut chain, OWNER, 1000, create_msg("waiting"));
+    let (allowed, reasons) = can_i_create(&chain);
+    assert!(!allowed);
+    assert!(reasons[0].starts_with("At most 1 of your creations can wait for registration"));
+    // the pending creation expired, even though maintenance did not clean it up yet
+    handle_at(&mut chain, OWNER, 1011, set_key());
+    assert_eq!(can_i_create(&chain), (true, vec![]));
+}
+
+/// This test checks that an offspring can only be nudged once its inactivity limit has passed
+/// since its last action, that a nudge deactivates it and tells the factory it expired, and that
+/// the nudge itself does not count as activity.
+#[test]
+fn test_nudge() {
+    use offspring::state::{load as load_state, save as save_state, State, CONFIG_KEY};
+
+    let mut chain = Chain::new();
+    chain.handle_factory(OWNER, create_msg("idle"));
+    let height = mock_env(OWNER, &[]).block.height;
+    // the factory does not give its offspring an inactivity limit, so it is set in the state
+    let storage = &mut chain.offspring[0].1.storage;
+    let mut state: State = load_state(storage, CONFIG_KEY).unwrap();
+    state.inactivity_limit = Some(10);
+    save_state(storage, CONFIG_KEY, &state).unwrap();
+
+    let handle_at = |chain: &mut Chain, sender: &str, height: u64, msg| {
+        let mut env = mock_env(sender, &[]);
+        env.contract.address = HumanAddr::from("offspring0");
+        env.block.height = height;
+        offspring::handle(&mut chain.offspring[0].1, env, msg)
+    };
+    let increment = offspring::msg::HandleMsg::Increment { nonce: None };
+    let res = handle_at(&mut chain, OWNER, height + 5, increment).unwrap();
+    chain.dispatch(HumanAddr::from("offspring0"), res.messages);
+
+    let nudge = offspring::msg::HandleMsg::Nudge {};
+    let err = handle_at(&mut chain, "bob", height + 14, nudge.clone()).unwrap_err();
+    assert!(err.to_string().contains(&format!("before block {}", height + 15)));
+
+    let res = handle_at(&mut chain, "bob", height + 15, nudge).unwrap();
+    match &res.messages[0] {
+        CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr, msg, ..
+        }) => {
+            assert_eq!(contract_addr, &HumanAddr::from(FACTORY));
+            match from_binary(msg).unwrap() {
+                HandleMsg::DeactivateOffspring { owner, expired } => {
+                    assert_eq!(owner, HumanAddr::from(OWNER));
+                    assert!(expired);
+                }
+                _ => panic!("unexpected message"),
+            }
+        }
+        other => panic!("unexpected message {:?}", other),
+    }
+    chain.dispatch(HumanAddr::from("offspring0"), res.messages);
+    match chain.query_factory(list_inactive()) {
+        QueryAnswer::ListInactiveOffspring { inactive, .. } => {
+            assert_eq!(inactive[0].deactivated_by, DeactivationSource::Expired);
+        }
+        _ => panic!("unexpected answer"),
+    }
+    let state: State = load_state(&chain.offspring[0].1.storage, CONFIG_KEY).unwrap();
+    assert!(!state.active);
+    assert_eq!(state.last_action_height, height + 5);
+}
+
+/// This test checks that a registry exported in batches can be imported into another factory,
+/// and that an export fails once the registry changes under it.
+#[test]
+fn test_export_import_registry() {
+    let mut source = Chain::new();
+    for label in &["first", "second", "third"] {
+        source.handle_factory(OWNER, create_msg(label));
+    }
+    source.handle_offspring(OWNER, 1, offspring::msg::HandleMsg::Deactivate {});
+    source.handle_factory(
+        ADMIN,
+        HandleMsg::SetViewingKey {
+            key: "admin key".to_string(),
+            padding: None,
+        },
+    );
+    let export = |token: Option<String>| QueryMsg::ExportRegistry {
+        address: HumanAddr::from(ADMIN),
+        viewing_key: "admin key".to_string(),
+        token,
+        batch_size: Some(2),
+    };
+
+    let mut target = Chain::new();
+    let mut token = None;
+    let mut batches = 0;
+    loop {
+        let (records, next_token) = match source.query_factory(export(token.clone())) {
+            QueryAnswer::ExportRegistry {
+                records,
+                next_token,
+            } => (records, next_token),
+            _ => panic!("unexpected answer"),
+        };
+        batches += 1;
+        target.handle_factory(
+            ADMIN,
+            HandleMsg::ImportRegistry {
+                token,
+                next_token: next_token.clone(),
+                records,
+            },
+        );
+        match next_token {
+            Some(next) => token = Some(next),
+            None => break,
+        }
+    }
+    assert_eq!(batches, 2);
+    for chain in [&source, &target].iter() {
+        match chain.query_factory(list_active()) {
+            QueryAnswer::ListActiveOffspring { active, .. } => {
+                let mut labels: Vec<&str> = active.iter().map(|o| o.label.as_str()).collect();
+                labels.sort_unstable();
+                assert_eq!(labels, vec!["first", "third"]);
+            }
+            _ => panic!("unexpected answer"),
+        }
+        match chain.query_factory(list_inactive()) {
+            QueryAnswer::ListInactiveOffspring { inactive, .. } => {
+                assert_eq!(inactive.len(), 1);
+                assert_eq!(inactive[0].address, HumanAddr::from("offspring1"));
+                assert_eq!(inactive[0].deactivated_by, DeactivationSource::Owner);
+            }
+            _ => panic!("unexpected answer"),
+        }
+    }
+
+    let token = match source.query_factory(export(None)) {
+        QueryAnswer::ExportRegistry { next_token, .. } => next_token,
+        _ => panic!("unexpected answer"),
+    };
+    source.handle_factory(OWNER, create_msg("fourth"));
+    let err = contract::query(&source.factory, export(token)).unwrap_err();
+    assert!(err.to_string().contains("registry changed"));
+}
+
+/// This test checks that creation_quota reads the rate limit window at the height of the
+/// factory's latest handle.
+#[test]
+fn test_creation_quota() {
+    let mut chain = Chain::with_init(
+        br#"{"entropy":"scenario entropy","offspring_contract":{"code_id":2,"code_hash":"cdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcd"},"creation_rules":{"rate_limit":{"max_creations":2,"window_blocks":100}},"maintenance_budget":0}"#,
+    );
+    let handle_at = |chain: &mut Chain, height: u64, msg: HandleMsg| {
+        let mut env = mock_env(OWNER, &[]);
+        env.contract.address = HumanAddr::from(FACTORY);
+        env.block.height = height;
+        contract::handle(&mut chain.factory, env, msg).unwrap();
+    };
+    let set_key = || HandleMsg::SetViewingKey {
+        key: "owner key".to_string(),
+        padding: None,
+    };
+    let quota = |chain: &Chain| match chain.query_factory(QueryMsg::CreationQuota {
+        address: HumanAddr::from(OWNER),
+        viewing_key: "owner key".to_string(),
+    }) {
+        QueryAnswer::CreationQuota {
+            remaining,
+            resets_at,
+            ..
+        } => (remaining, resets_at),
+        _ => panic!("unexpected answer"),
+    };
+
+    handle_at(&mut chain, 1000, set_key());
+    assert_eq!(quota(&chain), (Some(2), None));
+    handle_at(&mut chain, 1000, create_msg("first"));
+    assert_eq!(quota(&chain), (Some(1), Some(1100)));
+    handle_at(&mut chain, 1099, set_key());
+    assert_eq!(quota(&chain), (Some(1), Some(1100)));
+    // the window ended at the height of the latest handle
+    handle_at(&mut chain, 1100, set_key());
+    assert_eq!(quota(&chain), (Some(2), None));
+}
+
+/// This test checks that a removed offspring is reported as purged until its tombstone expires,
+/// and as unknown afterwards.
+#[test]
+fn test_tombstone_expiry() {
+    let mut chain = Chain::with_init(
+        br#"{"entropy":"scenario entropy","offspring_contract":{"code_id":2,"code_hash":"cdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcd"},"tombstone_retention":10,"maintenance_budget":0}"#,
+    );
+    chain.handle_factory(OWNER, create_msg("first"));
+    chain.handle_offspring(OWNER, 0, offspring::msg::HandleMsg::Deactivate {});
+    chain.handle_factory(
+        OWNER,
+        HandleMsg::RemoveOffspring {
+            addresses: vec![HumanAddr::from("offspring0")],
+        },
+    );
+    let status = |chain: &Chain| match chain.query_factory(QueryMsg::OffspringLifecycle {
+        address: HumanAddr::from("offspring0"),
+        viewer: None,
+        viewing_key: None,
+    }) {
+        QueryAnswer::OffspringLifecycle { status, .. } => status,
+        _ => panic!("unexpected answer"),
+    };
+    assert!(matches!(status(&chain), LifecycleStatus::Purged { .. }));
+
+    // any later handle tells the factory the current height, even if nothing is swept
+    let mut env = mock_env("bob", &[]);
+    env.contract.address = HumanAddr::from(FACTORY);
+    env.block.height += 10;
+    let set_key = HandleMsg::SetViewingKey {
+        key: "bob key".to_string(),
+        padding: None,
+    };
+    contract::handle(&mut chain.factory, env, set_key).unwrap();
+    assert!(matches!(status(&chain), LifecycleStatus::Unknown {}));
+}
+
+/// This test checks that newest_first lists the most recently activated or deactivated offspring
+/// first, skipping offspring that left the list and counting a reactivation as the newest.
+#[test]
+fn test_newest_first() {
+    let mut chain = Chain::new();
+    for label in &["first", "second", "third"] {
+        chain.handle_factory(OWNER, create_msg(label));
+    }
+    let active = |chain: &Chain, start_page: Option<u32>, page_size: Option<u32>| {
+        match chain.query_factory(QueryMsg::ListActiveOffspring {
+            start_page,
+            page_size,
+            address: None,
+            viewing_key: None,
+            if_modified_since: None,
+            newest_first: Some(true),
+        }) {
+            QueryAnswer::ListActiveOffspring {
+                active,
+                total_count,
+                has_more,
+                ..
+            } => (
+                active.into_iter().map(|info| info.label).collect::<Vec<String>>(),
+                total_count,
+                has_more,
+            ),
+            _ => panic!("unexpected answer"),
+        }
+    };
+    assert_eq!(active(&chain, None, None).0, vec!["third", "second", "first"]);
+
+    chain.handle_offspring(OWNER, 1, offspring::msg::HandleMsg::Deactivate {});
+    chain.handle_offspring(OWNER, 0, offspring::msg::HandleMsg::Deactivate {});
+    chain.handle_offspring(OWNER, 0, offspring::msg::HandleMsg::Reactivate {});
+    assert_eq!(active(&chain, None, None).0, vec!["first", "third"]);
+    assert_eq!(
+        active(&chain, Some(1), Some(1)),
+        (vec!["third".to_string()], 2, false)
+    );
+    match chain.query_factory(QueryMsg::ListInactiveOffspring {
+        start_page: None,
+        page_size: None,
+        address: None,
+        viewing_key: None,
+        if_modified_since: None,
+        newest_first: Some(true),
+    }) {
+        QueryAnswer::ListInactiveOffspring { inactive, .. } => {
+            assert_eq!(inactive.len(), 1);
+            assert_eq!(inactive[0].label, "second");
+        }
+        _ => panic!("unexpected answer"),
+    }
+}
+
+/// This test checks that an owner's reverse listing stays chronological after deactivations,
+/// reactivations and transfers reorder the underlying storage.
+#[test]
+fn test_reverse_owner_listing_after_churn() {
+    let mut chain = Chain::new();
+    for label in &["a", "b", "c", "d"] {
+        chain.handle_factory(OWNER, create_msg(label));
+    }
+    let mut msg = create_msg("gift");
+    if let HandleMsg::CreateOffspring { owner, .. } = &mut msg {
+        *owner = HumanAddr::from("bob");
+    }
+    chain.handle_factory("bob", msg);
+    chain.handle_factory(
+        OWNER,
+        HandleMsg::SetViewingKey {
+            key: "owner key".to_string(),
+            padding: None,
+        },
+    );
+    // removing "a" moves the last entry into its storage slot
+    chain.handle_offspring(OWNER, 0, offspring::msg::HandleMsg::Deactivate {});
+    chain.handle_offspring(OWNER, 1, offspring::msg::HandleMsg::Deactivate {});
+    chain.handle_offspring(OWNER, 1, offspring::msg::HandleMsg::Reactivate {});
+    let gift = chain.offspring[4].0.clone();
+    chain.handle_factory(
+        "bob",
+        HandleMsg::OfferTransfer {
+            offspring: gift.clone(),
+            recipient: HumanAddr::from(OWNER),
+            price: None,
+        },
+    );
+    chain.handle_factory(OWNER, HandleMsg::AcceptTransfer { offspring: gift });
+
+    let newest = |chain: &Chain, start_page: u32| match chain.query_factory(
+        QueryMsg::ListMyOffspring {
+            address: HumanAddr::from(OWNER),
+            viewing_key: "owner key".to_string(),
+            filter: Some(FilterTypes::Active),
+            start_page: Some(start_page),
+            page_size: Some(2),
+            reverse: Some(true),
+            label_contains: None,
+            if_modified_since: None,
+        },
+    ) {
+        QueryAnswer::ListMyOffspring { active, .. } => active
+            .unwrap()
+            .into_iter()
+            .map(|info| info.label)
+            .collect::<Vec<String>>(),
+        _ => panic!("unexpected answer"),
+    };
+    assert_eq!(newest(&chain, 0), vec!["gift", "b"]);
+    assert_eq!(newest(&chain, 1), vec!["d", "c"]);
+    assert!(newest(&chain, 2).is_empty());
+}
+
+/// This test checks that an offspring its owner deactivated can reactivate itself, and that the
+/// factory refuses to reactivate an offspring the admin deactivated.
+#[test]
+fn test_reactivate_offspring() {
+    let mut chain = Chain::new();
+    chain.handle_factory(OWNER, create_msg("first"));
+    chain.handle_factory(OWNER, create_msg("second"));
+    chain.handle_offspring(OWNER, 0, offspring::msg::HandleMsg::Deactivate {});
+    chain.handle_factory(
+        ADMIN,
+        HandleMsg::DeactivateOffspringAdmin {
+            offspring: HumanAddr::from("offspring1"),
+        },
+    );
+    chain.handle_offspring(OWNER, 0, offspring::msg::HandleMsg::Reactivate {});
+    let count = || QueryMsg::OffspringCount {
+        address: None,
+        viewing_key: None,
+    };
+    match chain.query_factory(count()) {
+        QueryAnswer::OffspringCounts {
+            active, inactive, ..
+        } => assert_eq!((active, inactive), (1, 1)),
+        _ => panic!("unexpected answer"),
+    }
+
+    let address = chain.offspring[1].0.clone();
+    let mut env = mock_env(OWNER, &[]);
+    env.contract.address = address.clone();
+    let res = offspring::handle(
+        &mut chain.offspring[1].1,
+        env,
+        offspring::msg::HandleMsg::Reactivate {},
+    )
+    .unwrap();
+    match res.messages.into_iter().next() {
+        Some(CosmosMsg::Wasm(WasmMsg::Execute { msg, .. })) => {
+            let mut env = mock_env(address, &[]);
+            env.contract.address = HumanAddr::from(FACTORY);
+            assert!(contract::handle(&mut chain.factory, env, from_binary(&msg).unwrap()).is_err());
+        }
+        other => panic!("unexpected message {:?}", other),
+    }
+}
+
+/// This test checks that a factory requiring the owner to be the sender only lets other addresses
+/// create offspring for an owner while the owner grants them creation rights.
+#[test]
+fn test_create_delegation() {
+    let mut chain = Chain::with_init(
+        br#"{"entropy":"scenario entropy","offspring_contract":{"code_id":2,"code_hash":"cdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcd"},"require_owner_is_sender":true}"#,
+    );
+    let create = |chain: &mut Chain| {
+        let mut env = mock_env("creator", &[]);
+        env.contract.address = HumanAddr::from(FACTORY);
+        contract::handle(&mut chain.factory, env, create_msg("delegated"))
+    };
+    assert!(create(&mut chain).is_err());
+
+    chain.handle_factory(
+        OWNER,
+        HandleMsg::GrantCreate {
+            creator: HumanAddr::from("creator"),
+        },
+    );
+    assert!(create(&mut chain).is_ok());
+
+    chain.handle_factory(
+        OWNER,
+        HandleMsg::RevokeCreate {
+            creator: HumanAddr::from("creator"),
+        },
+    );
+    assert!(create(&mut chain).is_err());
+}
+
+/// This test checks that permits for another contract are refused, both by permit queries and
+/// when offspring ask the factory to validate them.
+#[test]
+fn test_permit_for_other_contract() {
+    let chain = Chain::new();
+    let permit = || {
+        from_slice(
+            br#"{"params":{"allowed_tokens":["another contract"],"permit_name":"test","chain_id":"secret-4","permissions":["owner"]},"signature":{"pub_key":{"type":"tendermint/PubKeySecp256k1","value":"YWxpY2U="},"signature":"YWxpY2U="}}"#,
+        )
+        .unwrap()
+    };
+    let query = QueryMsg::WithPermit {
+        permit: permit(),
+        query: QueryWithPermit::OffspringCount {},
+    };
+    assert!(contract::query(&chain.factory, query).is_err());
+    match chain.query_factory(QueryMsg::IsPermitValid { permit: permit() }) {
+        QueryAnswer::IsPermitValid { is_valid, address } => {
+            assert!(!is_valid);
+            assert_eq!(address, None);
+        }
+        _ => panic!("unexpected answer"),
+    }
+}
+
+/// This test creates a batch of offspring in which one offspring has an invalid owner, and checks
+/// that the others are created and registered while the invalid one is reported as failed.
+#[test]
+fn test_batch_create() {
+    let mut chain = Chain::new();
+    let spec = |label: &str, owner: &str| OffspringSpec {
+        label: Some(label.to_string()),
+        owner: HumanAddr::from(owner),
+        count: 0,
+        description: None,
+        count_privacy: None,
+        version: None,
+    };
+    let mut env = mock_env(OWNER, &[]);
+    env.contract.address = HumanAddr::from(FACTORY);
+    let msg = HandleMsg::BatchCreateOffspring {
+        entropy: "batch entropy".to_string(),
+        offspring: vec![spec("first", OWNER), spec("invalid", ""), spec("second", OWNER)],
+    };
+    let res = contract::handle(&mut chain.factory, env, msg).unwrap();
+    match from_binary(&res.data.clone().unwrap()).unwrap() {
+        HandleAnswer::BatchCreateOffspring { results } => {
+            let statuses: Vec<bool> = results
+                .iter()
+                .map(|result| matches!(result.status, ResponseStatus::Success))
+                .collect();
+            assert_eq!(statuses, vec![true, false, true]);
+            assert_eq!(results[2].label.as_deref(), Some("second"));
+            assert!(results[1].error.is_some());
+        }
+        _ => panic!("unexpected answer"),
+    }
+    chain.dispatch(HumanAddr::from(FACTORY), res.messages);
+
+    match chain.query_factory(QueryMsg::OffspringCount {
+        address: None,
+        viewing_key: None,
+    }) {
+        QueryAnswer::OffspringCounts { active, .. } => assert_eq!(active, 2),
+        _ => panic!("unexpected answer"),
+    }
+}
+
+/// This test checks that paid creation fees are collected, and that the admin can withdraw them
+/// to a treasury but not more than was collected.
+#[test]
+fn test_withdraw_fees() {
+    let mut chain = Chain::with_init(
+        br#"{"entropy":"scenario entropy","offspring_contract":{"code_id":2,"code_hash":"cdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcd"},"creation_rules":{"fee":"1000"}}"#,
+    );
+    for label in &["first", "second"] {
+        let mut env = mock_env(OWNER, &coins(1000, "uscrt"));
+        env.contract.address = HumanAddr::from(FACTORY);
+        contract::handle(&mut chain.factory, env, create_msg(label)).unwrap();
+    }
+    let withdraw = |amount: Option<u128>| HandleMsg::WithdrawFees {
+        treasury: HumanAddr::from("treasury"),
+        amount: amount.map(Uint128),
+        token: None,
+    };
+    let mut env = mock_env(ADMIN, &[]);
+    env.contract.address = HumanAddr::from(FACTORY);
+    let res = contract::handle(&mut chain.factory, env.clone(), withdraw(Some(1500))).unwrap();
+    match &res.messages[0] {
+        CosmosMsg::Bank(BankMsg::Send {
+            to_address, amount, ..
+        }) => {
+            assert_eq!(to_address, &HumanAddr::from("treasury"));
+            assert_eq!(amount, &coins(1500, "uscrt"));
+        }
+        other => panic!("unexpected message {:?}", other),
+    }
+    assert!(contract::handle(&mut chain.factory, env.clone(), withdraw(Some(501))).is_err());
+    let res = contract::handle(&mut chain.factory, env, withdraw(None)).unwrap();
+    match from_binary(&res.data.unwrap()).unwrap() {
+        HandleAnswer::WithdrawFees {
+            withdrawn,
+            remaining,
+            ..
+        } => {
+            assert_eq!(withdrawn.u128(), 500);
+            assert_eq!(remaining.u128(), 0);
+        }
+        _ => panic!("unexpected answer"),
+    }
+
+    let mut env = mock_env(OWNER, &[]);
+    env.contract.address = HumanAddr::from(FACTORY);
+    assert!(contract::handle(&mut chain.factory, env, withdraw(None)).is_err());
+}
+
+/// This test checks that a recovery request only changes the offspring's owner once the admin
+/// approves it, and that factories that do not allow recovery refuse the request.
+#[test]
+fn test_recovery() {
+    let claimant = "bob";
+    let request = || offspring::msg::HandleMsg::RequestRecovery {};
+    let mut chain = Chain::new();
+    chain.handle_factory(OWNER, create_msg("first"));
+    let mut env = mock_env(claimant, &[]);
+    env.contract.address = HumanAddr::from("offspring0");
+    let res = offspring::handle(&mut chain.offspring[0].1, env, request()).unwrap();
+    let msg = match res.messages.into_iter().next() {
+        Some(CosmosMsg::Wasm(WasmMsg::Execute { msg, .. })) => msg,
+        other => panic!("unexpected message {:?}", other),
+    };
+    let mut env = mock_env("offspring0", &[]);
+    env.contract.address = HumanAddr::from(FACTORY);
+    assert!(contract::handle(&mut chain.factory, env, from_binary(&msg).unwrap()).is_err());
+
+    let mut chain = Chain::with_init(
+        br#"{"entropy":"scenario entropy","offspring_contract":{"code_id":2,"code_hash":"cdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcd"},"allow_recovery":true}"#,
+    );
+    chain.handle_factory(OWNER, create_msg("first"));
+    chain.handle_offspring(
+        OWNER,
+        0,
+        offspring::msg::HandleMsg::SetFallbackKey {
+            key: Some("fallback".to_string()),
+        },
+    );
+    chain.handle_offspring(claimant, 0, request());
+    let approve = || HandleMsg::ApproveRecovery {
+        offspring: HumanAddr::from("offspring0"),
+    };
+    let mut env = mock_env(OWNER, &[]);
+    env.contract.address = HumanAddr::from(FACTORY);
+    assert!(contract::handle(&mut chain.factory, env, approve()).is_err());
+    chain.handle_factory(ADMIN, approve());
+    // the previous owner's fallback key must not authenticate the claimant
+    for address in &[claimant, OWNER] {
+        assert!(offspring::query(
+            &chain.offspring[0].1,
+            offspring::msg::QueryMsg::GetCount {
+                address: Some(HumanAddr::from(*address)),
+                viewing_key: Some("fallback".to_string()),
+            },
+        )
+        .is_err());
+    }
+
+    let mut env = mock_env(OWNER, &[]);
+    env.contract.address = HumanAddr::from("offspring0");
+    let deactivate = || offspring::msg::HandleMsg::Deactivate {};
+    assert!(offspring::handle(&mut chain.offspring[0].1, env, deactivate()).is_err());
+    chain.handle_offspring(claimant, 0, deactivate());
+    let mut env = mock_env(ADMIN, &[]);
+    env.contract.address = HumanAddr::from(FACTORY);
+    assert!(contract::handle(&mut chain.factory, env, approve()).is_err());
+}
+
+/// This test checks that the registry only keeps a preview of descriptions longer than the
+/// factory's limit, and flags it, while the offspring keeps the full text.
+#[test]
+fn test_description_limit() {
+    let mut chain = Chain::with_init(
+        br#"{"entropy":"scenario entropy","offspring_contract":{"code_id":2,"code_hash":"cdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcd"},"description_limit":5}"#,
+    );
+    let mut msg = create_msg("first");
+    if let HandleMsg::CreateOffspring { description, .. } = &mut msg {
+        *description = Some("counts the tickets".to_string());
+    }
+    chain.handle_factory(OWNER, msg);
+    match chain.query_factory(list_active()) {
+        QueryAnswer::ListActiveOffspring { active, .. } => {
+            assert_eq!(active[0].description, Some("count".to_string()));
+            assert!(active[0].description_truncated);
+        }
+        _ => panic!("unexpected answer"),
+    }
+    let info = offspring::query(&chain.offspring[0].1, offspring::msg::QueryMsg::GetInfo {});
+    assert!(String::from_utf8(info.unwrap().0).unwrap().contains("counts the tickets"));
+
+    chain.handle_offspring(
+        OWNER,
+        0,
+        offspring::msg::HandleMsg::UpdateDescription {
+            description: Some("tix".to_string()),
+        },
+    );
+    match chain.query_factory(list_active()) {
+        QueryAnswer::ListActiveOffspring { active, .. } => {
+            assert_eq!(active[0].description, Some("tix".to_string()));
+            assert!(!active[0].description_truncated);
+        }
+        _ => panic!("unexpected answer"),
+    }
+}
+
+/// This test checks that sending the token fee with an embedded CreateOffspring creates the
+/// offspring for the owner of the tokens, that other tokens and amounts are refused, and that the
+/// admin can withdraw the collected tokens.
+#[test]
+fn test_token_fee() {
+    let mut chain = Chain::with_init(
+        br#"{"entropy":"scenario entropy","offspring_contract":{"code_id":2,"code_hash":"cdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcd"},"creation_rules":{"token_fee":{"token":{"code_hash":"token hash","address":"token"},"amount":"50"}}}"#,
+    );
+    let receive = |amount: u128| HandleMsg::Receive {
+        sender: HumanAddr::from(OWNER),
+        from: HumanAddr::from(OWNER),
+        amount: Uint128(amount),
+        msg: Some(to_binary(&create_msg("paid")).unwrap()),
+    };
+    let mut env = mock_env(OWNER, &[]);
+    env.contract.address = HumanAddr::from(FACTORY);
+    assert!(contract::handle(&mut chain.factory, env.clone(), create_msg("free")).is_err());
+    assert!(contract::handle(&mut chain.factory, env, receive(50)).is_err());
+    let mut env = mock_env(TOKEN, &[]);
+    env.contract.address = HumanAddr::from(FACTORY);
+    assert!(contract::handle(&mut chain.factory, env, receive(49)).is_err());
+
+    chain.handle_factory(TOKEN, receive(50));
+    assert_eq!(chain.offspring.len(), 1);
+    match chain.query_factory(list_active()) {
+        QueryAnswer::ListActiveOffspring { active, .. } => assert_eq!(active[0].label, "paid"),
+        _ => panic!("unexpected answer"),
+    }
+
+    let mut env = mock_env(ADMIN, &[]);
+    env.contract.address = HumanAddr::from(FACTORY);
+    let withdraw = HandleMsg::WithdrawFees {
+        treasury: HumanAddr::from("treasury"),
+        amount: None,
+        token: Some(ContractInfo {
+            code_hash: "token hash".to_string(),
+            address: HumanAddr::from(TOKEN),
+        }),
+    };
+    let res = contract::handle(&mut chain.factory, env, withdraw).unwrap();
+    match from_binary(&res.data.unwrap()).unwrap() {
+        HandleAnswer::WithdrawFees { withdrawn, .. } => assert_eq!(withdrawn.u128(), 50),
+        _ => panic!("unexpected answer"),
+    }
+}
+
+/// This test checks that a referrer embedded in a token payment is refused while the referrals
+/// feature is disabled, and accepted once the admin enables it.
+#[test]
+fn test_referrals_in_token_payment() {
+    let mut chain = Chain::with_init(
+        br#"{"entropy":"scenario entropy","offspring_contract":{"code_id":2,"code_hash":"cdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcd"},"creation_rules":{"token_fee":{"token":{"code_hash":"token hash","address":"token"},"amount":"50"}},"disabled_features":["referrals"]}"#,
+    );
+    let receive = |label: &str| {
+        let mut msg = create_msg(label);
+        if let HandleMsg::CreateOffspring { referrer, .. } = &mut msg {
+            *referrer = Some(HumanAddr::from("referrer"));
+        }
+        HandleMsg::Receive {
+            sender: HumanAddr::from(OWNER),
+            from: HumanAddr::from(OWNER),
+            amount: Uint128(50),
+            msg: Some(to_binary(&msg).unwrap()),
+        }
+    };
+    let mut env = mock_env(TOKEN, &[]);
+    env.contract.address = HumanAddr::from(FACTORY);
+    let err = contract::handle(&mut chain.factory, env, receive("referred")).unwrap_err();
+    assert!(err.to_string().contains("referrals feature is disabled"));
+    assert!(chain.offspring.is_empty());
+
+    chain.handle_factory(
+        ADMIN,
+        HandleMsg::SetFeature {
+            feature: Feature::Referrals,
+            enabled: true,
+        },
+    );
+    chain.handle_factory(TOKEN, receive("referred"));
+    assert_eq!(chain.offspring.len(), 1);
+}
+
+/// This test checks that the referral share of uscrt and token fees is credited to the referrer,
+/// that the admin can only withdraw the rest, and that the referrer can withdraw their share.
+#[test]
+fn test_referral_share() {
+    let mut chain = Chain::with_init(
+        br#"{"entropy":"scenario entropy","offspring_contract":{"code_id":2,"code_hash":"cdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcd"},"creation_rules":{"fee":"1000","token_fee":{"token":{"code_hash":"token hash","address":"token"},"amount":"50"},"referral_share":2000}}"#,
+    );
+    let referred = |label: &str| {
+        let mut msg = create_msg(label);
+        if let HandleMsg::CreateOffspring { referrer, .. } = &mut msg {
+            *referrer = Some(HumanAddr::from("referrer"));
+        }
+        msg
+    };
+    let mut env = mock_env(OWNER, &coins(1000, "uscrt"));
+    env.contract.address = HumanAddr::from(FACTORY);
+    contract::handle(&mut chain.factory, env.clone(), referred("native")).unwrap();
+    contract::handle(&mut chain.factory, env, create_msg("unreferred")).unwrap();
+    chain.handle_factory(
+        TOKEN,
+        HandleMsg::Receive {
+            sender: HumanAddr::from(OWNER),
+            from: HumanAddr::from(OWNER),
+            amount: Uint128(50),
+            msg: Some(to_binary(&referred("token")).unwrap()),
+        },
+    );
+
+    chain.handle_factory(
+        "referrer",
+        HandleMsg::SetViewingKey {
+            key: "referrer key".to_string(),
+            padding: None,
+        },
+    );
+    match chain.query_factory(QueryMsg::ReferralStats {
+        address: HumanAddr::from("referrer"),
+        viewing_key: "referrer key".to_string(),
+    }) {
+        QueryAnswer::ReferralStats { stats } => {
+            assert_eq!(stats.referrals, 2);
+            assert_eq!(stats.earned.u128(), 200);
+            assert_eq!(stats.token_earned.len(), 1);
+            assert_eq!(stats.token_earned[0].token, HumanAddr::from(TOKEN));
+            assert_eq!(stats.token_earned[0].amount.u128(), 10);
+        }
+        _ => panic!("unexpected answer"),
+    }
+
+    let token = ContractInfo {
+        code_hash: "token hash".to_string(),
+        address: HumanAddr::from(TOKEN),
+    };
+    let mut env = mock_env(ADMIN, &[]);
+    env.contract.address = HumanAddr::from(FACTORY);
+    let admin_withdraw = |token: Option<ContractInfo>| HandleMsg::WithdrawFees {
+        treasury: HumanAddr::from("treasury"),
+        amount: None,
+        token,
+    };
+    for (token, collected) in [(None, 1800), (Some(token.clone()), 40)].iter() {
+        let msg = admin_withdraw(token.clone());
+        let res = contract::handle(&mut chain.factory, env.clone(), msg).unwrap();
+        match from_binary(&res.data.unwrap()).unwrap() {
+            HandleAnswer::WithdrawFees { withdrawn, .. } => assert_eq!(withdrawn.u128(), *collected),
+            _ => panic!("unexpected answer"),
+        }
+    }
+
+    let mut env = mock_env("referrer", &[]);
+    env.contract.address = HumanAddr::from(FACTORY);
+    let withdraw = |amount: Option<u128>, token: Option<ContractInfo>| {
+        HandleMsg::WithdrawReferralFees {
+            amount: amount.map(Uint128),
+            token,
+        }
+    };
+    assert!(contract::handle(&mut chain.factory, env.clone(), withdraw(Some(201), None)).is_err());
+    let res = contract::handle(&mut chain.factory, env.clone(), withdraw(Some(150), None)).unwrap();
+    match &res.messages[0] {
+        CosmosMsg::Bank(BankMsg::Send {
+            to_address, amount, ..
+        }) => {
+            assert_eq!(to_address, &HumanAddr::from("referrer"));
+            assert_eq!(amount, &coins(150, "uscrt"));
+        }
+        other => panic!("unexpected message {:?}", other),
+    }
+    match from_binary(&res.data.unwrap()).unwrap() {
+        HandleAnswer::WithdrawReferralFees { remaining, .. } => assert_eq!(remaining.u128(), 50),
+        _ => panic!("unexpected answer"),
+    }
+    let res = contract::handle(&mut chain.factory, env.clone(), withdraw(None, Some(token))).unwrap();
+    match from_binary(&res.data.unwrap()).unwrap() {
+        HandleAnswer::WithdrawReferralFees {
+            withdrawn,
+            remaining,
+            ..
+        } => {
+            assert_eq!(withdrawn.u128(), 10);
+            assert_eq!(remaining.u128(), 0);
+        }
+        _ => panic!("unexpected answer"),
+    }
+    let mut env = mock_env(OWNER, &[]);
+    env.contract.address = HumanAddr::from(FACTORY);
+    assert!(contract::handle(&mut chain.factory, env, withdraw(None, None)).is_err());
+}
+
+/// This test checks that a denied creator can not create offspring even without an allow list,
+/// and that the admin can list the denied creators.
+#[test]
+fn test_creator_lists() {
+    let mut chain = Chain::new();
+    let deny = |denied: bool| HandleMsg::SetCreatorDenied {
+        address: HumanAddr::from(OWNER),
+        denied,
+    };
+    chain.handle_factory(ADMIN, deny(true));
+    let mut env = mock_env(OWNER, &[]);
+    env.contract.address = HumanAddr::from(FACTORY);
+    assert!(contract::handle(&mut chain.factory, env, create_msg("first")).is_err());
+    chain.handle_factory(
+        ADMIN,
+        HandleMsg::SetViewingKey {
+            key: "admin key".to_string(),
+            padding: None,
+        },
+    );
+    let list = || QueryMsg::CreatorList {
+        address: HumanAddr::from(ADMIN),
+        viewing_key: "admin key".to_string(),
+        list: CreatorListType::Denied,
+        start_page: None,
+        page_size: None,
+    };
+    match chain.query_factory(list()) {
+        QueryAnswer::CreatorList { creators, total } => {
+            assert_eq!(creators, vec![HumanAddr::from(OWNER)]);
+            assert_eq!(total, 1);
+        }
+        _ => panic!("unexpected answer"),
+    }
+
+    chain.handle_factory(ADMIN, deny(false));
+    chain.handle_factory(OWNER, create_msg("first"));
+    match chain.query_factory(list()) {
+        QueryAnswer::CreatorList { total, .. } => assert_eq!(total, 0),
+        _ => panic!("unexpected answer"),
+    }
+}
+
+/// This test checks that only the admin can annotate an owner, and that the note shows in the
+/// admin's listing of the owner's offspring until it is cleared.
+#[test]
+fn test_owner_notes() {
+    let mut chain = Chain::new();
+    chain.handle_factory(OWNER, create_msg("supported"));
+    let set_note = || HandleMsg::SetOwnerNote {
+        owner: HumanAddr::from(OWNER),
+        note: "asked about fees".to_string(),
+    };
+    let mut env = mock_env(OWNER, &[]);
+    env.contract.address = HumanAddr::from(FACTORY);
+    assert!(contract::handle(&mut chain.factory, env, set_note()).is_err());
+
+    let note = |chain: &mut Chain| {
+        let mut env = mock_env(ADMIN, &[]);
+        env.contract.address = HumanAddr::from(FACTORY);
+        let msg = HandleMsg::ListOffspringOfOwnerAdmin {
+            owner: HumanAddr::from(OWNER),
+            filter: None,
+            start_page: None,
+            page_size: None,
+        };
+        let res = contract::handle(&mut chain.factory, env, msg).unwrap();
+        match from_binary(&res.data.unwrap()).unwrap() {
+            HandleAnswer::ListOffspringOfOwnerAdmin { note, .. } => note.map(|note| note.note),
+            _ => panic!("unexpected answer"),
+        }
+    };
+    assert_eq!(note(&mut chain), None);
+    chain.handle_factory(ADMIN, set_note());
+    assert_eq!(note(&mut chain), Some("asked about fees".to_string()));
+    // the note is only stored sealed
+    let owner_addr = chain.factory.api.canonical_address(&HumanAddr::from(OWNER)).unwrap();
+    let stored: OwnerNote = load(
+        &ReadonlyPrefixedStorage::new(PREFIX_OWNER_NOTES, &chain.factory.storage),
+        owner_addr.as_slice(),
+    )
+    .unwrap();
+    assert!(stored.note.starts_with("sealed1:"));
+    chain.handle_factory(
+        ADMIN,
+        HandleMsg::ClearOwnerNote {
+            owner: HumanAddr::from(OWNER),
+        },
+    );
+    assert_eq!(note(&mut chain), None);
+}
+
+/// This test checks that handles of a disabled feature are refused until the admin enables it,
+/// and that the features query follows.
+#[test]
+fn test_features() {
+    let mut chain = Chain::with_init(
+        br#"{"entropy":"scenario entropy","offspring_contract":{"code_id":2,"code_hash":"cdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcd"},"disabled_features":["batch_creation"]}"#,
+    );
+    let features = |chain: &Chain| match chain.query_factory(QueryMsg::Features {}) {
+        QueryAnswer::Features { enabled } => enabled,
+        _ => panic!("unexpected answer"),
+    };
+    assert!(!features(&chain).contains(&Feature::BatchCreation));
+    assert!(!features(&chain).contains(&Feature::Recovery));
+    assert!(features(&chain).contains(&Feature::Transfers));
+    let batch = || HandleMsg::BatchCreateOffspring {
+        entropy: "creation entropy".to_string(),
+        offspring: vec![OffspringSpec {
+            label: Some("first".to_string()),
+            owner: HumanAddr::from(OWNER),
+            count: 0,
+            description: None,
+            count_privacy: None,
+            version: None,
+        }],
+    };
+    let mut env = mock_env(OWNER, &[]);
+    env.contract.address = HumanAddr::from(FACTORY);
+    assert!(contract::handle(&mut chain.factory, env, batch()).is_err());
+
+    chain.handle_factory(
+        ADMIN,
+        HandleMsg::SetFeature {
+            feature: Feature::BatchCreation,
+            enabled: true,
+        },
+    );
+    assert!(features(&chain).contains(&Feature::BatchCreation));
+    chain.handle_factory(OWNER, batch());
+    assert_eq!(chain.offspring.len(), 1);
+}
+
+/// This test checks that an offspring rejects a retried increment that reuses a client nonce.
+#[test]
+fn test_client_nonce() {
+    let mut chain = Chain::new();
+    chain.handle_factory(OWNER, create_msg("first"));
+    let increment = || offspring::msg::HandleMsg::Increment { nonce: Some(7) };
+    chain.handle_offspring(OWNER, 0, increment());
+
+    let mut env = mock_env(OWNER, &[]);
+    env.contract.address = chain.offspring[0].0.clone();
+    assert!(offspring::handle(&mut chain.offspring[0].1, env, increment()).is_err());
+}
+
+/// This test creates offspring with creation contexts and checks that the admin sees them
+/// counted per context.
+#[test]
+fn test_creation_contexts() {
+    let mut chain = Chain::new();
+    for label in &["first", "second"] {
+        let msg = match create_msg(label) {
+            HandleMsg::CreateOffspring {
+                label,
+                entropy,
+                owner,
+                count,
+                ..
+            } => HandleMsg::CreateOffspring {
+                label,
+                entropy,
+                owner,
+                count,
+                description: None,
+                dependencies: None,
+                referrer: None,
+                count_privacy: None,
+                version: None,
+                context: Some("campaign".to_string()),
+            },
+            _ => unreachable!(),
+        };
+        chain.handle_factory(OWNER, msg);
+    }
+    chain.handle_factory(
+        ADMIN,
+        HandleMsg::SetViewingKey {
+            key: "admin key".to_string(),
+            padding: None,
+        },
+    );
+    match chain.query_factory(QueryMsg::CreationContexts {
+        address: HumanAddr::from(ADMIN),
+        viewing_key: "admin key".to_string(),
+        start_page: None,
+        page_size: None,
+    }) {
+        QueryAnswer::CreationContexts { contexts, total } => {
+            assert_eq!(total, 1);
+            assert_eq!(contexts[0].context, "campaign");
+            assert_eq!(contexts[0].creations, 2);
+        }
+        _ => panic!("unexpected answer"),
+    }
+}
+
+/// This test checks that an owner can narrow their listing to offspring whose label contains some
+/// text, and that pages and the pagination metadata are counted among the matching offspring.
+#[test]
+fn test_label_search() {
+    let mut chain = Chain::new();
+    for label in &["Red counter", "blue counter", "red ledger"] {
+        chain.handle_factory(OWNER, create_msg(label));
+    }
+    chain.handle_factory(
+        OWNER,
+        HandleMsg::SetViewingKey {
+            key: "owner key".to_string(),
+            padding: None,
+        },
+    );
+    let search = |start_page: Option<u32>| QueryMsg::ListMyOffspring {
+        address: HumanAddr::from(OWNER),
+        viewing_key: "owner key".to_string(),
+        filter: None,
+        start_page,
+        page_size: Some(1),
+        reverse: None,
+        label_contains: Some("RED".to_string()),
+        if_modified_since: None,
+    };
+    let labels = |answer: QueryAnswer| match answer {
+        QueryAnswer::ListMyOffspring { active, .. } => active
+            .unwrap()
+            .into_iter()
+            .map(|info| info.label)
+            .collect::<Vec<String>>(),
+        _ => panic!("unexpected answer"),
+    };
+    assert_eq!(labels(chain.query_factory(search(None))), vec!["Red counter"]);
+    assert_eq!(labels(chain.query_factory(search(Some(1)))), vec!["red ledger"]);
+    assert!(labels(chain.query_factory(search(Some(2)))).is_empty());
+    let metadata = |answer: QueryAnswer| match answer {
+        QueryAnswer::ListMyOffspring {
+            total_count,
+            page,
+            page_size,
+            has_more,
+            ..
+        } => (total_count, page, page_size, has_more),
+        _ => panic!("unexpected answer"),
+    };
+    assert_eq!(metadata(chain.query_factory(search(None))), (2, 0, 1, true));
+    assert_eq!(metadata(chain.query_factory(search(Some(1)))), (2, 1, 1, false));
+}
+
+/// This test checks that a registered offspring can be found by its label, and that its label
+/// can not be used for another creation.
+#[test]
+fn test_offspring_by_label() {
+    let mut chain = Chain::new();
+    chain.handle_factory(OWNER, create_msg("unique"));
+    let lookup = |chain: &Chain, label: &str| {
+        match chain.query_factory(QueryMsg::OffspringByLabel {
+            label: label.to_string(),
+            viewer: None,
+            viewing_key: None,
+        }) {
+            QueryAnswer::OffspringByLabel { address, status, .. } => (address, status),
+            _ => panic!("unexpected answer"),
+        }
+    };
+    match lookup(&chain, "unique") {
+        (Some(address), LifecycleStatus::Active { info }) => {
+            assert_eq!(address, chain.offspring[0].0);
+            assert_eq!(info.label, "unique");
+        }
+        _ => panic!("unexpected lookup"),
+    }
+    assert!(matches!(lookup(&chain, "other"), (None, LifecycleStatus::Unknown {})));
+
+    let mut env = mock_env(OWNER, &[]);
+    env.contract.address = HumanAddr::from(FACTORY);
+    let err = contract::handle(&mut chain.factory, env, create_msg("unique")).unwrap_err();
+    assert!(err.to_string().contains("already used"));
+}
+
+/// This test checks that a label is reserved from the creation of its offspring until the
+/// offspring is removed: a second item of a batch with the same label is skipped without
+/// reverting the batch, a creation waiting for registration holds its label, and the label of a
+/// removed offspring can be used again.
+#[test]
+fn test_label_reservations() {
+    let mut chain = Chain::new();
+    let spec = |label: &str| OffspringSpec {
+        label: Some(label.to_string()),
+        owner: HumanAddr::from(OWNER),
+        count: 0,
+        description: None,
+        count_privacy: None,
+        version: None,
+    };
+    let mut env = mock_env(OWNER, &[]);
+    env.contract.address = HumanAddr::from(FACTORY);
+    let msg = HandleMsg::BatchCreateOffspring {
+        entropy: "batch entropy".to_string(),
+        offspring: vec![spec("twin"), spec("twin")],
+    };
+    let res = contract::handle(&mut chain.factory, env.clone(), msg).unwrap();
+    match from_binary(&res.data.clone().unwrap()).unwrap() {
+        HandleAnswer::BatchCreateOffspring { results } => {
+            assert!(matches!(results[0].status, ResponseStatus::Success));
+            assert!(matches!(results[1].status, ResponseStatus::Failure));
+            assert!(results[1].error.as_ref().unwrap().contains("reserved"));
+        }
+        _ => panic!("unexpected answer"),
+    }
+    chain.dispatch(HumanAddr::from(FACTORY), res.messages);
+    assert_eq!(chain.offspring.len(), 1);
+
+    chain.create_unregistered(OWNER, create_msg("waiting"));
+    let err = contract::handle(&mut chain.factory, env.clone(), create_msg("waiting")).unwrap_err();
+    assert!(err.to_string().contains("reserved"));
+
+    let lookup = |chain: &Chain| match chain.query_factory(QueryMsg::OffspringByLabel {
+        label: "twin".to_string(),
+        viewer: None,
+        viewing_key: None,
+    }) {
+        QueryAnswer::OffspringByLabel { address, .. } => address,
+        _ => panic!("unexpected answer"),
+    };
+    chain.handle_offspring(OWNER, 0, offspring::msg::HandleMsg::Deactivate {});
+    chain.handle_factory(
+        OWNER,
+        HandleMsg::RemoveOffspring {
+            addresses: vec![HumanAddr::from("offspring0")],
+        },
+    );
+    assert_eq!(lookup(&chain), None);
+    chain.handle_factory(OWNER, create_msg("twin"));
+    assert_eq!(lookup(&chain), Some(HumanAddr::from("offspring2")));
+}
+
+/// This test checks that a single offspring's info can be looked up by address while it is active
+/// or inactive, and that unknown addresses are reported as such.
+#[test]
+fn test_offspring_info() {
+    let mut chain = Chain::new();
+    chain.handle_factory(OWNER, create_msg("single"));
+    let info = |chain: &Chain, address: &str| match chain.query_factory(QueryMsg::OffspringInfo {
+        address: HumanAddr::from(address),
+        viewer: None,
+        viewing_key: None,
+    }) {
+        QueryAnswer::OffspringInfo {
+            status,
+            active,
+            inactive,
+            deprecated,
+            ..
+        } => (status, active, inactive, deprecated),
+        _ => panic!("unexpected answer"),
+    };
+    let (status, active, inactive, deprecated) = info(&chain, "offspring0");
+    assert_eq!(status, OffspringStatus::Active);
+    assert_eq!(active.unwrap().label, "single");
+    assert!(inactive.is_none());
+    assert!(!deprecated);
+
+    chain.handle_offspring(OWNER, 0, offspring::msg::HandleMsg::Deactivate {});
+    let (status, active, inactive, _) = info(&chain, "offspring0");
+    assert_eq!(status, OffspringStatus::Inactive);
+    assert!(active.is_none());
+    assert_eq!(inactive.unwrap().label, "single");
+
+    let (status, active, inactive, _) = info(&chain, "stranger");
+    assert_eq!(status, OffspringStatus::Unknown);
+    assert!(active.is_none() && inactive.is_none());
+}
+
+/// This test checks that with private listing the single offspring lookups only report which
+/// list the offspring is in unless a valid viewing key is supplied, and that they open sealed
+/// descriptions for authorized callers.
+#[test]
+fn test_private_offspring_lookups() {
+    let mut chain = Chain::with_init(
+        br#"{"entropy":"scenario entropy","offspring_contract":{"code_id":2,"code_hash":"cdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcd"},"private_listing":true}"#,
+    );
+    let mut msg = create_msg("hidden");
+    if let HandleMsg::CreateOffspring { description, .. } = &mut msg {
+        *description = Some("secret plans".to_string());
+    }
+    chain.handle_factory(OWNER, msg);
+    chain.handle_factory(
+        OWNER,
+        HandleMsg::SetViewingKey {
+            key: "owner key".to_string(),
+            padding: None,
+        },
+    );
+    let queries = |key: Option<&str>| {
+        let viewer = key.map(|_| HumanAddr::from(OWNER));
+        let viewing_key = key.map(String::from);
+        vec![
+            QueryMsg::OffspringLifecycle {
+                address: HumanAddr::from("offspring0"),
+                viewer: viewer.clone(),
+                viewing_key: viewing_key.clone(),
+            },
+            QueryMsg::OffspringByLabel {
+                label: "hidden".to_string(),
+                viewer: viewer.clone(),
+                viewing_key: viewing_key.clone(),
+            },
+            QueryMsg::OffspringInfo {
+                address: HumanAddr::from("offspring0"),
+                viewer,
+                viewing_key,
+            },
+        ]
+    };
+    for key in &[None, Some("wrong key")] {
+        for query in queries(*key) {
+            match chain.query_factory(query) {
+                QueryAnswer::OffspringStatus { status } => {
+                    assert_eq!(status, OffspringStatus::Active)
+                }
+                _ => panic!("unexpected answer"),
+            }
+        }
+    }
+
+    for query in queries(Some("owner key")) {
+        let answer = contract::query(&chain.factory, query).unwrap();
+        let text = String::from_utf8(answer.0).unwrap();
+        assert!(text.contains("secret plans"));
+        assert!(!text.contains("sealed1:"));
+    }
+    match chain.query_factory(QueryMsg::OffspringInfo {
+        address: HumanAddr::from("offspring0"),
+        viewer: Some(HumanAddr::from(OWNER)),
+        viewing_key: Some("owner key".to_string()),
+    }) {
+        QueryAnswer::OffspringInfo { active, .. } => {
+            assert_eq!(active.unwrap().description, Some("secret plans".to_string()))
+        }
+        _ => panic!("unexpected answer"),
+    }
+}
+
+/// This test checks that only the owner can attach a metadata url to their offspring, and that
+/// public lists only show it once the offspring's count is public.
+#[test]
+fn test_metadata_url() {
+    let mut chain = Chain::new();
+    chain.handle_factory(OWNER, create_msg("linked"));
+    chain.handle_factory(
+        OWNER,
+        HandleMsg::SetViewingKey {
+            key: "owner key".to_string(),
+            padding: None,
+        },
+    );
+    let offspring = chain.offspring[0].0.clone();
+    let set_url = |url: &str| HandleMsg::SetMetadataUrl {
+        offspring: offspring.clone(),
+        url: Some(url.to_string()),
+    };
+
+    let mut env = mock_env("bob", &[]);
+    env.contract.address = HumanAddr::from(FACTORY);
+    let err = contract::handle(&mut chain.factory, env, set_url("ipfs://bafy")).unwrap_err();
+    assert!(err.to_string().contains("Only the owner"));
+    let mut env = mock_env(OWNER, &[]);
+    env.contract.address = HumanAddr::from(FACTORY);
+    let err = contract::handle(&mut chain.factory, env, set_url("not a url")).unwrap_err();
+    assert!(err.to_string().contains("scheme"));
+    chain.handle_factory(OWNER, set_url("ipfs://bafy"));
+
+    let public_url = |chain: &Chain| match chain.query_factory(list_active()) {
+        QueryAnswer::ListActiveOffspring { active, .. } => active[0].metadata_url.clone(),
+        _ => panic!("unexpected answer"),
+    };
+    assert_eq!(public_url(&chain), None);
+    match chain.query_factory(list_my(OWNER, "owner key")) {
+        QueryAnswer::ListMyOffspring { active, .. } => {
+            assert_eq!(active.unwrap()[0].metadata_url, Some("ipfs://bafy".to_string()));
+        }
+        _ => panic!("unexpected answer"),
+    }
+
+    chain.handle_offspring(
+        OWNER,
+        0,
+        offspring::msg::HandleMsg::SetCountPrivacy {
+            privacy: offspring::msg::CountPrivacy::Public,
+            viewers: None,
+        },
+    );
+    assert_eq!(public_url(&chain), Some("ipfs://bafy".to_string()));
+
+    chain.handle_factory(
+        OWNER,
+        HandleMsg::SetMetadataUrl {
+            offspring,
+            url: None,
+        },
+    );
+    assert_eq!(public_url(&chain), None);
+}
+
+/// This test checks that creating an offspring logs the lifecycle attributes indexers rely on.
+#[test]
+fn test_lifecycle_log() {
+    let mut chain = Chain::new();
+    let mut env = mock_env(OWNER, &[]);
+    env.contract.address = HumanAddr::from(FACTORY);
+    let res = contract::handle(&mut chain.factory, env, create_msg("logged")).unwrap();
+    // log values are padded along with the rest of the response
+    let attribute = |key: &str| {
+        res.log
+            .iter()
+            .find(|attribute| attribute.key.trim_end() == key)
+            .map(|attribute| attribute.value.trim_end().to_string())
+    };
+    assert_eq!(attribute("action"), Some("create".to_string()));
+    assert_eq!(attribute("owner"), Some(OWNER.to_string()));
+    assert_eq!(attribute("label"), Some("logged".to_string()));
+    assert!(attribute("block_time").is_some());
+    assert!(attribute("offspring_address").is_none());
+}
+
+/// This test checks that every Nth registration logs a checkpoint of the registry's totals, and
+/// that other registrations do not.
+#[test]
+fn test_stats_checkpoint() {
+    let mut chain = Chain::with_init(
+        br#"{"entropy":"scenario entropy","offspring_contract":{"code_id":2,"code_hash":"cdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcd"},"stats_checkpoint_interval":2}"#,
+    );
+    let mut register_logs = vec![];
+    for label in &["first", "second"] {
+        chain.create_unregistered(OWNER, create_msg(label));
+        let (address, deps) = chain.offspring.last_mut().unwrap();
+        let mut env = mock_env(address.clone(), &[]);
+        env.contract.address = address.clone();
+        let res = offspring::handle(deps, env, offspring::msg::HandleMsg::RetryRegistration {});
+        let msg = match res.unwrap().messages.into_iter().next() {
+            Some(CosmosMsg::Wasm(WasmMsg::Execute { msg, .. })) => msg,
+            other => panic!("unexpected message {:?}", other),
+        };
+        let mut env = mock_env(address.clone(), &[]);
+        env.contract.address = HumanAddr::from(FACTORY);
+        let res = contract::handle(&mut chain.factory, env, from_binary(&msg).unwrap()).unwrap();
+        register_logs.push(res.log);
+    }
+    // log values are padded along with the rest of the response
+    let attribute = |index: usize, key: &str| {
+        register_logs[index]
+            .iter()
+            .find(|attribute| attribute.key.trim_end() == key)
+            .map(|attribute| attribute.value.trim_end().to_string())
+    };
+    assert!(attribute(0, "stats_checkpoint").is_none());
+    assert_eq!(attribute(1, "stats_checkpoint"), Some("1".to_string()));
+    assert_eq!(attribute(1, "total_active"), Some("2".to_string()));
+    assert_eq!(attribute(1, "total_created"), Some("2".to_string()));
+}
+
+/// This test checks that versioned queries wrap the answer in an envelope, that version 1 and 2
+/// answers leave out fields added later, and that unsupported or nested versions are refused.
+#[test]
+fn test_versioned_answers() {
+    let mut chain = Chain::new();
+    chain.handle_factory(OWNER, create_msg("counter"));
+    chain.handle_offspring(OWNER, 0, offspring::msg::HandleMsg::Deactivate {});
+    let versioned = |api_version: Option<u32>| QueryMsg::Versioned {
+        api_version,
+        query: Box::new(list_inactive()),
+    };
+
+    let current = contract::query(&chain.factory, versioned(None)).unwrap();
+    let answer: VersionedAnswer<QueryAnswer> = from_binary(&current).unwrap();
+    assert_eq!(answer.api_version, 4);
+    match answer.body {
+        QueryAnswer::ListInactiveOffspring { inactive, .. } => {
+            assert_eq!(inactive[0].deactivated_by, DeactivationSource::Owner);
+        }
+        _ => panic!("unexpected answer"),
+    }
+    let v1 = contract::query(&chain.factory, versioned(Some(1))).unwrap();
+    let v1 = String::from_utf8(v1.0).unwrap();
+    assert!(v1.starts_with(r#"{"api_version":1,"body":{"list_inactive_offspring":"#));
+    assert!(!v1.contains("deactivated_by"));
+    assert!(!v1.contains("revision"));
+    let v2 = contract::query(&chain.factory, versioned(Some(2))).unwrap();
+    let v2 = String::from_utf8(v2.0).unwrap();
+    assert!(v2.contains("deactivated_by"));
+    assert!(!v2.contains("revision"));
+    let v3 = contract::query(&chain.factory, versioned(Some(3))).unwrap();
+    let v3 = String::from_utf8(v3.0).unwrap();
+    assert!(v3.contains("revision"));
+    assert!(!v3.contains("has_more"));
+
+    assert!(contract::query(&chain.factory, versioned(Some(5))).is_err());
+    let nested = QueryMsg::Versioned {
+        api_version: None,
+        query: Box::new(versioned(None)),
+    };
+    assert!(contract::query(&chain.factory, nested).is_err());
+}
+
+/// This test checks that list queries answer NotModified until the registry changes after the
+/// revision the client last saw.
+#[test]
+fn test_if_modified_since() {
+    let mut chain = Chain::new();
+    chain.handle_factory(OWNER, create_msg("counter"));
+    let list_active = |if_modified_since: Option<u64>| QueryMsg::ListActiveOffspring {
+        start_page: None,
+        page_size: None,
+        address: None,
+        viewing_key: None,
+        if_modified_since,
+        newest_first: None,
+    };
+    let revision = match chain.query_factory(list_active(None)) {
+        QueryAnswer::ListActiveOffspring { revision, .. } => revision,
+        _ => panic!("unexpected answer"),
+    };
+    match chain.query_factory(list_active(Some(revision))) {
+        QueryAnswer::NotModified { revision: current } => assert_eq!(current, revision),
+        _ => panic!("unexpected answer"),
+    }
+
+    chain.handle_offspring(OWNER, 0, offspring::msg::HandleMsg::Deactivate {});
+    match chain.query_factory(list_active(Some(revision))) {
+        QueryAnswer::ListActiveOffspring {
+            active,
+            revision: current,
+            ..
+        } => {
+            assert!(active.is_empty());
+            assert!(current > revision);
+        }
+        _ => panic!("unexpected answer"),
+    }
+}
+
+/// This test checks that a registry snapshot is built over several calls, that the latest
+/// complete snapshot stays available meanwhile, and that the build starts over when the registry
+/// changes before it is complete.
+#[test]
+fn test_snapshot_in_steps() {
+    let mut chain = Chain::new();
+    for label in &["first", "second", "third"] {
+        chain.handle_factory(OWNER, create_msg(label));
+    }
+    let snapshot = |chain: &mut Chain, max_items: u32| {
+        let mut env = mock_env(ADMIN, &[]);
+        env.contract.address = HumanAddr::from(FACTORY);
+        let msg = HandleMsg::SnapshotRegistry {
+            max_items: Some(max_items),
+        };
+        let res = contract::handle(&mut chain.factory, env, msg).unwrap();
+        match from_binary(&res.data.unwrap()).unwrap() {
+            HandleAnswer::SnapshotRegistry {
+                processed,
+                restarted,
+                done,
+                leaf_count,
+                ..
+            } => (processed, restarted, done, leaf_count),
+            _ => panic!("unexpected answer"),
+        }
+    };
+    let leaf_count = |chain: &Chain| match chain.query_factory(QueryMsg::RegistrySnapshot {}) {
+        QueryAnswer::RegistrySnapshot { snapshot } => snapshot.map(|s| s.leaf_count),
+        _ => panic!("unexpected answer"),
+    };
+
+    // 3 leaves, then 2 and 1 nodes
+    assert_eq!(snapshot(&mut chain, 4), (4, false, false, None));
+    assert_eq!(leaf_count(&chain), None);
+    assert_eq!(snapshot(&mut chain, 4), (2, false, true, Some(3)));
+    assert_eq!(leaf_count(&chain), Some(3));
+
+    assert_eq!(snapshot(&mut chain, 2), (2, false, false, None));
+    chain.handle_factory(OWNER, create_msg("fourth"));
+    // 4 leaves, then 2 and 1 nodes
+    assert_eq!(snapshot(&mut chain, 2), (2, true, false, None));
+    assert_eq!(leaf_count(&chain), Some(3));
+    assert_eq!(snapshot(&mut chain, 10), (5, false, true, Some(4)));
+    assert_eq!(leaf_count(&chain), Some(4));
+}
+
+/// This test checks that a membership proof from the factory is accepted by the offspring it
+/// proves, which hashes it independently, and that it is rejected with a wrong owner.
+#[test]
+fn test_verify_membership() {
+    let mut chain = Chain::new();
+    for label in &["first", "second", "third"] {
+        chain.handle_factory(OWNER, create_msg(label));
+    }
+    chain.handle_factory(ADMIN, HandleMsg::SnapshotRegistry { max_items: None });
+    let proof = match chain.query_factory(QueryMsg::MembershipProof {
+        offspring: HumanAddr::from("offspring1"),
+        address: None,
+        viewing_key: None,
+    }) {
+        QueryAnswer::MembershipProof { proof } => proof.unwrap(),
+        _ => panic!("unexpected answer"),
+    };
+    assert_eq!((proof.label.as_str(), proof.owner.as_str()), ("second", OWNER));
+    let siblings: Vec<offspring::msg::ProofNode> = proof
+        .siblings
+        .iter()
+        .map(|node| offspring::msg::ProofNode {
+            hash: node.hash.clone(),
+            is_left: node.is_left,
+        })
+        .collect();
+    let verify = |chain: &mut Chain, index: usize, owner: &str| {
+        let msg = offspring::msg::QueryMsg::VerifyMembership {
+            label: proof.label.clone(),
+            owner: HumanAddr::from(owner),
+            siblings: siblings.clone(),
+        };
+        match chain.query_linked_offspring(index, msg) {
+            offspring::msg::QueryAnswer::VerifyMembership { is_member, height } => {
+                assert_eq!(height, Some(proof.height));
+                is_member
+            }
+            _ => panic!("unexpected answer"),
+        }
+    };
+
+    assert!(verify(&mut chain, 1, OWNER));
+    assert!(!verify(&mut chain, 1, "bob"));
+    // the proof is bound to the offspring's own address
+    assert!(!verify(&mut chain, 0, OWNER));
+}
+
+/// This test checks that an admin batch executes its actions in order, and that an action that
+/// fails fails the whole batch, so the chain discards the actions before it too.
+#[test]
+fn test_admin_batch() {
+    let mut chain = Chain::new();
+    let new_version = |code_hash: &str| AdminAction::NewOffspringContract {
+        offspring_contract: OffspringContractInfo {
+            code_id: 3,
+            code_hash: code_hash.to_string(),
+        },
+    };
+    let batch = |code_hash: &str| HandleMsg::AdminBatch {
+        actions: vec![
+            AdminAction::SetStatus { stop: true },
+            new_version(code_hash),
+            AdminAction::SetStatus { stop: false },
+        ],
+    };
+    let mut env = mock_env(ADMIN, &[]);
+    env.contract.address = HumanAddr::from(FACTORY);
+
+    let err = contract::handle(&mut chain.factory, env.clone(), batch("not a hash")).unwrap_err();
+    assert!(err.to_string().contains("64 hexadecimal characters"));
+
+    let res = contract::handle(&mut chain.factory, env, batch(&"ef".repeat(32))).unwrap();
+    match from_binary(&res.data.unwrap()).unwrap() {
+        HandleAnswer::AdminBatch { executed, .. } => assert_eq!(executed, 3),
+        _ => panic!("unexpected answer"),
+    }
+    match chain.query_factory(QueryMsg::FactoryConfig {}) {
+        QueryAnswer::FactoryConfig {
+            offspring_contract,
+            stopped,
+            ..
+        } => {
+            assert_eq!(offspring_contract.code_id, 3);
+            assert!(!stopped);
+        }
+        _ => panic!("unexpected answer"),
+    }
+}
+
+/// This test checks that an offspring whose address the admin blocked can not register, while
+/// other offspring can.
+#[test]
+fn test_blocked_address_can_not_register() {
+    let mut chain = Chain::new();
+    chain.handle_factory(
+        ADMIN,
+        HandleMsg::SetAddressBlocked {
+            address: HumanAddr::from("offspring0"),
+            blocked: true,
+            reason: Some("impersonates another project".to_string()),
+        },
+    );
+    chain.create_unregistered(OWNER, create_msg("blocked"));
+    let (address, deps) = chain.offspring.last_mut().unwrap();
+    let mut env = mock_env(address.clone(), &[]);
+    env.contract.address = address.clone();
+    let res = offspring::handle(deps, env, offspring::msg::HandleMsg::RetryRegistration {});
+    let msg = match res.unwrap().messages.into_iter().next() {
+        Some(CosmosMsg::Wasm(WasmMsg::Execute { msg, .. })) => from_binary(&msg).unwrap(),
+        other => panic!("unexpected message {:?}", other),
+    };
+    let mut env = mock_env("offspring0", &[]);
+    env.contract.address = HumanAddr::from(FACTORY);
+    let err = contract::handle(&mut chain.factory, env, msg).unwrap_err();
+    assert!(err.to_string().contains("blocked by the factory admin"));
+    match chain.query_factory(list_active()) {
+        QueryAnswer::ListActiveOffspring { active, .. } => assert!(active.is_empty()),
+        _ => panic!("unexpected answer"),
+    }
+
+    // other offspring register as usual
+    chain.handle_factory(OWNER, create_msg("allowed"));
+    match chain.query_factory(list_active()) {
+        QueryAnswer::ListActiveOffspring { active, .. } => {
+            assert_eq!(active.len(), 1);
+            assert_eq!(active[0].address, HumanAddr::from("offspring1"));
+        }
+        _ => panic!("unexpected answer"),
+    }
+}
+
+/// This test checks that a registration arriving after its retry window is quarantined for the
+/// admin's review when quarantine is enabled, and rejected when it is not.
+#[test]
+fn test_late_registration() {
+    let late_registration = |init: &[u8]| {
+        let mut chain = Chain::with_init(init);
+        chain.create_unregistered(OWNER, create_msg("late"));
+        let (address, deps) = chain.offspring.last_mut().unwrap();
+        let mut env = mock_env(address.clone(), &[]);
+        env.contract.address = address.clone();
+        let res = offspring::handle(deps, env, offspring::msg::HandleMsg::RetryRegistration {});
+        let msg = match res.unwrap().messages.into_iter().next() {
+            Some(CosmosMsg::Wasm(WasmMsg::Execute { msg, .. })) => from_binary(&msg).unwrap(),
+            other => panic!("unexpected message {:?}", other),
+        };
+        let mut env = mock_env("offspring0", &[]);
+        env.contract.address = HumanAddr::from(FACTORY);
+        env.block.height += 11;
+        let res = contract::handle(&mut chain.factory, env, msg);
+        (chain, res)
+    };
+
+    let (_, res) = late_registration(
+        br#"{"entropy":"scenario entropy","offspring_contract":{"code_id":2,"code_hash":"cdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcd"},"registration_retry_blocks":10}"#,
+    );
+    let err = res.unwrap_err();
+    assert!(err.to_string().contains("The time to register this offspring has passed"));
+
+    let (mut chain, res) = late_registration(
+        br#"{"entropy":"scenario entropy","offspring_contract":{"code_id":2,"code_hash":"cdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcd"},"registration_retry_blocks":10,"quarantine_registrations":true}"#,
+    );
+    res.unwrap();
+    match chain.query_factory(list_active()) {
+        QueryAnswer::ListActiveOffspring { active, .. } => assert!(active.is_empty()),
+        _ => panic!("unexpected answer"),
+    }
+    chain.handle_factory(
+        ADMIN,
+        HandleMsg::SetViewingKey {
+            key: "admin key".to_string(),
+            padding: None,
+        },
+    );
+    match chain.query_factory(QueryMsg::Quarantine {
+        address: HumanAddr::from(ADMIN),
+        viewing_key: "admin key".to_string(),
+        start_page: None,
+        page_size: None,
+    }) {
+        QueryAnswer::Quarantine { registrations, .. } => {
+            assert_eq!(registrations.len(), 1);
+            assert_eq!(registrations[0].info.label, "late");
+            assert_eq!(registrations[0].owner, HumanAddr::from(OWNER));
+        }
+        _ => panic!("unexpected answer"),
+    }
+}
+
+/// This test checks that an owner can flag an issue through their offspring, which the factory
+/// adds to the admin's issue queue, and that the factory only accepts issues from registered
+/// offspring.
+#[test]
+fn test_flag_issue() {
+    let mut chain = Chain::new();
+    chain.handle_factory(OWNER, create_msg("flagged"));
+    let flag = |category: &str| offspring::msg::HandleMsg::FlagIssue {
+        category: category.to_string(),
+        details: "the count was reset unexpectedly".to_string(),
+    };
+    chain.handle_offspring(OWNER, 0, flag("bug"));
+
+    let mut env = mock_env("bob", &[]);
+    env.contract.address = HumanAddr::from("offspring0");
+    assert!(offspring::handle(&mut chain.offspring[0].1, env, flag("bug")).is_err());
+    let mut env = mock_env("offspring0", &[]);
+    env.contract.address = HumanAddr::from(FACTORY);
+    let msg = HandleMsg::FlagIssue {
+        category: String::new(),
+        details: String::new(),
+    };
+    let err = contract::handle(&mut chain.factory, env, msg).unwrap_err();
+    assert!(err.to_string().contains("Issue categories must be between 1"));
+    let mut env = mock_env("not_an_offspring", &[]);
+    env.contract.address = HumanAddr::from(FACTORY);
+    let msg = HandleMsg::FlagIssue {
+        category: "spam".to_string(),
+        details: String::new(),
+    };
+    assert!(contract::handle(&mut chain.factory, env, msg).is_err());
+
+    chain.handle_factory(
+        ADMIN,
+        HandleMsg::SetViewingKey {
+            key: "admin key".to_string(),
+            padding: None,
+        },
+    );
+    match chain.query_factory(QueryMsg::IssueQueue {
+        address: HumanAddr::from(ADMIN),
+        viewing_key: "admin key".to_string(),
+        start_page: None,
+        page_size: None,
+    }) {
+        QueryAnswer::IssueQueue { issues, total } => {
+            assert_eq!(total, 1);
+            assert_eq!(issues[0].offspring, HumanAddr::from("offspring0"));
+            assert_eq!(issues[0].label, "flagged");
+            assert_eq!(issues[0].owner, HumanAddr::from(OWNER));
+            assert_eq!(issues[0].category, "bug");
+            assert_eq!(issues[0].status, IssueStatus::Open);
+        }
+        _ => panic!("unexpected answer"),
+    }
+}
+
+/// This test checks that the lifecycle query follows an offspring from unknown, to active with
+/// its registration index, to inactive once its owner deactivates it.
+#[test]
+fn test_offspring_lifecycle() {
+    let mut chain = Chain::new();
+    let lifecycle = |chain: &Chain| match chain.query_factory(QueryMsg::OffspringLifecycle {
+        address: HumanAddr::from("offspring0"),
+        viewer: None,
+        viewing_key: None,
+    }) {
+        QueryAnswer::OffspringLifecycle {
+            status,
+            registration_index,
+            blocked,
+            ..
+        } => (status, registration_index, blocked),
+        _ => panic!("unexpected answer"),
+    };
+    let (status, registration_index, blocked) = lifecycle(&chain);
+    assert!(matches!(status, LifecycleStatus::Unknown {}));
+    assert_eq!(registration_index, None);
+    assert!(!blocked);
+
+    chain.handle_factory(OWNER, create_msg("first"));
+    let (status, registration_index, _) = lifecycle(&chain);
+    match status {
+        LifecycleStatus::Active { info } => assert_eq!(info.label, "first"),
+        _ => panic!("offspring should be active"),
+    }
+    assert_eq!(registration_index, Some(0));
+
+    chain.handle_offspring(OWNER, 0, offspring::msg::HandleMsg::Deactivate {});
+    let (status, registration_index, _) = lifecycle(&chain);
+    match status {
+        LifecycleStatus::Inactive { info } => assert_eq!(info.label, "first"),
+        _ => panic!("offspring should be inactive"),
+    }
+    assert_eq!(registration_index, Some(0));
+}
+
+/// This test checks that the admin can disable creation from one offspring version while other
+/// versions can still be created, and that re-enabling the version allows creation again.
+#[test]
+fn test_version_status() {
+    let mut chain = Chain::new();
+    chain.handle_factory(
+        ADMIN,
+        HandleMsg::NewOffspringContract {
+            offspring_contract: OffspringContractInfo {
+                code_id: 3,
+                code_hash: "ab".repeat(32),
+            },
+        },
+    );
+    let set_status = |enabled: bool| HandleMsg::SetVersionStatus { code_id: 3, enabled };
+    let mut env = mock_env(OWNER, &[]);
+    env.contract.address = HumanAddr::from(FACTORY);
+    assert!(contract::handle(&mut chain.factory, env.clone(), set_status(false)).is_err());
+    chain.handle_factory(ADMIN, set_status(false));
+    match chain.query_factory(QueryMsg::OffspringVersions {}) {
+        QueryAnswer::OffspringVersions { versions } => {
+            assert!(versions[0].enabled);
+            assert!(versions[1].current && !versions[1].enabled);
+        }
+        _ => panic!("unexpected answer"),
+    }
+
+    let create = |label: &str, version: Option<u32>| match create_msg(label) {
+        HandleMsg::CreateOffspring {
+            label,
+            entropy,
+            owner,
+            count,
+            ..
+        } => HandleMsg::CreateOffspring {
+            label,
+            entropy,
+            owner,
+            count,
+            description: None,
+            dependencies: None,
+            referrer: None,
+            count_privacy: None,
+            version,
+            context: None,
+        },
+        _ => unreachable!(),
+    };
+    let err =
+        contract::handle(&mut chain.factory, env.clone(), create("current", None)).unwrap_err();
+    assert!(format!("{:?}", err).contains("this version has been disabled"));
+    let res = contract::handle(&mut chain.factory, env.clone(), create("older", Some(0))).unwrap();
+    match res.messages.as_slice() {
+        [CosmosMsg::Wasm(WasmMsg::Instantiate { code_id, .. })] => assert_eq!(*code_id, 2),
+        other => panic!("unexpected messages {:?}", other),
+    }
+
+    chain.handle_factory(ADMIN, set_status(true));
+    let res = contract::handle(&mut chain.factory, env, create("current", None)).unwrap();
+    match res.messages.as_slice() {
+        [CosmosMsg::Wasm(WasmMsg::Instantiate { code_id, .. })] => assert_eq!(*code_id, 3),
+        other => panic!("unexpected messages {:?}", other),
+    }
+}
+
+/// This test checks that each compaction call resumes where the previous one stopped, and that
+/// an offspring left in the active list after it was deactivated is removed from it.
+#[test]
+fn test_compaction_resumes() {
+    // maintenance would otherwise compact the registry as part of every handle
+    let mut chain = Chain::with_init(
+        br#"{"entropy":"scenario entropy","offspring_contract":{"code_id":2,"code_hash":"cdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcd"},"maintenance_budget":0}"#,
+    );
+    for label in &["first", "second", "third"] {
+        chain.handle_factory(OWNER, create_msg(label));
+    }
+    // leave a stale active entry behind the deactivated offspring
+    let stale = chain.factory.api.canonical_address(&HumanAddr::from("offspring1")).unwrap();
+    let active_store: ReadOnlyCashMap<StoreOffspringInfo, _> =
+        ReadOnlyCashMap::init(ACTIVE_KEY, &chain.factory.storage);
+    let info = active_store.get(stale.as_slice()).unwrap();
+    chain.handle_offspring(OWNER, 1, offspring::msg::HandleMsg::Deactivate {});
+    let mut active_store: CashMap<StoreOffspringInfo, _> =
+        CashMap::init(ACTIVE_KEY, &mut chain.factory.storage);
+    active_store.insert(stale.as_slice(), info).unwrap();
+
+    let compact = |chain: &mut Chain, sender: &str| {
+        let mut env = mock_env(sender, &[]);
+        env.contract.address = HumanAddr::from(FACTORY);
+        let msg = HandleMsg::Compact { max_items: 2 };
+        contract::handle(&mut chain.factory, env, msg).map(|res| {
+            match from_binary(&res.data.unwrap()).unwrap() {
+                HandleAnswer::Compact {
+                    processed,
+                    removed,
+                    done,
+                } => (processed, removed, done),
+                _ => panic!("unexpected answer"),
+            }
+        })
+    };
+    assert!(compact(&mut chain, OWNER).is_err());
+    assert_eq!(compact(&mut chain, ADMIN).unwrap(), (2, 0, false));
+    assert_eq!(compact(&mut chain, ADMIN).unwrap(), (1, 1, true));
+    match chain.query_factory(list_active()) {
+        QueryAnswer::ListActiveOffspring { active, .. } => {
+            assert_eq!(active.len(), 2);
+            assert!(active.iter().all(|info| info.address != HumanAddr::from("offspring1")));
+        }
+        _ => panic!("unexpected answer"),
+    }
+    // a finished pass starts over on the next call
+    assert_eq!(compact(&mut chain, ADMIN).unwrap(), (2, 0, true));
+}
+
+/// This test checks that when registration must happen in the same transaction as the creation,
+/// a registration retried in a later block or naming a different owner is rejected.
+#[test]
+fn test_same_tx_registration() {
+    let mut chain = Chain::with_init(
+        br#"{"entropy":"scenario entropy","offspring_contract":{"code_id":2,"code_hash":"cdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcd"},"require_registration_same_tx":true,"registration_retry_blocks":10}"#,
+    );
+    chain.handle_factory(OWNER, create_msg("first"));
+    match chain.query_factory(list_active()) {
+        QueryAnswer::ListActiveOffspring { active, .. } => assert_eq!(active.len(), 1),
+        _ => panic!("unexpected answer"),
+    }
+
+    let register_msg = |chain: &mut Chain, index: usize| {
+        let (address, deps) = &mut chain.offspring[index];
+        let mut env = mock_env(address.clone(), &[]);
+        env.contract.address = address.clone();
+        let res = offspring::handle(deps, env, offspring::msg::HandleMsg::RetryRegistration {});
+        match res.unwrap().messages.into_iter().next() {
+            Some(CosmosMsg::Wasm(WasmMsg::Execute { msg, .. })) => from_binary(&msg).unwrap(),
+            other => panic!("unexpected message {:?}", other),
+        }
+    };
+    chain.create_unregistered(OWNER, create_msg("late"));
+    let msg: HandleMsg = register_msg(&mut chain, 1);
+    let mut env = mock_env("offspring1", &[]);
+    env.contract.address = HumanAddr::from(FACTORY);
+    env.block.height += 1;
+    let err = contract::handle(&mut chain.factory, env, msg).unwrap_err();
+    assert!(err.to_string().contains("The time to register this offspring has passed"));
+
+    chain.create_unregistered(OWNER, create_msg("impostor"));
+    let msg = match register_msg(&mut chain, 2) {
+        HandleMsg::RegisterOffspring { offspring, .. } => HandleMsg::RegisterOffspring {
+            owner: HumanAddr::from("bob"),
+            offspring,
+        },
+        _ => panic!("unexpected message"),
+    };
+    let mut env = mock_env("offspring2", &[]);
+    env.contract.address = HumanAddr::from(FACTORY);
+    let err = contract::handle(&mut chain.factory, env, msg).unwrap_err();
+    assert!(err.to_string().contains("owner does not match"));
+    match chain.query_factory(list_active()) {
+        QueryAnswer::ListActiveOffspring { active, .. } => assert_eq!(active.len(), 1),
+        _ => panic!("unexpected answer"),
+    }
+}
+
+/// This test checks that the inactive list and the lifecycle query record whether an offspring
+/// was deactivated by its owner or by the admin.
+#[test]
+fn test_deactivation_source() {
+    let mut chain = Chain::new();
+    chain.handle_factory(OWNER, create_msg("first"));
+    chain.handle_factory(OWNER, create_msg("second"));
+    chain.handle_offspring(OWNER, 0, offspring::msg::HandleMsg::Deactivate {});
+    chain.handle_factory(
+        ADMIN,
+        HandleMsg::DeactivateOffspringAdmin {
+            offspring: HumanAddr::from("offspring1"),
+        },
+    );
+    match chain.query_factory(list_inactive()) {
+        QueryAnswer::ListInactiveOffspring { inactive, .. } => {
+            let sources: Vec<(HumanAddr, DeactivationSource)> = inactive
+                .into_iter()
+                .map(|info| (info.address, info.deactivated_by))
+                .collect();
+            assert_eq!(
+                sources,
+                vec![
+                    (HumanAddr::from("offspring0"), DeactivationSource::Owner),
+                    (HumanAddr::from("offspring1"), DeactivationSource::Admin),
+                ]
+            );
+        }
+        _ => panic!("unexpected answer"),
+    }
+    match chain.query_factory(QueryMsg::OffspringLifecycle {
+        address: HumanAddr::from("offspring1"),
+        viewer: None,
+        viewing_key: None,
+    }) {
+        QueryAnswer::OffspringLifecycle {
+            status: LifecycleStatus::Inactive { info },
+            ..
+        } => assert_eq!(info.deactivated_by, DeactivationSource::Admin),
+        _ => panic!("offspring should be inactive"),
+    }
+}
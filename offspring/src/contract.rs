@@ -1,16 +1,63 @@
 use cosmwasm_std::{
-    to_binary, Api, Env, Extern, HandleResponse, HandleResult, HumanAddr,
+    to_binary, Api, CosmosMsg, Env, Extern, HandleResult, HumanAddr,
     InitResponse, InitResult, Querier, QueryResult, StdError, StdResult, Storage,
 };
-use secret_toolkit::utils::{HandleCallback, Query};
+use cosmwasm_storage::{PrefixedStorage, ReadonlyPrefixedStorage};
+use secret_toolkit::utils::{pad_handle_result, pad_query_result, HandleCallback, Query};
+use sha2::{Digest, Sha256};
 
+use crate::listener_msg::ListenerHandleMsg;
 use crate::factory_msg::{
     FactoryHandleMsg, FactoryOffspringInfo, FactoryQueryMsg, IsKeyValidWrapper,
+    IsPermitValid, IsPermitValidWrapper, RegistrySnapshotWrapper, ResolveNamesWrapper,
 };
 use crate::msg::{
-    HandleMsg, InitMsg, QueryAnswer, QueryMsg,
+    ContractInfo, CountPrivacy, Dependency, HandleAnswer, HandleMsg, InitMsg, ProofNode, QueryAnswer, QueryMsg,
+    QueryWithPermit, StateExport,
 };
-use crate::state::{State, save, CONFIG_KEY, load};
+use secret_toolkit::permit::Permit;
+use crate::response::{ok_answer, ok_empty, ok_with_msgs, permission_denied, unauthorized};
+use crate::state::{
+    Listener, State, save, remove, BLOCK_SIZE, CONFIG_KEY, DEPENDENCIES_KEY, FALLBACK_KEY,
+    LISTENER_KEY, MAX_REMEMBERED_NONCES, PREFIX_NONCES, load, may_load,
+};
+
+/// handle messages supported by this template, as reported by the Interface query
+const SUPPORTED_HANDLES: &[&str] = &[
+    "increment",
+    "reset",
+    "deactivate",
+    "reactivate",
+    "refresh_dependencies",
+    "update_description",
+    "set_count_privacy",
+    "transfer_ownership",
+    "change_owner",
+    "deactivate_by_factory",
+    "confirm_registration",
+    "retry_registration",
+    "nudge",
+    "set_fallback_key",
+    "set_factory",
+    "flag_issue",
+    "request_recovery",
+    "set_listener",
+    "notify_listener",
+    "set_response_data",
+];
+/// queries supported by this template, as reported by the Interface query
+const SUPPORTED_QUERIES: &[&str] = &[
+    "get_count",
+    "get_info",
+    "interface",
+    "dependencies",
+    "export_state",
+    "epoch",
+    "verify_membership",
+    "is_active",
+    "last_nonce",
+    "with_permit",
+];
 
 ////////////////////////////////////// Init ///////////////////////////////////////
 /// Returns InitResult
@@ -33,24 +80,29 @@ pub fn init<S: Storage, A: Api, Q: Querier>(
         password: msg.password,
         active: true,
         offspring_addr: env.contract.address,
-        description: msg.description,
+        description: msg.description.clone(),
+        epoch: 0,
         count: msg.count,
         owner: msg.owner.clone(),
+        count_privacy: msg.count_privacy.unwrap_or(CountPrivacy::Owner),
+        count_viewers: msg.count_viewers.unwrap_or_default(),
+        factory_build: msg.factory_build,
+        registration_confirmed: false,
+        registration_index: None,
+        last_action_height: env.block.height,
+        inactivity_limit: msg.inactivity_limit,
+        response_data: msg.response_data.unwrap_or(false),
     };
 
     save(&mut deps.storage, CONFIG_KEY, &state)?;
 
+    // resolve and cache any named dependencies
+    let names = msg.dependencies.unwrap_or_default();
+    let dependencies = resolve_dependencies(deps, &msg.factory, names)?;
+    save(&mut deps.storage, DEPENDENCIES_KEY, &dependencies)?;
+
     // perform register callback to factory
-    let offspring = FactoryOffspringInfo {
-        label: msg.label,
-        password: msg.password,
-    };
-    let reg_offspring_msg = FactoryHandleMsg::RegisterOffspring {
-        owner: msg.owner,
-        offspring,
-    };
-    let cosmos_msg =
-        reg_offspring_msg.to_cosmos_msg(msg.factory.code_hash, msg.factory.address, None)?;
+    let cosmos_msg = registration_msg(&state)?;
 
     Ok(InitResponse {
         messages: vec![cosmos_msg],
@@ -58,6 +110,25 @@ pub fn init<S: Storage, A: Api, Q: Querier>(
     })
 }
 
+/// Returns StdResult<CosmosMsg> with the callback registering this offspring with the factory
+///
+/// # Arguments
+///
+/// * `state` - a reference to the offspring's state
+fn registration_msg(state: &State) -> StdResult<CosmosMsg> {
+    let offspring = FactoryOffspringInfo {
+        label: state.label.clone(),
+        password: state.password,
+        description: state.description.clone(),
+        count_privacy: Some(state.count_privacy),
+    };
+    FactoryHandleMsg::RegisterOffspring {
+        owner: state.owner.clone(),
+        offspring,
+    }
+    .to_cosmos_msg(state.factory.code_hash.clone(), state.factory.address.clone(), None)
+}
+
 ///////////////////////////////////// Handle //////////////////////////////////////
 /// Returns HandleResult
 ///
@@ -71,11 +142,574 @@ pub fn handle<S: Storage, A: Api, Q: Querier>(
     env: Env,
     msg: HandleMsg,
 ) -> HandleResult {
-    match msg {
-        HandleMsg::Increment {} => try_increment(deps),
-        HandleMsg::Reset { count } => try_reset(deps, env, count),
+    let height = env.block.height;
+    let is_nudge = msg == HandleMsg::Nudge {};
+    let response = match msg {
+        HandleMsg::Increment { nonce } => try_increment(deps, env, nonce),
+        HandleMsg::Reset { count, nonce } => try_reset(deps, env, count, nonce),
         HandleMsg::Deactivate {} => try_deactivate(deps, env),
+        HandleMsg::Reactivate {} => try_reactivate(deps, env),
+        HandleMsg::RefreshDependencies {} => try_refresh_dependencies(deps, env),
+        HandleMsg::UpdateDescription { description } => {
+            try_update_description(deps, env, description)
+        }
+        HandleMsg::SetCountPrivacy { privacy, viewers } => {
+            try_set_count_privacy(deps, env, privacy, viewers)
+        }
+        HandleMsg::TransferOwnership { new_owner } => {
+            try_transfer_ownership(deps, env, new_owner)
+        }
+        HandleMsg::ChangeOwner { owner } => try_change_owner(deps, env, owner),
+        HandleMsg::DeactivateByFactory {} => try_deactivate_by_factory(deps, env),
+        HandleMsg::ConfirmRegistration { index } => try_confirm_registration(deps, env, index),
+        HandleMsg::RetryRegistration {} => try_retry_registration(deps),
+        HandleMsg::Nudge {} => try_nudge(deps, env),
+        HandleMsg::SetFallbackKey { key } => try_set_fallback_key(deps, env, key),
+        HandleMsg::SetFactory { factory } => try_set_factory(deps, env, factory),
+        HandleMsg::FlagIssue { category, details } => {
+            try_flag_issue(deps, env, category, details)
+        }
+        HandleMsg::RequestRecovery {} => try_request_recovery(deps, env),
+        HandleMsg::SetListener {
+            listener,
+            min_delta,
+        } => try_set_listener(deps, env, listener, min_delta),
+        HandleMsg::NotifyListener {} => try_notify_listener(deps),
+        HandleMsg::SetResponseData { enabled } => try_set_response_data(deps, env, enabled),
+    };
+    // nudging must not keep a stale offspring alive
+    let response = response.and_then(|resp| {
+        if !is_nudge {
+            let mut state: State = load(&deps.storage, CONFIG_KEY)?;
+            state.last_action_height = height;
+            save(&mut deps.storage, CONFIG_KEY, &state)?;
+        }
+        Ok(resp)
+    });
+    pad_handle_result(response, BLOCK_SIZE)
+}
+
+/// Returns HandleResult
+///
+/// deactivates the offspring and lets the factory know if there was no activity for the
+/// configured number of blocks. Can be executed by anyone.
+///
+/// # Arguments
+///
+/// * `deps`  - mutable reference to Extern containing all the contract's external dependencies
+/// * `env`   - Env of contract's environment
+pub fn try_nudge<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+) -> HandleResult {
+    let mut state: State = load(&deps.storage, CONFIG_KEY)?;
+    enforce_active(&state)?;
+    let limit = state.inactivity_limit.ok_or_else(|| {
+        StdError::generic_err("This offspring does not expire from inactivity")
+    })?;
+    let expires_at = state.last_action_height.saturating_add(limit);
+    if env.block.height < expires_at {
+        return Err(StdError::generic_err(format!(
+            "This offspring can not be deactivated for inactivity before block {}",
+            expires_at
+        )));
+    }
+    state.active = false;
+    state.epoch += 1;
+    save(&mut deps.storage, CONFIG_KEY, &state)?;
+
+    // let factory know
+    let deactivate_msg = FactoryHandleMsg::DeactivateOffspring {
+        owner: state.owner.clone(),
+        expired: true,
+    }
+    .to_cosmos_msg(state.factory.code_hash, state.factory.address, None)?;
+
+    ok_with_msgs(vec![deactivate_msg])
+}
+
+/// Returns HandleResult
+///
+/// enables or disables the owner's local fallback viewing key, which authenticates the owner's
+/// queries if the factory can not be queried. Can only be executed by owner.
+///
+/// # Arguments
+///
+/// * `deps` - mutable reference to Extern containing all the contract's external dependencies
+/// * `env`  - Env of contract's environment
+/// * `key`  - the fallback viewing key, or None to disable it
+pub fn try_set_fallback_key<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    key: Option<String>,
+) -> HandleResult {
+    let mut state: State = load(&deps.storage, CONFIG_KEY)?;
+    if env.message.sender != state.owner {
+        return Err(unauthorized());
+    }
+    match key {
+        Some(key) => save(&mut deps.storage, FALLBACK_KEY, &key)?,
+        None => remove(&mut deps.storage, FALLBACK_KEY),
+    }
+    state.epoch += 1;
+    save(&mut deps.storage, CONFIG_KEY, &state)?;
+
+    ok_empty()
+}
+
+/// Returns HandleResult
+///
+/// repoints this offspring to another factory, which is then used to authenticate viewing keys
+/// and is notified of changes. A registered, active offspring tells the old factory it left, so
+/// the old factory's registry stops listing it as active. Can only be executed by owner.
+///
+/// # Arguments
+///
+/// * `deps`    - mutable reference to Extern containing all the contract's external dependencies
+/// * `env`     - Env of contract's environment
+/// * `factory` - code hash and address of the new factory
+pub fn try_set_factory<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    factory: ContractInfo,
+) -> HandleResult {
+    let mut state: State = load(&deps.storage, CONFIG_KEY)?;
+    if env.message.sender != state.owner {
+        return Err(unauthorized());
+    }
+    if factory.address == state.factory.address {
+        return Err(StdError::generic_err("This offspring already uses that factory"));
+    }
+    let mut messages = vec![];
+    if state.active && state.registration_confirmed {
+        messages.push(FactoryHandleMsg::DetachOffspring {}.to_cosmos_msg(
+            state.factory.code_hash.clone(),
+            state.factory.address.clone(),
+            None,
+        )?);
+    }
+    state.factory = factory;
+    state.epoch += 1;
+    save(&mut deps.storage, CONFIG_KEY, &state)?;
+
+    ok_with_msgs(messages)
+}
+
+/// Returns HandleResult
+///
+/// sends the registration callback to the factory again, if the factory has not confirmed the
+/// registration yet. Can be executed by anyone.
+///
+/// # Arguments
+///
+/// * `deps` - mutable reference to Extern containing all the contract's external dependencies
+pub fn try_retry_registration<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+) -> HandleResult {
+    let state: State = load(&deps.storage, CONFIG_KEY)?;
+    enforce_active(&state)?;
+    if state.registration_confirmed {
+        return Err(StdError::generic_err(
+            "The factory already confirmed the registration of this offspring",
+        ));
     }
+
+    ok_with_msgs(vec![registration_msg(&state)?])
+}
+
+/// Returns HandleResult
+///
+/// records that the factory registered this offspring. Can only be executed by the factory.
+///
+/// # Arguments
+///
+/// * `deps`  - mutable reference to Extern containing all the contract's external dependencies
+/// * `env`   - Env of contract's environment
+/// * `index` - sequence number the factory gave this offspring's registration
+pub fn try_confirm_registration<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    index: u64,
+) -> HandleResult {
+    let mut state: State = load(&deps.storage, CONFIG_KEY)?;
+    if env.message.sender != state.factory.address {
+        return Err(unauthorized());
+    }
+    state.registration_confirmed = true;
+    state.registration_index = Some(index);
+    state.epoch += 1;
+    save(&mut deps.storage, CONFIG_KEY, &state)?;
+
+    ok_empty()
+}
+
+/// Returns HandleResult
+///
+/// makes another address the owner of this offspring and lets the factory know. The fallback
+/// viewing key belongs to the previous owner, so it is disabled. Can only be executed by owner.
+///
+/// # Arguments
+///
+/// * `deps`      - mutable reference to Extern containing all the contract's external dependencies
+/// * `env`       - Env of contract's environment
+/// * `new_owner` - address of the new owner
+pub fn try_transfer_ownership<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    new_owner: HumanAddr,
+) -> HandleResult {
+    let mut state: State = load(&deps.storage, CONFIG_KEY)?;
+    enforce_active(&state)?;
+    if env.message.sender != state.owner {
+        return Err(unauthorized());
+    }
+    if new_owner == state.owner {
+        return Err(StdError::generic_err("You already own this offspring"));
+    }
+    state.owner = new_owner.clone();
+    state.epoch += 1;
+    save(&mut deps.storage, CONFIG_KEY, &state)?;
+    remove(&mut deps.storage, FALLBACK_KEY);
+
+    // let factory know
+    let update_msg = FactoryHandleMsg::UpdateOffspringOwner { new_owner }
+        .to_cosmos_msg(state.factory.code_hash, state.factory.address, None)?;
+
+    ok_with_msgs(vec![update_msg])
+}
+
+/// Returns HandleResult
+///
+/// records the new owner after the factory transferred this offspring. The fallback viewing key
+/// belongs to the previous owner, so it is disabled. Can only be executed by the factory.
+///
+/// # Arguments
+///
+/// * `deps`  - mutable reference to Extern containing all the contract's external dependencies
+/// * `env`   - Env of contract's environment
+/// * `owner` - address of the new owner
+pub fn try_change_owner<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    owner: HumanAddr,
+) -> HandleResult {
+    let mut state: State = load(&deps.storage, CONFIG_KEY)?;
+    enforce_active(&state)?;
+    if env.message.sender != state.factory.address {
+        return Err(unauthorized());
+    }
+    state.owner = owner;
+    state.epoch += 1;
+    save(&mut deps.storage, CONFIG_KEY, &state)?;
+    remove(&mut deps.storage, FALLBACK_KEY);
+
+    ok_empty()
+}
+
+/// Returns HandleResult
+///
+/// deactivates the offspring after the factory's admin deactivated it in the registry. Can only
+/// be executed by the factory.
+///
+/// # Arguments
+///
+/// * `deps` - mutable reference to Extern containing all the contract's external dependencies
+/// * `env`  - Env of contract's environment
+pub fn try_deactivate_by_factory<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+) -> HandleResult {
+    let mut state: State = load(&deps.storage, CONFIG_KEY)?;
+    enforce_active(&state)?;
+    if env.message.sender != state.factory.address {
+        return Err(unauthorized());
+    }
+    state.active = false;
+    state.epoch += 1;
+    save(&mut deps.storage, CONFIG_KEY, &state)?;
+
+    ok_empty()
+}
+
+/// Returns HandleResult
+///
+/// changes who may view the count and lets the factory know. Can only be executed by owner.
+///
+/// # Arguments
+///
+/// * `deps`    - mutable reference to Extern containing all the contract's external dependencies
+/// * `env`     - Env of contract's environment
+/// * `privacy` - the new privacy level of the count
+/// * `viewers` - optional new list of addresses allowed to view the count
+pub fn try_set_count_privacy<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    privacy: CountPrivacy,
+    viewers: Option<Vec<HumanAddr>>,
+) -> HandleResult {
+    let mut state: State = load(&deps.storage, CONFIG_KEY)?;
+    enforce_active(&state)?;
+    if env.message.sender != state.owner {
+        return Err(unauthorized());
+    }
+    state.count_privacy = privacy;
+    if let Some(viewers) = viewers {
+        state.count_viewers = viewers;
+    }
+    state.epoch += 1;
+    save(&mut deps.storage, CONFIG_KEY, &state)?;
+
+    // let factory know
+    let update_msg = FactoryHandleMsg::UpdateOffspringCountPrivacy {
+        count_privacy: Some(privacy),
+    }
+    .to_cosmos_msg(state.factory.code_hash, state.factory.address, None)?;
+
+    ok_with_msgs(vec![update_msg])
+}
+
+/// Returns HandleResult
+///
+/// updates the description and lets the factory know. Can only be executed by owner.
+///
+/// # Arguments
+///
+/// * `deps`        - mutable reference to Extern containing all the contract's external dependencies
+/// * `env`         - Env of contract's environment
+/// * `description` - the new description
+pub fn try_update_description<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    description: Option<String>,
+) -> HandleResult {
+    let mut state: State = load(&deps.storage, CONFIG_KEY)?;
+    enforce_active(&state)?;
+    if env.message.sender != state.owner {
+        return Err(unauthorized());
+    }
+    state.description = description.clone();
+    state.epoch += 1;
+    save(&mut deps.storage, CONFIG_KEY, &state)?;
+
+    // let factory know
+    let update_msg = FactoryHandleMsg::UpdateOffspringDescription { description }
+        .to_cosmos_msg(state.factory.code_hash, state.factory.address, None)?;
+
+    ok_with_msgs(vec![update_msg])
+}
+
+/// Returns HandleResult
+///
+/// reports an issue to the factory's admin. Can only be executed by owner.
+///
+/// # Arguments
+///
+/// * `deps`     - mutable reference to Extern containing all the contract's external dependencies
+/// * `env`      - Env of contract's environment
+/// * `category` - short category of the issue
+/// * `details`  - description of the issue
+pub fn try_flag_issue<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    category: String,
+    details: String,
+) -> HandleResult {
+    let state: State = load(&deps.storage, CONFIG_KEY)?;
+    enforce_active(&state)?;
+    if env.message.sender != state.owner {
+        return Err(unauthorized());
+    }
+
+    // the factory validates and stores the report
+    let flag_msg = FactoryHandleMsg::FlagIssue { category, details }
+        .to_cosmos_msg(state.factory.code_hash, state.factory.address, None)?;
+
+    ok_with_msgs(vec![flag_msg])
+}
+
+/// Returns HandleResult
+///
+/// asks the factory's admin to make the sender the owner of this offspring. Can not be executed
+/// by owner.
+///
+/// # Arguments
+///
+/// * `deps` - mutable reference to Extern containing all the contract's external dependencies
+/// * `env`  - Env of contract's environment
+pub fn try_request_recovery<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+) -> HandleResult {
+    let state: State = load(&deps.storage, CONFIG_KEY)?;
+    enforce_active(&state)?;
+    if env.message.sender == state.owner {
+        return Err(StdError::generic_err("The sender already owns this offspring"));
+    }
+
+    // the factory keeps the request until its admin decides
+    let recovery_msg = FactoryHandleMsg::RequestRecovery {
+        claimant: env.message.sender,
+    }
+    .to_cosmos_msg(state.factory.code_hash, state.factory.address, None)?;
+
+    ok_with_msgs(vec![recovery_msg])
+}
+
+/// Returns HandleResult
+///
+/// registers the listener contract that is notified of count changes, or removes it. Can only
+/// be executed by owner.
+///
+/// # Arguments
+///
+/// * `deps`      - mutable reference to Extern containing all the contract's external dependencies
+/// * `env`       - Env of contract's environment
+/// * `listener`  - code hash and address of the listener, or None to remove it
+/// * `min_delta` - optional minimum change of the count that triggers a notification
+pub fn try_set_listener<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    listener: Option<ContractInfo>,
+    min_delta: Option<u32>,
+) -> HandleResult {
+    let mut state: State = load(&deps.storage, CONFIG_KEY)?;
+    enforce_active(&state)?;
+    if env.message.sender != state.owner {
+        return Err(unauthorized());
+    }
+    match listener {
+        Some(contract) => {
+            let min_delta = min_delta.unwrap_or(1);
+            if min_delta == 0 {
+                return Err(StdError::generic_err("min_delta must be at least 1"));
+            }
+            let listener = Listener {
+                contract,
+                min_delta,
+                notified_count: state.count,
+                pending_from: None,
+            };
+            save(&mut deps.storage, LISTENER_KEY, &listener)?;
+        }
+        None => remove(&mut deps.storage, LISTENER_KEY),
+    }
+    state.epoch += 1;
+    save(&mut deps.storage, CONFIG_KEY, &state)?;
+
+    ok_empty()
+}
+
+/// Returns HandleResult
+///
+/// delivers the pending count change notification to the listener. If the listener fails, only
+/// this message is reverted and the notification stays pending. Can be executed by anyone.
+///
+/// # Arguments
+///
+/// * `deps` - mutable reference to Extern containing all the contract's external dependencies
+pub fn try_notify_listener<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+) -> HandleResult {
+    let state: State = load(&deps.storage, CONFIG_KEY)?;
+    let listener: Option<Listener> = may_load(&deps.storage, LISTENER_KEY)?;
+    let mut listener = match listener {
+        Some(listener) => listener,
+        None => return Err(StdError::generic_err("This offspring has no listener")),
+    };
+    let previous = listener.pending_from.take().ok_or_else(|| {
+        StdError::generic_err("There is no count change to notify the listener of")
+    })?;
+    listener.notified_count = state.count;
+    save(&mut deps.storage, LISTENER_KEY, &listener)?;
+
+    let notify_msg = ListenerHandleMsg::CountChanged {
+        offspring: state.offspring_addr,
+        previous,
+        count: state.count,
+    }
+    .to_cosmos_msg(listener.contract.code_hash, listener.contract.address, None)?;
+
+    ok_with_msgs(vec![notify_msg])
+}
+
+/// Returns StdResult<()>
+///
+/// queues a notification for the listener if the count moved by at least the listener's
+/// min_delta since the last one. The notification is delivered by NotifyListener in a later
+/// message, because a failing message reverts the whole transaction
+///
+/// # Arguments
+///
+/// * `storage` - mutable reference to the contract's storage
+/// * `count`   - the new count
+fn track_count_change<S: Storage>(storage: &mut S, count: i32) -> StdResult<()> {
+    let listener: Option<Listener> = may_load(storage, LISTENER_KEY)?;
+    let mut listener = match listener {
+        Some(listener) => listener,
+        None => return Ok(()),
+    };
+    let delta = (i64::from(count) - i64::from(listener.notified_count)).abs();
+    if delta < i64::from(listener.min_delta) {
+        return Ok(());
+    }
+    if listener.pending_from.is_none() {
+        listener.pending_from = Some(listener.notified_count);
+    }
+    listener.notified_count = count;
+    save(storage, LISTENER_KEY, &listener)
+}
+
+/// Returns HandleResult
+///
+/// re-resolves the named dependencies through the factory's address book and updates the cache.
+/// Can only be executed by owner.
+///
+/// # Arguments
+///
+/// * `deps`  - mutable reference to Extern containing all the contract's external dependencies
+/// * `env`   - Env of contract's environment
+pub fn try_refresh_dependencies<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+) -> HandleResult {
+    let mut state: State = load(&deps.storage, CONFIG_KEY)?;
+    enforce_active(&state)?;
+    if env.message.sender != state.owner {
+        return Err(unauthorized());
+    }
+    let cached: Vec<Dependency> = may_load(&deps.storage, DEPENDENCIES_KEY)?.unwrap_or_default();
+    let names = cached.into_iter().map(|d| d.name).collect();
+    let dependencies = resolve_dependencies(deps, &state.factory, names)?;
+    save(&mut deps.storage, DEPENDENCIES_KEY, &dependencies)?;
+    state.epoch += 1;
+    save(&mut deps.storage, CONFIG_KEY, &state)?;
+
+    ok_empty()
+}
+
+/// Returns StdResult<Vec<Dependency>>
+///
+/// resolves dependency names through the factory's address book.
+///
+/// # Arguments
+///
+/// * `deps`    - reference to Extern containing all the contract's external dependencies
+/// * `factory` - reference to the factory's code hash and address
+/// * `names`   - names of the address book entries to resolve
+fn resolve_dependencies<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    factory: &ContractInfo,
+    names: Vec<String>,
+) -> StdResult<Vec<Dependency>> {
+    if names.is_empty() {
+        return Ok(vec![]);
+    }
+    let resolved: ResolveNamesWrapper = FactoryQueryMsg::ResolveNames { names }.query(
+        &deps.querier,
+        factory.code_hash.clone(),
+        factory.address.clone(),
+    )?;
+    Ok(resolved.resolve_names.entries)
 }
 
 /// Returns HandleResult
@@ -93,22 +727,53 @@ pub fn try_deactivate<S: Storage, A: Api, Q: Querier>(
     let mut state: State = load(&mut deps.storage, CONFIG_KEY)?;
     enforce_active(&state)?;
     if env.message.sender != state.owner {
-        return Err(StdError::Unauthorized { backtrace: None });
+        return Err(unauthorized());
     }
     state.active = false;
+    state.epoch += 1;
     save(&mut deps.storage, CONFIG_KEY, &state)?;
 
     // let factory know
     let deactivate_msg = FactoryHandleMsg::DeactivateOffspring {
         owner: state.owner.clone(),
+        expired: false,
     }
     .to_cosmos_msg(state.factory.code_hash.clone(), state.factory.address.clone(), None)?;
 
-    Ok(HandleResponse {
-        messages: vec![deactivate_msg],
-        log: vec![],
-        data: None,
-    })
+    ok_with_msgs(vec![deactivate_msg])
+}
+
+/// Returns HandleResult
+///
+/// reactivates the offspring and asks the factory to list it as active again. The factory
+/// rejects the request if its admin deactivated the offspring.
+///
+/// # Arguments
+///
+/// * `deps`  - mutable reference to Extern containing all the contract's external dependencies
+/// * `env`   - Env of contract's environment
+pub fn try_reactivate<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+) -> HandleResult {
+    let mut state: State = load(&deps.storage, CONFIG_KEY)?;
+    if state.active {
+        return Err(StdError::generic_err("This contract is already active"));
+    }
+    if env.message.sender != state.owner {
+        return Err(unauthorized());
+    }
+    state.active = true;
+    state.epoch += 1;
+    save(&mut deps.storage, CONFIG_KEY, &state)?;
+
+    // let factory know
+    let reactivate_msg = FactoryHandleMsg::ReactivateOffspring {
+        password: state.password,
+    }
+    .to_cosmos_msg(state.factory.code_hash.clone(), state.factory.address.clone(), None)?;
+
+    ok_with_msgs(vec![reactivate_msg])
 }
 
 /// Returns HandleResult
@@ -117,14 +782,34 @@ pub fn try_deactivate<S: Storage, A: Api, Q: Querier>(
 ///
 /// # Arguments
 ///
-/// * `deps` - mutable reference to Extern containing all the contract's external dependencies
-pub fn try_increment<S: Storage, A: Api, Q: Querier>(deps: &mut Extern<S, A, Q>) -> HandleResult {
+/// * `deps`  - mutable reference to Extern containing all the contract's external dependencies
+/// * `env`   - Env of contract's environment
+/// * `nonce` - optional client nonce, rejected if the sender used it recently
+pub fn try_increment<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    nonce: Option<u64>,
+) -> HandleResult {
     let mut state: State = load(&mut deps.storage, CONFIG_KEY)?;
     enforce_active(&state)?;
+    accept_nonce(&mut deps.storage, &env.message.sender, nonce)?;
     state.count += 1;
+    state.epoch += 1;
     save(&mut deps.storage, CONFIG_KEY, &state)?;
+    track_count_change(&mut deps.storage, state.count)?;
 
-    Ok(HandleResponse::default())
+    if !state.response_data {
+        return ok_empty();
+    }
+    // anyone can increment, so only reveal the count to those who may view it
+    let count = if state.count_privacy == CountPrivacy::Public
+        || may_view_count(&state, &env.message.sender)
+    {
+        Some(state.count)
+    } else {
+        None
+    };
+    ok_answer(HandleAnswer::Increment { count })
 }
 
 /// Returns HandleResult
@@ -136,20 +821,107 @@ pub fn try_increment<S: Storage, A: Api, Q: Querier>(deps: &mut Extern<S, A, Q>)
 /// * `deps`  - mutable reference to Extern containing all the contract's external dependencies
 /// * `env`   - Env of contract's environment
 /// * `count` - The value to reset the counter to.
+/// * `nonce` - optional client nonce, rejected if the sender used it recently
 pub fn try_reset<S: Storage, A: Api, Q: Querier>(
     deps: &mut Extern<S, A, Q>,
     env: Env,
     count: i32,
+    nonce: Option<u64>,
 ) -> HandleResult {
     let mut state: State = load(&mut deps.storage, CONFIG_KEY)?;
     enforce_active(&state)?;
     if env.message.sender != state.owner {
-        return Err(StdError::Unauthorized { backtrace: None });
+        return Err(unauthorized());
     }
+    accept_nonce(&mut deps.storage, &env.message.sender, nonce)?;
     state.count = count;
+    state.epoch += 1;
     save(&mut deps.storage, CONFIG_KEY, &state)?;
+    track_count_change(&mut deps.storage, count)?;
 
-    Ok(HandleResponse::default())
+    if !state.response_data {
+        return ok_empty();
+    }
+    ok_answer(HandleAnswer::Reset { count })
+}
+
+/// Returns HandleResult
+///
+/// chooses whether Increment and Reset answer with the resulting count. Can only be executed by
+/// owner.
+///
+/// # Arguments
+///
+/// * `deps`    - mutable reference to Extern containing all the contract's external dependencies
+/// * `env`     - Env of contract's environment
+/// * `enabled` - true if the answers should include the count
+pub fn try_set_response_data<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    enabled: bool,
+) -> HandleResult {
+    let mut state: State = load(&deps.storage, CONFIG_KEY)?;
+    if env.message.sender != state.owner {
+        return Err(unauthorized());
+    }
+    state.response_data = enabled;
+    state.epoch += 1;
+    save(&mut deps.storage, CONFIG_KEY, &state)?;
+
+    ok_empty()
+}
+
+/// Returns StdResult<()>
+///
+/// remembers the sender's client nonce, failing if the sender used it recently. A failed handle
+/// reverts its storage changes, so only nonces of applied operations are remembered
+///
+/// # Arguments
+///
+/// * `storage` - mutable reference to the contract's storage
+/// * `sender` - a reference to the address that sent the nonce
+/// * `nonce` - optional client nonce
+fn accept_nonce<S: Storage>(
+    storage: &mut S,
+    sender: &HumanAddr,
+    nonce: Option<u64>,
+) -> StdResult<()> {
+    let nonce = match nonce {
+        Some(nonce) => nonce,
+        None => return Ok(()),
+    };
+    let mut nonce_store = PrefixedStorage::new(PREFIX_NONCES, storage);
+    let mut nonces: Vec<u64> =
+        may_load(&nonce_store, sender.as_str().as_bytes())?.unwrap_or_default();
+    if nonces.contains(&nonce) {
+        return Err(StdError::generic_err(format!(
+            "Nonce {} was already used",
+            nonce
+        )));
+    }
+    nonces.push(nonce);
+    if nonces.len() > MAX_REMEMBERED_NONCES {
+        nonces.remove(0);
+    }
+    save(&mut nonce_store, sender.as_str().as_bytes(), &nonces)
+}
+
+/// Returns StdResult<QueryAnswer> with the last client nonce accepted from an address
+///
+/// # Arguments
+///
+/// * `deps` - reference to Extern containing all the contract's external dependencies
+/// * `address` - a reference to the address that sent the nonces
+fn last_nonce<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    address: &HumanAddr,
+) -> StdResult<QueryAnswer> {
+    let nonce_store = ReadonlyPrefixedStorage::new(PREFIX_NONCES, &deps.storage);
+    let nonces: Vec<u64> =
+        may_load(&nonce_store, address.as_str().as_bytes())?.unwrap_or_default();
+    Ok(QueryAnswer::LastNonce {
+        nonce: nonces.last().copied(),
+    })
 }
 
 /////////////////////////////////////// Query /////////////////////////////////////
@@ -160,41 +932,312 @@ pub fn try_reset<S: Storage, A: Api, Q: Querier>(
 /// * `deps` - reference to Extern containing all the contract's external dependencies
 /// * `msg` - QueryMsg passed in with the query call
 pub fn query<S: Storage, A: Api, Q: Querier>(deps: &Extern<S, A, Q>, msg: QueryMsg) -> QueryResult {
-    match msg {
+    let response = match msg {
         QueryMsg::GetCount {
             address,
             viewing_key,
-        } => to_binary(&query_count(deps, &address, viewing_key)?),
-    }
+        } => to_binary(&query_count(deps, address, viewing_key)?),
+        QueryMsg::GetInfo {} => to_binary(&query_info(deps)?),
+        QueryMsg::Interface {} => to_binary(&query_interface(deps)?),
+        QueryMsg::Dependencies {} => to_binary(&query_dependencies(deps)?),
+        QueryMsg::ExportState {
+            address,
+            viewing_key,
+        } => to_binary(&query_export_state(deps, &address, viewing_key)?),
+        QueryMsg::Epoch {} => {
+            let state: State = load(&deps.storage, CONFIG_KEY)?;
+            to_binary(&QueryAnswer::Epoch { epoch: state.epoch })
+        }
+        QueryMsg::IsActive {} => {
+            let state: State = load(&deps.storage, CONFIG_KEY)?;
+            to_binary(&QueryAnswer::IsActive {
+                active: state.active,
+            })
+        }
+        QueryMsg::VerifyMembership {
+            label,
+            owner,
+            siblings,
+        } => to_binary(&query_verify_membership(deps, &label, &owner, &siblings)?),
+        QueryMsg::LastNonce {
+            address,
+            viewing_key,
+        } => {
+            let state: State = load(&deps.storage, CONFIG_KEY)?;
+            enforce_valid_viewing_key(deps, &state, &address, viewing_key)?;
+            to_binary(&last_nonce(deps, &address)?)
+        }
+        QueryMsg::WithPermit { permit, query } => {
+            to_binary(&query_with_permit(deps, &permit, query)?)
+        }
+    };
+    pad_query_result(response, BLOCK_SIZE)
 }
 
-/// Returns StdResult<CountResponse> displaying the count.
+/// Returns StdResult<QueryAnswer> exporting the full state of this offspring to its owner
 ///
 /// # Arguments
 ///
 /// * `deps` - reference to Extern containing all the contract's external dependencies
 /// * `address` - a reference to the address whose viewing key is being validated.
 /// * `viewing_key` - String key used to authenticate the query.
-fn query_count<S: Storage, A: Api, Q: Querier>(
+fn query_export_state<S: Storage, A: Api, Q: Querier>(
     deps: &Extern<S, A, Q>,
     address: &HumanAddr,
     viewing_key: String,
 ) -> StdResult<QueryAnswer> {
     let state: State = load(&deps.storage, CONFIG_KEY)?;
-    if state.owner == *address {
-        enforce_valid_viewing_key(deps, &state, address, viewing_key)?;
+    if state.owner != *address {
+        return Err(permission_denied());
+    }
+    enforce_valid_viewing_key(deps, &state, address, viewing_key)?;
+    export_state(deps, state)
+}
+
+/// Returns StdResult<QueryAnswer> exporting the full state of this offspring
+///
+/// # Arguments
+///
+/// * `deps` - reference to Extern containing all the contract's external dependencies
+/// * `state` - the State of the contract
+fn export_state<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    state: State,
+) -> StdResult<QueryAnswer> {
+    let dependencies: Vec<Dependency> =
+        may_load(&deps.storage, DEPENDENCIES_KEY)?.unwrap_or_default();
+    Ok(QueryAnswer::ExportState {
+        state: StateExport {
+            label: state.label,
+            description: state.description,
+            owner: state.owner,
+            count: state.count,
+            count_privacy: state.count_privacy,
+            count_viewers: state.count_viewers,
+            dependencies,
+            active: state.active,
+            epoch: state.epoch,
+            factory: state.factory,
+            factory_build: state.factory_build,
+            registration_index: state.registration_index,
+            last_action_height: state.last_action_height,
+            inactivity_limit: state.inactivity_limit,
+            response_data: state.response_data,
+        },
+    })
+}
+
+/// Returns StdResult<QueryAnswer> indicating whether a membership proof leads from this
+/// offspring to the root of the factory's latest registry snapshot
+///
+/// # Arguments
+///
+/// * `deps` - reference to Extern containing all the contract's external dependencies
+/// * `label` - a reference to the label given in the proof
+/// * `owner` - a reference to the owner given in the proof
+/// * `siblings` - the sibling hashes given in the proof
+fn query_verify_membership<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    label: &str,
+    owner: &HumanAddr,
+    siblings: &[ProofNode],
+) -> StdResult<QueryAnswer> {
+    let state: State = load(&deps.storage, CONFIG_KEY)?;
+    let response: RegistrySnapshotWrapper = FactoryQueryMsg::RegistrySnapshot {}.query(
+        &deps.querier,
+        state.factory.code_hash,
+        state.factory.address,
+    )?;
+    let snapshot = match response.registry_snapshot.snapshot {
+        Some(snapshot) => snapshot,
+        None => {
+            return Ok(QueryAnswer::VerifyMembership {
+                is_member: false,
+                height: None,
+            })
+        }
+    };
+    // the leaf and node hashes must match the factory's snapshot format
+    let mut leaf = vec![0u8];
+    for field in [&state.offspring_addr.0, label, &owner.0].iter() {
+        leaf.extend_from_slice(&(field.len() as u32).to_be_bytes());
+        leaf.extend_from_slice(field.as_bytes());
+    }
+    let mut hash = Sha256::digest(&leaf).to_vec();
+    for node in siblings {
+        let mut data = vec![1u8];
+        if node.is_left {
+            data.extend_from_slice(node.hash.as_slice());
+            data.extend_from_slice(&hash);
+        } else {
+            data.extend_from_slice(&hash);
+            data.extend_from_slice(node.hash.as_slice());
+        }
+        hash = Sha256::digest(&data).to_vec();
+    }
+    Ok(QueryAnswer::VerifyMembership {
+        is_member: hash.as_slice() == snapshot.root.as_slice(),
+        height: Some(snapshot.height),
+    })
+}
+
+/// Returns StdResult<QueryAnswer> displaying the public info of this offspring
+///
+/// # Arguments
+///
+/// * `deps` - reference to Extern containing all the contract's external dependencies
+fn query_info<S: Storage, A: Api, Q: Querier>(deps: &Extern<S, A, Q>) -> StdResult<QueryAnswer> {
+    let state: State = load(&deps.storage, CONFIG_KEY)?;
+    Ok(QueryAnswer::Info {
+        label: state.label,
+        description: state.description,
+        factory: state.factory,
+        active: state.active,
+        registration_confirmed: state.registration_confirmed,
+        registration_index: state.registration_index,
+    })
+}
+
+/// Returns StdResult<QueryAnswer> listing the cached named dependencies
+///
+/// # Arguments
+///
+/// * `deps` - reference to Extern containing all the contract's external dependencies
+fn query_dependencies<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+) -> StdResult<QueryAnswer> {
+    let dependencies: Vec<Dependency> =
+        may_load(&deps.storage, DEPENDENCIES_KEY)?.unwrap_or_default();
+    Ok(QueryAnswer::Dependencies { dependencies })
+}
+
+/// Returns StdResult<QueryAnswer> describing the template name, version, supported messages, and
+/// the factory build that created this offspring
+///
+/// # Arguments
+///
+/// * `deps` - reference to Extern containing all the contract's external dependencies
+fn query_interface<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+) -> StdResult<QueryAnswer> {
+    let state: State = load(&deps.storage, CONFIG_KEY)?;
+    Ok(QueryAnswer::Interface {
+        name: env!("CARGO_PKG_NAME").to_string(),
+        version: env!("CARGO_PKG_VERSION").to_string(),
+        handles: SUPPORTED_HANDLES.iter().map(|h| h.to_string()).collect(),
+        queries: SUPPORTED_QUERIES.iter().map(|q| q.to_string()).collect(),
+        factory_build: state.factory_build,
+    })
+}
+
+/// Returns StdResult<CountResponse> displaying the count.
+///
+/// # Arguments
+///
+/// * `deps` - reference to Extern containing all the contract's external dependencies
+/// * `address` - optional address whose viewing key is being validated.
+/// * `viewing_key` - optional String key used to authenticate the query.
+fn query_count<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    address: Option<HumanAddr>,
+    viewing_key: Option<String>,
+) -> StdResult<QueryAnswer> {
+    let state: State = load(&deps.storage, CONFIG_KEY)?;
+    if state.count_privacy == CountPrivacy::Public {
+        return Ok(QueryAnswer::CountResponse { count: state.count });
+    }
+    let permitted = match &address {
+        Some(address) => may_view_count(&state, address),
+        None => false,
+    };
+    if let (true, Some(address), Some(viewing_key)) = (permitted, address, viewing_key) {
+        enforce_valid_viewing_key(deps, &state, &address, viewing_key)?;
         return Ok(QueryAnswer::CountResponse { count: state.count });
     } else {
-        return Err(StdError::generic_err(
-            // error message chosen as to not leak information.
-            "This address does not have permission and/or viewing key is not valid",
-        ));
+        return Err(permission_denied());
+    }
+}
+
+/// Returns bool result of checking whether an address may view the count
+///
+/// # Arguments
+///
+/// * `state` - a reference to the State of the contract.
+/// * `address` - a reference to the address that wants to view the count.
+fn may_view_count(state: &State, address: &HumanAddr) -> bool {
+    state.owner == *address
+        || (state.count_privacy == CountPrivacy::Viewers && state.count_viewers.contains(address))
+}
+
+/// Returns StdResult<QueryAnswer> answering a query for the signer of the permit
+///
+/// # Arguments
+///
+/// * `deps` - reference to Extern containing all the contract's external dependencies
+/// * `permit` - a reference to the permit authenticating the query
+/// * `query` - the query to answer
+fn query_with_permit<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    permit: &Permit,
+    query: QueryWithPermit,
+) -> StdResult<QueryAnswer> {
+    let state: State = load(&deps.storage, CONFIG_KEY)?;
+    match query {
+        QueryWithPermit::GetCount {} => {
+            if state.count_privacy == CountPrivacy::Public
+                || may_view_count(&state, &permit_signer(deps, &state, permit)?)
+            {
+                Ok(QueryAnswer::CountResponse { count: state.count })
+            } else {
+                Err(permission_denied())
+            }
+        }
+        QueryWithPermit::LastNonce {} => last_nonce(deps, &permit_signer(deps, &state, permit)?),
+        QueryWithPermit::ExportState {} => {
+            if permit_signer(deps, &state, permit)? != state.owner {
+                return Err(permission_denied());
+            }
+            export_state(deps, state)
+        }
+    }
+}
+
+/// Returns StdResult<HumanAddr> with the address that signed the permit
+///
+/// asks the factory to validate the permit. Unlike viewing keys, permits can not be checked in
+/// degraded mode, because only the factory knows which permits were revoked
+///
+/// # Arguments
+///
+/// * `deps` - a reference to Extern containing all the contract's external dependencies.
+/// * `state` - a reference to the State of the contract.
+/// * `permit` - a reference to the permit to validate.
+fn permit_signer<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    state: &State,
+    permit: &Permit,
+) -> StdResult<HumanAddr> {
+    let permit_valid_msg = FactoryQueryMsg::IsPermitValid {
+        permit: permit.clone(),
+    };
+    let response: IsPermitValidWrapper = permit_valid_msg.query(
+        &deps.querier,
+        state.factory.code_hash.clone(),
+        state.factory.address.clone(),
+    )?;
+    match response.is_permit_valid {
+        IsPermitValid {
+            is_valid: true,
+            address: Some(address),
+        } => Ok(address),
+        _ => Err(permission_denied()),
     }
 }
 
 /// Returns StdResult<()>
 ///
-/// makes sure that the address and the viewing key match in the factory contract.
+/// makes sure that the address and the viewing key match in the factory contract. If the
+/// factory can not be queried, the owner may authenticate with the local fallback key instead
 ///
 /// # Arguments
 ///
@@ -211,21 +1254,26 @@ fn enforce_valid_viewing_key<S: Storage, A: Api, Q: Querier>(
     let state_clone = state.clone();
     let key_valid_msg = FactoryQueryMsg::IsKeyValid {
         address: address.clone(),
-        viewing_key,
+        viewing_key: viewing_key.clone(),
     };
-    let key_valid_response: IsKeyValidWrapper = key_valid_msg.query(
+    let key_valid_response: StdResult<IsKeyValidWrapper> = key_valid_msg.query(
         &deps.querier,
         state_clone.factory.code_hash,
         state_clone.factory.address,
-    )?;
-    // if authenticated
-    if key_valid_response.is_key_valid.is_valid {
-        Ok(())
-    } else {
-        return Err(StdError::generic_err(
-            // error message chosen as to not leak information.
-            "This address does not have permission and/or viewing key is not valid",
-        ));
+    );
+    match key_valid_response {
+        // if authenticated
+        Ok(response) if response.is_key_valid.is_valid => Ok(()),
+        Ok(_) => Err(permission_denied()),
+        // the factory is unreachable, so operate in degraded mode if the owner enabled it
+        Err(e) => {
+            let fallback: Option<String> = may_load(&deps.storage, FALLBACK_KEY)?;
+            match fallback {
+                Some(key) if *address == state.owner && key == viewing_key => Ok(()),
+                Some(_) => Err(permission_denied()),
+                None => Err(e),
+            }
+        }
     }
 }
 
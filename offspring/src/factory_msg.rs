@@ -1,8 +1,10 @@
-use cosmwasm_std::HumanAddr;
+use cosmwasm_std::{Binary, HumanAddr};
 use serde::{Deserialize, Serialize};
 
+use secret_toolkit::permit::Permit;
 use secret_toolkit::utils::{HandleCallback, Query};
 
+use crate::msg::{CountPrivacy, Dependency};
 use crate::state::BLOCK_SIZE;
 
 /// Factory handle messages to be used by offspring.
@@ -24,6 +26,59 @@ pub enum FactoryHandleMsg {
     DeactivateOffspring {
         /// offspring's owner
         owner: HumanAddr,
+        /// true if the offspring expired from inactivity rather than being deactivated by its
+        /// owner
+        expired: bool,
+    },
+
+    /// DetachOffspring tells the factory that the owner repointed this offspring to another
+    /// factory.
+    DetachOffspring {},
+
+    /// ReactivateOffspring asks the factory to move the offspring back to the active list.
+    ReactivateOffspring {
+        /// offspring password
+        password: [u8; 32],
+    },
+
+    /// UpdateOffspringDescription tells the factory that the offspring's description changed.
+    UpdateOffspringDescription {
+        /// the offspring's new description
+        description: Option<String>,
+    },
+
+    /// UpdateOffspringAttributes replaces the custom attributes shown with this offspring in the
+    /// factory's registry. The counter template does not use it, but forks can surface
+    /// contract-specific summary data with it.
+    UpdateOffspringAttributes {
+        /// the offspring's attributes
+        attributes: Vec<FactoryOffspringAttribute>,
+    },
+
+    /// UpdateOffspringCountPrivacy tells the factory that the count's privacy level changed.
+    UpdateOffspringCountPrivacy {
+        /// the count's new privacy level
+        count_privacy: Option<CountPrivacy>,
+    },
+
+    /// FlagIssue adds an issue the owner reported to the factory admin's issue queue.
+    FlagIssue {
+        /// short category of the issue
+        category: String,
+        /// description of the issue
+        details: String,
+    },
+
+    /// UpdateOffspringOwner tells the factory that the owner transferred this offspring.
+    UpdateOffspringOwner {
+        /// the offspring's new owner
+        new_owner: HumanAddr,
+    },
+
+    /// RequestRecovery asks the factory admin to make the claimant the owner of this offspring.
+    RequestRecovery {
+        /// address asking to become the owner
+        claimant: HumanAddr,
     },
 }
 
@@ -39,6 +94,20 @@ pub struct FactoryOffspringInfo {
     pub label: String,
     /// offspring password
     pub password: [u8; 32],
+    /// Optional text description of this offspring
+    pub description: Option<String>,
+    /// privacy level of the count
+    pub count_privacy: Option<CountPrivacy>,
+}
+
+/// this corresponds to OffspringAttribute in factory, a custom key/value attribute shown with the
+/// offspring in the factory's registry
+#[derive(Serialize)]
+pub struct FactoryOffspringAttribute {
+    /// attribute name
+    pub key: String,
+    /// attribute value
+    pub value: String,
 }
 
 /// the factory's query messages this offspring will call
@@ -52,6 +121,27 @@ pub enum FactoryQueryMsg {
         /// viewing key
         viewing_key: String,
     },
+    /// authenticates the supplied address/viewing key and reports the roles the address holds
+    AuthenticateRole {
+        /// address whose viewing key is being authenticated
+        address: HumanAddr,
+        /// viewing key
+        viewing_key: String,
+        /// optional owner to check whether the address is one of its delegates
+        owner: Option<HumanAddr>,
+    },
+    /// resolves names in the factory's address book
+    ResolveNames {
+        /// names to resolve
+        names: Vec<String>,
+    },
+    /// displays the root and height of the latest registry snapshot
+    RegistrySnapshot {},
+    /// validates a permit for the factory and reports who signed it
+    IsPermitValid {
+        /// permit to validate
+        permit: Permit,
+    },
 }
 
 impl Query for FactoryQueryMsg {
@@ -68,4 +158,62 @@ pub struct IsKeyValid {
 #[derive(Serialize, Deserialize, Debug)]
 pub struct IsKeyValidWrapper {
     pub is_key_valid: IsKeyValid,
-}
\ No newline at end of file
+}
+
+/// result of validating a permit
+#[derive(Serialize, Deserialize, Debug)]
+pub struct IsPermitValid {
+    pub is_valid: bool,
+    pub address: Option<HumanAddr>,
+}
+
+/// IsPermitValid wrapper struct
+#[derive(Serialize, Deserialize, Debug)]
+pub struct IsPermitValidWrapper {
+    pub is_permit_valid: IsPermitValid,
+}
+
+/// result of authenticating an address/key pair and its roles
+#[derive(Serialize, Deserialize, Debug)]
+pub struct AuthenticateRole {
+    pub is_valid: bool,
+    pub is_admin: bool,
+    pub is_delegate: bool,
+}
+
+/// AuthenticateRole wrapper struct
+#[derive(Serialize, Deserialize, Debug)]
+pub struct AuthenticateRoleWrapper {
+    pub authenticate_role: AuthenticateRole,
+}
+
+/// resolved address book entries
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ResolveNames {
+    pub entries: Vec<Dependency>,
+}
+
+/// ResolveNames wrapper struct
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ResolveNamesWrapper {
+    pub resolve_names: ResolveNames,
+}
+/// root and height of a registry snapshot
+#[derive(Serialize, Deserialize, Debug)]
+pub struct SnapshotInfo {
+    pub root: Binary,
+    pub height: u64,
+    pub leaf_count: u32,
+}
+
+/// latest registry snapshot
+#[derive(Serialize, Deserialize, Debug)]
+pub struct RegistrySnapshot {
+    pub snapshot: Option<SnapshotInfo>,
+}
+
+/// RegistrySnapshot wrapper struct
+#[derive(Serialize, Deserialize, Debug)]
+pub struct RegistrySnapshotWrapper {
+    pub registry_snapshot: RegistrySnapshot,
+}
@@ -2,8 +2,15 @@ pub mod contract;
 pub mod msg;
 pub mod state;
 pub mod factory_msg;
+pub mod listener_msg;
+mod response;
 
-#[cfg(target_arch = "wasm32")]
+/// the contract's entry points, for crates that link the offspring natively with the library
+/// feature
+#[cfg(feature = "library")]
+pub use crate::contract::{handle, init, query};
+
+#[cfg(all(target_arch = "wasm32", not(feature = "library")))]
 mod wasm {
     use super::contract;
     use cosmwasm_std::{
@@ -0,0 +1,27 @@
+use cosmwasm_std::HumanAddr;
+use serde::Serialize;
+
+use secret_toolkit::utils::HandleCallback;
+
+use crate::state::BLOCK_SIZE;
+
+/// handle messages this offspring sends to the listener contract its owner registered. A
+/// listener only needs to accept this one message.
+#[derive(Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ListenerHandleMsg {
+    /// CountChanged tells the listener that the count moved by at least the owner's chosen delta
+    /// since the listener was last told about it
+    CountChanged {
+        /// address of the offspring whose count changed
+        offspring: HumanAddr,
+        /// the count the listener was last told about
+        previous: i32,
+        /// the current count
+        count: i32,
+    },
+}
+
+impl HandleCallback for ListenerHandleMsg {
+    const BLOCK_SIZE: usize = BLOCK_SIZE;
+}
@@ -1,7 +1,9 @@
-use cosmwasm_std::HumanAddr;
+use cosmwasm_std::{Binary, HumanAddr};
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
+use secret_toolkit::permit::Permit;
+
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct InitMsg {
     /// factory contract code hash and address
@@ -16,30 +18,204 @@ pub struct InitMsg {
     
     pub owner: HumanAddr,
     pub count: i32,
+    /// Optional names of factory address book entries this offspring depends on
+    #[serde(default)]
+    pub dependencies: Option<Vec<String>>,
+    /// Optional level of privacy of the count. Default: owner only
+    #[serde(default)]
+    pub count_privacy: Option<CountPrivacy>,
+    /// Optional addresses allowed to view the count when its privacy level is Viewers
+    #[serde(default)]
+    pub count_viewers: Option<Vec<HumanAddr>>,
+    /// Optional version and commit of the factory creating this offspring
+    #[serde(default)]
+    pub factory_build: Option<BuildInfo>,
+    /// Optional number of blocks without activity after which anyone may deactivate this
+    /// offspring with Nudge. Default: never expires
+    #[serde(default)]
+    pub inactivity_limit: Option<u64>,
+    /// Optionally true if Increment and Reset should answer with the resulting count.
+    /// Default: false
+    #[serde(default)]
+    pub response_data: Option<bool>,
 }
 
 /// Handle messages
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum HandleMsg {
-    Increment {},
-    Reset { count: i32 },
+    // Increment and Reset take an optional client nonce. The last MAX_REMEMBERED_NONCES nonces
+    // of each sender are remembered, and a message reusing one of them is rejected, so a wallet
+    // retrying after a timeout can not apply the same operation twice.
+    Increment {
+        #[serde(default)]
+        nonce: Option<u64>,
+    },
+    Reset {
+        count: i32,
+        #[serde(default)]
+        nonce: Option<u64>,
+    },
     // Deactivate can only be called by owner in this template
     Deactivate {},
+    // Reactivate reactivates this offspring and asks the factory to list it as active again. Can
+    // only be called by owner, and not after the factory's admin deactivated this offspring.
+    Reactivate {},
+    // RefreshDependencies re-resolves the dependency names through the factory's address book.
+    // Can only be called by owner.
+    RefreshDependencies {},
+    // UpdateDescription changes the description and lets the factory know. Can only be called by
+    // owner.
+    UpdateDescription { description: Option<String> },
+    // SetCountPrivacy changes who may view the count and lets the factory know. If viewers is
+    // not given, the current viewers are kept. Can only be called by owner.
+    SetCountPrivacy {
+        privacy: CountPrivacy,
+        viewers: Option<Vec<HumanAddr>>,
+    },
+    // TransferOwnership makes new_owner the owner of this offspring and lets the factory know, so
+    // it lists the offspring under the new owner. Can only be called by owner.
+    TransferOwnership { new_owner: HumanAddr },
+    // ChangeOwner is sent by the factory when the ownership of this offspring was transferred.
+    // Can only be called by the factory.
+    ChangeOwner { owner: HumanAddr },
+    // DeactivateByFactory is sent by the factory when its admin deactivated this offspring. Can
+    // only be called by the factory.
+    DeactivateByFactory {},
+    // ConfirmRegistration is sent by the factory once it registered this offspring. Can only be
+    // called by the factory.
+    ConfirmRegistration { index: u64 },
+    // RetryRegistration sends the registration to the factory again if the factory has not
+    // confirmed it yet, e.g. because the first attempt failed. Can be called by anyone.
+    RetryRegistration {},
+    // Nudge deactivates this offspring and lets the factory know if there was no activity for
+    // the configured number of blocks. Can be called by anyone.
+    Nudge {},
+    // SetFallbackKey enables a local viewing key the owner can use if the factory can not be
+    // queried, or disables it if key is not specified. Can only be called by owner.
+    SetFallbackKey { key: Option<String> },
+    // SetFactory repoints this offspring to another factory, e.g. after the factory migrated.
+    // Can only be called by owner.
+    SetFactory { factory: ContractInfo },
+    // FlagIssue reports an issue to the factory's admin, who sees it along with this offspring's
+    // registry record. Can only be called by owner.
+    FlagIssue { category: String, details: String },
+    // RequestRecovery asks the factory's admin to make the sender the owner of this offspring,
+    // e.g. after the owner lost their key. The owner only changes once the admin approves.
+    RequestRecovery {},
+    // SetListener registers a listener contract that is notified when the count moves by at
+    // least min_delta (default 1), or removes it if listener is not specified. Can only be
+    // called by owner.
+    SetListener {
+        listener: Option<ContractInfo>,
+        min_delta: Option<u32>,
+    },
+    // NotifyListener delivers the pending count change notification to the listener. Count
+    // changes only queue the notification, so a failing listener can not revert them. Can be
+    // called by anyone.
+    NotifyListener {},
+    // SetResponseData chooses whether Increment and Reset answer with the resulting count. Can
+    // only be called by owner.
+    SetResponseData { enabled: bool },
+}
+
+/// responses to handle messages, only returned if response data is enabled
+#[derive(Serialize, Deserialize, Debug, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum HandleAnswer {
+    Increment {
+        /// the count after incrementing, or None if the sender may not view it
+        count: Option<i32>,
+    },
+    Reset {
+        /// the count after resetting
+        count: i32,
+    },
 }
 
 /// Queries
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum QueryMsg {
-    // GetCount returns the current count as a json-encoded number. Can only be queried by the owner,
-    // to demonstrate how to use the viewing key in the factory.
+    // GetCount returns the current count as a json-encoded number. Unless the count is public, it
+    // can only be queried by the owner or the owner's chosen viewers, to demonstrate how to use
+    // the viewing key in the factory.
     GetCount {
-        /// address to authenticate as a viewer
+        /// address to authenticate as a viewer. Not needed if the count is public
+        address: Option<HumanAddr>,
+        /// viewer's viewing key. Not needed if the count is public
+        viewing_key: Option<String>,
+    },
+    // GetInfo returns the public info of this offspring, including whether the factory confirmed
+    // its registration.
+    GetInfo {},
+    // Interface describes this template so tooling can introspect offspring generically.
+    Interface {},
+    // Dependencies lists the cached addresses of this offspring's named dependencies.
+    Dependencies {},
+    // ExportState returns the full state of this offspring in one answer, so the owner can migrate
+    // its data before retiring it. Can only be queried by the owner.
+    ExportState {
+        /// owner's address
         address: HumanAddr,
-        /// viewer's viewing key
+        /// owner's viewing key
         viewing_key: String,
     },
+    // Epoch returns the number of state changes so far, letting clients reuse cached answers of
+    // expensive queries for as long as it is unchanged.
+    Epoch {},
+    // VerifyMembership checks a membership proof issued by the factory's MembershipProof query
+    // against the root of the factory's latest registry snapshot, so anyone can confirm this
+    // offspring was created by the factory even if the factory only lists privately.
+    VerifyMembership {
+        /// label of this offspring when the snapshot was taken, as given in the proof
+        label: String,
+        /// owner of this offspring when the snapshot was taken, as given in the proof
+        owner: HumanAddr,
+        /// sibling hashes from the leaf level up to the root, as given in the proof
+        siblings: Vec<ProofNode>,
+    },
+    // IsActive returns only whether this offspring is active, as a cheap health probe for the
+    // factory and other contracts. The answer is padded like every other answer, so its size
+    // does not reveal the state.
+    IsActive {},
+    // LastNonce returns the last client nonce accepted from an address, so a wallet can check
+    // whether a retried operation was applied. Can only be queried with the address' viewing key.
+    LastNonce {
+        /// address that sent the nonces
+        address: HumanAddr,
+        /// address' viewing key
+        viewing_key: String,
+    },
+    // WithPermit answers a query authenticated with a permit for the factory instead of a viewing
+    // key. The permit is validated by the factory, so a single permit works for the factory and
+    // all its offspring.
+    WithPermit {
+        permit: Permit,
+        query: QueryWithPermit,
+    },
+}
+
+/// Queries that can be authenticated with a permit
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum QueryWithPermit {
+    // GetCount returns the current count if the signer may view it, like GetCount.
+    GetCount {},
+    // LastNonce returns the client nonce the signer used last, like LastNonce.
+    LastNonce {},
+    // ExportState returns the full state of this offspring if the signer is the owner, like
+    // ExportState.
+    ExportState {},
+}
+
+/// sibling hash on the path from a registry snapshot leaf to its root
+#[derive(Serialize, Deserialize, JsonSchema, Clone, PartialEq, Debug)]
+pub struct ProofNode {
+    /// the sibling's hash
+    pub hash: Binary,
+    /// true if the sibling is hashed on the left
+    pub is_left: bool,
 }
 
 /// code hash and address of a contract
@@ -51,11 +227,132 @@ pub struct ContractInfo {
     pub address: HumanAddr,
 }
 
+/// version and commit of a factory build
+#[derive(Serialize, Deserialize, JsonSchema, Clone, PartialEq, Debug)]
+pub struct BuildInfo {
+    /// crate version of the factory
+    pub version: String,
+    /// commit the factory was built from, if known
+    pub commit: Option<String>,
+}
+
+/// who may view the count
+#[derive(Serialize, Deserialize, JsonSchema, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum CountPrivacy {
+    /// only the owner
+    Owner,
+    /// the owner and the viewers the owner chose
+    Viewers,
+    /// anyone
+    Public,
+}
+
+/// full state of an offspring, as exported to its owner
+#[derive(Serialize, Deserialize, JsonSchema, Clone, PartialEq, Debug)]
+pub struct StateExport {
+    /// label used when initializing offspring
+    pub label: String,
+    /// Optional text description of this offspring
+    pub description: Option<String>,
+    /// address of the owner
+    pub owner: HumanAddr,
+    /// the count for the counter
+    pub count: i32,
+    /// who may view the count
+    pub count_privacy: CountPrivacy,
+    /// addresses allowed to view the count when its privacy level is Viewers
+    pub count_viewers: Vec<HumanAddr>,
+    /// cached named dependencies
+    pub dependencies: Vec<Dependency>,
+    /// true if this offspring is active
+    pub active: bool,
+    /// number of state changes so far
+    pub epoch: u64,
+    /// factory code hash and address
+    pub factory: ContractInfo,
+    /// version and commit of the factory that created this offspring
+    pub factory_build: Option<BuildInfo>,
+    /// sequence number the factory gave this offspring's registration
+    pub registration_index: Option<u64>,
+    /// block height of the last activity
+    pub last_action_height: u64,
+    /// number of blocks without activity after which anyone may deactivate this offspring
+    pub inactivity_limit: Option<u64>,
+    /// true if Increment and Reset answer with the resulting count
+    pub response_data: bool,
+}
+
+/// named dependency resolved through the factory's address book
+#[derive(Serialize, Deserialize, JsonSchema, Clone, PartialEq, Debug)]
+pub struct Dependency {
+    /// name of the address book entry
+    pub name: String,
+    /// code hash and address of the dependency
+    pub contract: ContractInfo,
+}
+
 /// responses to queries
 #[derive(Serialize, Deserialize, Debug, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum QueryAnswer {
     CountResponse {
         count: i32,
-    }
+    },
+    /// machine-readable description of this offspring template
+    Interface {
+        /// template name
+        name: String,
+        /// template version
+        version: String,
+        /// names of the supported handle messages
+        handles: Vec<String>,
+        /// names of the supported queries
+        queries: Vec<String>,
+        /// version and commit of the factory that created this offspring, if it reported them
+        factory_build: Option<BuildInfo>,
+    },
+    /// cached named dependencies
+    Dependencies {
+        dependencies: Vec<Dependency>,
+    },
+    /// public info of this offspring
+    Info {
+        /// label used when initializing offspring
+        label: String,
+        /// Optional text description of this offspring
+        description: Option<String>,
+        /// factory code hash and address
+        factory: ContractInfo,
+        /// true if this offspring is active
+        active: bool,
+        /// true once the factory confirmed this offspring's registration
+        registration_confirmed: bool,
+        /// sequence number the factory gave this offspring's registration
+        registration_index: Option<u64>,
+    },
+    /// full state of this offspring
+    ExportState {
+        state: StateExport,
+    },
+    /// number of state changes so far
+    Epoch {
+        epoch: u64,
+    },
+    /// whether this offspring is active
+    IsActive {
+        active: bool,
+    },
+    /// last client nonce accepted from an address
+    LastNonce {
+        /// None if no nonce was accepted from the address
+        nonce: Option<u64>,
+    },
+    /// result of verifying a membership proof
+    VerifyMembership {
+        /// true if the proof leads to the root of the factory's latest registry snapshot
+        is_member: bool,
+        /// block height of the factory's latest registry snapshot, if one was taken
+        height: Option<u64>,
+    },
 }
\ No newline at end of file
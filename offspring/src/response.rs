@@ -0,0 +1,48 @@
+use cosmwasm_std::{to_binary, CosmosMsg, HandleResponse, HandleResult, StdError};
+
+use crate::msg::HandleAnswer;
+
+// Responses built here are padded to BLOCK_SIZE by `contract::handle`, so every handler gets the
+// same padding no matter which of these helpers it uses.
+
+/// Returns HandleResult without messages or response data
+pub fn ok_empty() -> HandleResult {
+    ok_with_msgs(vec![])
+}
+
+/// Returns HandleResult that sends messages, usually to let the factory know of a change
+///
+/// # Arguments
+///
+/// * `messages` - messages to send
+pub fn ok_with_msgs(messages: Vec<CosmosMsg>) -> HandleResult {
+    Ok(HandleResponse {
+        messages,
+        log: vec![],
+        data: None,
+    })
+}
+
+/// Returns HandleResult with the answer as response data
+///
+/// # Arguments
+///
+/// * `answer` - the HandleAnswer to return
+pub fn ok_answer(answer: HandleAnswer) -> HandleResult {
+    Ok(HandleResponse {
+        messages: vec![],
+        log: vec![],
+        data: Some(to_binary(&answer)?),
+    })
+}
+
+/// Returns StdError for a sender that may not perform the action
+pub fn unauthorized() -> StdError {
+    StdError::Unauthorized { backtrace: None }
+}
+
+/// Returns StdError for a failed query authentication. The message is chosen as to not leak
+/// whether the address or the viewing key was wrong
+pub fn permission_denied() -> StdError {
+    StdError::generic_err("This address does not have permission and/or viewing key is not valid")
+}
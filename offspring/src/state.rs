@@ -6,9 +6,19 @@ use serde::{Deserialize, Serialize, de::DeserializeOwned};
 
 use cosmwasm_std::{Storage, HumanAddr, ReadonlyStorage, StdResult, StdError};
 
-use crate::msg::ContractInfo;
+use crate::msg::{BuildInfo, ContractInfo, CountPrivacy};
 
 pub const CONFIG_KEY: &[u8] = b"config";
+/// storage key for the cached named dependencies
+pub const DEPENDENCIES_KEY: &[u8] = b"dependencies";
+/// storage key for the owner's local fallback viewing key
+pub const FALLBACK_KEY: &[u8] = b"fallbackkey";
+/// storage key for the listener contract notified of count changes
+pub const LISTENER_KEY: &[u8] = b"listener";
+/// prefix for storage of the client nonces each sender used most recently
+pub const PREFIX_NONCES: &[u8] = b"nonces";
+/// the number of client nonces remembered per sender
+pub const MAX_REMEMBERED_NONCES: usize = 16;
 
 /// pad handle responses and log attributes to blocks of 256 bytes to prevent leaking info based on
 /// response size
@@ -29,12 +39,47 @@ pub struct State {
     pub offspring_addr: HumanAddr,
     /// Optional text description of this offspring
     pub description: Option<String>,
+    /// incremented by every handle that changes state. Queries can not write to storage, so
+    /// their results can not be cached by the contract. Instead clients may cache the answers of
+    /// expensive authenticated queries and reuse them for as long as the epoch is unchanged
+    pub epoch: u64,
     
     // rest are contract specific data
     /// the count for the counter
     pub count: i32,
     /// address of the owner associated to this offspring contract
     pub owner: HumanAddr,
+    /// who may view the count
+    pub count_privacy: CountPrivacy,
+    /// addresses allowed to view the count when its privacy level is Viewers
+    pub count_viewers: Vec<HumanAddr>,
+    /// version and commit of the factory that created this offspring
+    pub factory_build: Option<BuildInfo>,
+    /// true once the factory confirmed it registered this offspring, which completes the
+    /// creation handshake
+    pub registration_confirmed: bool,
+    /// sequence number the factory gave this offspring's registration
+    pub registration_index: Option<u64>,
+    /// block height of the last successful handle other than Nudge
+    pub last_action_height: u64,
+    /// number of blocks without activity after which anyone may deactivate this offspring with
+    /// Nudge. None if it never expires
+    pub inactivity_limit: Option<u64>,
+    /// true if Increment and Reset answer with the resulting count
+    pub response_data: bool,
+}
+
+/// listener contract the owner registered to be notified of count changes
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct Listener {
+    /// code hash and address of the listener
+    pub contract: ContractInfo,
+    /// minimum change of the count since the last notification that triggers a new one
+    pub min_delta: u32,
+    /// count the last notification was queued or delivered at, which changes are measured from
+    pub notified_count: i32,
+    /// count the listener was last told about, if a notification is waiting to be delivered
+    pub pending_from: Option<i32>,
 }
 
 /// Returns StdResult<()> resulting from saving an item to storage
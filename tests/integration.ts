@@ -402,7 +402,7 @@ async function deactivateOffsprings(
   console.log("List active offspring: ", queryActive);
   console.log("List inactive offspring: ", queryInactive);
 
-  const test = await createOffsprings(factoryContractInfo, "offspring", "entropy", myAddress, 2, 20);
+  const test = await createOffsprings(factoryContractInfo, "offspring", "some_entropy", myAddress, 2, 20);
 
   //for (let index = 0; index < 7; index++) {
   //  await deactivateOffspring(offspringInfoList[index*3], wallet);